@@ -1,5 +1,7 @@
-use crate::board::ParseFenError;
-use crate::piece::Piece;
+use crate::board::position::Position;
+use crate::board::{FenErrorKind, ParseFenError};
+use crate::piece::{Color, Piece, PieceType};
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 #[derive(Copy, Clone, Debug)]
@@ -19,6 +21,68 @@ impl CastlingState {
             black_long: false,
         }
     }
+
+    pub fn short(&self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_short,
+            Color::Black => self.black_short,
+        }
+    }
+
+    pub fn long(&self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_long,
+            Color::Black => self.black_long,
+        }
+    }
+
+    /// Drops the rights that are lost by a move touching the king or rook squares.
+    pub fn update(&mut self, from: &Position, to: &Position) {
+        for pos in [from, to] {
+            match (pos.rank, pos.file) {
+                (0, 4) => {
+                    self.white_short = false;
+                    self.white_long = false;
+                }
+                (0, 7) => self.white_short = false,
+                (0, 0) => self.white_long = false,
+                (7, 4) => {
+                    self.black_short = false;
+                    self.black_long = false;
+                }
+                (7, 7) => self.black_short = false,
+                (7, 0) => self.black_long = false,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Default for CastlingState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes the rights as in FEN, e.g. `KQkq` or `-`.
+impl Display for CastlingState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let rights = [
+            (self.white_short, 'K'),
+            (self.white_long, 'Q'),
+            (self.black_short, 'k'),
+            (self.black_long, 'q'),
+        ];
+
+        if rights.iter().all(|(has, _)| !has) {
+            return write!(f, "-");
+        }
+        for (_, c) in rights.iter().filter(|(has, _)| *has) {
+            write!(f, "{}", c)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl FromStr for CastlingState {
@@ -47,6 +111,7 @@ impl FromStr for CastlingState {
 
         if count == 0 && s != "-" || count > 0 && s.len() != count {
             return Err(ParseFenError {
+                kind: FenErrorKind::Castling,
                 description: format!("invalid castling rights '{}'", s),
             });
         }
@@ -61,6 +126,16 @@ pub struct GameState {
     pub en_passant_file: Option<u8>,
     pub fifty_move_counter: u8,
     pub captured_piece: Option<Piece>,
+    /// The checks given by white and black, in variants that count them.
+    pub checks: [u8; 2],
+    /// The pieces in hand of white and black in variants with drops, indexed by `pocket_index`.
+    pub pockets: [[u8; 5]; 2],
+    /// The squares of pieces that were promoted from pawns, in variants with drops.
+    pub promoted: u64,
+    /// The pieces a capture blew up in Atomic: those around the capture square in the order of
+    /// the king's steps, then the capturer.
+    pub exploded: [Option<Piece>; 9],
+    pub hash: u64,
 }
 
 impl GameState {
@@ -70,6 +145,11 @@ impl GameState {
             en_passant_file: None,
             fifty_move_counter: 0,
             captured_piece: None,
+            checks: [0; 2],
+            pockets: [[0; 5]; 2],
+            promoted: 0,
+            exploded: [None; 9],
+            hash: 0,
         }
     }
 
@@ -77,8 +157,24 @@ impl GameState {
         GameState {
             castling: self.castling,
             en_passant_file: None,
-            fifty_move_counter: self.fifty_move_counter + 1,
+            fifty_move_counter: self.fifty_move_counter.saturating_add(1),
             captured_piece: None,
+            checks: self.checks,
+            pockets: self.pockets,
+            promoted: self.promoted,
+            exploded: [None; 9],
+            hash: self.hash,
         }
     }
 }
+
+/// Where pieces of type `t` are counted in a pocket, kings never are.
+pub fn pocket_index(t: PieceType) -> usize {
+    t as usize - 1
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,7 +1,7 @@
 use crate::board::position::Position;
 use crate::piece::Piece;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Grid {
     cells: [Option<Piece>; 64],
 }
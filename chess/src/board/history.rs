@@ -0,0 +1,139 @@
+use crate::board::{Board, FenErrorKind, ParseFenError};
+use crate::chess_move::Move;
+
+/// The reasons a player may claim a draw.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum DrawClaim {
+    /// The current position occurred for (at least) the third time.
+    ThreefoldRepetition,
+    /// No capture or pawn move happened in the last fifty moves of each player.
+    FiftyMoveRule,
+}
+
+impl Board {
+    /// Sets up the position of the fen string and then plays the given moves in UCI notation,
+    /// so that the earlier positions count towards repetitions.
+    pub fn from_fen_and_moves<'a>(
+        fen: &str,
+        moves: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Board, ParseFenError> {
+        let mut b: Board = fen.parse()?;
+
+        for (idx, s) in moves.into_iter().enumerate() {
+            match b.parse_uci_move(s) {
+                Some(m) => b.make_move(m),
+                None => {
+                    return Err(ParseFenError {
+                        kind: FenErrorKind::IllegalMove,
+                        description: format!("illegal move '{}' at index {}", s, idx),
+                    })
+                }
+            }
+        }
+
+        Ok(b)
+    }
+
+    /// Finds the legal move written in UCI notation, e.g. `e2e4` or `e7e8q`.
+    pub fn parse_uci_move(&mut self, s: &str) -> Option<Move> {
+        self.legal_moves()
+            .iter()
+            .copied()
+            .find(|m| m.to_string() == s)
+    }
+
+    /// How often the current position occurred, the current occurrence included.
+    ///
+    /// Only the positions since the last capture or pawn move are looked at, as no earlier
+    /// position can repeat.
+    pub fn repetition_count(&self) -> usize {
+        let current = self.state_stack.len() - 1;
+        let reversible = self.get_state().fifty_move_counter as usize;
+
+        1 + (2..=reversible.min(current))
+            .step_by(2)
+            .filter(|back| self.state_stack[current - back].hash == self.hash)
+            .count()
+    }
+
+    /// The draw the side to move may claim in the current position, if any.
+    ///
+    /// Threefold repetition takes precedence if both apply.
+    pub fn can_claim_draw(&self) -> Option<DrawClaim> {
+        if self.repetition_count() >= 3 {
+            Some(DrawClaim::ThreefoldRepetition)
+        } else if self.get_state().fifty_move_counter >= 100 {
+            Some(DrawClaim::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::history::DrawClaim;
+    use crate::board::Board;
+
+    const SHUFFLE: [&str; 8] = [
+        "g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1", "f6g8",
+    ];
+
+    #[test]
+    fn threefold_repetition() {
+        let mut b = Board::new();
+        assert_eq!(1, b.repetition_count());
+
+        for (idx, s) in SHUFFLE.iter().enumerate() {
+            let m = b.parse_uci_move(s).unwrap();
+            b.make_move(m);
+
+            match idx {
+                3 => assert_eq!(2, b.repetition_count()),
+                7 => assert_eq!(3, b.repetition_count()),
+                _ => assert!(b.repetition_count() < 3),
+            }
+        }
+
+        assert_eq!(Some(DrawClaim::ThreefoldRepetition), b.can_claim_draw());
+    }
+
+    #[test]
+    fn irreversible_move_resets_repetitions() {
+        let mut b = Board::from_fen_and_moves(
+            Board::STARTING_FEN,
+            ["g1f3", "g8f6", "f3g1", "f6g8", "e2e4", "e7e5"],
+        )
+        .unwrap();
+        assert_eq!(1, b.repetition_count());
+
+        for s in &SHUFFLE[..4] {
+            let m = b.parse_uci_move(s).unwrap();
+            b.make_move(m);
+        }
+        assert_eq!(2, b.repetition_count());
+        assert_eq!(None, b.can_claim_draw());
+    }
+
+    #[test]
+    fn seeded_history() {
+        let b = Board::from_fen_and_moves(Board::STARTING_FEN, SHUFFLE).unwrap();
+
+        assert_eq!(3, b.repetition_count());
+        assert_eq!(Some(DrawClaim::ThreefoldRepetition), b.can_claim_draw());
+    }
+
+    #[test]
+    fn illegal_move_in_history() {
+        assert!(Board::from_fen_and_moves(Board::STARTING_FEN, ["e2e4", "e2e4"]).is_err());
+    }
+
+    #[test]
+    fn fifty_move_rule() {
+        let b: Board = "4k3/8/8/8/8/8/8/4K2R w - - 100 80".parse().unwrap();
+        assert_eq!(Some(DrawClaim::FiftyMoveRule), b.can_claim_draw());
+
+        let b: Board = "4k3/8/8/8/8/8/8/4K2R w - - 99 80".parse().unwrap();
+        assert_eq!(None, b.can_claim_draw());
+    }
+}
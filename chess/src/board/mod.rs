@@ -1,27 +1,48 @@
-mod game_state;
+pub mod game_state;
 mod grid;
+pub mod history;
+pub mod movegen;
+pub mod outcome;
+pub mod packed;
 mod piece_list;
 pub mod position;
+mod san;
+mod zobrist;
 
-use crate::board::game_state::GameState;
+use crate::board::game_state::{pocket_index, CastlingState, GameState};
 use crate::board::grid::Grid;
 use crate::board::piece_list::PieceList;
-use crate::piece::{Color, Piece};
+use crate::chess_move::{Move, MoveKind};
+#[cfg(feature = "nnue")]
+use crate::nnue::{Accumulator, Network};
+use crate::piece::{Color, Piece, PieceType};
+#[cfg(feature = "syzygy")]
+use crate::syzygy::Tablebase;
+use crate::variant::{Standard, Variant};
 use position::Position;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
+#[cfg(any(feature = "nnue", feature = "syzygy"))]
+use std::sync::Arc;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Board {
     grid: Grid,
     piece_list: PieceList,
     state_stack: Vec<GameState>,
     current_player: Color,
     ply: u32,
+    hash: u64,
+    variant: &'static dyn Variant,
+    #[cfg(feature = "nnue")]
+    nnue: Option<Accumulator>,
+    #[cfg(feature = "syzygy")]
+    tablebase: Option<Arc<Tablebase>>,
 }
 
 impl Board {
-    const STARTING_FEN: &'static str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    pub(crate) const STARTING_FEN: &'static str =
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
     pub fn new() -> Board {
         Self::STARTING_FEN
@@ -29,18 +50,92 @@ impl Board {
             .expect("Invalid starting fen supplied")
     }
 
-    fn get_piece(&self, pos: &Position) -> Option<Piece> {
+    /// The starting position of `variant`.
+    pub fn new_variant(variant: &'static dyn Variant) -> Board {
+        Self::from_fen_variant(variant.starting_fen(), variant)
+            .expect("Invalid starting fen supplied")
+    }
+
+    pub fn variant(&self) -> &'static dyn Variant {
+        self.variant
+    }
+
+    pub fn get_piece(&self, pos: &Position) -> Option<Piece> {
         self.grid.get_piece(pos)
     }
 
+    pub fn current_player(&self) -> Color {
+        self.current_player
+    }
+
+    pub fn ply(&self) -> u32 {
+        self.ply
+    }
+
+    pub fn fifty_move_counter(&self) -> u8 {
+        self.get_state().fifty_move_counter
+    }
+
+    /// Zobrist hash of the position, including side to move, castling rights and en passant file.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn castling(&self) -> CastlingState {
+        self.get_state().castling
+    }
+
+    pub fn en_passant_file(&self) -> Option<u8> {
+        self.get_state().en_passant_file
+    }
+
+    pub fn king_position(&self, color: Color) -> Option<Position> {
+        self.piece_list
+            .pos_of_piece(&Piece::new(color, PieceType::King))
+            .next()
+    }
+
+    /// The squares `piece` stands on.
+    pub fn pieces(&self, piece: &Piece) -> impl Iterator<Item = Position> + '_ {
+        self.piece_list.pos_of_piece(piece)
+    }
+
+    /// How many pieces of type `t` `color` has in hand, in variants with drops like Crazyhouse.
+    pub fn pocket(&self, color: Color, t: PieceType) -> u8 {
+        match t {
+            PieceType::King => 0,
+            _ => self.get_state().pockets[color as usize][pocket_index(t)],
+        }
+    }
+
+    /// Whether the piece on `pos` was promoted from a pawn, tracked in variants with drops.
+    pub fn is_promoted(&self, pos: &Position) -> bool {
+        self.get_state().promoted & 1 << pos.idx() != 0
+    }
+
+    /// The checks `color` gave so far, only counted in variants like Three-check.
+    pub fn checks_given(&self, color: Color) -> u8 {
+        self.get_state().checks[color as usize]
+    }
+
     fn put_piece(&mut self, pos: &Position, p: Option<Piece>) -> Option<Piece> {
         let captured = self.grid.put_piece(pos, p);
 
-        if let Some(piece) = p {
-            self.piece_list.put_piece(&piece, pos);
-        }
         if let Some(piece) = captured {
             self.piece_list.remove_piece(&piece, pos);
+            self.hash ^= zobrist::piece_key(&piece, pos);
+            #[cfg(feature = "nnue")]
+            if let Some(acc) = &mut self.nnue {
+                acc.remove(&piece, pos);
+            }
+        }
+        if let Some(piece) = p {
+            self.piece_list.put_piece(&piece, pos);
+            self.hash ^= zobrist::piece_key(&piece, pos);
+            #[cfg(feature = "nnue")]
+            if let Some(acc) = &mut self.nnue {
+                acc.add(&piece, pos);
+            }
         }
 
         captured
@@ -51,6 +146,11 @@ impl Board {
 
         if let Some(piece) = removed {
             self.piece_list.remove_piece(&piece, pos);
+            self.hash ^= zobrist::piece_key(&piece, pos);
+            #[cfg(feature = "nnue")]
+            if let Some(acc) = &mut self.nnue {
+                acc.remove(&piece, pos);
+            }
         }
 
         removed
@@ -60,11 +160,22 @@ impl Board {
         let moved = self.get_piece(from);
         let captured = self.grid.move_piece(from, to);
 
-        if let Some(piece) = moved {
-            self.piece_list.move_piece(&piece, from, to);
-        }
         if let Some(piece) = captured {
             self.piece_list.remove_piece(&piece, to);
+            self.hash ^= zobrist::piece_key(&piece, to);
+            #[cfg(feature = "nnue")]
+            if let Some(acc) = &mut self.nnue {
+                acc.remove(&piece, to);
+            }
+        }
+        if let Some(piece) = moved {
+            self.piece_list.move_piece(&piece, from, to);
+            self.hash ^= zobrist::piece_key(&piece, from) ^ zobrist::piece_key(&piece, to);
+            #[cfg(feature = "nnue")]
+            if let Some(acc) = &mut self.nnue {
+                acc.remove(&piece, from);
+                acc.add(&piece, to);
+            }
         }
 
         captured
@@ -81,6 +192,286 @@ impl Board {
             .last_mut()
             .expect("game state stack should not be empty")
     }
+
+    /// Plays a move generated for the current position.
+    ///
+    /// The move isn't checked for legality, use `is_legal` or the legal move generator for that.
+    pub fn make_move(&mut self, m: Move) {
+        let mover = match m.kind {
+            MoveKind::Drop(t) => Piece::new(self.current_player, t),
+            _ => self
+                .get_piece(&m.from)
+                .expect("there should be a piece on the from square of a move"),
+        };
+        let mut state = self.get_state().next();
+
+        match m.kind {
+            MoveKind::Capture => state.captured_piece = self.remove_piece(&m.to),
+            MoveKind::EnPassant => {
+                state.captured_piece = self.remove_piece(&Self::en_passant_victim(&m))
+            }
+            MoveKind::DoublePawnPush => {
+                // only remember the file if the capture is possible, otherwise positions that
+                // are the same for all purposes would get different hashes. Pawns pushed from
+                // the first rank in Horde can't be taken en passant.
+                let enemy_pawn = Some(Piece::new(mover.color.opponent(), PieceType::Pawn));
+                if m.from.rank != mover.color.back_rank() && [-1, 1].iter().any(|&df| {
+                    m.to.offset(0, df).and_then(|pos| self.get_piece(&pos)) == enemy_pawn
+                }) {
+                    state.en_passant_file = Some(m.from.file);
+                }
+            }
+            MoveKind::CastleShort | MoveKind::CastleLong => {
+                let (rook_from, rook_to) = Self::castling_rook_move(&m);
+                self.move_piece(&rook_from, &rook_to);
+            }
+            MoveKind::Quiet | MoveKind::Drop(_) => {}
+        }
+
+        if m.is_drop() {
+            self.put_piece(&m.to, Some(mover));
+        } else {
+            self.move_piece(&m.from, &m.to);
+        }
+        if let Some(t) = m.promotion {
+            self.put_piece(&m.to, Some(Piece::new(mover.color, t)));
+        }
+        if self.variant.has_pockets() {
+            Self::update_pockets(&mut state, &m, mover.color);
+        }
+        if self.variant.has_explosions() && state.captured_piece.is_some() {
+            self.explode(&m.to, &mut state);
+        }
+
+        if mover.piece_type == PieceType::Pawn || state.captured_piece.is_some() {
+            state.fifty_move_counter = 0;
+        }
+        state.castling.update(&m.from, &m.to);
+
+        self.push_state(state);
+        if self.variant.counts_checks() && self.in_check() {
+            self.add_check(mover.color);
+        }
+    }
+
+    /// Takes a dropped piece out of the pocket and puts a captured one into the pocket of `us`,
+    /// as a pawn if it was promoted. Keeps track of the promoted pieces as well.
+    fn update_pockets(state: &mut GameState, m: &Move, us: Color) {
+        let captured_at = match m.kind {
+            MoveKind::EnPassant => Self::en_passant_victim(m),
+            _ => m.to,
+        };
+        let pocket = &mut state.pockets[us as usize];
+
+        if let MoveKind::Drop(t) = m.kind {
+            pocket[pocket_index(t)] -= 1;
+        }
+        if let Some(p) = state.captured_piece {
+            let t = if state.promoted & 1 << captured_at.idx() != 0 {
+                PieceType::Pawn
+            } else {
+                p.piece_type
+            };
+            pocket[pocket_index(t)] += 1;
+        }
+
+        let moved_promoted = state.promoted & 1 << m.from.idx() != 0;
+        state.promoted &= !(1 << m.from.idx() | 1 << captured_at.idx());
+        if moved_promoted || m.promotion.is_some() {
+            state.promoted |= 1 << m.to.idx();
+        }
+    }
+
+    /// Removes the capturer on `at` and the pieces other than pawns around it, keeping them in
+    /// `state` for `unmake_move`.
+    fn explode(&mut self, at: &Position, state: &mut GameState) {
+        state.exploded[8] = self.remove_piece(at);
+
+        for (i, &(dr, df)) in movegen::KING_OFFSETS.iter().enumerate() {
+            let Some(pos) = at.offset(dr, df) else {
+                continue;
+            };
+            if self
+                .get_piece(&pos)
+                .is_some_and(|p| p.piece_type != PieceType::Pawn)
+            {
+                state.exploded[i] = self.remove_piece(&pos);
+                state.castling.update(&pos, &pos);
+            }
+        }
+    }
+
+    /// Counts a check given by `color` in the current position.
+    fn add_check(&mut self, color: Color) {
+        let prev = self.get_state().checks;
+        let state = self.get_state_mut();
+        state.checks[color as usize] += 1;
+        let checks = state.checks;
+
+        self.hash ^= zobrist::checks_key(&prev) ^ zobrist::checks_key(&checks);
+        self.get_state_mut().hash = self.hash;
+    }
+
+    /// Takes back the last move, which has to be `m`.
+    pub fn unmake_move(&mut self, m: Move) {
+        let state = self.pop_state();
+
+        if state.exploded[8].is_some() {
+            self.put_piece(&m.to, state.exploded[8]);
+            for (i, &(dr, df)) in movegen::KING_OFFSETS.iter().enumerate() {
+                if let (Some(pos), Some(p)) = (m.to.offset(dr, df), state.exploded[i]) {
+                    self.put_piece(&pos, Some(p));
+                }
+            }
+        }
+
+        if m.promotion.is_some() {
+            let pawn = Piece::new(self.current_player, PieceType::Pawn);
+            self.put_piece(&m.to, Some(pawn));
+        }
+        if m.is_drop() {
+            self.remove_piece(&m.to);
+        } else {
+            self.move_piece(&m.to, &m.from);
+        }
+
+        match m.kind {
+            MoveKind::Capture => {
+                self.put_piece(&m.to, state.captured_piece);
+            }
+            MoveKind::EnPassant => {
+                self.put_piece(&Self::en_passant_victim(&m), state.captured_piece);
+            }
+            MoveKind::CastleShort | MoveKind::CastleLong => {
+                let (rook_from, rook_to) = Self::castling_rook_move(&m);
+                self.move_piece(&rook_to, &rook_from);
+            }
+            MoveKind::Quiet | MoveKind::DoublePawnPush | MoveKind::Drop(_) => {}
+        }
+
+        // the piece moves above restored the piece keys, this also restores the rest
+        self.hash = self.get_state().hash;
+    }
+
+    /// Passes the turn to the opponent without moving a piece.
+    ///
+    /// Returns `false` and leaves the board untouched if the side to move is in check, as passing
+    /// would leave the king capturable.
+    pub fn make_null_move(&mut self) -> bool {
+        if self.in_check() {
+            return false;
+        }
+
+        let state = self.get_state().next();
+        self.push_state(state);
+
+        true
+    }
+
+    /// Takes back a null move made by `make_null_move`.
+    pub fn unmake_null_move(&mut self) {
+        self.pop_state();
+        self.hash = self.get_state().hash;
+    }
+
+    /// Finishes a move: hands the turn over and records the new state, updating the hash for the
+    /// parts of the position that aren't covered by the piece moves.
+    fn push_state(&mut self, mut state: GameState) {
+        let prev = *self.get_state();
+
+        self.hash ^= zobrist::castling_key(&prev.castling) ^ zobrist::castling_key(&state.castling);
+        self.hash ^= zobrist::en_passant_key(prev.en_passant_file)
+            ^ zobrist::en_passant_key(state.en_passant_file);
+        if prev.pockets != state.pockets {
+            self.hash ^= zobrist::pockets_key(&prev.pockets) ^ zobrist::pockets_key(&state.pockets);
+        }
+        self.hash ^= zobrist::side_key(Color::Black);
+        state.hash = self.hash;
+
+        self.state_stack.push(state);
+        self.current_player = self.current_player.opponent();
+        self.ply += 1;
+    }
+
+    fn pop_state(&mut self) -> GameState {
+        let state = self
+            .state_stack
+            .pop()
+            .expect("game state stack should not be empty");
+        self.current_player = self.current_player.opponent();
+        self.ply -= 1;
+
+        state
+    }
+
+    /// Computes the hash from scratch, the incrementally updated `hash` has to match this.
+    fn compute_hash(&self) -> u64 {
+        let state = self.get_state();
+        let pieces = Position::iter()
+            .filter_map(|pos| self.get_piece(&pos).map(|p| zobrist::piece_key(&p, &pos)))
+            .fold(0, |hash, key| hash ^ key);
+
+        pieces
+            ^ zobrist::side_key(self.current_player)
+            ^ zobrist::castling_key(&state.castling)
+            ^ zobrist::en_passant_key(state.en_passant_file)
+            ^ zobrist::checks_key(&state.checks)
+            ^ zobrist::pockets_key(&state.pockets)
+    }
+
+    /// The square of the pawn captured by an en passant move.
+    fn en_passant_victim(m: &Move) -> Position {
+        Position::from(m.from.rank, m.to.file)
+    }
+
+    /// Where the rook moves from and to when castling.
+    fn castling_rook_move(m: &Move) -> (Position, Position) {
+        let rank = m.from.rank;
+
+        match m.kind {
+            MoveKind::CastleLong => (Position::from(rank, 0), Position::from(rank, 3)),
+            _ => (Position::from(rank, 7), Position::from(rank, 5)),
+        }
+    }
+}
+
+#[cfg(feature = "nnue")]
+impl Board {
+    /// Evaluates the position with `network` from now on, or with the handcrafted evaluation if
+    /// there is none.
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        let pieces = Position::iter().filter_map(|pos| Some((self.get_piece(&pos)?, pos)));
+        self.nnue = network.map(|net| Accumulator::new(net, pieces.collect::<Vec<_>>()));
+    }
+
+    pub fn network(&self) -> Option<&Arc<Network>> {
+        self.nnue.as_ref().map(Accumulator::network)
+    }
+
+    /// The evaluation of the network from the view of the side to move, if there is one.
+    pub fn nnue_evaluate(&self) -> Option<i32> {
+        self.nnue
+            .as_ref()
+            .map(|acc| acc.evaluate(self.current_player))
+    }
+}
+
+#[cfg(feature = "syzygy")]
+impl Board {
+    /// Probes `tablebase` for the outcome and at the root of searches from now on.
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<Tablebase>>) {
+        self.tablebase = tablebase;
+    }
+
+    pub fn tablebase(&self) -> Option<&Arc<Tablebase>> {
+        self.tablebase.as_ref()
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Display for Board {
@@ -112,14 +503,71 @@ impl Display for Board {
     }
 }
 
+/// What was wrong when reading a FEN string or a part of one.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum FenErrorKind {
+    Placement,
+    ActiveColor,
+    Castling,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber,
+    /// A square name like `e4`.
+    Square,
+    /// A move played after the position was set up.
+    IllegalMove,
+    /// The checks of Three-check.
+    Checks,
+    /// The pieces in hand of Crazyhouse.
+    Pocket,
+}
+
+impl Display for FenErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            FenErrorKind::Placement => "piece placement",
+            FenErrorKind::ActiveColor => "active color",
+            FenErrorKind::Castling => "castling rights",
+            FenErrorKind::EnPassant => "en passant square",
+            FenErrorKind::HalfmoveClock => "halfmove clock",
+            FenErrorKind::FullmoveNumber => "fullmove number",
+            FenErrorKind::Square => "square",
+            FenErrorKind::IllegalMove => "move",
+            FenErrorKind::Checks => "checks",
+            FenErrorKind::Pocket => "pocket",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ParseFenError {
+    kind: FenErrorKind,
     description: String,
 }
 
+impl ParseFenError {
+    pub fn kind(&self) -> FenErrorKind {
+        self.kind
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+impl Display for ParseFenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for ParseFenError {}
+
 macro_rules! parse_fen_error {
-    ($($fmt:expr),*) => {
-        Err(ParseFenError{description:format!($($fmt),*)})
+    ($kind:ident, $($fmt:expr),*) => {
+        Err(ParseFenError{kind: FenErrorKind::$kind, description:format!($($fmt),*)})
     }
 }
 
@@ -127,19 +575,58 @@ impl FromStr for Board {
     type Err = ParseFenError;
 
     fn from_str(s: &str) -> Result<Board, ParseFenError> {
-        let mut b = Board {
+        Board::from_fen_variant(s, &Standard)
+    }
+}
+
+impl Board {
+    /// A board without pieces with white to move, the hash still has to be computed.
+    fn empty(variant: &'static dyn Variant) -> Board {
+        Board {
             grid: Grid::new(),
             piece_list: PieceList::new(),
             state_stack: vec![GameState::new()],
             current_player: Color::White,
             ply: 0,
-        };
-        let mut iter = s.split_whitespace();
+            hash: 0,
+            variant,
+            #[cfg(feature = "nnue")]
+            nnue: None,
+            #[cfg(feature = "syzygy")]
+            tablebase: None,
+        }
+    }
 
-        // position
+    /// Reads a FEN string of a position in `variant`.
+    ///
+    /// Three-check positions have the remaining checks after the en passant square, e.g.
+    /// `3+2`, or the checks given at the end, e.g. `+0+1`. Crazyhouse positions have the
+    /// pockets after the pieces, e.g. `[Qn]` or `/Qn`, and promoted pieces marked like `Q~`.
+    pub fn from_fen_variant(
+        s: &str,
+        variant: &'static dyn Variant,
+    ) -> Result<Board, ParseFenError> {
+        let mut b = Board::empty(variant);
+        let mut iter = s.split_whitespace().peekable();
+
+        // position, with the pockets either in brackets or as a ninth rank
         match iter.next() {
+            Some(fen_pos) if variant.has_pockets() => {
+                let (placement, pocket) = match fen_pos.split_once('[') {
+                    Some((placement, pocket)) => match pocket.strip_suffix(']') {
+                        Some(pocket) => (placement, pocket),
+                        None => return parse_fen_error!(Pocket, "Invalid fen string '{}': unclosed pocket", s),
+                    },
+                    None if fen_pos.matches('/').count() == 8 => {
+                        fen_pos.rsplit_once('/').unwrap_or_default()
+                    }
+                    None => (fen_pos, ""),
+                };
+                b.read_fen_pos(placement)?;
+                b.read_pocket(pocket)?;
+            }
             Some(fen_pos) => b.read_fen_pos(fen_pos)?,
-            None => return parse_fen_error!("Invalid fen string '{}': no position supplied", s),
+            None => return parse_fen_error!(Placement, "Invalid fen string '{}': no position supplied", s),
         };
 
         // active color
@@ -148,13 +635,14 @@ impl FromStr for Board {
             Some("b") => b.current_player = Color::Black,
             Some(col) => {
                 return parse_fen_error!(
+                    ActiveColor,
                     "Invalid fen string '{}': invalid active color '{}'",
                     s,
                     col
                 )
             }
             None => {
-                return parse_fen_error!("Invalid fen string '{}': no active color specified", s)
+                return parse_fen_error!(ActiveColor, "Invalid fen string '{}': no active color specified", s)
             }
         };
 
@@ -162,12 +650,12 @@ impl FromStr for Board {
         match iter.next() {
             Some(rights) => match rights.parse() {
                 Ok(castling_state) => b.get_state_mut().castling = castling_state,
-                Err(ParseFenError { description }) => {
-                    return parse_fen_error!("Invalid fen string '{}': {}", s, description)
+                Err(ParseFenError { description, .. }) => {
+                    return parse_fen_error!(Castling, "Invalid fen string '{}': {}", s, description)
                 }
             },
             None => {
-                return parse_fen_error!("Invalid fen string '{}': no castling rights specified", s)
+                return parse_fen_error!(Castling, "Invalid fen string '{}': no castling rights specified", s)
             }
         };
 
@@ -176,21 +664,29 @@ impl FromStr for Board {
             Some("-") => b.get_state_mut().en_passant_file = None,
             Some(square) => match square.parse::<Position>() {
                 Ok(pos) => b.get_state_mut().en_passant_file = Some(pos.file),
-                Err(ParseFenError { description }) => {
-                    return parse_fen_error!("Invalid fen string '{}': {}", s, description)
+                Err(ParseFenError { description, .. }) => {
+                    return parse_fen_error!(EnPassant, "Invalid fen string '{}': {}", s, description)
                 }
             },
             None => {
-                return parse_fen_error!("Invalid fen string '{}': no en-passant file specified", s)
+                return parse_fen_error!(EnPassant, "Invalid fen string '{}': no en-passant file specified", s)
             }
         };
 
+        // remaining checks
+        if variant.counts_checks() {
+            if let Some(checks) = iter.next_if(|f| f.contains('+') && !f.starts_with('+')) {
+                b.get_state_mut().checks = read_checks(s, checks, true)?;
+            }
+        }
+
         // fifty move clock
         match iter.next() {
             Some(count_str) => match count_str.parse::<u8>() {
                 Ok(count) => b.get_state_mut().fifty_move_counter = count,
                 Err(_) => {
                     return parse_fen_error!(
+                        HalfmoveClock,
                         "Invalid fen string '{}': invalid fifty move count '{}'",
                         s,
                         count_str
@@ -199,6 +695,7 @@ impl FromStr for Board {
             },
             None => {
                 return parse_fen_error!(
+                    HalfmoveClock,
                     "Invalid fen string '{}': no fifty move count specified",
                     s
                 )
@@ -209,6 +706,13 @@ impl FromStr for Board {
         // FEN stores the move counter (not the ply count), starting at 1, so we need to adjust a little bit
         match iter.next() {
             Some(count_str) => match count_str.parse::<u32>() {
+                Ok(0) => {
+                    return parse_fen_error!(
+                        FullmoveNumber,
+                        "Invalid fen string '{}': the move count starts at 1",
+                        s
+                    )
+                }
                 Ok(count) => {
                     let mut ply = (count - 1) * 2;
                     if b.current_player == Color::Black {
@@ -219,28 +723,139 @@ impl FromStr for Board {
                 }
                 Err(_) => {
                     return parse_fen_error!(
+                        FullmoveNumber,
                         "Invalid fen string '{}': invalid move count '{}'",
                         s,
                         count_str
                     )
                 }
             },
-            None => return parse_fen_error!("Invalid fen string '{}': no move count specified", s),
+            None => return parse_fen_error!(FullmoveNumber, "Invalid fen string '{}': no move count specified", s),
+        }
+
+        // checks given
+        if variant.counts_checks() {
+            if let Some(checks) = iter.next() {
+                b.get_state_mut().checks = read_checks(s, checks, false)?;
+            }
         }
 
+        b.hash = b.compute_hash();
+        b.get_state_mut().hash = b.hash;
+
         Ok(b)
     }
 }
 
+/// Reads the checks of both sides as `3+2` if they are the remaining checks, or as `+0+1` if
+/// they are the checks given, returning the checks given.
+fn read_checks(fen: &str, s: &str, remaining: bool) -> Result<[u8; 2], ParseFenError> {
+    let counts = if remaining {
+        s.split_once('+')
+    } else {
+        s.strip_prefix('+').and_then(|s| s.split_once('+'))
+    };
+    let count = |c: &str| c.parse::<u8>().ok().filter(|c| *c <= 3);
+
+    match counts.and_then(|(w, b)| Some((count(w)?, count(b)?))) {
+        Some((w, b)) if remaining => Ok([3 - w, 3 - b]),
+        Some((w, b)) => Ok([w, b]),
+        None => parse_fen_error!(Checks, "Invalid fen string '{}': invalid checks '{}'", fen, s),
+    }
+}
+
 impl Board {
+    /// The position as a FEN string, which reads back into the same position.
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.get_piece(&Position::from(rank, file)) {
+                    Some(p) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(p.fen_char());
+                        if self.is_promoted(&Position::from(rank, file)) {
+                            fen.push('~');
+                        }
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+        if self.variant.has_pockets() {
+            fen.push('[');
+            for c in Color::iter() {
+                for t in PieceType::iter() {
+                    for _ in 0..self.pocket(c, t) {
+                        fen.push(Piece::new(c, t).fen_char());
+                    }
+                }
+            }
+            fen.push(']');
+        }
+
+        let (color, ep_rank) = match self.current_player {
+            Color::White => ('w', 5),
+            Color::Black => ('b', 2),
+        };
+        let mut en_passant = match self.en_passant_file() {
+            Some(file) => Position::from(ep_rank, file).to_string(),
+            None => String::from("-"),
+        };
+        if self.variant.counts_checks() {
+            let remaining = |c: Color| 3u8.saturating_sub(self.checks_given(c));
+            en_passant += &format!(" {}+{}", remaining(Color::White), remaining(Color::Black));
+        }
+
+        format!(
+            "{} {} {} {} {} {}",
+            fen,
+            color,
+            self.castling(),
+            en_passant,
+            self.fifty_move_counter(),
+            self.ply / 2 + 1
+        )
+    }
+
     fn read_fen_pos(&mut self, fen_pos: &str) -> Result<(), ParseFenError> {
         let mut rank: u8 = 7;
         let mut file: u8 = 0;
+        let mut last_piece: Option<Position> = None;
 
         for (idx, c) in fen_pos.chars().enumerate() {
+            if c == '~' && self.variant.has_pockets() {
+                // the piece before was promoted
+                match last_piece.take() {
+                    Some(pos) => self.get_state_mut().promoted |= 1 << pos.idx(),
+                    None => {
+                        return parse_fen_error!(
+                            Placement,
+                            "Invalid fen string '{}': '~' without a piece at pos {}",
+                            fen_pos,
+                            idx
+                        )
+                    }
+                }
+                continue;
+            }
+            last_piece = None;
+
             if c == '/' {
                 if file != 8 || rank == 0 {
                     return parse_fen_error!(
+                        Placement,
                         "Invalid fen string '{}': didn't expect '/' at pos {}",
                         fen_pos,
                         idx
@@ -249,10 +864,11 @@ impl Board {
 
                 rank -= 1;
                 file = 0;
-            } else if c.is_digit(10) {
+            } else if c.is_ascii_digit() {
                 let offset = c.to_digit(10).unwrap() as u8;
                 if file + offset > 8 {
                     return parse_fen_error!(
+                        Placement,
                         "Invalid fen string '{}': invalid offset {} at pos {}",
                         fen_pos,
                         offset,
@@ -264,16 +880,19 @@ impl Board {
             } else {
                 if file >= 8 {
                     return parse_fen_error!(
+                        Placement,
                         "Invalid fen string '{}': position goes out of bounds at pos {}",
                         fen_pos,
                         idx
                     );
                 }
 
+                last_piece = Some(Position::from(rank, file));
                 match Piece::from_fen_char(&c) {
                     Some(p) => self.put_piece(&Position::from(rank, file), Some(p)),
                     None => {
                         return parse_fen_error!(
+                            Placement,
                             "Invalid fen string '{}': '{}' at pos {} isn't a fen char",
                             fen_pos,
                             c,
@@ -286,6 +905,35 @@ impl Board {
             }
         }
 
+        if rank != 0 || file != 8 {
+            return parse_fen_error!(
+                Placement,
+                "Invalid fen string '{}': not all squares are described",
+                fen_pos
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Reads the pieces in hand of both sides, e.g. `QRpp`.
+    fn read_pocket(&mut self, pocket: &str) -> Result<(), ParseFenError> {
+        for c in pocket.chars() {
+            match Piece::from_fen_char(&c) {
+                Some(p) if p.piece_type != PieceType::King => {
+                    self.get_state_mut().pockets[p.color as usize][pocket_index(p.piece_type)] += 1
+                }
+                _ => {
+                    return parse_fen_error!(
+                        Pocket,
+                        "Invalid fen string: '{}' in the pocket '{}' isn't a piece in hand",
+                        c,
+                        pocket
+                    )
+                }
+            }
+        }
+
         Ok(())
     }
 }
@@ -338,8 +986,8 @@ mod tests {
 
     mod fen_parsing {
         use crate::board::position::Position;
-        use crate::piece::{Color, Piece};
-        use crate::board::Board;
+        use crate::piece::Color;
+        use crate::board::{Board, FenErrorKind};
 
         #[test]
         fn start_position() {
@@ -428,5 +1076,169 @@ mod tests {
 
             assert_eq!(2, b.get_state().en_passant_file.unwrap());
         }
+
+        #[test]
+        fn write_fen() {
+            assert_eq!(Board::STARTING_FEN, Board::new().to_fen());
+
+            for fen in [
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+                "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40",
+                "4k3/8/8/8/8/8/8/4K2R b K - 3 61",
+            ] {
+                assert_eq!(fen, fen.parse::<Board>().unwrap().to_fen());
+            }
+
+            let b = Board::from_fen_and_moves(Board::STARTING_FEN, ["e2e4", "c7c5", "g1f3"]).unwrap();
+            assert_eq!(
+                "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+                b.to_fen()
+            );
+        }
+
+        #[test]
+        fn halfmove_clock_saturates() {
+            let b = Board::from_fen_and_moves("4k3/8/8/8/8/8/8/4K3 w - - 255 200", ["e1d1"]).unwrap();
+            assert_eq!(255, b.fifty_move_counter());
+        }
+
+        #[test]
+        fn error_kinds() {
+            let kind = |fen: &str| fen.parse::<Board>().unwrap_err().kind();
+
+            assert_eq!(FenErrorKind::Placement, kind(""));
+            assert_eq!(FenErrorKind::Placement, kind("8/8/8/8/8/8/8 w - - 0 1"));
+            assert_eq!(FenErrorKind::Placement, kind("8/8/8/8/8/8/8/7X w - - 0 1"));
+            assert_eq!(FenErrorKind::ActiveColor, kind("8/8/8/8/8/8/8/8 x - - 0 1"));
+            assert_eq!(FenErrorKind::Castling, kind("8/8/8/8/8/8/8/8 w KX - 0 1"));
+            assert_eq!(FenErrorKind::EnPassant, kind("8/8/8/8/8/8/8/8 w - e9 0 1"));
+            assert_eq!(FenErrorKind::HalfmoveClock, kind("8/8/8/8/8/8/8/8 w - - x 1"));
+            assert_eq!(FenErrorKind::FullmoveNumber, kind("8/8/8/8/8/8/8/8 w - - 0 0"));
+            assert_eq!(FenErrorKind::FullmoveNumber, kind("8/8/8/8/8/8/8/8 w - - 0"));
+
+            let err = "8/8/8/8/8/8/8/8 w - - 0 0".parse::<Board>().unwrap_err();
+            assert_eq!(err.description(), err.to_string());
+        }
+    }
+
+    mod hashing {
+        use crate::board::Board;
+        use crate::chess_move::Move;
+
+        fn play(b: &mut Board, uci: &str) -> Move {
+            let m = *b
+                .legal_moves()
+                .iter()
+                .find(|m| m.to_string() == uci)
+                .unwrap();
+            b.make_move(m);
+            m
+        }
+
+        fn check_tree(b: &mut Board, depth: u32) {
+            assert_eq!(b.compute_hash(), b.hash());
+            if depth == 0 {
+                return;
+            }
+
+            for &m in &b.legal_moves() {
+                let before = b.hash();
+                b.make_move(m);
+                check_tree(b, depth - 1);
+                b.unmake_move(m);
+                assert_eq!(before, b.hash());
+            }
+        }
+
+        #[test]
+        fn incremental_matches_computed() {
+            let mut b: Board =
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"
+                    .parse()
+                    .unwrap();
+            check_tree(&mut b, 2);
+
+            let mut b: Board = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"
+                .parse()
+                .unwrap();
+            check_tree(&mut b, 2);
+        }
+
+        #[test]
+        fn transpositions() {
+            let mut a = Board::new();
+            let start = a.hash();
+            for m in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                play(&mut a, m);
+            }
+            assert_eq!(start, a.hash());
+
+            let mut a = Board::new();
+            let mut b = Board::new();
+            for m in ["e2e3", "e7e6", "d2d3"] {
+                play(&mut a, m);
+            }
+            for m in ["d2d3", "e7e6", "e2e3"] {
+                play(&mut b, m);
+            }
+            assert_eq!(a.hash(), b.hash());
+
+            // the same pieces, but only one of them allows en passant
+            let mut b: Board = "rnbqkbnr/ppp1pppp/8/8/3p4/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+                .parse()
+                .unwrap();
+            play(&mut b, "e2e4");
+            let with_ep: Board = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+                .parse()
+                .unwrap();
+            let without_ep: Board = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+                .parse()
+                .unwrap();
+            assert_eq!(with_ep.hash(), b.hash());
+            assert_ne!(without_ep.hash(), b.hash());
+
+            // without a pawn to capture it, the double push doesn't create an en passant square
+            let mut b = Board::new();
+            play(&mut b, "e2e4");
+            assert_eq!(None, b.en_passant_file());
+        }
+    }
+
+    mod null_move {
+        use crate::board::Board;
+        use crate::piece::Color;
+
+        #[test]
+        fn passes_turn() {
+            // 1. e4, black could capture en passant on e3 if it was there
+            let mut b: Board = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+                .parse()
+                .unwrap();
+            let hash = b.hash();
+
+            assert!(b.make_null_move());
+            assert_eq!(Color::White, b.current_player());
+            assert_eq!(None, b.en_passant_file());
+            assert_eq!(2, b.ply());
+            assert_eq!(b.compute_hash(), b.hash());
+            assert_ne!(hash, b.hash());
+
+            b.unmake_null_move();
+            assert_eq!(Color::Black, b.current_player());
+            assert_eq!(Some(4), b.en_passant_file());
+            assert_eq!(1, b.ply());
+            assert_eq!(hash, b.hash());
+        }
+
+        #[test]
+        fn refused_in_check() {
+            let mut b: Board = "4k3/8/8/8/8/8/8/4K2r w - - 0 1".parse().unwrap();
+            let hash = b.hash();
+
+            assert!(!b.make_null_move());
+            assert_eq!(Color::White, b.current_player());
+            assert_eq!(hash, b.hash());
+        }
     }
 }
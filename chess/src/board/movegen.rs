@@ -0,0 +1,820 @@
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::chess_move::{Move, MoveKind, MoveList};
+use crate::piece::{Color, Piece, PieceType};
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+
+pub(super) const KING_OFFSETS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+/// Selects which subset of the pseudo-legal moves gets generated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GenType {
+    /// Every pseudo-legal move.
+    All,
+    /// Captures (including en passant) and promotions.
+    Tactical,
+    /// Everything not generated by `Tactical`, castling included.
+    Quiet,
+    /// Moves that may resolve a check: king moves, and if there is only one checker, captures of
+    /// the checker and interpositions. Same as `All` if the side to move isn't in check.
+    Evasions,
+}
+
+impl Board {
+    pub fn is_square_attacked(&self, pos: &Position, by: Color) -> bool {
+        let is = |p: Option<Position>, types: &[PieceType]| match p.and_then(|p| self.get_piece(&p))
+        {
+            Some(piece) => piece.color == by && types.contains(&piece.piece_type),
+            None => false,
+        };
+
+        // a pawn of color `by` attacks `pos` if it stands one rank behind it, seen from `by`
+        let dir = -by.pawn_direction();
+        if is(pos.offset(dir, -1), &[PieceType::Pawn]) || is(pos.offset(dir, 1), &[PieceType::Pawn])
+        {
+            return true;
+        }
+
+        if KNIGHT_OFFSETS
+            .iter()
+            .any(|&(dr, df)| is(pos.offset(dr, df), &[PieceType::Knight]))
+        {
+            return true;
+        }
+
+        if KING_OFFSETS
+            .iter()
+            .any(|&(dr, df)| is(pos.offset(dr, df), &[PieceType::King]))
+        {
+            return true;
+        }
+
+        let rook_like = [PieceType::Rook, PieceType::Queen];
+        let bishop_like = [PieceType::Bishop, PieceType::Queen];
+
+        ROOK_DIRECTIONS
+            .iter()
+            .any(|&dir| is(self.first_piece_on_ray(pos, dir), &rook_like))
+            || BISHOP_DIRECTIONS
+                .iter()
+                .any(|&dir| is(self.first_piece_on_ray(pos, dir), &bishop_like))
+    }
+
+    pub fn in_check(&self) -> bool {
+        self.variant.in_check(self, self.current_player)
+    }
+
+    /// The squares of the opponent's pieces giving check to the side to move, as a bitboard
+    /// with bit 0 for a1 and bit 63 for h8.
+    pub fn checkers(&self) -> u64 {
+        let king = match self.king_position(self.current_player) {
+            Some(king) => king,
+            None => return 0,
+        };
+        let them = self.current_player.opponent();
+
+        Position::iter()
+            .filter(|pos| match self.get_piece(pos) {
+                Some(p) if p.color == them => self.attacks(pos, &p, &king),
+                _ => false,
+            })
+            .fold(0, |checkers, pos| checkers | 1 << pos.idx())
+    }
+
+    /// Generates the pseudo-legal moves of the given type, i.e. moves that follow the movement
+    /// rules but might leave the own king in check.
+    pub fn generate_pseudo_legal(&self, gen: GenType, list: &mut MoveList) {
+        if gen == GenType::Evasions && self.variant.has_standard_evasions() && self.in_check() {
+            self.generate_evasions(list);
+            return;
+        }
+
+        let us = self.current_player;
+
+        for t in PieceType::iter() {
+            for from in self.piece_list.pos_of_piece(&Piece::new(us, t)) {
+                self.generate_piece_moves(t, &from, gen, list);
+            }
+        }
+
+        if matches!(gen, GenType::All | GenType::Quiet | GenType::Evasions) {
+            self.generate_castling(list);
+            self.generate_drops(list);
+        }
+    }
+
+    /// Generates the legal moves of the given type, none if the variant's rules ended the game.
+    pub fn generate_legal(&mut self, gen: GenType, list: &mut MoveList) {
+        if self.variant.outcome(self).is_some() {
+            return;
+        }
+
+        let start = list.len();
+        self.generate_pseudo_legal(gen, list);
+
+        let mut idx = start;
+        while idx < list.len() {
+            if self.is_legal(list[idx]) {
+                idx += 1;
+            } else {
+                list.swap_remove(idx);
+            }
+        }
+        self.variant.filter_legal_moves(list);
+    }
+
+    /// All legal moves in the current position.
+    pub fn legal_moves(&mut self) -> MoveList {
+        let mut list = MoveList::new();
+        self.generate_legal(GenType::Evasions, &mut list);
+        list
+    }
+
+    /// Checks whether a pseudo-legal move leaves the own king safe, or whatever else the variant
+    /// requires.
+    pub fn is_legal(&mut self, m: Move) -> bool {
+        let us = self.current_player;
+
+        self.make_move(m);
+        let legal = self.variant.is_legal_after(self, us);
+        self.unmake_move(m);
+
+        legal
+    }
+
+    /// Whether `m` is one of the pseudo-legal moves, e.g. to check a move from the transposition
+    /// table before trying it. Only the moves of the piece on `m.from` get generated.
+    pub fn is_pseudo_legal(&self, m: Move) -> bool {
+        let mut list = MoveList::new();
+
+        match m.kind {
+            MoveKind::Drop(_) => self.generate_drops(&mut list),
+            MoveKind::CastleShort | MoveKind::CastleLong => self.generate_castling(&mut list),
+            _ => match self.get_piece(&m.from) {
+                Some(p) if p.color == self.current_player => {
+                    self.generate_piece_moves(p.piece_type, &m.from, GenType::All, &mut list)
+                }
+                _ => return false,
+            },
+        }
+
+        list.contains(&m)
+    }
+
+    /// Counts the leaf nodes of the legal move tree of the given depth.
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        let mut nodes = 0;
+        for &m in &moves {
+            self.make_move(m);
+            nodes += self.perft(depth - 1);
+            self.unmake_move(m);
+        }
+
+        nodes
+    }
+
+    fn first_piece_on_ray(&self, from: &Position, (dr, df): (i8, i8)) -> Option<Position> {
+        let mut pos = *from;
+
+        while let Some(next) = pos.offset(dr, df) {
+            if self.get_piece(&next).is_some() {
+                return Some(next);
+            }
+            pos = next;
+        }
+
+        None
+    }
+
+    /// Whether `piece` standing on `from` attacks `target`.
+    fn attacks(&self, from: &Position, piece: &Piece, target: &Position) -> bool {
+        let dr = target.rank as i8 - from.rank as i8;
+        let df = target.file as i8 - from.file as i8;
+        let ray = |directions: &[(i8, i8)]| {
+            let dir = (dr.signum(), df.signum());
+            (dr == 0 || df == 0 || dr.abs() == df.abs())
+                && directions.contains(&dir)
+                && self.first_piece_on_ray(from, dir) == Some(*target)
+        };
+
+        match piece.piece_type {
+            PieceType::Pawn => dr == piece.color.pawn_direction() && df.abs() == 1,
+            PieceType::Knight => KNIGHT_OFFSETS.contains(&(dr, df)),
+            PieceType::King => KING_OFFSETS.contains(&(dr, df)),
+            PieceType::Bishop => ray(&BISHOP_DIRECTIONS),
+            PieceType::Rook => ray(&ROOK_DIRECTIONS),
+            PieceType::Queen => ray(&BISHOP_DIRECTIONS) || ray(&ROOK_DIRECTIONS),
+        }
+    }
+
+    fn generate_evasions(&self, list: &mut MoveList) {
+        let us = self.current_player;
+        let king = match self.king_position(us) {
+            Some(king) => king,
+            None => return,
+        };
+
+        self.generate_step_moves(&king, &KING_OFFSETS, GenType::All, list);
+
+        let checkers = self.checkers();
+        // in double check only the king can move
+        if checkers.count_ones() != 1 {
+            return;
+        }
+        let checker = <Position as From<usize>>::from(checkers.trailing_zeros() as usize);
+
+        // with a single checker, the other pieces can only capture it or block its ray
+        let mut targets: u64 = 1 << checker.idx();
+        if matches!(
+            self.get_piece(&checker).map(|p| p.piece_type),
+            Some(PieceType::Bishop | PieceType::Rook | PieceType::Queen)
+        ) {
+            let dr = (checker.rank as i8 - king.rank as i8).signum();
+            let df = (checker.file as i8 - king.file as i8).signum();
+            let mut pos = king.offset(dr, df);
+
+            while let Some(p) = pos.filter(|p| *p != checker) {
+                targets |= 1 << p.idx();
+                pos = p.offset(dr, df);
+            }
+        }
+
+        let start = list.len();
+        for t in PieceType::iter().filter(|t| *t != PieceType::King) {
+            for from in self.piece_list.pos_of_piece(&Piece::new(us, t)) {
+                self.generate_piece_moves(t, &from, GenType::All, list);
+            }
+        }
+        self.generate_drops(list);
+
+        let mut idx = start;
+        while idx < list.len() {
+            let m = list[idx];
+            let captured = if m.kind == MoveKind::EnPassant {
+                Position::from(m.from.rank, m.to.file)
+            } else {
+                m.to
+            };
+
+            if targets & (1 << m.to.idx() | 1 << captured.idx()) != 0 {
+                idx += 1;
+            } else {
+                list.swap_remove(idx);
+            }
+        }
+    }
+
+    fn generate_piece_moves(
+        &self,
+        t: PieceType,
+        from: &Position,
+        gen: GenType,
+        list: &mut MoveList,
+    ) {
+        match t {
+            PieceType::Pawn => self.generate_pawn_moves(from, gen, list),
+            PieceType::Knight => self.generate_step_moves(from, &KNIGHT_OFFSETS, gen, list),
+            PieceType::King => self.generate_step_moves(from, &KING_OFFSETS, gen, list),
+            PieceType::Bishop => self.generate_slider_moves(from, &BISHOP_DIRECTIONS, gen, list),
+            PieceType::Rook => self.generate_slider_moves(from, &ROOK_DIRECTIONS, gen, list),
+            PieceType::Queen => {
+                self.generate_slider_moves(from, &ROOK_DIRECTIONS, gen, list);
+                self.generate_slider_moves(from, &BISHOP_DIRECTIONS, gen, list);
+            }
+        }
+    }
+
+    fn push_pawn_move(&self, m: Move, list: &mut MoveList) {
+        let last_rank = self.current_player.opponent().back_rank();
+
+        if m.to.rank == last_rank {
+            for &t in self.variant.promotion_types() {
+                list.push(Move::promotion(m.from, m.to, m.kind, t));
+            }
+        } else {
+            list.push(m);
+        }
+    }
+
+    fn generate_pawn_moves(&self, from: &Position, gen: GenType, list: &mut MoveList) {
+        let us = self.current_player;
+        let dir = us.pawn_direction();
+        let last_rank = us.opponent().back_rank();
+
+        let tactical = gen != GenType::Quiet;
+        let quiet = gen != GenType::Tactical;
+
+        if let Some(to) = from
+            .offset(dir, 0)
+            .filter(|to| self.get_piece(to).is_none())
+        {
+            // promotions count as tactical moves even without a capture
+            if (to.rank == last_rank && tactical) || (to.rank != last_rank && quiet) {
+                self.push_pawn_move(Move::new(*from, to, MoveKind::Quiet), list);
+            }
+
+            if quiet && self.variant.is_double_push_rank(us, from.rank) {
+                if let Some(to) = to.offset(dir, 0).filter(|to| self.get_piece(to).is_none()) {
+                    list.push(Move::new(*from, to, MoveKind::DoublePawnPush));
+                }
+            }
+        }
+
+        if !tactical {
+            return;
+        }
+
+        for df in [-1, 1] {
+            let to = match from.offset(dir, df) {
+                Some(to) => to,
+                None => continue,
+            };
+
+            match self.get_piece(&to) {
+                Some(p) if p.color != us => {
+                    self.push_pawn_move(Move::new(*from, to, MoveKind::Capture), list)
+                }
+                None if self.en_passant_file() == Some(to.file)
+                    && to.rank == (us.opponent().back_rank() as i8 - 2 * dir) as u8 =>
+                {
+                    list.push(Move::new(*from, to, MoveKind::EnPassant))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn generate_step_moves(
+        &self,
+        from: &Position,
+        offsets: &[(i8, i8)],
+        gen: GenType,
+        list: &mut MoveList,
+    ) {
+        for &(dr, df) in offsets {
+            if let Some(to) = from.offset(dr, df) {
+                self.push_move_to(from, &to, gen, list);
+            }
+        }
+    }
+
+    fn generate_slider_moves(
+        &self,
+        from: &Position,
+        directions: &[(i8, i8)],
+        gen: GenType,
+        list: &mut MoveList,
+    ) {
+        for &(dr, df) in directions {
+            let mut pos = *from;
+
+            while let Some(to) = pos.offset(dr, df) {
+                if !self.push_move_to(from, &to, gen, list) {
+                    break;
+                }
+                pos = to;
+            }
+        }
+    }
+
+    /// Pushes the move to `to` if it is of the requested type.
+    /// Returns whether `to` was empty, i.e. a slider may continue past it.
+    fn push_move_to(
+        &self,
+        from: &Position,
+        to: &Position,
+        gen: GenType,
+        list: &mut MoveList,
+    ) -> bool {
+        match self.get_piece(to) {
+            None => {
+                if gen != GenType::Tactical {
+                    list.push(Move::new(*from, *to, MoveKind::Quiet));
+                }
+                true
+            }
+            Some(p) => {
+                if p.color != self.current_player && gen != GenType::Quiet {
+                    list.push(Move::new(*from, *to, MoveKind::Capture));
+                }
+                false
+            }
+        }
+    }
+
+    /// Drops of the pieces in hand on the empty squares, pawns not on the first or last rank.
+    fn generate_drops(&self, list: &mut MoveList) {
+        if !self.variant.has_pockets() {
+            return;
+        }
+        let us = self.current_player;
+
+        for t in PieceType::iter().filter(|t| self.pocket(us, *t) > 0) {
+            for to in Position::iter().filter(|pos| self.get_piece(pos).is_none()) {
+                if t != PieceType::Pawn || (1..7).contains(&to.rank) {
+                    list.push(Move::drop(t, to));
+                }
+            }
+        }
+    }
+
+    fn generate_castling(&self, list: &mut MoveList) {
+        let us = self.current_player;
+        let them = us.opponent();
+        let rank = us.back_rank();
+        let king = Position::from(rank, 4);
+        let castling = self.get_state().castling;
+
+        if self.get_piece(&king) != Some(Piece::new(us, PieceType::King))
+            || self.is_square_attacked(&king, them)
+        {
+            return;
+        }
+
+        let rook = Some(Piece::new(us, PieceType::Rook));
+        let empty = |files: &[u8]| {
+            files
+                .iter()
+                .all(|&f| self.get_piece(&Position::from(rank, f)).is_none())
+        };
+        let safe = |files: &[u8]| {
+            files
+                .iter()
+                .all(|&f| !self.is_square_attacked(&Position::from(rank, f), them))
+        };
+
+        if castling.short(us)
+            && self.get_piece(&Position::from(rank, 7)) == rook
+            && empty(&[5, 6])
+            && safe(&[5, 6])
+        {
+            list.push(Move::new(
+                king,
+                Position::from(rank, 6),
+                MoveKind::CastleShort,
+            ));
+        }
+
+        if castling.long(us)
+            && self.get_piece(&Position::from(rank, 0)) == rook
+            && empty(&[1, 2, 3])
+            && safe(&[2, 3])
+        {
+            list.push(Move::new(
+                king,
+                Position::from(rank, 2),
+                MoveKind::CastleLong,
+            ));
+        }
+    }
+}
+
+/// Hands out the legal moves of a position in stages: the transposition table move, captures and
+/// promotions, then the quiet moves. When the side to move is in check the evasions take the
+/// place of the captures and quiet moves, and variants that restrict the legal moves, like
+/// Antichess, get all of them in one stage.
+///
+/// Each stage is generated lazily, so a search that cuts off after the first moves never pays for
+/// the quiet moves. Within a stage the moves come in the order of the key passed to `next_by`.
+pub struct StagedMoveGen {
+    stage: Stage,
+    moves: MoveList,
+    keys: [i32; MoveList::CAPACITY],
+    idx: usize,
+    legal: bool,
+    /// Whether the legality of `moves` was checked when they were generated.
+    checked: bool,
+    tt_move: Option<Move>,
+    after_tt_move: Stage,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Stage {
+    TtMove,
+    Tactical,
+    Quiet,
+    Evasions,
+    All,
+    Done,
+}
+
+impl StagedMoveGen {
+    /// A generator handing out legal moves only.
+    pub fn legal(board: &Board) -> Self {
+        Self::with_legality(board, true)
+    }
+
+    /// A generator handing out pseudo-legal moves, the caller has to check the king's safety.
+    pub fn pseudo_legal(board: &Board) -> Self {
+        Self::with_legality(board, false)
+    }
+
+    fn with_legality(board: &Board, legal: bool) -> Self {
+        let stage = if board.variant.outcome(board).is_some() {
+            Stage::Done
+        } else if board.variant.restricts_moves() {
+            Stage::All
+        } else if board.in_check() {
+            Stage::Evasions
+        } else {
+            Stage::Tactical
+        };
+
+        StagedMoveGen {
+            stage,
+            moves: MoveList::new(),
+            keys: [0; MoveList::CAPACITY],
+            idx: 0,
+            legal,
+            checked: false,
+            tt_move: None,
+            after_tt_move: stage,
+        }
+    }
+
+    /// Hands out `m` first if it's a legal move, without generating the other moves for it.
+    pub fn with_tt_move(mut self, m: Option<Move>) -> Self {
+        if m.is_some() && matches!(self.stage, Stage::Tactical | Stage::Evasions) {
+            self.tt_move = m;
+            self.stage = Stage::TtMove;
+        }
+        self
+    }
+
+    pub fn next(&mut self, board: &mut Board) -> Option<Move> {
+        self.next_by(board, &|_, _| 0)
+    }
+
+    /// The next move, the moves of each stage ordered by `key` from highest to lowest.
+    pub fn next_by(
+        &mut self,
+        board: &mut Board,
+        key: &impl Fn(&Board, &Move) -> i32,
+    ) -> Option<Move> {
+        loop {
+            while self.idx < self.moves.len() {
+                let best = (self.idx..self.moves.len())
+                    .max_by_key(|&j| self.keys[j])
+                    .unwrap_or(self.idx);
+                self.moves.swap(self.idx, best);
+                self.keys.swap(self.idx, best);
+                let m = self.moves[self.idx];
+                self.idx += 1;
+
+                // the transposition table move was handed out already
+                if Some(m) != self.tt_move && (!self.legal || self.checked || board.is_legal(m)) {
+                    return Some(m);
+                }
+            }
+
+            let gen = match self.stage {
+                Stage::TtMove => {
+                    self.stage = self.after_tt_move;
+                    match self.tt_move {
+                        Some(m)
+                            if board.is_pseudo_legal(m) && (!self.legal || board.is_legal(m)) =>
+                        {
+                            return Some(m)
+                        }
+                        _ => continue,
+                    }
+                }
+                Stage::Tactical => {
+                    self.stage = Stage::Quiet;
+                    GenType::Tactical
+                }
+                Stage::Quiet => {
+                    self.stage = Stage::Done;
+                    GenType::Quiet
+                }
+                Stage::Evasions => {
+                    self.stage = Stage::Done;
+                    GenType::Evasions
+                }
+                Stage::All => {
+                    self.stage = Stage::Done;
+                    GenType::All
+                }
+                Stage::Done => return None,
+            };
+
+            self.moves.clear();
+            self.idx = 0;
+            // the variant's restrictions only apply to the complete list of legal moves
+            self.checked = gen == GenType::All && self.legal;
+            if self.checked {
+                board.generate_legal(gen, &mut self.moves);
+            } else {
+                board.generate_pseudo_legal(gen, &mut self.moves);
+            }
+            for (i, m) in self.moves.iter().enumerate() {
+                self.keys[i] = key(board, m);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::movegen::{GenType, StagedMoveGen};
+    use crate::board::position::Position;
+    use crate::board::Board;
+    use crate::chess_move::{Move, MoveKind, MoveList};
+    use crate::piece::PieceType;
+    use crate::variant::Antichess;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    fn perft(fen: &str, depth: u32) -> u64 {
+        let mut b: Board = fen.parse().unwrap();
+        b.perft(depth)
+    }
+
+    #[test]
+    fn perft_start_position() {
+        let mut b = Board::new();
+
+        assert_eq!(20, b.perft(1));
+        assert_eq!(400, b.perft(2));
+        assert_eq!(8_902, b.perft(3));
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        assert_eq!(48, perft(KIWIPETE, 1));
+        assert_eq!(2_039, perft(KIWIPETE, 2));
+        assert_eq!(97_862, perft(KIWIPETE, 3));
+    }
+
+    #[test]
+    fn perft_endgame() {
+        let fen = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+
+        assert_eq!(14, perft(fen, 1));
+        assert_eq!(191, perft(fen, 2));
+        assert_eq!(2_812, perft(fen, 3));
+        assert_eq!(43_238, perft(fen, 4));
+    }
+
+    #[test]
+    fn perft_promotions() {
+        let fen = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+
+        assert_eq!(6, perft(fen, 1));
+        assert_eq!(264, perft(fen, 2));
+        assert_eq!(9_467, perft(fen, 3));
+    }
+
+    #[test]
+    fn perft_castling_into_check() {
+        let fen = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+
+        assert_eq!(44, perft(fen, 1));
+        assert_eq!(1_486, perft(fen, 2));
+        assert_eq!(62_379, perft(fen, 3));
+    }
+
+    #[test]
+    fn make_unmake_restores_position() {
+        let mut b: Board = KIWIPETE.parse().unwrap();
+        let before = b.to_string();
+
+        for &m in &b.legal_moves() {
+            b.make_move(m);
+            b.unmake_move(m);
+            assert_eq!(before, b.to_string(), "after {}", m);
+        }
+    }
+
+    #[test]
+    fn tactical_and_quiet_partition_all() {
+        let b: Board = KIWIPETE.parse().unwrap();
+        let mut all = MoveList::new();
+        let mut tactical = MoveList::new();
+        let mut quiet = MoveList::new();
+
+        b.generate_pseudo_legal(GenType::All, &mut all);
+        b.generate_pseudo_legal(GenType::Tactical, &mut tactical);
+        b.generate_pseudo_legal(GenType::Quiet, &mut quiet);
+
+        assert_eq!(all.len(), tactical.len() + quiet.len());
+        assert!(tactical.iter().all(|m| m.is_tactical()));
+        assert!(quiet.iter().all(|m| !m.is_tactical()));
+    }
+
+    #[test]
+    fn evasions() {
+        // the rook on e8 checks the king, only the bishop blocking and king moves help
+        let mut b: Board = "4r2k/8/8/8/2B5/8/3P1P2/4K3 w - - 0 1".parse().unwrap();
+        assert!(b.in_check());
+
+        let mut evasions = MoveList::new();
+        b.generate_legal(GenType::Evasions, &mut evasions);
+
+        let mut names: Vec<String> = evasions.iter().map(|m| m.to_string()).collect();
+        names.sort();
+        assert_eq!(vec!["c4e2", "c4e6", "e1d1", "e1f1"], names);
+    }
+
+    #[test]
+    fn staged_generation() {
+        let mut b: Board = KIWIPETE.parse().unwrap();
+        let mut gen = StagedMoveGen::legal(&b);
+        let mut moves = Vec::new();
+
+        while let Some(m) = gen.next(&mut b) {
+            moves.push(m);
+        }
+
+        assert_eq!(48, moves.len());
+        let first_quiet = moves.iter().position(|m| !m.is_tactical()).unwrap();
+        assert!(moves[first_quiet..].iter().all(|m| !m.is_tactical()));
+    }
+
+    #[test]
+    fn staged_tt_move() {
+        let mut b: Board = KIWIPETE.parse().unwrap();
+        let castle = b.parse_uci_move("e1g1");
+        let mut gen = StagedMoveGen::legal(&b).with_tt_move(castle);
+        let mut moves = Vec::new();
+        while let Some(m) = gen.next(&mut b) {
+            moves.push(m);
+        }
+        assert_eq!(castle, moves.first().copied());
+        assert_eq!(48, moves.len());
+
+        // a move of the opponent is no legal TT move
+        let theirs = Move::new(Position::from(6, 0), Position::from(5, 0), MoveKind::Quiet);
+        assert!(!b.is_pseudo_legal(theirs));
+        let mut gen = StagedMoveGen::legal(&b).with_tt_move(Some(theirs));
+        let mut count = 0;
+        while let Some(m) = gen.next(&mut b) {
+            assert_ne!(theirs, m);
+            count += 1;
+        }
+        assert_eq!(48, count);
+    }
+
+    #[test]
+    fn staged_ordering_and_variants() {
+        let mut b: Board = KIWIPETE.parse().unwrap();
+        let mut gen = StagedMoveGen::legal(&b);
+        // e2a6 is the only capture by a bishop
+        let first = gen.next_by(&mut b, &|b, m| {
+            b.get_piece(&m.from)
+                .map_or(0, |p| (p.piece_type == PieceType::Bishop) as i32)
+        });
+        assert_eq!("e2a6", first.unwrap().to_string());
+
+        // the forced captures of Antichess apply
+        let mut b =
+            Board::from_fen_variant("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", &Antichess).unwrap();
+        let mut gen = StagedMoveGen::legal(&b);
+        assert_eq!("e4d5", gen.next(&mut b).unwrap().to_string());
+        assert_eq!(None, gen.next(&mut b));
+    }
+
+    #[test]
+    fn checkers() {
+        let b: Board = KIWIPETE.parse().unwrap();
+        assert_eq!(0, b.checkers());
+
+        // the knight on d3 and the rook on e8 both check
+        let b: Board = "4r2k/8/8/8/8/3n4/8/4K3 w - - 0 1".parse().unwrap();
+        assert_eq!(
+            1 << Position::from(2, 3).idx() | 1 << Position::from(7, 4).idx(),
+            b.checkers()
+        );
+    }
+}
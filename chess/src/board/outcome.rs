@@ -0,0 +1,218 @@
+use crate::board::Board;
+use crate::piece::{Color, Piece, PieceType};
+use std::fmt::{Display, Formatter};
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum WinReason {
+    Checkmate,
+    /// The loser's flag fell.
+    TimeForfeit,
+    /// The winner's king reached the center (King of the Hill).
+    KingInCenter,
+    /// The winner gave the third check (Three-check).
+    ThirdCheck,
+    /// All pieces of the loser were captured (Horde).
+    AllPiecesCaptured,
+    /// The loser's king was blown up by a capture next to it (Atomic).
+    KingExploded,
+    /// The winner lost all pieces (Antichess).
+    AllPiecesLost,
+    /// The winner has no legal moves (Antichess).
+    Stalemated,
+    /// The endgame tablebase shows a forced win.
+    Tablebase,
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DrawReason {
+    Stalemate,
+    InsufficientMaterial,
+    /// The same position occurred five times, no claim needed.
+    FivefoldRepetition,
+    /// 75 moves of each player without a capture or pawn move, no claim needed.
+    SeventyFiveMoveRule,
+    /// A flag fell, but the opponent could never have checkmated.
+    TimeoutVsInsufficientMaterial,
+    /// The endgame tablebase shows that neither side can force a win.
+    Tablebase,
+}
+
+/// How a game ended.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Outcome {
+    Win { winner: Color, reason: WinReason },
+    Draw(DrawReason),
+}
+
+impl Outcome {
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            Outcome::Win { winner, .. } => Some(*winner),
+            Outcome::Draw(_) => None,
+        }
+    }
+}
+
+/// Prints the result as written in PGN, e.g. `1-0`.
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.winner() {
+            Some(Color::White) => write!(f, "1-0"),
+            Some(Color::Black) => write!(f, "0-1"),
+            None => write!(f, "1/2-1/2"),
+        }
+    }
+}
+
+impl Board {
+    /// The outcome of the game if it ended by the rules alone, i.e. without a claim, resignation
+    /// or agreement, or if the attached tablebase settles it.
+    pub fn outcome(&mut self) -> Option<Outcome> {
+        if let Some(outcome) = self.variant.outcome(self) {
+            return Some(outcome);
+        }
+        if self.legal_moves().is_empty() {
+            return Some(self.variant.outcome_without_moves(self));
+        }
+
+        if self.is_insufficient_material() {
+            Some(Outcome::Draw(DrawReason::InsufficientMaterial))
+        } else if self.repetition_count() >= 5 {
+            Some(Outcome::Draw(DrawReason::FivefoldRepetition))
+        } else if self.get_state().fifty_move_counter >= 150 {
+            Some(Outcome::Draw(DrawReason::SeventyFiveMoveRule))
+        } else {
+            #[cfg(feature = "syzygy")]
+            if let Some(outcome) = self.tablebase_outcome() {
+                return Some(outcome);
+            }
+            None
+        }
+    }
+
+    /// The outcome when `flagged` runs out of time: a loss, unless the opponent lacks the
+    /// material to ever checkmate.
+    pub fn outcome_on_time(&self, flagged: Color) -> Outcome {
+        if self.has_insufficient_material(flagged.opponent()) {
+            Outcome::Draw(DrawReason::TimeoutVsInsufficientMaterial)
+        } else {
+            Outcome::Win {
+                winner: flagged.opponent(),
+                reason: WinReason::TimeForfeit,
+            }
+        }
+    }
+
+    /// Whether neither side can win anymore by the rules of the variant.
+    pub fn is_insufficient_material(&self) -> bool {
+        self.variant.is_insufficient_material(self)
+    }
+
+    /// Whether neither side can possibly checkmate: only kings and at most one minor piece, or
+    /// only bishops that all stand on squares of the same color.
+    pub fn lacks_mating_material(&self) -> bool {
+        let count = |t: PieceType| {
+            Color::iter()
+                .map(|c| self.piece_list.pos_of_piece(&Piece::new(c, t)).count())
+                .sum::<usize>()
+        };
+
+        if count(PieceType::Pawn) + count(PieceType::Rook) + count(PieceType::Queen) > 0 {
+            return false;
+        }
+        if count(PieceType::Knight) + count(PieceType::Bishop) <= 1 {
+            return true;
+        }
+        if count(PieceType::Knight) > 0 {
+            return false;
+        }
+
+        let mut bishop_square_colors = Color::iter()
+            .flat_map(|c| {
+                self.piece_list
+                    .pos_of_piece(&Piece::new(c, PieceType::Bishop))
+            })
+            .map(|pos| (pos.rank + pos.file) % 2);
+        let first = bishop_square_colors.next();
+        bishop_square_colors.all(|c| Some(c) == first)
+    }
+
+    /// Whether `color` lacks the material to ever checkmate, no matter what the opponent has:
+    /// a lone king, or a king with a single minor piece.
+    pub fn has_insufficient_material(&self, color: Color) -> bool {
+        let count = |t: PieceType| self.piece_list.pos_of_piece(&Piece::new(color, t)).count();
+
+        count(PieceType::Pawn) + count(PieceType::Rook) + count(PieceType::Queen) == 0
+            && count(PieceType::Knight) + count(PieceType::Bishop) <= 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::outcome::{DrawReason, Outcome, WinReason};
+    use crate::board::Board;
+    use crate::piece::Color;
+
+    fn outcome(fen: &str) -> Option<Outcome> {
+        fen.parse::<Board>().unwrap().outcome()
+    }
+
+    #[test]
+    fn checkmate() {
+        // fool's mate
+        let o = outcome("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+
+        assert_eq!(
+            Outcome::Win {
+                winner: Color::Black,
+                reason: WinReason::Checkmate
+            },
+            o
+        );
+        assert_eq!("0-1", o.to_string());
+    }
+
+    #[test]
+    fn stalemate() {
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::Stalemate)),
+            outcome("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")
+        );
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let draw = Some(Outcome::Draw(DrawReason::InsufficientMaterial));
+
+        assert_eq!(draw, outcome("8/8/4k3/8/8/3K4/8/8 w - - 0 1"));
+        assert_eq!(draw, outcome("8/8/4k3/8/8/3KN3/8/8 w - - 0 1"));
+        // all bishops on dark squares
+        assert_eq!(draw, outcome("8/8/4kb2/8/8/3KB3/8/8 w - - 0 1"));
+        assert_eq!(None, outcome("8/8/4k1b1/8/8/3KB3/8/8 w - - 0 1"));
+        assert_eq!(None, outcome("8/8/4kn2/8/8/3KN3/8/8 w - - 0 1"));
+        assert_eq!(None, outcome("8/8/4k3/8/8/3KP3/8/8 w - - 0 1"));
+    }
+
+    #[test]
+    fn has_insufficient_material() {
+        let b: Board = "8/8/4kn2/8/8/3KR3/8/8 w - - 0 1".parse().unwrap();
+
+        assert!(!b.has_insufficient_material(Color::White));
+        assert!(b.has_insufficient_material(Color::Black));
+    }
+
+    #[test]
+    fn seventy_five_move_rule() {
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::SeventyFiveMoveRule)),
+            outcome("4k3/8/8/8/8/8/8/4K2R w - - 150 120")
+        );
+        assert_eq!(None, outcome("4k3/8/8/8/8/8/8/4K2R w - - 149 120"));
+    }
+}
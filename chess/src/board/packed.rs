@@ -0,0 +1,412 @@
+//! A compact binary encoding of standard chess positions, for storing large numbers of them.
+//!
+//! A packed position is [`PACKED_SIZE`] bytes:
+//!
+//! | Bytes | Content |
+//! |---|---|
+//! | 0..8 | the occupied squares as a little-endian bitboard, bit 0 is a1 and bit 63 is h8 |
+//! | 8..24 | a 4-bit piece code for each occupied square from a1 to h8, the first one in the low bits |
+//! | 24 | the side to move in bit 0 (1 for black), the castling rights in bits 1 to 4 (white short, white long, black short, black long) |
+//! | 25 | the en passant file, or 255 for none |
+//! | 26 | the halfmove clock |
+//! | 27..29 | the fullmove number as a little-endian `u16` |
+//! | 29..32 | reserved, always 0 |
+//!
+//! A piece code is 1 to 6 for a white king, queen, rook, bishop, knight or pawn and 9 to 14 for
+//! the black ones, unused nibbles are 0.
+
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::piece::{Color, Piece, PieceType};
+use crate::variant::{Standard, Variant};
+use std::fmt::{Display, Formatter};
+use std::io::{self, Read, Write};
+
+/// The size of a packed position in bytes.
+pub const PACKED_SIZE: usize = 32;
+
+#[derive(Clone, Debug)]
+pub struct PackError {
+    description: String,
+}
+
+impl Display for PackError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for PackError {}
+
+macro_rules! pack_error {
+    ($($fmt:expr),*) => {
+        Err(PackError{description:format!($($fmt),*)})
+    }
+}
+
+fn piece_code(p: &Piece) -> u8 {
+    (p.color as u8) << 3 | (p.piece_type as u8 + 1)
+}
+
+fn code_piece(code: u8) -> Option<Piece> {
+    let color = if code & 8 == 0 {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let piece_type = PieceType::iter().nth(((code & 7) as usize).checked_sub(1)?)?;
+    Some(Piece::new(color, piece_type))
+}
+
+impl Board {
+    /// Packs the position into [`PACKED_SIZE`] bytes, see the [module docs](self).
+    ///
+    /// Only standard chess positions with at most 32 pieces and less than 65536 moves can be
+    /// packed. The repetition history isn't kept.
+    pub fn to_packed(&self) -> Result<[u8; PACKED_SIZE], PackError> {
+        if self.variant.name() != Standard.name() {
+            return pack_error!("can't pack a {} position", self.variant.name());
+        }
+        let fullmove = match u16::try_from(self.ply / 2 + 1) {
+            Ok(fullmove) => fullmove,
+            Err(_) => return pack_error!("fullmove number {} is too large", self.ply / 2 + 1),
+        };
+
+        let mut bytes = [0; PACKED_SIZE];
+        let mut occupied = 0u64;
+        let mut count = 0;
+        for pos in Position::iter() {
+            if let Some(p) = self.get_piece(&pos) {
+                if count == 32 {
+                    return pack_error!("can't pack more than 32 pieces");
+                }
+                occupied |= 1 << pos.idx();
+                bytes[8 + count / 2] |= piece_code(&p) << (4 * (count % 2));
+                count += 1;
+            }
+        }
+        bytes[0..8].copy_from_slice(&occupied.to_le_bytes());
+
+        let castling = self.castling();
+        bytes[24] = self.current_player as u8
+            | (castling.white_short as u8) << 1
+            | (castling.white_long as u8) << 2
+            | (castling.black_short as u8) << 3
+            | (castling.black_long as u8) << 4;
+        bytes[25] = self.en_passant_file().unwrap_or(255);
+        bytes[26] = self.fifty_move_counter();
+        bytes[27..29].copy_from_slice(&fullmove.to_le_bytes());
+
+        Ok(bytes)
+    }
+
+    /// Unpacks a position written by [`Board::to_packed`].
+    ///
+    /// Besides the format, the position is checked to be one that move generation can work with:
+    /// one king per side, no pawns on the back ranks, the side not to move not in check, and
+    /// castling rights and the en passant file matching the pieces.
+    pub fn from_packed(bytes: &[u8; PACKED_SIZE]) -> Result<Board, PackError> {
+        let mut b = Board::empty(&Standard);
+
+        let occupied = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        if occupied.count_ones() > 32 {
+            return pack_error!("{} occupied squares, at most 32 fit", occupied.count_ones());
+        }
+        let mut count = 0;
+        for pos in Position::iter().filter(|pos| occupied >> pos.idx() & 1 == 1) {
+            let code = bytes[8 + count / 2] >> (4 * (count % 2)) & 15;
+            match code_piece(code) {
+                Some(p) => b.put_piece(&pos, Some(p)),
+                None => return pack_error!("invalid piece code {} on {}", code, pos),
+            };
+            count += 1;
+        }
+        if (count..32).any(|n| bytes[8 + n / 2] >> (4 * (n % 2)) & 15 != 0) {
+            return pack_error!("piece codes after the last occupied square");
+        }
+
+        let flags = bytes[24];
+        if flags >> 5 != 0 {
+            return pack_error!("invalid flags {:#04x}", flags);
+        }
+        b.current_player = if flags & 1 == 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let state = b.get_state_mut();
+        state.castling.white_short = flags >> 1 & 1 == 1;
+        state.castling.white_long = flags >> 2 & 1 == 1;
+        state.castling.black_short = flags >> 3 & 1 == 1;
+        state.castling.black_long = flags >> 4 & 1 == 1;
+        state.en_passant_file = match bytes[25] {
+            255 => None,
+            file @ 0..=7 => Some(file),
+            file => return pack_error!("invalid en passant file {}", file),
+        };
+        state.fifty_move_counter = bytes[26];
+
+        let fullmove = u16::from_le_bytes([bytes[27], bytes[28]]);
+        if fullmove == 0 {
+            return pack_error!("the fullmove number starts at 1");
+        }
+        b.ply = (fullmove as u32 - 1) * 2 + b.current_player as u32;
+        if bytes[29..].iter().any(|byte| *byte != 0) {
+            return pack_error!("reserved bytes aren't 0");
+        }
+
+        b.validate()?;
+        b.hash = b.compute_hash();
+        b.get_state_mut().hash = b.hash;
+
+        Ok(b)
+    }
+
+    fn validate(&self) -> Result<(), PackError> {
+        for color in Color::iter() {
+            let kings = self.pieces(&Piece::new(color, PieceType::King)).count();
+            if kings != 1 {
+                return pack_error!("{:?} has {} kings", color, kings);
+            }
+        }
+        if let Some(pos) = Position::iter().find(|pos| {
+            (pos.rank == 0 || pos.rank == 7)
+                && self.get_piece(pos).map(|p| p.piece_type) == Some(PieceType::Pawn)
+        }) {
+            return pack_error!("pawn on {}", pos);
+        }
+        if Standard.in_check(self, self.current_player.opponent()) {
+            return pack_error!("the side not to move is in check");
+        }
+
+        let castling = self.castling();
+        for (color, rights) in [
+            (Color::White, [castling.white_short, castling.white_long]),
+            (Color::Black, [castling.black_short, castling.black_long]),
+        ] {
+            let rank = color.back_rank();
+            let is = |file: u8, t: PieceType| {
+                self.get_piece(&Position::from(rank, file)) == Some(Piece::new(color, t))
+            };
+            for (has, rook_file) in rights.into_iter().zip([7, 0]) {
+                if has && !(is(4, PieceType::King) && is(rook_file, PieceType::Rook)) {
+                    return pack_error!("{:?} can't castle without king and rook at home", color);
+                }
+            }
+        }
+
+        if let Some(file) = self.en_passant_file() {
+            // the pawn that just moved two squares and the two squares it passed
+            let them = self.current_player.opponent();
+            let dir = them.pawn_direction();
+            let rank = (them.back_rank() as i8 + 3 * dir) as u8;
+            let pawn = Position::from(rank, file);
+            let passed = [pawn.offset(-dir, 0), pawn.offset(-2 * dir, 0)];
+            if self.get_piece(&pawn) != Some(Piece::new(them, PieceType::Pawn))
+                || passed
+                    .iter()
+                    .flatten()
+                    .any(|pos| self.get_piece(pos).is_some())
+            {
+                return pack_error!("no pawn to capture en passant on file {}", file);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes packed positions to a stream one after the other.
+pub struct PackedWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> PackedWriter<W> {
+    pub fn new(inner: W) -> Self {
+        PackedWriter { inner }
+    }
+
+    /// Packs and writes `board`, a position that can't be packed is an `InvalidInput` error.
+    pub fn write(&mut self, board: &Board) -> io::Result<()> {
+        let bytes = board
+            .to_packed()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.inner.write_all(&bytes)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads packed positions from a stream, one item per position until the end of the stream.
+///
+/// An invalid position or a record cut off at the end is an `InvalidData` error, after which
+/// the reader stops.
+pub struct PackedReader<R: Read> {
+    inner: R,
+    read: usize,
+    done: bool,
+}
+
+impl<R: Read> PackedReader<R> {
+    pub fn new(inner: R) -> Self {
+        PackedReader {
+            inner,
+            read: 0,
+            done: false,
+        }
+    }
+
+    /// Fills `bytes`, `false` if the stream ended before the first byte.
+    fn read_record(&mut self, bytes: &mut [u8; PACKED_SIZE]) -> io::Result<bool> {
+        let mut filled = 0;
+        while filled < PACKED_SIZE {
+            match self.inner.read(&mut bytes[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("record {} is cut off", self.read + 1),
+                    ))
+                }
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl<R: Read> Iterator for PackedReader<R> {
+    type Item = io::Result<Board>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut bytes = [0; PACKED_SIZE];
+        let result = match self.read_record(&mut bytes) {
+            Ok(false) => None,
+            Ok(true) => Some(Board::from_packed(&bytes).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("record {}: {}", self.read + 1, e),
+                )
+            })),
+            Err(e) => Some(Err(e)),
+        };
+        self.read += 1;
+        self.done = !matches!(result, Some(Ok(_)));
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::packed::{PackedReader, PackedWriter, PACKED_SIZE};
+    use crate::board::Board;
+    use crate::variant::Horde;
+
+    const FENS: [&str; 4] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 0 1",
+        "8/8/4k3/8/8/8/1K6/8 b - - 37 300",
+        "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+    ];
+
+    fn packed(fen: &str) -> [u8; PACKED_SIZE] {
+        fen.parse::<Board>().unwrap().to_packed().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        for fen in FENS {
+            let b: Board = fen.parse().unwrap();
+            let read = Board::from_packed(&b.to_packed().unwrap()).unwrap();
+            assert_eq!(fen, read.to_fen());
+            assert_eq!(b.hash(), read.hash());
+        }
+
+        let bytes = packed(FENS[0]);
+        assert_eq!(
+            0xffff00000000ffff,
+            u64::from_le_bytes(bytes[..8].try_into().unwrap())
+        );
+        // a1 rook, b1 knight, ... a8 black rook
+        assert_eq!([0x53, 0x24, 0x41, 0x35], bytes[8..12]);
+        assert_eq!(0xbd, bytes[23]);
+        assert_eq!(0x1e, bytes[24]);
+        assert_eq!([255, 0, 1, 0, 0, 0, 0], bytes[25..]);
+    }
+
+    #[test]
+    fn unpackable() {
+        assert!(Board::new_variant(&Horde).to_packed().is_err());
+
+        let mut b = Board::new();
+        b.ply = 2 * 65536;
+        assert!(b.to_packed().is_err());
+    }
+
+    #[test]
+    fn validation() {
+        let invalid = |fen: &str, change: &dyn Fn(&mut [u8; PACKED_SIZE])| {
+            let mut bytes = packed(fen);
+            change(&mut bytes);
+            assert!(Board::from_packed(&bytes).is_err(), "{}", fen);
+        };
+
+        // 7 isn't a piece code
+        invalid(FENS[0], &|b| b[8] = 0x57);
+        // a piece code without a square
+        invalid(FENS[2], &|b| b[9] = 0x01);
+        invalid(FENS[0], &|b| b[24] |= 0x20);
+        invalid(FENS[0], &|b| b[25] = 8);
+        invalid(FENS[0], &|b| b[27] = 0);
+        invalid(FENS[0], &|b| b[31] = 1);
+        // a third king
+        invalid(FENS[0], &|b| b[8] = 0x51);
+        invalid("8/8/4k3/8/8/8/1K6/P7 w - - 0 1", &|_| {});
+        invalid("8/8/4k3/8/8/8/1K6/8 w - - 0 1", &|b| b[24] |= 0x2);
+        invalid("r3k3/8/8/8/8/8/8/4K2R w - - 0 1", &|b| b[24] |= 0x08);
+        invalid(FENS[1], &|b| b[25] = 4);
+        invalid("4k3/8/8/8/8/8/4R3/4K3 w - - 0 1", &|_| {});
+        assert!(Board::from_packed(&packed("4k3/8/8/8/8/8/4R3/4K3 b - - 0 1")).is_ok());
+    }
+
+    #[test]
+    fn streams() {
+        let mut w = PackedWriter::new(Vec::new());
+        for fen in FENS {
+            w.write(&fen.parse().unwrap()).unwrap();
+        }
+        assert!(w.write(&Board::new_variant(&Horde)).is_err());
+        let mut bytes = w.into_inner();
+        assert_eq!(FENS.len() * PACKED_SIZE, bytes.len());
+
+        let read: Vec<_> = PackedReader::new(bytes.as_slice())
+            .map(|b| b.unwrap().to_fen())
+            .collect();
+        assert_eq!(FENS.to_vec(), read);
+
+        let cut_off: Vec<_> = PackedReader::new(&bytes[..PACKED_SIZE + 5]).collect();
+        assert_eq!(2, cut_off.len());
+        assert!(cut_off[0].is_ok());
+        assert!(cut_off[1].is_err());
+
+        bytes[PACKED_SIZE + 31] = 1;
+        let mut reader = PackedReader::new(bytes.as_slice());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
@@ -1,10 +1,16 @@
 use crate::board::position::Position;
 use crate::piece::{Color, Piece, PieceType};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasherDefault;
 
-#[derive(Debug)]
+/// The squares of a piece. The hasher has fixed keys so that the squares are always visited in
+/// the same order, which keeps move generation and search deterministic.
+type Squares = HashSet<Position, BuildHasherDefault<DefaultHasher>>;
+
+#[derive(Clone, Debug)]
 pub struct PieceList {
-    lists: HashMap<Color, HashMap<PieceType, HashSet<Position>>>,
+    lists: HashMap<Color, HashMap<PieceType, Squares>>,
 }
 
 impl PieceList {
@@ -18,14 +24,14 @@ impl PieceList {
             let cl = l.lists.get_mut(&c).unwrap();
 
             for t in PieceType::iter() {
-                cl.insert(t, HashSet::new());
+                cl.insert(t, Squares::default());
             }
         }
 
         l
     }
 
-    fn get(&self, p: &Piece) -> &HashSet<Position> {
+    fn get(&self, p: &Piece) -> &Squares {
         self.lists
             .get(&p.color)
             .unwrap()
@@ -33,7 +39,7 @@ impl PieceList {
             .unwrap()
     }
 
-    fn get_mut(&mut self, p: &Piece) -> &mut HashSet<Position> {
+    fn get_mut(&mut self, p: &Piece) -> &mut Squares {
         self.lists
             .get_mut(&p.color)
             .unwrap()
@@ -63,9 +69,7 @@ impl PieceList {
 #[cfg(test)]
 mod tests {
     use crate::board::piece_list::PieceList;
-    use crate::board::position::Position;
     use crate::piece::Piece;
-    use std::str::FromStr;
 
     #[test]
     fn put_piece() {
@@ -1,4 +1,4 @@
-use crate::board::ParseFenError;
+use crate::board::{FenErrorKind, ParseFenError};
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
@@ -37,6 +37,22 @@ impl Position {
     pub fn idx(&self) -> usize {
         (self.rank * 8 + self.file) as usize
     }
+
+    /// The position `dr` ranks and `df` files away, if it is still on the board.
+    pub fn offset(&self, dr: i8, df: i8) -> Option<Position> {
+        let r = self.rank as i8 + dr;
+        let f = self.file as i8 + df;
+
+        if (0..8).contains(&r) && (0..8).contains(&f) {
+            Some(Position::from(r as u8, f as u8))
+        } else {
+            None
+        }
+    }
+
+    pub fn iter() -> impl Iterator<Item = Position> {
+        (0..64usize).map(<Position as From<usize>>::from)
+    }
 }
 
 impl FromStr for Position {
@@ -46,8 +62,9 @@ impl FromStr for Position {
         let mut chars = s.chars();
         match (chars.next(), chars.next(), chars.next()) {
             (Some(f), Some(r), None) => {
-                if !('1' <= r && r <= '8' && 'a' <= f && f <= 'h') {
+                if !(('1'..='8').contains(&r) && ('a'..='h').contains(&f)) {
                     Err(ParseFenError {
+                        kind: FenErrorKind::Square,
                         description: format!("invalid position '{}'", s),
                     })
                 } else {
@@ -55,6 +72,7 @@ impl FromStr for Position {
                 }
             }
             _ => Err(ParseFenError {
+                kind: FenErrorKind::Square,
                 description: format!("invalid position '{}'", s),
             }),
         }
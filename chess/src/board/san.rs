@@ -0,0 +1,265 @@
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::chess_move::{Move, MoveKind};
+use crate::piece::PieceType;
+
+impl Board {
+    /// Writes a legal move in standard algebraic notation, e.g. `Nbd7`, `exd6`, `O-O`, `e8=Q#`
+    /// or the drops `N@f3` and `@e4`.
+    pub fn san(&mut self, m: Move) -> String {
+        let mut san = match m.kind {
+            MoveKind::CastleShort => String::from("O-O"),
+            MoveKind::CastleLong => String::from("O-O-O"),
+            MoveKind::Drop(PieceType::Pawn) => format!("@{}", m.to),
+            MoveKind::Drop(t) => format!("{}@{}", t.get_fen_char().to_ascii_uppercase(), m.to),
+            _ => self.san_without_suffix(m),
+        };
+
+        self.make_move(m);
+        if self.in_check() {
+            san.push(if self.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        self.unmake_move(m);
+
+        san
+    }
+
+    /// Finds the legal move written in standard algebraic notation.
+    ///
+    /// The parsing is lenient: check and annotation suffixes are ignored, `0-0` is accepted for
+    /// castling, the `=` before a promotion is optional and superfluous disambiguation is fine.
+    /// Pawn drops may be written as `P@e4`.
+    pub fn parse_san(&mut self, s: &str) -> Option<Move> {
+        let s = s.trim_end_matches(['+', '#', '!', '?']);
+
+        match s {
+            "O-O" | "0-0" => return self.find_move(|_, m| m.kind == MoveKind::CastleShort),
+            "O-O-O" | "0-0-0" => return self.find_move(|_, m| m.kind == MoveKind::CastleLong),
+            _ => {}
+        }
+
+        if let Some((piece, to)) = s.split_once('@') {
+            let piece_type = match piece {
+                "" => PieceType::Pawn,
+                _ if piece.len() == 1 && piece.chars().all(|c| c.is_ascii_uppercase()) => {
+                    PieceType::from_fen_char(&piece.chars().next()?)?
+                }
+                _ => return None,
+            };
+            let to: Position = to.parse().ok()?;
+            return self.find_move(|_, m| m.kind == MoveKind::Drop(piece_type) && m.to == to);
+        }
+
+        let mut chars: Vec<char> = s.chars().collect();
+
+        let promotion = match chars.last().and_then(PieceType::from_fen_char) {
+            Some(t) if chars.len() > 2 && chars.last().unwrap().is_ascii_uppercase() => {
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+                Some(t)
+            }
+            _ => None,
+        };
+
+        let piece_type = match chars.first() {
+            Some(c) if c.is_ascii_uppercase() => {
+                let t = PieceType::from_fen_char(c)?;
+                chars.remove(0);
+                t
+            }
+            _ => PieceType::Pawn,
+        };
+
+        if chars.len() < 2 {
+            return None;
+        }
+        let to: Position = chars[chars.len() - 2..]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()?;
+        chars.truncate(chars.len() - 2);
+        if chars.last() == Some(&'x') {
+            chars.pop();
+        }
+
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in chars {
+            match c {
+                'a'..='h' => from_file = Some(c as u8 - b'a'),
+                '1'..='8' => from_rank = Some(c as u8 - b'1'),
+                _ => return None,
+            }
+        }
+
+        self.find_move(|b, m| {
+            !m.is_castle()
+                && m.to == to
+                && m.promotion == promotion
+                && from_file.is_none_or(|f| m.from.file == f)
+                && from_rank.is_none_or(|r| m.from.rank == r)
+                && b.get_piece(&m.from).map(|p| p.piece_type) == Some(piece_type)
+        })
+    }
+
+    fn san_without_suffix(&mut self, m: Move) -> String {
+        let piece_type = self
+            .get_piece(&m.from)
+            .expect("there should be a piece on the from square of a move")
+            .piece_type;
+        let mut san = String::new();
+
+        if piece_type == PieceType::Pawn {
+            if m.is_capture() {
+                san.push((b'a' + m.from.file) as char);
+            }
+        } else {
+            san.push(piece_type.get_fen_char().to_ascii_uppercase());
+
+            let others: Vec<Move> = self
+                .legal_moves()
+                .iter()
+                .copied()
+                .filter(|o| {
+                    o.to == m.to
+                        && o.from != m.from
+                        && self.get_piece(&o.from).map(|p| p.piece_type) == Some(piece_type)
+                })
+                .collect();
+
+            if !others.is_empty() {
+                if others.iter().all(|o| o.from.file != m.from.file) {
+                    san.push((b'a' + m.from.file) as char);
+                } else if others.iter().all(|o| o.from.rank != m.from.rank) {
+                    san.push((b'1' + m.from.rank) as char);
+                } else {
+                    san.push_str(&m.from.to_string());
+                }
+            }
+        }
+
+        if m.is_capture() {
+            san.push('x');
+        }
+        san.push_str(&m.to.to_string());
+
+        if let Some(t) = m.promotion {
+            san.push('=');
+            san.push(t.get_fen_char().to_ascii_uppercase());
+        }
+
+        san
+    }
+
+    /// The only legal move matching the predicate.
+    fn find_move(&mut self, f: impl Fn(&Board, &Move) -> bool) -> Option<Move> {
+        let moves = self.legal_moves();
+        let mut matching = moves.iter().filter(|m| f(self, m));
+
+        match (matching.next(), matching.next()) {
+            (Some(m), None) => Some(*m),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+
+    fn san(fen: &str, uci: &str) -> String {
+        let mut b: Board = fen.parse().unwrap();
+        let m = b.parse_uci_move(uci).unwrap();
+        b.san(m)
+    }
+
+    fn parse(fen: &str, san: &str) -> Option<String> {
+        let mut b: Board = fen.parse().unwrap();
+        b.parse_san(san).map(|m| m.to_string())
+    }
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[test]
+    fn write() {
+        assert_eq!("e4", san(Board::STARTING_FEN, "e2e4"));
+        assert_eq!("Nf3", san(Board::STARTING_FEN, "g1f3"));
+        assert_eq!("O-O", san(KIWIPETE, "e1g1"));
+        assert_eq!("O-O-O", san(KIWIPETE, "e1c1"));
+        assert_eq!("dxe6", san(KIWIPETE, "d5e6"));
+        assert_eq!("Qxf6", san(KIWIPETE, "f3f6"));
+        assert_eq!("Nxf7", san(KIWIPETE, "e5f7"));
+        assert_eq!("Bxa6", san(KIWIPETE, "e2a6"));
+    }
+
+    #[test]
+    fn write_disambiguation() {
+        // knights on b1 and f3 can both go to d2, rooks on a1 and a5 to a3
+        let fen = "4k3/8/8/R7/8/5N2/8/RN2K3 w - - 0 1";
+
+        assert_eq!("Nbd2", san(fen, "b1d2"));
+        assert_eq!("Nfd2", san(fen, "f3d2"));
+        assert_eq!("R1a3", san(fen, "a1a3"));
+        assert_eq!("R5a3", san(fen, "a5a3"));
+
+        // queens on b2, b4 and d2 can all go to d4
+        let fen = "4k3/8/8/8/1Q6/8/1Q1Q4/4K3 w - - 0 1";
+        assert_eq!("Qb2d4", san(fen, "b2d4"));
+        assert_eq!("Q4d4", san(fen, "b4d4"));
+        assert_eq!("Qdd4", san(fen, "d2d4"));
+    }
+
+    #[test]
+    fn write_promotion_and_mate() {
+        assert_eq!("b8=Q+", san("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q"));
+        assert_eq!("Ra8#", san("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"));
+    }
+
+    #[test]
+    fn read() {
+        assert_eq!(Some("e2e4".to_string()), parse(Board::STARTING_FEN, "e4"));
+        assert_eq!(Some("g1f3".to_string()), parse(Board::STARTING_FEN, "Nf3"));
+        assert_eq!(Some("e1g1".to_string()), parse(KIWIPETE, "O-O"));
+        assert_eq!(Some("e1c1".to_string()), parse(KIWIPETE, "0-0-0"));
+        assert_eq!(Some("d5e6".to_string()), parse(KIWIPETE, "dxe6"));
+        assert_eq!(Some("f3f6".to_string()), parse(KIWIPETE, "Qxf6!?"));
+        assert_eq!(
+            Some("b7b8n".to_string()),
+            parse("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b8N")
+        );
+        assert_eq!(
+            Some("b7b8q".to_string()),
+            parse("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b8=Q+")
+        );
+    }
+
+    #[test]
+    fn read_ambiguous_and_illegal() {
+        let fen = "4k3/8/8/R7/8/5N2/8/RN2K3 w - - 0 1";
+
+        assert_eq!(None, parse(fen, "Nd2"));
+        assert_eq!(Some("b1d2".to_string()), parse(fen, "Nbd2"));
+        assert_eq!(Some("a1a3".to_string()), parse(fen, "R1a3"));
+        assert_eq!(Some("a1a3".to_string()), parse(fen, "Ra1a3"));
+        assert_eq!(None, parse(Board::STARTING_FEN, "e5"));
+        assert_eq!(None, parse(Board::STARTING_FEN, "Ke2"));
+        assert_eq!(None, parse(Board::STARTING_FEN, "xyz"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut b: Board = KIWIPETE.parse().unwrap();
+
+        for &m in &b.legal_moves() {
+            let san = b.san(m);
+            assert_eq!(Some(m), b.parse_san(&san), "{}", san);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use crate::board::game_state::CastlingState;
+use crate::board::position::Position;
+use crate::piece::{Color, Piece};
+
+struct Keys {
+    pieces: [[u64; 64]; 12],
+    black_to_move: u64,
+    castling: [u64; 4],
+    en_passant: [u64; 8],
+    /// One to three checks given by white and black.
+    checks: [[u64; 3]; 2],
+    /// One to 16 pieces of a type in the pocket of white and black.
+    pockets: [[[u64; 16]; 5]; 2],
+}
+
+/// splitmix64, used to fill the key tables at compile time.
+const fn next_random(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_keys() -> Keys {
+    let mut keys = Keys {
+        pieces: [[0; 64]; 12],
+        black_to_move: 0,
+        castling: [0; 4],
+        en_passant: [0; 8],
+        checks: [[0; 3]; 2],
+        pockets: [[[0; 16]; 5]; 2],
+    };
+    let mut state = 0x2545_f491_4f6c_dd1d;
+    let mut random;
+
+    let mut piece = 0;
+    while piece < 12 {
+        let mut square = 0;
+        while square < 64 {
+            (state, random) = next_random(state);
+            keys.pieces[piece][square] = random;
+            square += 1;
+        }
+        piece += 1;
+    }
+
+    (state, random) = next_random(state);
+    keys.black_to_move = random;
+
+    let mut i = 0;
+    while i < 4 {
+        (state, random) = next_random(state);
+        keys.castling[i] = random;
+        i += 1;
+    }
+
+    let mut i = 0;
+    while i < 8 {
+        (state, random) = next_random(state);
+        keys.en_passant[i] = random;
+        i += 1;
+    }
+
+    let mut color = 0;
+    while color < 2 {
+        let mut i = 0;
+        while i < 3 {
+            (state, random) = next_random(state);
+            keys.checks[color][i] = random;
+            i += 1;
+        }
+        color += 1;
+    }
+
+    let mut color = 0;
+    while color < 2 {
+        let mut kind = 0;
+        while kind < 5 {
+            let mut i = 0;
+            while i < 16 {
+                (state, random) = next_random(state);
+                keys.pockets[color][kind][i] = random;
+                i += 1;
+            }
+            kind += 1;
+        }
+        color += 1;
+    }
+
+    keys
+}
+
+static KEYS: Keys = generate_keys();
+
+pub fn piece_key(p: &Piece, pos: &Position) -> u64 {
+    KEYS.pieces[p.color as usize * 6 + p.piece_type as usize][pos.idx()]
+}
+
+pub fn side_key(c: Color) -> u64 {
+    match c {
+        Color::White => 0,
+        Color::Black => KEYS.black_to_move,
+    }
+}
+
+pub fn castling_key(c: &CastlingState) -> u64 {
+    [c.white_short, c.white_long, c.black_short, c.black_long]
+        .iter()
+        .zip(KEYS.castling.iter())
+        .filter(|(&right, _)| right)
+        .fold(0, |key, (_, k)| key ^ k)
+}
+
+pub fn en_passant_key(file: Option<u8>) -> u64 {
+    match file {
+        Some(f) => KEYS.en_passant[f as usize],
+        None => 0,
+    }
+}
+
+pub fn checks_key(checks: &[u8; 2]) -> u64 {
+    checks
+        .iter()
+        .zip(KEYS.checks.iter())
+        .filter(|(&count, _)| count > 0)
+        .fold(0, |key, (&count, k)| key ^ k[count.min(3) as usize - 1])
+}
+
+pub fn pockets_key(pockets: &[[u8; 5]; 2]) -> u64 {
+    let mut key = 0;
+
+    for (pocket, keys) in pockets.iter().zip(KEYS.pockets.iter()) {
+        for (&count, k) in pocket.iter().zip(keys.iter()) {
+            if count > 0 {
+                key ^= k[count.min(16) as usize - 1];
+            }
+        }
+    }
+
+    key
+}
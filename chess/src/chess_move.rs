@@ -0,0 +1,239 @@
+use crate::board::position::Position;
+use crate::piece::PieceType;
+use std::fmt::{Display, Formatter};
+use std::ops::{Deref, DerefMut};
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MoveKind {
+    Quiet,
+    DoublePawnPush,
+    Capture,
+    EnPassant,
+    CastleShort,
+    CastleLong,
+    /// A piece from the pocket put on an empty square (Crazyhouse).
+    Drop(PieceType),
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Move {
+    pub from: Position,
+    pub to: Position,
+    pub kind: MoveKind,
+    pub promotion: Option<PieceType>,
+}
+
+impl Move {
+    pub fn new(from: Position, to: Position, kind: MoveKind) -> Move {
+        Move {
+            from,
+            to,
+            kind,
+            promotion: None,
+        }
+    }
+
+    pub fn promotion(from: Position, to: Position, kind: MoveKind, piece_type: PieceType) -> Move {
+        Move {
+            from,
+            to,
+            kind,
+            promotion: Some(piece_type),
+        }
+    }
+
+    /// Puts a piece from the pocket on `to`, the move's `from` is `to` as well.
+    pub fn drop(piece_type: PieceType, to: Position) -> Move {
+        Move {
+            from: to,
+            to,
+            kind: MoveKind::Drop(piece_type),
+            promotion: None,
+        }
+    }
+
+    pub fn is_capture(&self) -> bool {
+        matches!(self.kind, MoveKind::Capture | MoveKind::EnPassant)
+    }
+
+    pub fn is_castle(&self) -> bool {
+        matches!(self.kind, MoveKind::CastleShort | MoveKind::CastleLong)
+    }
+
+    pub fn is_drop(&self) -> bool {
+        matches!(self.kind, MoveKind::Drop(_))
+    }
+
+    /// Captures and promotions, i.e. the moves generated in the first stage.
+    pub fn is_tactical(&self) -> bool {
+        self.is_capture() || self.promotion.is_some()
+    }
+}
+
+/// Prints the move in long algebraic notation as used by UCI, e.g. `e2e4`, `e7e8q` or `N@f3`.
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let MoveKind::Drop(t) = self.kind {
+            return write!(f, "{}@{}", t.get_fen_char().to_ascii_uppercase(), self.to);
+        }
+        write!(f, "{}{}", self.from, self.to)?;
+
+        if let Some(t) = self.promotion {
+            write!(f, "{}", t.get_fen_char())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Fixed capacity move buffer so that move generation doesn't allocate.
+///
+/// No legal chess position has more than 218 moves, but with full pockets in Crazyhouse the drops
+/// alone can be over 300, so there are 512 slots.
+pub struct MoveList {
+    moves: [Move; MoveList::CAPACITY],
+    len: usize,
+}
+
+impl MoveList {
+    pub const CAPACITY: usize = 512;
+
+    const EMPTY: Move = Move {
+        from: Position { rank: 0, file: 0 },
+        to: Position { rank: 0, file: 0 },
+        kind: MoveKind::Quiet,
+        promotion: None,
+    };
+
+    pub fn new() -> Self {
+        MoveList {
+            moves: [Self::EMPTY; Self::CAPACITY],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, m: Move) {
+        self.moves[self.len] = m;
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn swap_remove(&mut self, idx: usize) -> Move {
+        let m = self.moves[idx];
+        self.len -= 1;
+        self.moves[idx] = self.moves[self.len];
+        m
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&Move) -> bool) {
+        let mut kept = 0;
+
+        for idx in 0..self.len {
+            if f(&self.moves[idx]) {
+                self.moves[kept] = self.moves[idx];
+                kept += 1;
+            }
+        }
+
+        self.len = kept;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_move::{Move, MoveKind, MoveList};
+    use crate::piece::PieceType;
+
+    #[test]
+    fn display() {
+        let m = Move::new(
+            "e2".parse().unwrap(),
+            "e4".parse().unwrap(),
+            MoveKind::DoublePawnPush,
+        );
+        assert_eq!("e2e4", m.to_string());
+
+        let m = Move::promotion(
+            "b7".parse().unwrap(),
+            "a8".parse().unwrap(),
+            MoveKind::Capture,
+            PieceType::Knight,
+        );
+        assert_eq!("b7a8n", m.to_string());
+
+        let m = Move::drop(PieceType::Knight, "f3".parse().unwrap());
+        assert_eq!("N@f3", m.to_string());
+    }
+
+    #[test]
+    fn move_list() {
+        let mut l = MoveList::new();
+        let a = Move::new(
+            "a1".parse().unwrap(),
+            "a2".parse().unwrap(),
+            MoveKind::Quiet,
+        );
+        let b = Move::new(
+            "b1".parse().unwrap(),
+            "b2".parse().unwrap(),
+            MoveKind::Capture,
+        );
+        let c = Move::new(
+            "c1".parse().unwrap(),
+            "c2".parse().unwrap(),
+            MoveKind::Quiet,
+        );
+
+        l.push(a);
+        l.push(b);
+        l.push(c);
+        assert_eq!(3, l.len());
+
+        l.retain(|m| !m.is_capture());
+        assert_eq!(&[a, c], &l[..]);
+
+        assert_eq!(a, l.swap_remove(0));
+        assert_eq!(&[c], &l[..]);
+    }
+}
@@ -0,0 +1,550 @@
+use crate::board::outcome::Outcome;
+use crate::board::Board;
+use crate::piece::Color;
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Where a clock takes the current time from.
+///
+/// Only differences between two readings matter, so any starting point is fine.
+pub trait TimeSource {
+    fn now(&self) -> Duration;
+}
+
+/// The system's monotonic clock.
+#[derive(Clone, Debug)]
+pub struct MonotonicTime {
+    start: Instant,
+}
+
+impl MonotonicTime {
+    pub fn new() -> Self {
+        MonotonicTime {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for MonotonicTime {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A time source that only moves when told to. Clones share the same time.
+#[derive(Clone, Debug, Default)]
+pub struct ManualTime {
+    now: Rc<Cell<Duration>>,
+}
+
+impl ManualTime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, d: Duration) {
+        self.now.set(self.now.get() + d);
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+/// What a player gets in addition to the base time of a stage.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Delay {
+    None,
+    /// Added to the clock after every move.
+    Fischer(Duration),
+    /// After every move the time used is given back, but at most this much.
+    Bronstein(Duration),
+    /// The clock only starts running down after this much time into each move.
+    Simple(Duration),
+}
+
+/// One period of a time control, e.g. 40 moves in 90 minutes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Stage {
+    /// Moves to be played in this stage, `None` for the rest of the game.
+    pub moves: Option<u32>,
+    pub time: Duration,
+    pub delay: Delay,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimeControl {
+    stages: Vec<Stage>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ParseTimeControlError {
+    description: String,
+}
+
+impl Display for ParseTimeControlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for ParseTimeControlError {}
+
+impl TimeControl {
+    /// A time control of the given stages. Every stage but the last must have a move count, the
+    /// last one is repeated if it has one too. Move counts can't be 0.
+    pub fn new(stages: Vec<Stage>) -> TimeControl {
+        assert!(!stages.is_empty(), "a time control needs at least one stage");
+        assert!(
+            stages.iter().all(|s| s.moves != Some(0)),
+            "a stage needs at least one move"
+        );
+        assert!(
+            stages[..stages.len() - 1].iter().all(|s| s.moves.is_some()),
+            "only the last stage may last for the rest of the game"
+        );
+
+        TimeControl { stages }
+    }
+
+    pub fn sudden_death(time: Duration) -> TimeControl {
+        Self::single(time, Delay::None)
+    }
+
+    pub fn fischer(time: Duration, increment: Duration) -> TimeControl {
+        Self::single(time, Delay::Fischer(increment))
+    }
+
+    pub fn bronstein(time: Duration, delay: Duration) -> TimeControl {
+        Self::single(time, Delay::Bronstein(delay))
+    }
+
+    pub fn simple_delay(time: Duration, delay: Duration) -> TimeControl {
+        Self::single(time, Delay::Simple(delay))
+    }
+
+    fn single(time: Duration, delay: Delay) -> TimeControl {
+        Self::new(vec![Stage {
+            moves: None,
+            time,
+            delay,
+        }])
+    }
+
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    fn stage(&self, idx: usize) -> &Stage {
+        &self.stages[idx.min(self.stages.len() - 1)]
+    }
+}
+
+/// Parses the notation of the PGN `TimeControl` tag, with times in seconds: stages separated by
+/// `:`, each written as `moves/seconds` or `seconds` for the rest of the game, optionally
+/// followed by `+increment`. E.g. `40/5400+30:1800+30`. Fractions of seconds like `10+0.1` are
+/// accepted too.
+impl FromStr for TimeControl {
+    type Err = ParseTimeControlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseTimeControlError {
+            description: format!("invalid time control '{}'", s),
+        };
+        let seconds = |t: &str| {
+            t.parse::<f64>()
+                .ok()
+                .filter(|_| t.chars().all(|c| c.is_ascii_digit() || c == '.'))
+                .and_then(|t| Duration::try_from_secs_f64(t).ok())
+                .ok_or_else(err)
+        };
+
+        let mut stages = Vec::new();
+        for part in s.split(':') {
+            let (period, delay) = match part.split_once('+') {
+                Some((period, inc)) => (period, Delay::Fischer(seconds(inc)?)),
+                None => (part, Delay::None),
+            };
+            let (moves, time) = match period.split_once('/') {
+                Some((moves, time)) => {
+                    let moves = moves.parse().ok().filter(|&m| m > 0).ok_or_else(err)?;
+                    (Some(moves), seconds(time)?)
+                }
+                None => (None, seconds(period)?),
+            };
+
+            stages.push(Stage { moves, time, delay });
+        }
+
+        if stages[..stages.len() - 1].iter().any(|s| s.moves.is_none()) {
+            return Err(err());
+        }
+
+        Ok(TimeControl { stages })
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Side {
+    remaining: Duration,
+    stage: usize,
+    moves_in_stage: u32,
+}
+
+/// A chess clock for both players.
+///
+/// Nothing runs until `start` is called, after that exactly one side's time runs down until
+/// that side presses the clock after moving.
+pub struct Clock<T: TimeSource = MonotonicTime> {
+    control: TimeControl,
+    time_source: T,
+    sides: [Side; 2],
+    running: Option<Color>,
+    turn_started: Duration,
+    flagged: Option<Color>,
+}
+
+impl<T: TimeSource> Clock<T> {
+    pub fn new(control: TimeControl, time_source: T) -> Self {
+        let side = Side {
+            remaining: control.stage(0).time,
+            stage: 0,
+            moves_in_stage: 0,
+        };
+
+        Clock {
+            control,
+            time_source,
+            sides: [side; 2],
+            running: None,
+            turn_started: Duration::ZERO,
+            flagged: None,
+        }
+    }
+
+    /// Starts the clock of the side to move.
+    pub fn start(&mut self, color: Color) {
+        self.running = Some(color);
+        self.turn_started = self.time_source.now();
+    }
+
+    /// Stops the running side's clock, charging it the time used so far.
+    pub fn pause(&mut self) {
+        if let Some(color) = self.running {
+            let used = self.charge(color);
+            let side = &mut self.sides[color as usize];
+            side.remaining = side.remaining.saturating_sub(used);
+            self.running = None;
+        }
+    }
+
+    pub fn running(&self) -> Option<Color> {
+        self.running
+    }
+
+    /// The time left for `color`, including the time running down right now.
+    pub fn remaining(&self, color: Color) -> Duration {
+        let side = &self.sides[color as usize];
+
+        match self.running {
+            Some(c) if c == color => side.remaining.saturating_sub(self.charge(color)),
+            _ => side.remaining,
+        }
+    }
+
+    /// The moves `color` has left in the current stage, `None` if it lasts for the rest of the
+    /// game.
+    pub fn moves_to_go(&self, color: Color) -> Option<u32> {
+        let side = &self.sides[color as usize];
+        self.control
+            .stage(side.stage)
+            .moves
+            .map(|moves| moves - side.moves_in_stage)
+    }
+
+    /// The Fischer increment `color` gets in the current stage.
+    pub fn increment(&self, color: Color) -> Duration {
+        match self.control.stage(self.sides[color as usize].stage).delay {
+            Delay::Fischer(inc) => inc,
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Ends the turn of the side to move after it made its move and starts the opponent's clock.
+    ///
+    /// Returns the side whose flag fell if the move came too late, the clock stops then.
+    pub fn press(&mut self) -> Option<Color> {
+        let color = self.running?;
+        if self.flag().is_some() {
+            return self.flagged;
+        }
+
+        let now = self.time_source.now();
+        let used = now - self.turn_started;
+        let charged = self.charge(color);
+        let stage = *self.control.stage(self.sides[color as usize].stage);
+        let side = &mut self.sides[color as usize];
+
+        side.remaining -= charged;
+        match stage.delay {
+            Delay::Fischer(inc) => side.remaining += inc,
+            Delay::Bronstein(delay) => side.remaining += used.min(delay),
+            Delay::None | Delay::Simple(_) => {}
+        }
+
+        side.moves_in_stage += 1;
+        if stage.moves == Some(side.moves_in_stage) {
+            side.stage += 1;
+            side.moves_in_stage = 0;
+            side.remaining += self.control.stage(side.stage).time;
+        }
+
+        self.running = Some(color.opponent());
+        self.turn_started = now;
+        None
+    }
+
+    /// The side that ran out of time, if any. A fallen flag stops the clock.
+    pub fn flag(&mut self) -> Option<Color> {
+        if let Some(color) = self.running {
+            if self.remaining(color).is_zero() {
+                self.sides[color as usize].remaining = Duration::ZERO;
+                self.running = None;
+                self.flagged = Some(color);
+            }
+        }
+
+        self.flagged
+    }
+
+    /// The outcome if a flag fell in the game on `board`, see `Board::outcome_on_time`.
+    pub fn outcome(&mut self, board: &Board) -> Option<Outcome> {
+        self.flag().map(|color| board.outcome_on_time(color))
+    }
+
+    /// The time of the current turn that is taken off the clock, i.e. the time used minus a
+    /// simple delay.
+    fn charge(&self, color: Color) -> Duration {
+        let used = self.time_source.now() - self.turn_started;
+
+        match self.control.stage(self.sides[color as usize].stage).delay {
+            Delay::Simple(delay) => used.saturating_sub(delay),
+            _ => used,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::outcome::{DrawReason, Outcome, WinReason};
+    use crate::board::Board;
+    use crate::clock::{Clock, Delay, ManualTime, Stage, TimeControl};
+    use crate::piece::Color;
+    use std::time::Duration;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    fn clock(control: TimeControl) -> (Clock<ManualTime>, ManualTime) {
+        let time = ManualTime::new();
+        let mut clock = Clock::new(control, time.clone());
+        clock.start(Color::White);
+        (clock, time)
+    }
+
+    #[test]
+    fn sudden_death() {
+        let (mut c, time) = clock(TimeControl::sudden_death(secs(60)));
+
+        time.advance(secs(10));
+        assert_eq!(secs(50), c.remaining(Color::White));
+        assert_eq!(secs(60), c.remaining(Color::Black));
+        assert_eq!(None, c.press());
+        assert_eq!(Some(Color::Black), c.running());
+
+        time.advance(secs(60));
+        assert_eq!(Some(Color::Black), c.flag());
+        assert_eq!(None, c.running());
+        assert_eq!(Duration::ZERO, c.remaining(Color::Black));
+        assert_eq!(secs(50), c.remaining(Color::White));
+    }
+
+    #[test]
+    fn fischer() {
+        let (mut c, time) = clock(TimeControl::fischer(secs(60), secs(2)));
+
+        time.advance(secs(10));
+        c.press();
+        assert_eq!(secs(52), c.remaining(Color::White));
+    }
+
+    #[test]
+    fn bronstein() {
+        let (mut c, time) = clock(TimeControl::bronstein(secs(60), secs(5)));
+
+        time.advance(secs(3));
+        c.press();
+        assert_eq!(secs(60), c.remaining(Color::White));
+
+        time.advance(secs(10));
+        c.press();
+        assert_eq!(secs(55), c.remaining(Color::Black));
+    }
+
+    #[test]
+    fn simple_delay() {
+        let (mut c, time) = clock(TimeControl::simple_delay(secs(60), secs(5)));
+
+        time.advance(secs(3));
+        assert_eq!(secs(60), c.remaining(Color::White));
+        time.advance(secs(4));
+        assert_eq!(secs(58), c.remaining(Color::White));
+        c.press();
+        assert_eq!(secs(58), c.remaining(Color::White));
+    }
+
+    #[test]
+    fn multiple_stages() {
+        // 2 moves in 100 seconds, then 50 seconds for the rest, 1 second increment throughout
+        let control: TimeControl = "2/100+1:50+1".parse().unwrap();
+        assert_eq!(
+            &[
+                Stage {
+                    moves: Some(2),
+                    time: secs(100),
+                    delay: Delay::Fischer(secs(1))
+                },
+                Stage {
+                    moves: None,
+                    time: secs(50),
+                    delay: Delay::Fischer(secs(1))
+                }
+            ],
+            control.stages()
+        );
+
+        let (mut c, time) = clock(control);
+        assert_eq!(Some(2), c.moves_to_go(Color::White));
+        for _ in 0..4 {
+            time.advance(secs(10));
+            c.press();
+        }
+        assert_eq!(None, c.moves_to_go(Color::White));
+        assert_eq!(secs(1), c.increment(Color::Black));
+
+        // 100 - 2 * 10 + 2 * 1 + 50
+        assert_eq!(secs(132), c.remaining(Color::White));
+        assert_eq!(secs(132), c.remaining(Color::Black));
+    }
+
+    #[test]
+    fn repeating_stage() {
+        let control: TimeControl = "1/10".parse().unwrap();
+        let (mut c, time) = clock(control);
+
+        for _ in 0..6 {
+            time.advance(secs(5));
+            c.press();
+        }
+
+        assert_eq!(secs(25), c.remaining(Color::White));
+    }
+
+    #[test]
+    fn invalid_time_controls() {
+        assert!("".parse::<TimeControl>().is_err());
+        assert!("40/".parse::<TimeControl>().is_err());
+        assert!("300:40/100".parse::<TimeControl>().is_err());
+        assert!("5m".parse::<TimeControl>().is_err());
+        assert!("-5".parse::<TimeControl>().is_err());
+        assert!("1e3".parse::<TimeControl>().is_err());
+        assert!("0/60".parse::<TimeControl>().is_err());
+        assert!("40/5400:0/60".parse::<TimeControl>().is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "a stage needs at least one move")]
+    fn stage_without_moves() {
+        TimeControl::new(vec![Stage {
+            moves: Some(0),
+            time: secs(60),
+            delay: Delay::None,
+        }]);
+    }
+
+    #[test]
+    fn fractions_of_seconds() {
+        let control: TimeControl = "10+0.1".parse().unwrap();
+        assert_eq!(
+            TimeControl::fischer(secs(10), Duration::from_millis(100)),
+            control
+        );
+
+        let (mut c, time) = clock("1/0.5".parse().unwrap());
+        assert_eq!(Some(1), c.moves_to_go(Color::White));
+        assert_eq!(Duration::ZERO, c.increment(Color::White));
+        time.advance(Duration::from_millis(200));
+        c.press();
+        assert_eq!(Duration::from_millis(800), c.remaining(Color::White));
+    }
+
+    #[test]
+    fn late_press_flags() {
+        let (mut c, time) = clock(TimeControl::fischer(secs(5), secs(5)));
+
+        time.advance(secs(6));
+        assert_eq!(Some(Color::White), c.press());
+        assert_eq!(Duration::ZERO, c.remaining(Color::White));
+    }
+
+    #[test]
+    fn pause() {
+        let (mut c, time) = clock(TimeControl::sudden_death(secs(60)));
+
+        time.advance(secs(10));
+        c.pause();
+        time.advance(secs(100));
+        assert_eq!(secs(50), c.remaining(Color::White));
+        assert_eq!(None, c.flag());
+    }
+
+    #[test]
+    fn time_forfeit() {
+        let b = Board::new();
+        let (mut c, time) = clock(TimeControl::sudden_death(secs(60)));
+
+        time.advance(secs(61));
+        assert_eq!(
+            Some(Outcome::Win {
+                winner: Color::Black,
+                reason: WinReason::TimeForfeit
+            }),
+            c.outcome(&b)
+        );
+    }
+
+    #[test]
+    fn timeout_vs_insufficient_material() {
+        // black only has a knight left, so white's flag falling is a draw
+        let b: Board = "4k3/8/8/3n4/8/8/3PP3/4K3 w - - 0 1".parse().unwrap();
+        let (mut c, time) = clock(TimeControl::sudden_death(secs(60)));
+
+        time.advance(secs(61));
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::TimeoutVsInsufficientMaterial)),
+            c.outcome(&b)
+        );
+    }
+}
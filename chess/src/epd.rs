@@ -0,0 +1,333 @@
+use crate::board::Board;
+use crate::chess_move::Move;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A position in Extended Position Description: the first four FEN fields followed by
+/// operations like `bm Nf3; id "test 1";`.
+#[derive(Clone, Debug)]
+pub struct Epd {
+    /// The position, with the counters taken from the `hmvc` and `fmvn` operations if present.
+    pub board: Board,
+    /// The opcodes with their operands, quoted strings without their quotes.
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ParseEpdError {
+    description: String,
+}
+
+impl Display for ParseEpdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for ParseEpdError {}
+
+macro_rules! parse_epd_error {
+    ($($fmt:expr),*) => {
+        Err(ParseEpdError{description:format!($($fmt),*)})
+    }
+}
+
+impl Epd {
+    pub fn new(board: Board) -> Epd {
+        Epd {
+            board,
+            operations: Vec::new(),
+        }
+    }
+
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(o, _)| o == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    /// Replaces the operands of `opcode`, adding the operation if there is none yet.
+    pub fn set_operation(&mut self, opcode: &str, operands: Vec<String>) {
+        match self.operations.iter_mut().find(|(o, _)| o == opcode) {
+            Some((_, o)) => *o = operands,
+            None => self.operations.push((opcode.to_string(), operands)),
+        }
+    }
+
+    /// The position's name from the `id` operation.
+    pub fn id(&self) -> Option<&str> {
+        self.operation("id")?.first().map(String::as_str)
+    }
+
+    /// The search depth of the `acd` operation.
+    pub fn depth(&self) -> Option<u32> {
+        self.operation("acd")?.first()?.parse().ok()
+    }
+
+    /// The centipawn evaluation of the `ce` operation.
+    pub fn centipawns(&self) -> Option<i32> {
+        self.operation("ce")?.first()?.parse().ok()
+    }
+
+    /// The operands of `opcode` read as moves, in SAN or in UCI notation.
+    pub fn moves(&self, opcode: &str) -> Result<Vec<Move>, ParseEpdError> {
+        let mut board = self.board.clone();
+
+        self.operation(opcode)
+            .unwrap_or_default()
+            .iter()
+            .map(
+                |s| match board.parse_san(s).or_else(|| board.parse_uci_move(s)) {
+                    Some(m) => Ok(m),
+                    None => parse_epd_error!("illegal move '{}' in '{}'", s, opcode),
+                },
+            )
+            .collect()
+    }
+
+    /// Whether `m` passes the `bm` and `am` tests of the position, `None` if it has neither.
+    pub fn test_move(&self, m: Move) -> Result<Option<bool>, ParseEpdError> {
+        let best = self.moves("bm")?;
+        let avoid = self.moves("am")?;
+        if best.is_empty() && avoid.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            (best.is_empty() || best.contains(&m)) && !avoid.contains(&m),
+        ))
+    }
+}
+
+impl FromStr for Epd {
+    type Err = ParseEpdError;
+
+    fn from_str(s: &str) -> Result<Epd, ParseEpdError> {
+        let mut rest = s.trim();
+        let mut fields = Vec::with_capacity(4);
+        while fields.len() < 4 {
+            match rest.split_once(char::is_whitespace) {
+                Some((field, r)) => {
+                    fields.push(field);
+                    rest = r.trim_start();
+                }
+                None if !rest.is_empty() => {
+                    fields.push(rest);
+                    rest = "";
+                }
+                None => return parse_epd_error!("Invalid epd '{}': expected four fields", s),
+            }
+        }
+
+        let operations = match parse_operations(rest) {
+            Ok(operations) => operations,
+            Err(e) => return parse_epd_error!("Invalid epd '{}': {}", s, e),
+        };
+        let counter = |opcode: &str, default: &str| {
+            operations
+                .iter()
+                .find(|(o, _)| o == opcode)
+                .and_then(|(_, operands)| operands.first().cloned())
+                .unwrap_or_else(|| default.to_string())
+        };
+
+        let fen = format!(
+            "{} {} {}",
+            fields.join(" "),
+            counter("hmvc", "0"),
+            counter("fmvn", "1")
+        );
+        match fen.parse() {
+            Ok(board) => Ok(Epd { board, operations }),
+            Err(e) => parse_epd_error!("{}", e),
+        }
+    }
+}
+
+/// Writes the position back as EPD, e.g. `4k3/8/8/8/8/8/4P3/4K3 w - - bm Kd2; id "pawn";`.
+impl Display for Epd {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let fen = self.board.to_fen();
+        let fields: Vec<&str> = fen.split(' ').take(4).collect();
+        write!(f, "{}", fields.join(" "))?;
+
+        for (opcode, operands) in &self.operations {
+            write!(f, " {}", opcode)?;
+            for operand in operands {
+                if is_string_opcode(opcode) || operand.is_empty() || operand.contains([' ', ';']) {
+                    write!(f, " \"{}\"", operand)?;
+                } else {
+                    write!(f, " {}", operand)?;
+                }
+            }
+            write!(f, ";")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the operands of `opcode` are always strings: `id` and the comments `c0` to `c9`.
+fn is_string_opcode(opcode: &str) -> bool {
+    opcode == "id" || matches!(opcode.as_bytes(), [b'c', b'0'..=b'9'])
+}
+
+fn parse_operations(s: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut operations = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut opcode = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+            opcode.push(c);
+        }
+        if !opcode.starts_with(|c: char| c.is_ascii_alphabetic())
+            || !opcode
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(format!("invalid opcode '{}'", opcode));
+        }
+
+        let mut operands = Vec::new();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            match chars.next() {
+                // the last operation may go without its semicolon
+                Some(';') | None => break,
+                Some('"') => {
+                    let mut operand = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some(c) => operand.push(c),
+                            None => return Err(format!("unterminated string in '{}'", opcode)),
+                        }
+                    }
+                    operands.push(operand);
+                }
+                Some(c) => {
+                    let mut operand = c.to_string();
+                    while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+                        operand.push(c);
+                    }
+                    operands.push(operand);
+                }
+            }
+        }
+
+        operations.push((opcode, operands));
+    }
+
+    Ok(operations)
+}
+
+/// Reads an EPD file, a position per line. Empty lines and those starting with `#` are skipped.
+pub fn parse_epd(s: &str) -> Result<Vec<Epd>, ParseEpdError> {
+    s.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(idx, line)| match line.parse() {
+            Ok(epd) => Ok(epd),
+            Err(e) => parse_epd_error!("line {}: {}", idx + 1, e),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::epd::{parse_epd, Epd};
+
+    #[test]
+    fn read_and_write() {
+        let s = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - \
+                 bm Bb5 Bc4; am a3; id \"italian or spanish\"; acd 12; ce -15; c0 \"a; b\"";
+        let epd: Epd = s.parse().unwrap();
+
+        assert_eq!(Some("italian or spanish"), epd.id());
+        assert_eq!(Some(12), epd.depth());
+        assert_eq!(Some(-15), epd.centipawns());
+        assert_eq!(Some(&["a; b".to_string()][..]), epd.operation("c0"));
+        assert_eq!(2, epd.moves("bm").unwrap().len());
+        assert_eq!(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1",
+            epd.board.to_fen()
+        );
+
+        assert_eq!(format!("{};", s), epd.to_string());
+        assert_eq!(
+            epd.to_string(),
+            epd.to_string().parse::<Epd>().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn counters_and_operations() {
+        let mut epd: Epd = "4k3/8/8/8/8/8/4P3/4K3 b - - hmvc 12; fmvn 40;"
+            .parse()
+            .unwrap();
+        assert_eq!("4k3/8/8/8/8/8/4P3/4K3 b - - 12 40", epd.board.to_fen());
+        assert_eq!(None, epd.id());
+
+        epd.set_operation("acd", vec!["5".to_string()]);
+        epd.set_operation("acd", vec!["6".to_string()]);
+        epd.set_operation("id", vec!["endgame".to_string()]);
+        assert_eq!(
+            "4k3/8/8/8/8/8/4P3/4K3 b - - hmvc 12; fmvn 40; acd 6; id \"endgame\";",
+            epd.to_string()
+        );
+
+        assert_eq!(
+            "4k3/8/8/8/8/8/4P3/4K3 w - -",
+            Epd::new("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".parse().unwrap()).to_string()
+        );
+    }
+
+    #[test]
+    fn test_moves() {
+        let epd: Epd = "4k3/8/8/8/8/8/4P3/4K3 w - - bm e4 Kd2; am Kf1;"
+            .parse()
+            .unwrap();
+        let mut board = epd.board.clone();
+        let m = |b: &mut Board, s: &str| b.parse_san(s).unwrap();
+
+        assert_eq!(Some(true), epd.test_move(m(&mut board, "e4")).unwrap());
+        assert_eq!(Some(false), epd.test_move(m(&mut board, "e3")).unwrap());
+
+        let avoid: Epd = "4k3/8/8/8/8/8/4P3/4K3 w - - am Kf1".parse().unwrap();
+        assert_eq!(Some(true), avoid.test_move(m(&mut board, "e3")).unwrap());
+        assert_eq!(Some(false), avoid.test_move(m(&mut board, "Kf1")).unwrap());
+
+        let no_test: Epd = "4k3/8/8/8/8/8/4P3/4K3 w - - id \"x\";".parse().unwrap();
+        assert_eq!(None, no_test.test_move(m(&mut board, "e3")).unwrap());
+
+        let uci: Epd = "4k3/8/8/8/8/8/4P3/4K3 w - - bm e2e4;".parse().unwrap();
+        assert_eq!(Some(true), uci.test_move(m(&mut board, "e4")).unwrap());
+
+        let illegal: Epd = "4k3/8/8/8/8/8/4P3/4K3 w - - bm e5;".parse().unwrap();
+        assert!(illegal.test_move(m(&mut board, "e4")).is_err());
+    }
+
+    #[test]
+    fn errors() {
+        assert!("4k3/8/8/8/8/8/4P3/4K3 w -".parse::<Epd>().is_err());
+        assert!("4k3/8/8/8/8/8/4P3/4K3 x - -".parse::<Epd>().is_err());
+        assert!("4k3/8/8/8/8/8/4P3/4K3 w - - id \"open"
+            .parse::<Epd>()
+            .is_err());
+        assert!("4k3/8/8/8/8/8/4P3/4K3 w - - 5x e4;".parse::<Epd>().is_err());
+
+        let file = "# a suite\n4k3/8/8/8/8/8/4P3/4K3 w - - bm e4;\n\n4k3/8/8/8/8/8/4P3/4K3 b - - bm Kd7;\n";
+        assert_eq!(2, parse_epd(file).unwrap().len());
+
+        let err = parse_epd("4k3/8/8/8/8/8/4P3/4K3 w - -\n4k3/8/8 w - -\n").unwrap_err();
+        assert!(err.to_string().starts_with("line 2: "));
+    }
+}
@@ -0,0 +1,451 @@
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::piece::{Color, Piece, PieceType};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+
+pub const PAWN_VALUE: i32 = 100;
+pub const KNIGHT_VALUE: i32 = 320;
+pub const BISHOP_VALUE: i32 = 330;
+pub const ROOK_VALUE: i32 = 500;
+pub const QUEEN_VALUE: i32 = 900;
+
+pub fn piece_value(t: PieceType) -> i32 {
+    match t {
+        PieceType::King => 0,
+        PieceType::Queen => QUEEN_VALUE,
+        PieceType::Rook => ROOK_VALUE,
+        PieceType::Bishop => BISHOP_VALUE,
+        PieceType::Knight => KNIGHT_VALUE,
+        PieceType::Pawn => PAWN_VALUE,
+    }
+}
+
+// The piece-square tables are written as seen from white, a8 first.
+
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_MIDDLEGAME_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+/// Non-pawn material of both sides at the start, the king tables are blended by how much of it
+/// is left.
+const FULL_PHASE: i32 = 2 * (2 * KNIGHT_VALUE + 2 * BISHOP_VALUE + 2 * ROOK_VALUE + QUEEN_VALUE);
+
+fn table_idx(piece: &Piece, pos: &Position) -> usize {
+    match piece.color {
+        Color::White => (7 - pos.rank as usize) * 8 + pos.file as usize,
+        Color::Black => pos.rank as usize * 8 + pos.file as usize,
+    }
+}
+
+/// How far the game is from the endgame, from `FULL_PHASE` at the start down to zero. The
+/// phase uses the fixed piece values, so that tuning them doesn't move it.
+fn phase(board: &Board) -> i32 {
+    Position::iter()
+        .filter_map(|pos| board.get_piece(&pos))
+        .filter(|p| p.piece_type != PieceType::Pawn)
+        .map(|p| piece_value(p.piece_type))
+        .sum::<i32>()
+        .min(FULL_PHASE)
+}
+
+#[derive(Debug)]
+pub struct ParseEvalParamsError {
+    description: String,
+}
+
+impl Display for ParseEvalParamsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for ParseEvalParamsError {}
+
+macro_rules! parse_eval_params_error {
+    ($($fmt:expr),*) => {
+        Err(ParseEvalParamsError{description:format!($($fmt),*)})
+    }
+}
+
+/// The names of the weights in a weights file, each followed by its values.
+const SECTIONS: [&str; 8] = [
+    "material",
+    "king",
+    "queen",
+    "rook",
+    "bishop",
+    "knight",
+    "pawn",
+    "king_endgame",
+];
+
+/// The weights of the handcrafted evaluation in centipawns, indexed by `PieceType`.
+///
+/// They are written to and read from a weights file as sections of a name and its numbers, the
+/// material first, then the piece-square tables as seen from white, a8 first.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EvalParams {
+    pub material: [i32; 6],
+    /// The king's table is the one for the middlegame.
+    pub tables: [[i32; 64]; 6],
+    pub king_endgame: [i32; 64],
+}
+
+impl EvalParams {
+    pub const DEFAULT: EvalParams = EvalParams {
+        material: [0, QUEEN_VALUE, ROOK_VALUE, BISHOP_VALUE, KNIGHT_VALUE, PAWN_VALUE],
+        tables: [
+            KING_MIDDLEGAME_TABLE,
+            QUEEN_TABLE,
+            ROOK_TABLE,
+            BISHOP_TABLE,
+            KNIGHT_TABLE,
+            PAWN_TABLE,
+        ],
+        king_endgame: KING_ENDGAME_TABLE,
+    };
+    /// The number of weights.
+    pub const LEN: usize = 6 + 7 * 64;
+
+    /// Reads a weights file, as written by the tuner.
+    pub fn load(path: impl AsRef<Path>) -> Result<EvalParams, ParseEvalParamsError> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(contents) => match contents.parse() {
+                Ok(params) => Ok(params),
+                Err(e) => parse_eval_params_error!("{}: {}", path.display(), e),
+            },
+            Err(e) => parse_eval_params_error!("{}: {}", path.display(), e),
+        }
+    }
+
+    /// All weights in the order of the weights file.
+    pub fn weights(&self) -> Vec<i32> {
+        let tables = self.tables.iter().chain([&self.king_endgame]);
+        self.material.iter().chain(tables.flatten()).copied().collect()
+    }
+
+    /// The parameters with `weights` in the order of the weights file, `None` if there are too
+    /// few.
+    pub fn from_weights(weights: &[i32]) -> Option<EvalParams> {
+        if weights.len() < Self::LEN {
+            return None;
+        }
+
+        let mut params = EvalParams::DEFAULT;
+        let tables = params.tables.iter_mut().chain([&mut params.king_endgame]);
+        for (w, v) in params.material.iter_mut().chain(tables.flatten()).zip(weights) {
+            *w = *v;
+        }
+        Some(params)
+    }
+
+    /// The coefficients of the weights in the evaluation of the position from white's view, so
+    /// that it is the sum of every coefficient times its weight. The king tables are blended by
+    /// the phase, so their coefficients are fractions.
+    pub fn features(board: &Board) -> Vec<(usize, f64)> {
+        let phase = phase(board) as f64 / FULL_PHASE as f64;
+        let mut features = Vec::with_capacity(64);
+
+        for pos in Position::iter() {
+            let Some(piece) = board.get_piece(&pos) else {
+                continue;
+            };
+            let sign = match piece.color {
+                Color::White => 1.0,
+                Color::Black => -1.0,
+            };
+            let t = piece.piece_type as usize;
+            let idx = table_idx(&piece, &pos);
+
+            features.push((t, sign));
+            if piece.piece_type == PieceType::King {
+                features.push((6 + idx, sign * phase));
+                features.push((6 + 6 * 64 + idx, sign * (1.0 - phase)));
+            } else {
+                features.push((6 + t * 64 + idx, sign));
+            }
+        }
+
+        features
+    }
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Display for EvalParams {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let weights = self.weights();
+        let (material, tables) = weights.split_at(6);
+
+        writeln!(f, "{}", SECTIONS[0])?;
+        writeln!(f, "{}", join(material))?;
+        for (name, table) in SECTIONS[1..].iter().zip(tables.chunks(64)) {
+            writeln!(f, "\n{}", name)?;
+            for row in table.chunks(8) {
+                writeln!(f, "{}", join(row))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn join(weights: &[i32]) -> String {
+    let numbers: Vec<String> = weights.iter().map(|w| format!("{:4}", w)).collect();
+    numbers.join(" ")
+}
+
+impl FromStr for EvalParams {
+    type Err = ParseEvalParamsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace().peekable();
+        let mut weights = Vec::with_capacity(Self::LEN);
+
+        for (name, len) in SECTIONS.iter().zip([6].into_iter().chain([64; 7])) {
+            match words.next() {
+                Some(word) if word == *name => {}
+                Some(word) => return parse_eval_params_error!("expected '{}', got '{}'", name, word),
+                None => return parse_eval_params_error!("missing '{}'", name),
+            }
+            for _ in 0..len {
+                match words.next().map(|w| (w, w.parse::<i32>())) {
+                    Some((_, Ok(n))) => weights.push(n),
+                    Some((w, Err(_))) => {
+                        return parse_eval_params_error!("invalid weight '{}' in '{}'", w, name)
+                    }
+                    None => return parse_eval_params_error!("'{}' needs {} weights", name, len),
+                }
+            }
+        }
+        if let Some(word) = words.next() {
+            return parse_eval_params_error!("unexpected '{}' after the weights", word);
+        }
+
+        Ok(EvalParams::from_weights(&weights).expect("all weights were read"))
+    }
+}
+
+/// A static evaluation of the position in centipawns from the view of the side to move: material
+/// plus piece-square tables, or the network's evaluation if the board has one loaded.
+pub fn evaluate(board: &Board) -> i32 {
+    evaluate_using(board, &EvalParams::DEFAULT)
+}
+
+/// Like `evaluate`, with other weights for the handcrafted evaluation.
+pub fn evaluate_using(board: &Board, params: &EvalParams) -> i32 {
+    #[cfg(feature = "nnue")]
+    if let Some(score) = board.nnue_evaluate() {
+        return score;
+    }
+
+    evaluate_with(board, params)
+}
+
+/// The handcrafted evaluation with other weights, from the view of the side to move.
+pub fn evaluate_with(board: &Board, params: &EvalParams) -> i32 {
+    let mut score = [0; 2];
+    let mut king_score = [(0, 0); 2];
+
+    for pos in Position::iter() {
+        let piece = match board.get_piece(&pos) {
+            Some(p) => p,
+            None => continue,
+        };
+        let idx = table_idx(&piece, &pos);
+        let side = piece.color as usize;
+        let t = piece.piece_type as usize;
+
+        score[side] += params.material[t];
+        if piece.piece_type == PieceType::King {
+            king_score[side] = (params.tables[t][idx], params.king_endgame[idx]);
+        } else {
+            score[side] += params.tables[t][idx];
+        }
+    }
+
+    let phase = phase(board);
+    for side in 0..2 {
+        let (middlegame, endgame) = king_score[side];
+        score[side] += (middlegame * phase + endgame * (FULL_PHASE - phase)) / FULL_PHASE;
+    }
+
+    let us = board.current_player() as usize;
+    score[us] - score[1 - us]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::evaluation::{evaluate, evaluate_with, EvalParams};
+    use crate::piece::Color;
+
+    #[test]
+    fn symmetric() {
+        assert_eq!(0, evaluate(&Board::new()));
+
+        let w: Board = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3"
+            .parse()
+            .unwrap();
+        let b: Board = "rnbqkb1r/pppp1ppp/5n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq - 2 3"
+            .parse()
+            .unwrap();
+        assert_eq!(evaluate(&w), evaluate(&b));
+    }
+
+    #[test]
+    fn material() {
+        // white is a queen up
+        let b: Board = "4k3/8/8/8/8/8/8/3QK3 w - - 0 1".parse().unwrap();
+        assert!(evaluate(&b) > 800);
+
+        let b: Board = "4k3/8/8/8/8/8/8/3QK3 b - - 0 1".parse().unwrap();
+        assert!(evaluate(&b) < -800);
+    }
+
+    #[test]
+    fn weights_file() {
+        let text = EvalParams::DEFAULT.to_string();
+        assert!(text.starts_with("material\n   0  900  500  330  320  100\n\nking\n -30  -40"));
+        assert_eq!(EvalParams::DEFAULT, text.parse().unwrap());
+
+        let mut params = EvalParams::DEFAULT;
+        params.material[5] = 95;
+        params.king_endgame[63] = -7;
+        assert_eq!(params, params.to_string().parse().unwrap());
+        assert_eq!(Some(params.clone()), EvalParams::from_weights(&params.weights()));
+
+        let error = |s: &str| s.parse::<EvalParams>().unwrap_err().to_string();
+        assert_eq!("missing 'material'", error(""));
+        assert_eq!("expected 'material', got 'pawn'", error("pawn 1 2"));
+        assert_eq!("invalid weight 'x' in 'material'", error("material 1 x"));
+        assert_eq!("'king_endgame' needs 64 weights", error(&text[..text.len() - 10]));
+        assert_eq!(
+            "unexpected '3' after the weights",
+            error(&format!("{} 3", text))
+        );
+
+        let path = std::env::temp_dir().join(format!("weights-{}.txt", std::process::id()));
+        std::fs::write(&path, params.to_string()).unwrap();
+        assert_eq!(params, EvalParams::load(&path).unwrap());
+        std::fs::write(&path, "material 1 2").unwrap();
+        assert_eq!(
+            format!("{}: 'material' needs 6 weights", path.display()),
+            EvalParams::load(&path).unwrap_err().to_string()
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert!(EvalParams::load(&path).is_err());
+    }
+
+    #[test]
+    fn features() {
+        let mut params = EvalParams::DEFAULT;
+        params.material[1] = 1000;
+        params.king_endgame[10] = 33;
+        let weights = params.weights();
+
+        for fen in [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            "8/2k5/8/3p4/8/4K3/1q6/8 b - - 0 60",
+            "4k3/8/8/8/8/8/8/3QK3 w - - 0 1",
+        ] {
+            let b: Board = fen.parse().unwrap();
+            let white = match b.current_player() {
+                Color::White => evaluate_with(&b, &params),
+                Color::Black => -evaluate_with(&b, &params),
+            };
+            let linear: f64 = EvalParams::features(&b)
+                .iter()
+                .map(|(i, c)| c * weights[*i] as f64)
+                .sum();
+            assert!((linear - white as f64).abs() < 1.0, "{}: {} {}", fen, linear, white);
+        }
+    }
+}
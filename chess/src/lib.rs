@@ -1,3 +1,17 @@
 pub mod board;
+pub mod chess_move;
+pub mod clock;
+pub mod epd;
+pub mod evaluation;
+#[cfg(feature = "nnue")]
+pub mod nnue;
+pub mod pgn;
 pub mod piece;
-
+pub mod polyglot;
+pub mod search;
+#[cfg(feature = "serde")]
+pub mod serialization;
+#[cfg(feature = "syzygy")]
+pub mod syzygy;
+pub mod uci_client;
+pub mod variant;
@@ -0,0 +1,419 @@
+use crate::board::position::Position;
+use crate::piece::{Color, Piece, PieceType};
+use std::fmt::{Debug, Display, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+
+/// One input for every piece of either side on every square.
+pub const FEATURES: usize = 2 * 6 * 64;
+/// The largest hidden layer a network file may have.
+pub const MAX_HIDDEN: usize = 1024;
+
+const MAGIC: &[u8; 4] = b"NNUE";
+/// The quantization of the hidden layer, activations are clipped to `0..=QA`.
+const QA: i32 = 255;
+/// The quantization of the output weights.
+const QB: i32 = 64;
+/// Converts the network output to centipawns.
+const SCALE: i32 = 400;
+
+#[derive(Debug)]
+pub struct LoadNetworkError {
+    description: String,
+}
+
+impl Display for LoadNetworkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for LoadNetworkError {}
+
+macro_rules! load_network_error {
+    ($($fmt:expr),*) => {
+        Err(LoadNetworkError{description:format!($($fmt),*)})
+    }
+}
+
+/// An efficiently updatable neural network: the 768 piece-square inputs feed a hidden layer
+/// once from the view of each side, and the two halves, the side to move's first, feed the
+/// output.
+///
+/// The file format is the magic `NNUE`, the hidden size as a little-endian `u32`, then the
+/// input weights (input by input), the hidden biases and the output weights as little-endian
+/// `i16`s and the output bias as an `i32`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Network {
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+impl Network {
+    pub fn load(path: impl AsRef<Path>) -> Result<Network, LoadNetworkError> {
+        let path = path.as_ref();
+        match std::fs::read(path) {
+            Ok(bytes) => Network::from_bytes(&bytes),
+            Err(e) => load_network_error!("{}: {}", path.display(), e),
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, LoadNetworkError> {
+        if bytes.len() < 8 || &bytes[..4] != MAGIC {
+            return load_network_error!("not a network file");
+        }
+        let hidden = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+        if hidden == 0 || hidden > MAX_HIDDEN || !hidden.is_multiple_of(16) {
+            return load_network_error!(
+                "the hidden layer has {} neurons, expected a multiple of 16 up to {}",
+                hidden,
+                MAX_HIDDEN
+            );
+        }
+
+        let expected = 8 + 2 * (FEATURES * hidden + hidden + 2 * hidden) + 4;
+        if bytes.len() != expected {
+            return load_network_error!(
+                "the file has {} bytes, expected {} for {} hidden neurons",
+                bytes.len(),
+                expected,
+                hidden
+            );
+        }
+
+        let mut words = bytes[8..expected - 4]
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]));
+        let mut take = |n| words.by_ref().take(n).collect::<Vec<_>>();
+        let b = &bytes[expected - 4..];
+
+        Ok(Network {
+            hidden,
+            feature_weights: take(FEATURES * hidden),
+            feature_biases: take(hidden),
+            output_weights: take(2 * hidden),
+            output_bias: i32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        })
+    }
+
+    /// The network in its file format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend((self.hidden as u32).to_le_bytes());
+        for w in [
+            &self.feature_weights,
+            &self.feature_biases,
+            &self.output_weights,
+        ] {
+            bytes.extend(w.iter().flat_map(|w| w.to_le_bytes()));
+        }
+        bytes.extend(self.output_bias.to_le_bytes());
+
+        bytes
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.hidden
+    }
+
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+}
+
+/// The weights aren't worth printing.
+impl Debug for Network {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Network {{ hidden: {} }}", self.hidden)
+    }
+}
+
+/// The hidden layer of a network for the pieces on a board, from the view of both sides. It
+/// is updated with every piece put on or removed from the board instead of being recomputed.
+#[derive(Clone, Debug)]
+pub struct Accumulator {
+    network: Arc<Network>,
+    /// Indexed by the color whose view it is.
+    values: [Vec<i16>; 2],
+}
+
+impl Accumulator {
+    pub fn new(network: Arc<Network>, pieces: impl IntoIterator<Item = (Piece, Position)>) -> Self {
+        let mut acc = Accumulator {
+            values: [
+                network.feature_biases.clone(),
+                network.feature_biases.clone(),
+            ],
+            network,
+        };
+        for (piece, pos) in pieces {
+            acc.add(&piece, &pos);
+        }
+
+        acc
+    }
+
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    pub fn add(&mut self, piece: &Piece, pos: &Position) {
+        for view in Color::iter() {
+            let weights = self.network.weights(feature(view, piece, pos));
+            simd::add(&mut self.values[view as usize], weights);
+        }
+    }
+
+    pub fn remove(&mut self, piece: &Piece, pos: &Position) {
+        for view in Color::iter() {
+            let weights = self.network.weights(feature(view, piece, pos));
+            simd::sub(&mut self.values[view as usize], weights);
+        }
+    }
+
+    /// The evaluation in centipawns from the view of `side`.
+    pub fn evaluate(&self, side: Color) -> i32 {
+        let (us, them) = (
+            &self.values[side as usize],
+            &self.values[side.opponent() as usize],
+        );
+        let (ours, theirs) = self.network.output_weights.split_at(self.network.hidden);
+        let sum = simd::crelu_dot(us, ours) as i64
+            + simd::crelu_dot(them, theirs) as i64
+            + self.network.output_bias as i64;
+
+        (sum * SCALE as i64 / (QA * QB) as i64) as i32
+    }
+}
+
+/// The input of `piece` on `pos` from the view of `view`: the board is flipped for black, so
+/// that each side sees its own pieces first and moving up the board.
+fn feature(view: Color, piece: &Piece, pos: &Position) -> usize {
+    let square = match view {
+        Color::White => pos.idx(),
+        Color::Black => pos.idx() ^ 56,
+    };
+    let kind = match piece.piece_type {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    };
+    let side = (piece.color != view) as usize;
+
+    (side * 6 + kind) * 64 + square
+}
+
+/// The vector operations of the network, with AVX2 when the CPU has it.
+mod simd {
+    use super::QA;
+
+    pub fn add(acc: &mut [i16], weights: &[i16]) {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2.
+            return unsafe { avx2::add(acc, weights) };
+        }
+        scalar::add(acc, weights)
+    }
+
+    pub fn sub(acc: &mut [i16], weights: &[i16]) {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2.
+            return unsafe { avx2::sub(acc, weights) };
+        }
+        scalar::sub(acc, weights)
+    }
+
+    /// The dot product of the clipped ReLU of `acc` with `weights`.
+    pub fn crelu_dot(acc: &[i16], weights: &[i16]) -> i32 {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2.
+            return unsafe { avx2::crelu_dot(acc, weights) };
+        }
+        scalar::crelu_dot(acc, weights)
+    }
+
+    pub mod scalar {
+        use super::QA;
+
+        #[inline(always)]
+        pub fn add(acc: &mut [i16], weights: &[i16]) {
+            for (a, w) in acc.iter_mut().zip(weights) {
+                *a = a.wrapping_add(*w);
+            }
+        }
+
+        #[inline(always)]
+        pub fn sub(acc: &mut [i16], weights: &[i16]) {
+            for (a, w) in acc.iter_mut().zip(weights) {
+                *a = a.wrapping_sub(*w);
+            }
+        }
+
+        pub fn crelu_dot(acc: &[i16], weights: &[i16]) -> i32 {
+            acc.iter().zip(weights).fold(0i32, |sum, (a, w)| {
+                sum.wrapping_add((*a as i32).clamp(0, QA) * *w as i32)
+            })
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    mod avx2 {
+        use super::{scalar, QA};
+        use std::arch::x86_64::*;
+
+        // the plain loops vectorize well once the compiler may use AVX2
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn add(acc: &mut [i16], weights: &[i16]) {
+            scalar::add(acc, weights)
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn sub(acc: &mut [i16], weights: &[i16]) {
+            scalar::sub(acc, weights)
+        }
+
+        /// Expects a length that is a multiple of 16, as the network's hidden size is.
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn crelu_dot(acc: &[i16], weights: &[i16]) -> i32 {
+            let (zero, max) = (_mm256_setzero_si256(), _mm256_set1_epi16(QA as i16));
+            let mut sum = _mm256_setzero_si256();
+
+            for (a, w) in acc.chunks_exact(16).zip(weights.chunks_exact(16)) {
+                let a = _mm256_loadu_si256(a.as_ptr() as *const __m256i);
+                let w = _mm256_loadu_si256(w.as_ptr() as *const __m256i);
+                let a = _mm256_min_epi16(_mm256_max_epi16(a, zero), max);
+                sum = _mm256_add_epi32(sum, _mm256_madd_epi16(a, w));
+            }
+
+            let mut lanes = [0i32; 8];
+            _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sum);
+            lanes.iter().fold(0, |s, l| s.wrapping_add(*l))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::evaluation::evaluate;
+    use crate::nnue::{simd, Network, FEATURES};
+    use std::sync::Arc;
+
+    /// A network with pseudo-random weights of realistic size.
+    fn network(hidden: usize) -> Network {
+        let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = |range: i64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            ((seed % (2 * range as u64 + 1)) as i64 - range) as i16
+        };
+
+        Network {
+            hidden,
+            feature_weights: (0..FEATURES * hidden).map(|_| next(60)).collect(),
+            feature_biases: (0..hidden).map(|_| next(100)).collect(),
+            output_weights: (0..2 * hidden).map(|_| next(120)).collect(),
+            output_bias: 1234,
+        }
+    }
+
+    #[test]
+    fn file_format() {
+        let net = network(32);
+        let bytes = net.to_bytes();
+        assert_eq!(Some(&net), Network::from_bytes(&bytes).ok().as_ref());
+
+        let path = std::env::temp_dir().join(format!("nnue-test-{}.bin", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(32, Network::load(&path).unwrap().hidden_size());
+        std::fs::remove_file(&path).unwrap();
+
+        let error = |bytes: &[u8]| Network::from_bytes(bytes).unwrap_err().to_string();
+        assert_eq!("not a network file", error(b"PK\x03\x04rest"));
+        assert_eq!(
+            "the file has 49355 bytes, expected 49356 for 32 hidden neurons",
+            error(&bytes[..bytes.len() - 1])
+        );
+        let mut odd = bytes.clone();
+        odd[4] = 33;
+        assert!(error(&odd).starts_with("the hidden layer has 33 neurons"));
+        assert!(Network::load("/nonexistent/net.bin").is_err());
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let mut b: Board = "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1"
+            .parse()
+            .unwrap();
+        b.set_network(Some(Arc::new(network(64))));
+        let start = b.nnue_evaluate().unwrap();
+
+        // en passant, promotion with capture, castling and the undoing of all of them
+        let mut played = Vec::new();
+        for san in ["exd6", "O-O", "bxa8=Q", "Rxa8", "O-O-O", "Kg7"] {
+            let m = b.parse_san(san).unwrap();
+            b.make_move(m);
+            played.push(m);
+
+            let mut fresh = b.clone();
+            fresh.set_network(b.network().cloned());
+            assert_eq!(fresh.nnue_evaluate(), b.nnue_evaluate(), "after {}", san);
+        }
+        for m in played.into_iter().rev() {
+            b.unmake_move(m);
+        }
+        assert_eq!(Some(start), b.nnue_evaluate());
+    }
+
+    #[test]
+    fn evaluation() {
+        let net = Arc::new(network(64));
+        let mut b: Board = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4"
+            .parse()
+            .unwrap();
+
+        // without a network the handcrafted evaluation is used
+        assert_eq!(None, b.nnue_evaluate());
+        let handcrafted = evaluate(&b);
+        b.set_network(Some(net.clone()));
+        assert_eq!(b.nnue_evaluate(), Some(evaluate(&b)));
+        assert_ne!(handcrafted, evaluate(&b));
+
+        // both sides see the board the same way
+        let mut mirrored: Board =
+            "rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4"
+                .parse()
+                .unwrap();
+        mirrored.set_network(Some(net));
+        assert_eq!(b.nnue_evaluate(), mirrored.nnue_evaluate());
+    }
+
+    #[test]
+    fn vector_operations() {
+        let net = network(64);
+        let mut acc = net.feature_biases.clone();
+        let mut expected = acc.clone();
+
+        simd::add(&mut acc, net.weights(5));
+        simd::scalar::add(&mut expected, net.weights(5));
+        simd::sub(&mut acc, net.weights(700));
+        simd::scalar::sub(&mut expected, net.weights(700));
+        assert_eq!(expected, acc);
+
+        acc.iter_mut().step_by(3).for_each(|a| *a *= 9);
+        assert_eq!(
+            simd::scalar::crelu_dot(&acc, &net.output_weights[..64]),
+            simd::crelu_dot(&acc, &net.output_weights[..64])
+        );
+    }
+}
@@ -0,0 +1,525 @@
+use crate::board::Board;
+use crate::chess_move::Move;
+use std::fmt::{Display, Formatter};
+
+/// A game read from a PGN file: the tag pairs, the moves with their annotations and the result.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PgnGame {
+    pub headers: Vec<(String, String)>,
+    /// The SAN of the main line moves.
+    pub moves: Vec<String>,
+    /// The main line with comments, NAGs and variations.
+    pub main_line: Vec<PgnNode>,
+    /// A comment before the first move.
+    pub comment: Option<String>,
+    pub result: Option<String>,
+}
+
+/// A move in the movetext together with everything written after it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PgnNode {
+    pub san: String,
+    /// Numeric annotation glyphs, move suffixes like `!?` are stored as their NAG.
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+    /// Lines played instead of this move.
+    pub variations: Vec<Vec<PgnNode>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ParsePgnError {
+    description: String,
+}
+
+impl Display for ParsePgnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for ParsePgnError {}
+
+macro_rules! parse_pgn_error {
+    ($($fmt:expr),*) => {
+        Err(ParsePgnError{description:format!($($fmt),*)})
+    }
+}
+
+impl PgnGame {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The position the game starts from, taken from the `FEN` tag if there is one.
+    pub fn start_position(&self) -> Result<Board, ParsePgnError> {
+        match self.header("FEN") {
+            Some(fen) => match fen.parse() {
+                Ok(b) => Ok(b),
+                Err(_) => parse_pgn_error!("invalid FEN tag '{}'", fen),
+            },
+            None => Ok(Board::new()),
+        }
+    }
+
+    /// Plays through the main line, returning the start position and the moves.
+    pub fn replay(&self) -> Result<(Board, Vec<Move>), ParsePgnError> {
+        let start = self.start_position()?;
+        let mut b = self.start_position()?;
+        let mut moves = Vec::with_capacity(self.moves.len());
+
+        for san in &self.moves {
+            match b.parse_san(san) {
+                Some(m) => {
+                    b.make_move(m);
+                    moves.push(m);
+                }
+                None => {
+                    return parse_pgn_error!(
+                        "illegal or ambiguous move '{}' after {} plies",
+                        san,
+                        moves.len()
+                    )
+                }
+            }
+        }
+
+        Ok((start, moves))
+    }
+}
+
+/// Writes the game in PGN export format, wrapping the movetext at 80 characters.
+///
+/// The main line with its annotations is written, or the plain moves if there is none.
+impl Display for PgnGame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in &self.headers {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{} \"{}\"]", name, value)?;
+        }
+        if !self.headers.is_empty() {
+            writeln!(f)?;
+        }
+
+        let mut tokens = Vec::new();
+        if let Some(comment) = &self.comment {
+            tokens.push(format!("{{{}}}", comment));
+        }
+
+        let plain: Vec<PgnNode>;
+        let line = if self.main_line.is_empty() {
+            plain = self
+                .moves
+                .iter()
+                .map(|san| PgnNode {
+                    san: san.clone(),
+                    ..PgnNode::default()
+                })
+                .collect();
+            &plain
+        } else {
+            &self.main_line
+        };
+        let start_ply = self.start_position().map_or(0, |b| b.ply());
+        movetext(line, start_ply, &mut tokens);
+        tokens.push(self.result.clone().unwrap_or_else(|| String::from("*")));
+
+        let mut width = 0;
+        for token in tokens {
+            if width > 0 && width + 1 + token.len() > 80 {
+                writeln!(f)?;
+                width = 0;
+            }
+            if width > 0 {
+                write!(f, " ")?;
+                width += 1;
+            }
+            write!(f, "{}", token)?;
+            width += token.len();
+        }
+        writeln!(f)
+    }
+}
+
+/// Appends the tokens of a line starting at `ply`, numbering black's moves where the flow of
+/// moves was interrupted.
+fn movetext(line: &[PgnNode], mut ply: u32, tokens: &mut Vec<String>) {
+    let mut interrupted = true;
+
+    for node in line {
+        if ply.is_multiple_of(2) {
+            tokens.push(format!("{}.", ply / 2 + 1));
+        } else if interrupted {
+            tokens.push(format!("{}...", ply / 2 + 1));
+        }
+        tokens.push(node.san.clone());
+        interrupted = false;
+
+        tokens.extend(node.nags.iter().map(|nag| format!("${}", nag)));
+        if let Some(comment) = &node.comment {
+            tokens.push(format!("{{{}}}", comment));
+            interrupted = true;
+        }
+
+        for variation in &node.variations {
+            let first = tokens.len();
+            movetext(variation, ply, tokens);
+            if tokens.len() > first {
+                tokens[first].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+                interrupted = true;
+            }
+        }
+
+        ply += 1;
+    }
+}
+
+/// Reads all games of a PGN file, including comments, NAGs and variations.
+pub fn parse_pgn(s: &str) -> Result<Vec<PgnGame>, ParsePgnError> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    // the main line and the variations currently open
+    let mut lines: Vec<Vec<PgnNode>> = vec![Vec::new()];
+    let mut chars = s.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            '[' if lines.len() == 1 => {
+                if !lines[0].is_empty() {
+                    finish_game(&mut game, &mut lines[0]);
+                    games.push(std::mem::take(&mut game));
+                }
+                chars.next();
+
+                let tag: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                game.headers.push(parse_tag(&tag)?);
+            }
+            '{' => {
+                chars.next();
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => comment.push(c),
+                        None => return parse_pgn_error!("unterminated comment"),
+                    }
+                }
+
+                let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+                let depth = lines.len();
+                let target = match lines.last_mut().and_then(|l| l.last_mut()) {
+                    Some(node) => &mut node.comment,
+                    None if depth == 1 => &mut game.comment,
+                    // a comment before the first move of a variation has no place to go
+                    None => continue,
+                };
+                match target {
+                    Some(c) => {
+                        c.push(' ');
+                        c.push_str(&comment);
+                    }
+                    None => *target = Some(comment),
+                }
+            }
+            ';' => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '(' => {
+                chars.next();
+                if lines.last().is_none_or(|l| l.is_empty()) {
+                    return parse_pgn_error!("variation without a move to replace");
+                }
+                lines.push(Vec::new());
+            }
+            ')' => {
+                chars.next();
+                if lines.len() == 1 {
+                    return parse_pgn_error!("unexpected ')'");
+                }
+
+                let variation = lines.pop().unwrap();
+                if let Some(node) = lines.last_mut().and_then(|l| l.last_mut()) {
+                    node.variations.push(variation);
+                }
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                let mut token = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "[]{}();".contains(c) {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+
+                match token.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                        if lines.len() > 1 {
+                            return parse_pgn_error!("unterminated variation");
+                        }
+                        game.result = Some(token);
+                        finish_game(&mut game, &mut lines[0]);
+                        games.push(std::mem::take(&mut game));
+                    }
+                    t if t.starts_with('$') => match t[1..].parse() {
+                        Ok(nag) => {
+                            if let Some(node) = lines.last_mut().and_then(|l| l.last_mut()) {
+                                node.nags.push(nag);
+                            }
+                        }
+                        Err(_) => return parse_pgn_error!("invalid NAG '{}'", t),
+                    },
+                    t => {
+                        // strip move numbers like `12.` or `12...`, which may be glued to the move,
+                        // but not the zeros of castling written as `0-0`
+                        let number = t.trim_start_matches(|c: char| c.is_ascii_digit());
+                        let t = if number.starts_with('.') {
+                            number.trim_start_matches('.')
+                        } else {
+                            t
+                        };
+                        let san = t.trim_end_matches(['!', '?']);
+                        if san.is_empty() {
+                            continue;
+                        }
+
+                        lines.last_mut().unwrap().push(PgnNode {
+                            san: san.to_string(),
+                            nags: suffix_nag(&t[san.len()..]).into_iter().collect(),
+                            ..PgnNode::default()
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    if lines.len() > 1 {
+        return parse_pgn_error!("unterminated variation");
+    }
+    if !lines[0].is_empty() || !game.headers.is_empty() {
+        finish_game(&mut game, &mut lines[0]);
+        games.push(game);
+    }
+
+    Ok(games)
+}
+
+fn finish_game(game: &mut PgnGame, main_line: &mut Vec<PgnNode>) {
+    game.main_line = std::mem::take(main_line);
+    game.moves = game.main_line.iter().map(|n| n.san.clone()).collect();
+}
+
+/// The NAG of a move suffix like `!?`.
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    Some(match suffix {
+        "!" => 1,
+        "?" => 2,
+        "!!" => 3,
+        "??" => 4,
+        "!?" => 5,
+        "?!" => 6,
+        _ => return None,
+    })
+}
+
+/// The usual symbol of a NAG, e.g. `!?` for 5 or `+-` for 18, or `$n` for the others.
+pub fn nag_symbol(nag: u8) -> String {
+    let symbol = match nag {
+        1 => "!",
+        2 => "?",
+        3 => "!!",
+        4 => "??",
+        5 => "!?",
+        6 => "?!",
+        10 => "=",
+        13 => "∞",
+        14 => "+=",
+        15 => "=+",
+        16 => "+/-",
+        17 => "-/+",
+        18 => "+-",
+        19 => "-+",
+        _ => return format!("${}", nag),
+    };
+
+    symbol.to_string()
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String), ParsePgnError> {
+    let (name, value) = match tag.trim().split_once(char::is_whitespace) {
+        Some(t) => t,
+        None => return parse_pgn_error!("invalid tag '[{}]'", tag),
+    };
+
+    let value = value.trim();
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return parse_pgn_error!("invalid tag value in '[{}]'", tag);
+    }
+
+    let value = value[1..value.len() - 1]
+        .replace("\\\"", "\"")
+        .replace("\\\\", "\\");
+
+    Ok((name.to_string(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pgn::{nag_symbol, parse_pgn, PgnGame};
+
+    const GAMES: &str = r#"
+[Event "Casual game"]
+[White "Anderssen, Adolf"]
+[Black "Kieseritzky, Lionel"]
+[Result "1-0"]
+
+1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ {the famous immortal game} 4. Kf1 b5 5. Bxb5 Nf6
+6. Nf3 Qh6 7. d3 Nh5 8. Nh4 Qg5 9. Nf5 c6 10. g4 Nf6 11. Rg1 cxb5 12. h4 Qg6
+13. h5 Qg5 14. Qf3 Ng8 15. Bxf4 Qf6 16. Nc3 Bc5 17. Nd5 Qxb2 18. Bd6 Bxg1
+(18... Qxa1+ 19. Ke2 Qb2 $4) 19. e5 Qxa1+ 20. Ke2 Na6 21. Nxg7+ Kd8 22. Qf6+
+Nxf6 23. Be7# 1-0
+
+[Event "Second"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+[SetUp "1"]
+
+1.e4 Kd7 ; a line comment
+2.e5 *
+"#;
+
+    #[test]
+    fn headers_and_moves() {
+        let games = parse_pgn(GAMES).unwrap();
+        assert_eq!(2, games.len());
+
+        let g = &games[0];
+        assert_eq!(Some("Anderssen, Adolf"), g.header("White"));
+        assert_eq!(Some("1-0"), g.header("Result"));
+        assert_eq!(Some("1-0".to_string()), g.result);
+        assert_eq!(45, g.moves.len());
+        assert_eq!("Qh4+", g.moves[5]);
+        assert_eq!("Bxg1", g.moves[35]);
+        assert_eq!("e5", g.moves[36]);
+
+        let g = &games[1];
+        assert_eq!(vec!["e4", "Kd7", "e5"], g.moves);
+        assert_eq!(Some("*".to_string()), g.result);
+    }
+
+    #[test]
+    fn replay() {
+        let games = parse_pgn(GAMES).unwrap();
+
+        let (_, moves) = games[0].replay().unwrap();
+        assert_eq!(45, moves.len());
+        assert_eq!("d6e7", moves[44].to_string());
+
+        let (start, moves) = games[1].replay().unwrap();
+        assert!(start.get_piece(&"d1".parse().unwrap()).is_none());
+        assert_eq!("e4e5", moves[2].to_string());
+    }
+
+    #[test]
+    fn comments_nags_and_variations() {
+        let games = parse_pgn(GAMES).unwrap();
+        let line = &games[0].main_line;
+
+        assert_eq!(Some("the famous immortal game".to_string()), line[5].comment);
+        assert_eq!("Bxg1", line[35].san);
+        assert_eq!(1, line[35].variations.len());
+
+        let variation = &line[35].variations[0];
+        assert_eq!(3, variation.len());
+        assert_eq!("Qxa1+", variation[0].san);
+        assert_eq!(vec![4], variation[2].nags);
+    }
+
+    #[test]
+    fn nested_variations_and_suffixes() {
+        let pgn = "{Start} 1. e4 e5!? (1... c5 2. Nf3 (2. Nc3 Nc6) d6 {Najdorf next}) 2. Nf3?! $14 *";
+        let games = parse_pgn(pgn).unwrap();
+        let g = &games[0];
+
+        assert_eq!(Some("Start".to_string()), g.comment);
+        assert_eq!(vec!["e4", "e5", "Nf3"], g.moves);
+        assert_eq!(vec![5], g.main_line[1].nags);
+        assert_eq!(vec![6, 14], g.main_line[2].nags);
+
+        let sicilian = &g.main_line[1].variations[0];
+        assert_eq!(3, sicilian.len());
+        assert_eq!(Some("Najdorf next".to_string()), sicilian[2].comment);
+        assert_eq!("Nc3", sicilian[1].variations[0][0].san);
+        assert_eq!(2, sicilian[1].variations[0].len());
+
+        assert_eq!("!?", nag_symbol(5));
+        assert_eq!("+=", nag_symbol(14));
+        assert_eq!("$200", nag_symbol(200));
+    }
+
+    #[test]
+    fn castling_with_zeros() {
+        let pgn = r#"[FEN "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 5"] 5. 0-0 0-0-0 6.Kg2 *"#;
+        let games = parse_pgn(pgn).unwrap();
+        assert_eq!(vec!["0-0", "0-0-0", "Kg2"], games[0].moves);
+
+        let (_, moves) = games[0].replay().unwrap();
+        assert_eq!("e1g1", moves[0].to_string());
+        assert_eq!("e8c8", moves[1].to_string());
+    }
+
+    #[test]
+    fn escaped_tag_values() {
+        let games = parse_pgn(r#"[Annotator "The \"best\" one"] 1. d4 *"#).unwrap();
+        assert_eq!(Some(r#"The "best" one"#), games[0].header("Annotator"));
+    }
+
+    #[test]
+    fn write() {
+        let pgn = "[Event \"A \\\"quoted\\\" event\"]\n\n{Start} 1. e4 e5!? (1... c5 2. Nf3 (2. Nc3 Nc6) d6 {Najdorf next}) 2. Nf3?! $14 *";
+        let game = &parse_pgn(pgn).unwrap()[0];
+        assert_eq!(
+            "[Event \"A \\\"quoted\\\" event\"]\n\n\
+             {Start} 1. e4 e5 $5 (1... c5 2. Nf3 (2. Nc3 Nc6) 2... d6 {Najdorf next}) 2. Nf3\n\
+             $6 $14 *\n",
+            game.to_string()
+        );
+
+        // everything survives a round trip, including the long game wrapped over lines
+        for game in parse_pgn(GAMES).unwrap().iter().chain([game]) {
+            let written = game.to_string();
+            assert!(written.lines().all(|l| l.len() <= 80));
+            assert_eq!(vec![game.clone()], parse_pgn(&written).unwrap());
+        }
+
+        let plain = PgnGame {
+            headers: vec![("FEN".to_string(), "4k3/8/8/8/8/8/4P3/4K3 b - - 0 7".to_string())],
+            moves: vec!["Kd7".to_string(), "e4".to_string()],
+            ..PgnGame::default()
+        };
+        assert!(plain.to_string().ends_with("\n\n7... Kd7 8. e4 *\n"));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse_pgn("1. e4 { unterminated").is_err());
+        assert!(parse_pgn("1. e4 (1. d4").is_err());
+        assert!(parse_pgn("[Event]").is_err());
+        assert!(parse_pgn("1. e4 ) e5").is_err());
+        assert!(parse_pgn("(1. e4) *").is_err());
+        assert!(parse_pgn("1. e4 (1. d4 *").is_err());
+
+        let games = parse_pgn("1. e4 e4 *").unwrap();
+        assert!(games[0].replay().is_err());
+    }
+}
@@ -1,12 +1,16 @@
 use std::fmt::{Display, Formatter};
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Color {
     White,
     Black,
 }
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum PieceType {
     King,
     Queen,
@@ -34,6 +38,29 @@ impl Color {
     pub fn iter() -> impl Iterator<Item = Color> {
         [Color::White, Color::Black].iter().copied()
     }
+
+    pub fn opponent(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+
+    /// The rank direction pawns of this color move in.
+    pub fn pawn_direction(&self) -> i8 {
+        match self {
+            Color::White => 1,
+            Color::Black => -1,
+        }
+    }
+
+    /// The rank the pieces of this color start on.
+    pub fn back_rank(&self) -> u8 {
+        match self {
+            Color::White => 0,
+            Color::Black => 7,
+        }
+    }
 }
 
 impl PieceType {
@@ -75,12 +102,13 @@ impl PieceType {
 }
 
 impl Piece {
+    pub fn new(color: Color, piece_type: PieceType) -> Piece {
+        Piece { color, piece_type }
+    }
+
     pub fn from_fen_char(fen_char: &char) -> Option<Piece> {
         let c = Color::from_fen_char(fen_char);
-        let t = match PieceType::from_fen_char(fen_char) {
-            None => return None,
-            Some(t) => t,
-        };
+        let t = PieceType::from_fen_char(fen_char)?;
 
         Some(Piece {
             color: c,
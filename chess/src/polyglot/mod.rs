@@ -0,0 +1,465 @@
+mod random;
+
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::chess_move::{Move, MoveKind};
+use crate::pgn::PgnGame;
+use crate::piece::{Color, Piece, PieceType};
+use random::RANDOM64;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+/// The key Polyglot books are indexed by. It differs from `Board::hash`, as the random numbers
+/// are fixed by the format.
+pub fn polyglot_key(board: &Board) -> u64 {
+    let mut key = Position::iter()
+        .filter_map(|pos| board.get_piece(&pos).map(|p| piece_key(&p, &pos)))
+        .fold(0, |key, k| key ^ k);
+
+    let castling = board.castling();
+    for (idx, right) in [
+        castling.white_short,
+        castling.white_long,
+        castling.black_short,
+        castling.black_long,
+    ]
+    .iter()
+    .enumerate()
+    {
+        if *right {
+            key ^= RANDOM64[CASTLING_OFFSET + idx];
+        }
+    }
+
+    // the en passant file only counts if a pawn can actually capture there
+    if let Some(file) = board.en_passant_file() {
+        let us = board.current_player();
+        let rank = if us == Color::White { 4 } else { 3 };
+        let pawn = Some(Piece::new(us, PieceType::Pawn));
+
+        if [-1, 1].iter().any(|&df| {
+            Position::from(rank, file)
+                .offset(0, df)
+                .and_then(|pos| board.get_piece(&pos))
+                == pawn
+        }) {
+            key ^= RANDOM64[EN_PASSANT_OFFSET + file as usize];
+        }
+    }
+
+    if board.current_player() == Color::White {
+        key ^= RANDOM64[TURN_OFFSET];
+    }
+
+    key
+}
+
+fn piece_key(p: &Piece, pos: &Position) -> u64 {
+    let kind = match p.piece_type {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    } * 2
+        + (p.color == Color::White) as usize;
+
+    RANDOM64[64 * kind + pos.idx()]
+}
+
+/// Converts a move to the book's 16 bit format. Castling is written as the king capturing its
+/// own rook.
+pub fn encode_move(m: &Move) -> u16 {
+    let to = match m.kind {
+        MoveKind::CastleShort => Position::from(m.to.rank, 7),
+        MoveKind::CastleLong => Position::from(m.to.rank, 0),
+        _ => m.to,
+    };
+    let promotion = match m.promotion {
+        None => 0,
+        Some(PieceType::Knight) => 1,
+        Some(PieceType::Bishop) => 2,
+        Some(PieceType::Rook) => 3,
+        Some(_) => 4,
+    };
+
+    to.file as u16
+        | (to.rank as u16) << 3
+        | (m.from.file as u16) << 6
+        | (m.from.rank as u16) << 9
+        | promotion << 12
+}
+
+/// Finds the legal move a book move stands for.
+pub fn decode_move(board: &mut Board, raw: u16) -> Option<Move> {
+    let from = Position::from((raw >> 9 & 7) as u8, (raw >> 6 & 7) as u8);
+    let to = Position::from((raw >> 3 & 7) as u8, (raw & 7) as u8);
+    let promotion = match raw >> 12 & 7 {
+        0 => None,
+        1 => Some(PieceType::Knight),
+        2 => Some(PieceType::Bishop),
+        3 => Some(PieceType::Rook),
+        _ => Some(PieceType::Queen),
+    };
+
+    board.legal_moves().iter().copied().find(|m| {
+        m.from == from && m.promotion == promotion && encode_move(m) & 0x3f == to.idx() as u16
+    })
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    const SIZE: usize = 16;
+
+    fn from_bytes(b: &[u8; Self::SIZE]) -> BookEntry {
+        BookEntry {
+            key: u64::from_be_bytes(b[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(b[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(b[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(b[12..16].try_into().unwrap()),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; Self::SIZE] {
+        let mut b = [0; Self::SIZE];
+        b[0..8].copy_from_slice(&self.key.to_be_bytes());
+        b[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        b[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        b[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        b
+    }
+}
+
+/// Filters applied when building a book from games.
+#[derive(Copy, Clone, Debug)]
+pub struct BookOptions {
+    /// Moves played fewer times than this are left out.
+    pub min_count: u32,
+    /// Only the first `max_ply` plies of each game are used.
+    pub max_ply: u32,
+}
+
+impl Default for BookOptions {
+    fn default() -> Self {
+        BookOptions {
+            min_count: 1,
+            max_ply: 30,
+        }
+    }
+}
+
+/// An opening book in Polyglot's `.bin` format, held in memory sorted by key.
+#[derive(Clone, Debug, Default)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Book> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read(mut r: impl Read) -> std::io::Result<Book> {
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes)?;
+
+        if bytes.len() % BookEntry::SIZE != 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "book size isn't a multiple of the entry size",
+            ));
+        }
+
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(BookEntry::SIZE)
+            .map(|c| BookEntry::from_bytes(c.try_into().unwrap()))
+            .collect();
+        // books are supposed to be sorted already, but the lookup depends on it
+        entries.sort_by_key(|e| e.key);
+
+        Ok(Book { entries })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write(&mut w)?;
+        w.flush()
+    }
+
+    pub fn write(&self, mut w: impl Write) -> std::io::Result<()> {
+        for e in &self.entries {
+            w.write_all(&e.to_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries for the given key.
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|e| e.key < key);
+        let end = self.entries.partition_point(|e| e.key <= key);
+
+        &self.entries[start..end]
+    }
+
+    /// The book moves in the current position with their weights, best first.
+    pub fn moves(&self, board: &mut Board) -> Vec<(Move, u16)> {
+        let mut moves: Vec<(Move, u16)> = self
+            .entries(polyglot_key(board))
+            .iter()
+            .filter_map(|e| decode_move(board, e.raw_move).map(|m| (m, e.weight)))
+            .collect();
+        moves.sort_by_key(|(_, w)| std::cmp::Reverse(*w));
+
+        moves
+    }
+
+    /// Picks one of the book moves with a probability proportional to its weight.
+    ///
+    /// `random` is the source of randomness, any value works.
+    pub fn choose_move(&self, board: &mut Board, random: u64) -> Option<Move> {
+        let moves = self.moves(board);
+        let total: u64 = moves.iter().map(|(_, w)| *w as u64).sum();
+
+        if total == 0 {
+            return moves.first().map(|(m, _)| *m);
+        }
+
+        let mut pick = random % total;
+        for (m, w) in moves {
+            if pick < w as u64 {
+                return Some(m);
+            }
+            pick -= w as u64;
+        }
+
+        None
+    }
+
+    /// Builds a book from the main lines of the games.
+    ///
+    /// A move's weight is two points per win and one per draw of the side playing it. Games that
+    /// can't be replayed are skipped.
+    pub fn from_games(games: &[PgnGame], options: &BookOptions) -> Book {
+        // (count, score) per position and move
+        let mut stats: HashMap<(u64, u16), (u32, u64)> = HashMap::new();
+
+        for game in games {
+            let (mut b, moves) = match game.replay() {
+                Ok(r) => r,
+                Err(_) => continue,
+            };
+            let winner = match game.result.as_deref().or_else(|| game.header("Result")) {
+                Some("1-0") => Some(Some(Color::White)),
+                Some("0-1") => Some(Some(Color::Black)),
+                Some("1/2-1/2") => Some(None),
+                _ => None,
+            };
+
+            for m in moves.iter().take(options.max_ply as usize) {
+                let score = match winner {
+                    Some(Some(c)) if c == b.current_player() => 2,
+                    Some(None) => 1,
+                    _ => 0,
+                };
+                let s = stats
+                    .entry((polyglot_key(&b), encode_move(m)))
+                    .or_insert((0, 0));
+                s.0 += 1;
+                s.1 += score;
+
+                b.make_move(*m);
+            }
+        }
+
+        let max_score = stats.values().map(|(_, s)| *s).max().unwrap_or(0);
+        let scale = |s: u64| {
+            if max_score <= u16::MAX as u64 {
+                s as u16
+            } else {
+                // keep moves that scored at all distinguishable from those that didn't
+                (s * u16::MAX as u64 / max_score).max(s.min(1)) as u16
+            }
+        };
+
+        let mut entries: Vec<BookEntry> = stats
+            .into_iter()
+            .filter(|(_, (count, _))| *count >= options.min_count)
+            .map(|((key, raw_move), (_, score))| BookEntry {
+                key,
+                raw_move,
+                weight: scale(score),
+                learn: 0,
+            })
+            .collect();
+        entries.sort_by_key(|e| (e.key, std::cmp::Reverse(e.weight), e.raw_move));
+
+        Book { entries }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::pgn::parse_pgn;
+    use crate::polyglot::{decode_move, encode_move, polyglot_key, Book, BookOptions};
+
+    #[test]
+    fn reference_keys() {
+        // the examples from the format specification
+        let keys = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                0x463b_9618_1691_fc9c,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                0x823c_9b50_fd11_4196,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+                0x0756_b944_61c5_0fb0,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+                0x662f_afb9_65db_29d4,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                0x22a4_8b5a_8e47_ff78,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3",
+                0x652a_607c_a3f2_42c1,
+            ),
+            (
+                "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4",
+                0x00fd_d303_c946_bdd9,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+                0x3c81_23ea_7b06_7637,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+                0x5c3f_9b82_9b27_9560,
+            ),
+        ];
+
+        for (fen, key) in keys {
+            let b: Board = fen.parse().unwrap();
+            assert_eq!(key, polyglot_key(&b), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn move_encoding() {
+        let mut b: Board = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"
+            .parse()
+            .unwrap();
+
+        // castling is written as e1h1
+        let castle = b.parse_uci_move("e1g1").unwrap();
+        assert_eq!(4 << 6 | 7, encode_move(&castle));
+
+        for &m in &b.legal_moves() {
+            assert_eq!(Some(m), decode_move(&mut b, encode_move(&m)));
+        }
+    }
+
+    const GAMES: &str = r#"
+[Result "1-0"]
+1. e4 e5 2. Nf3 Nc6 1-0
+
+[Result "1/2-1/2"]
+1. e4 c5 2. Nf3 1/2-1/2
+
+[Result "0-1"]
+1. d4 d5 0-1
+"#;
+
+    #[test]
+    fn build_and_probe() {
+        let games = parse_pgn(GAMES).unwrap();
+        let book = Book::from_games(&games, &BookOptions::default());
+
+        let mut b = Board::new();
+        let moves: Vec<(String, u16)> = book
+            .moves(&mut b)
+            .into_iter()
+            .map(|(m, w)| (m.to_string(), w))
+            .collect();
+        assert_eq!(
+            vec![("e2e4".to_string(), 3), ("d2d4".to_string(), 0)],
+            moves
+        );
+
+        assert_eq!("e2e4", book.choose_move(&mut b, 0).unwrap().to_string());
+        assert_eq!("e2e4", book.choose_move(&mut b, 2).unwrap().to_string());
+
+        let e4 = b.parse_uci_move("e2e4").unwrap();
+        b.make_move(e4);
+        let moves = book.moves(&mut b);
+        assert_eq!(2, moves.len());
+    }
+
+    #[test]
+    fn filters() {
+        let games = parse_pgn(GAMES).unwrap();
+        let book = Book::from_games(
+            &games,
+            &BookOptions {
+                min_count: 2,
+                max_ply: 10,
+            },
+        );
+        // only 1. e4 and 2. Nf3 after it... which follow different black moves
+        assert_eq!(1, book.len());
+
+        let book = Book::from_games(
+            &games,
+            &BookOptions {
+                min_count: 1,
+                max_ply: 1,
+            },
+        );
+        assert_eq!(2, book.len());
+    }
+
+    #[test]
+    fn write_and_read() {
+        let games = parse_pgn(GAMES).unwrap();
+        let book = Book::from_games(&games, &BookOptions::default());
+
+        let mut bytes = Vec::new();
+        book.write(&mut bytes).unwrap();
+        assert_eq!(16 * book.len(), bytes.len());
+
+        let read = Book::read(&bytes[..]).unwrap();
+        assert_eq!(book.entries, read.entries);
+
+        assert!(Book::read(&bytes[..15]).is_err());
+    }
+}
@@ -0,0 +1,1073 @@
+pub mod time;
+pub mod tt;
+
+use crate::board::movegen::{GenType, StagedMoveGen};
+use crate::board::outcome::Outcome;
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::chess_move::{Move, MoveKind, MoveList};
+use crate::evaluation::{evaluate_using, piece_value, EvalParams};
+use crate::piece::PieceType;
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use time::{TimeLeft, TimeManager};
+use tt::{Bound, TranspositionTable, TtEntry};
+
+/// The internal score of being mated right now, mates further away score closer to zero.
+pub const MATE: i32 = 30_000;
+const INFINITY: i32 = 32_000;
+pub const MAX_PLY: usize = 64;
+
+/// A search score as presented to the user.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Score {
+    /// Centipawns from the view of the side to move.
+    Cp(i32),
+    /// Moves until mate, negative if the side to move gets mated.
+    Mate(i32),
+}
+
+impl Score {
+    pub fn from_internal(score: i32) -> Score {
+        let bound = MATE - MAX_PLY as i32;
+
+        if score > bound {
+            Score::Mate((MATE - score + 1) / 2)
+        } else if score < -bound {
+            Score::Mate(-(MATE + score) / 2)
+        } else {
+            Score::Cp(score)
+        }
+    }
+}
+
+/// Prints the score the way a human reads it, e.g. `+0.35` or `#-2`.
+impl Display for Score {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Cp(cp) => write!(f, "{:+.2}", *cp as f64 / 100.0),
+            Score::Mate(n) => write!(f, "#{}", n),
+        }
+    }
+}
+
+/// When to stop searching and what to search. Without any limit the search runs until it is
+/// stopped.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub time: Option<Duration>,
+    /// The clock of the side to move, the search decides itself how much of it to use.
+    pub clock: Option<TimeLeft>,
+    pub nodes: Option<u64>,
+    /// From 0 to 20, lower levels search shallower and misjudge positions on purpose.
+    pub skill_level: Option<u8>,
+    /// How many of the best moves get their own score and principal variation, one if `None`.
+    pub multi_pv: Option<usize>,
+    /// Only these root moves are searched, all of them if empty.
+    pub search_moves: Vec<Move>,
+}
+
+/// Progress reported after every completed iteration, once for each line in multi-PV mode.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchInfo {
+    /// The rank of the line, starting at 1 for the best move.
+    pub multipv: usize,
+    pub depth: u32,
+    pub score: Score,
+    pub nodes: u64,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+}
+
+impl SearchInfo {
+    pub fn nps(&self) -> u64 {
+        (self.nodes as u128 * 1000 / self.time.as_millis().max(1)) as u64
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    /// The expected reply, to think about on the opponent's time.
+    pub ponder: Option<Move>,
+    pub score: Score,
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+/// An iterative deepening alpha-beta search. The transposition table is kept between searches.
+///
+/// With more than one thread the search is a lazy SMP one: helper threads search the same
+/// position on their own board and share only the transposition table, which makes the main
+/// thread find more cutoffs. A single thread searches deterministically.
+pub struct Search {
+    tt: Arc<TranspositionTable>,
+    stop: Arc<AtomicBool>,
+    stopped: bool,
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    nodes: u64,
+    /// The nodes of all helper threads, counted in steps of 1024.
+    helper_nodes: Arc<AtomicU64>,
+    threads: usize,
+    /// Whether this is a helper thread's search, which goes on until the main thread stops it.
+    helper: bool,
+    start: Instant,
+    /// While set, time limits don't run yet, as the search is thinking on the opponent's time.
+    ponder: Arc<AtomicBool>,
+    /// When the time limits started to run, `None` while pondering.
+    clock_start: Option<Instant>,
+    time: Option<TimeManager>,
+    limits: SearchLimits,
+    /// The moves searched at the root in the current line.
+    root_moves: Vec<Move>,
+    /// Whether the root moves are all the legal moves, only then the root result is stored.
+    full_root: bool,
+    noise_seed: u64,
+    /// The weights of the handcrafted evaluation.
+    eval_params: Arc<EvalParams>,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self::with_hash_size(16)
+    }
+
+    /// A search with a transposition table of about `mb` megabytes.
+    pub fn with_hash_size(mb: usize) -> Self {
+        let noise_seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+
+        Search {
+            tt: Arc::new(TranspositionTable::new(mb)),
+            stop: Arc::new(AtomicBool::new(false)),
+            stopped: false,
+            killers: [[None; 2]; MAX_PLY],
+            nodes: 0,
+            helper_nodes: Arc::new(AtomicU64::new(0)),
+            threads: 1,
+            helper: false,
+            start: Instant::now(),
+            ponder: Arc::new(AtomicBool::new(false)),
+            clock_start: None,
+            time: None,
+            limits: SearchLimits::default(),
+            root_moves: Vec::new(),
+            full_root: false,
+            noise_seed,
+            eval_params: Arc::new(EvalParams::DEFAULT),
+        }
+    }
+
+    /// A flag that stops a running search from another thread when set.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// A flag that makes the search ponder while set: it searches without regard to its time
+    /// limits, which only start when the flag is cleared on a ponder hit.
+    ///
+    /// Set it before the search starts, as the search doesn't reset it.
+    pub fn ponder_handle(&self) -> Arc<AtomicBool> {
+        self.ponder.clone()
+    }
+
+    /// Replaces the transposition table with an empty one of about `mb` megabytes.
+    pub fn set_hash_size(&mut self, mb: usize) {
+        self.tt = Arc::new(TranspositionTable::new(mb));
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Searches with `threads` threads from the next search on, at least one.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn eval_params(&self) -> &EvalParams {
+        &self.eval_params
+    }
+
+    /// Evaluates with these weights from the next search on, e.g. tuned ones read with
+    /// `EvalParams::load`. A loaded network still takes precedence.
+    pub fn set_eval_params(&mut self, params: EvalParams) {
+        self.eval_params = Arc::new(params);
+    }
+
+    /// Forgets everything learned in earlier searches, e.g. for a new game.
+    pub fn clear(&mut self) {
+        self.tt.clear();
+        self.killers = [[None; 2]; MAX_PLY];
+    }
+
+    /// Searches the position until a limit is reached, calling `on_info` after every iteration.
+    ///
+    /// The board is returned in the state it was passed in.
+    pub fn run(
+        &mut self,
+        board: &mut Board,
+        limits: SearchLimits,
+        on_info: impl FnMut(&SearchInfo),
+    ) -> SearchResult {
+        self.stop.store(false, Ordering::Relaxed);
+        self.helper_nodes.store(0, Ordering::Relaxed);
+        self.stopped = false;
+        self.nodes = 0;
+        self.start = Instant::now();
+        self.clock_start = None;
+        self.time = limits.clock.map(TimeManager::new);
+        self.limits = limits.clone();
+        self.check_clock();
+
+        let mut max_depth = limits.depth.unwrap_or(MAX_PLY as u32 - 1);
+        if let Some(skill) = limits.skill_level {
+            max_depth = max_depth.min(1 + skill as u32 / 2);
+        }
+
+        let mut root_moves: Vec<Move> = board.legal_moves().iter().copied().collect();
+        if root_moves.iter().any(|m| limits.search_moves.contains(m)) {
+            root_moves.retain(|m| limits.search_moves.contains(m));
+        }
+        // only the moves that keep the best result the tablebase shows
+        #[cfg(feature = "syzygy")]
+        if let Some(ranks) = board
+            .tablebase()
+            .cloned()
+            .and_then(|tablebase| tablebase.rank_moves(board, &root_moves))
+        {
+            let best = ranks.iter().max().copied();
+            let mut ranks = ranks.into_iter();
+            root_moves.retain(|_| ranks.next() == best);
+        }
+        if root_moves.is_empty() {
+            return SearchResult {
+                best_move: None,
+                ponder: None,
+                score: Score::from_internal(terminal_score(board, 0)),
+                depth: 0,
+                nodes: 0,
+            };
+        }
+        let lines = limits.multi_pv.unwrap_or(1).clamp(1, root_moves.len());
+
+        if self.threads == 1 {
+            return self.iterate(board, &root_moves, lines, 1..=max_depth.max(1), on_info);
+        }
+
+        thread::scope(|s| {
+            for id in 1..self.threads {
+                let mut helper = self.helper(id);
+                let mut board = board.clone();
+                let root_moves = &root_moves;
+                // every other helper starts a depth deeper, so that they don't all search alike
+                let depths = (1 + id as u32 % 2).min(max_depth)..=max_depth.max(1);
+                s.spawn(move || helper.iterate(&mut board, root_moves, 1, depths, |_| {}));
+            }
+
+            let result = self.iterate(board, &root_moves, lines, 1..=max_depth.max(1), on_info);
+            self.stop.store(true, Ordering::Relaxed);
+            result
+        })
+    }
+
+    /// A search for a helper thread that shares the table, the stop flag and the node count.
+    fn helper(&self, id: usize) -> Search {
+        Search {
+            tt: self.tt.clone(),
+            stop: self.stop.clone(),
+            stopped: false,
+            killers: [[None; 2]; MAX_PLY],
+            nodes: 0,
+            helper_nodes: self.helper_nodes.clone(),
+            threads: 1,
+            helper: true,
+            start: self.start,
+            ponder: Arc::new(AtomicBool::new(false)),
+            clock_start: None,
+            time: None,
+            limits: SearchLimits {
+                skill_level: self.limits.skill_level,
+                ..SearchLimits::default()
+            },
+            root_moves: Vec::new(),
+            full_root: false,
+            noise_seed: self.noise_seed ^ id as u64,
+            eval_params: self.eval_params.clone(),
+        }
+    }
+
+    /// The nodes searched by all threads.
+    fn total_nodes(&self) -> u64 {
+        self.nodes + self.helper_nodes.load(Ordering::Relaxed)
+    }
+
+    /// Deepens the search of the `lines` best of the root moves until a limit is reached.
+    fn iterate(
+        &mut self,
+        board: &mut Board,
+        root_moves: &[Move],
+        lines: usize,
+        depths: RangeInclusive<u32>,
+        mut on_info: impl FnMut(&SearchInfo),
+    ) -> SearchResult {
+        let mut result = SearchResult {
+            best_move: root_moves.first().copied(),
+            ponder: None,
+            score: Score::Cp(0),
+            depth: 0,
+            nodes: 0,
+        };
+
+        let legal_moves = board.legal_moves().len();
+
+        for depth in depths {
+            // every line searches the root moves the better lines didn't take
+            let mut pvs: Vec<(i32, Vec<Move>)> = Vec::with_capacity(lines);
+            while pvs.len() < lines {
+                self.root_moves = root_moves
+                    .iter()
+                    .filter(|m| !pvs.iter().any(|(_, pv)| pv[0] == **m))
+                    .copied()
+                    .collect();
+                self.full_root = self.root_moves.len() == legal_moves;
+                let mut pv = Vec::new();
+                let score = self.alpha_beta(board, depth, 0, -INFINITY, INFINITY, &mut pv);
+
+                // an interrupted line might have missed the best move, only the first one counts
+                if pv.is_empty() || (self.stopped && depth > 1) {
+                    break;
+                }
+                pvs.push((score, pv));
+                if self.stopped {
+                    break;
+                }
+            }
+            if pvs.is_empty() {
+                break;
+            }
+
+            let (score, pv) = &pvs[0];
+            result = SearchResult {
+                best_move: pv.first().copied(),
+                ponder: pv.get(1).copied(),
+                score: Score::from_internal(*score),
+                depth,
+                nodes: self.total_nodes(),
+            };
+            if let (Some(time), Some(m)) = (&mut self.time, result.best_move) {
+                time.iteration(m, *score);
+            }
+            for (idx, (score, pv)) in pvs.into_iter().enumerate() {
+                on_info(&SearchInfo {
+                    multipv: idx + 1,
+                    depth,
+                    score: Score::from_internal(score),
+                    nodes: self.total_nodes(),
+                    time: self.start.elapsed(),
+                    pv,
+                });
+            }
+
+            if self.stopped {
+                break;
+            }
+            if self.out_of_time(root_moves.len()) {
+                break;
+            }
+        }
+
+        result.nodes = self.total_nodes();
+        result
+    }
+
+    fn alpha_beta(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        pv.clear();
+        if depth == 0 {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+
+        self.nodes += 1;
+        self.check_limits();
+        if self.stopped {
+            return 0;
+        }
+
+        if ply > 0
+            && (board.fifty_move_counter() >= 100
+                || board.repetition_count() >= 2
+                || board.is_insufficient_material())
+        {
+            return 0;
+        }
+        if ply >= MAX_PLY - 1 {
+            return self.eval(board);
+        }
+
+        let in_check = board.in_check();
+        let depth = if in_check { depth + 1 } else { depth };
+
+        let mut tt_move = None;
+        if let Some(e) = self.tt.probe(board.hash()) {
+            tt_move = e.best_move;
+            let score = score_from_tt(e.score, ply);
+
+            if ply > 0 && e.depth >= depth {
+                match e.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        let pv_node = beta - alpha > 1;
+        if !pv_node
+            && !in_check
+            && depth >= 3
+            && beta.abs() < MATE - MAX_PLY as i32
+            && has_non_pawn_material(board)
+            && self.eval(board) >= beta
+            && board.make_null_move()
+        {
+            let mut child_pv = Vec::new();
+            let score = -self.alpha_beta(board, depth - 3, ply + 1, -beta, -beta + 1, &mut child_pv);
+            board.unmake_null_move();
+
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+        }
+
+        let alpha_orig = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        let mut child_pv = Vec::new();
+        let mut searched = 0;
+        let mut gen = StagedMoveGen::legal(board).with_tt_move(tt_move);
+
+        while let Some(m) = gen.next_by(board, &|b, m| self.move_key(b, m, tt_move, ply)) {
+            if ply == 0 && !self.root_moves.contains(&m) {
+                continue;
+            }
+
+            board.make_move(m);
+            let score = if searched == 0 {
+                -self.alpha_beta(board, depth - 1, ply + 1, -beta, -alpha, &mut child_pv)
+            } else {
+                let s = -self.alpha_beta(board, depth - 1, ply + 1, -alpha - 1, -alpha, &mut child_pv);
+                if s > alpha && s < beta {
+                    -self.alpha_beta(board, depth - 1, ply + 1, -beta, -alpha, &mut child_pv)
+                } else {
+                    s
+                }
+            };
+            board.unmake_move(m);
+            searched += 1;
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                best_move = Some(m);
+
+                if score > alpha {
+                    alpha = score;
+                    pv.clear();
+                    pv.push(m);
+                    pv.extend_from_slice(&child_pv);
+
+                    if score >= beta {
+                        if !m.is_tactical() && self.killers[ply][0] != Some(m) {
+                            self.killers[ply] = [Some(m), self.killers[ply][0]];
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        if searched == 0 {
+            return terminal_score(board, ply);
+        }
+
+        // a root that leaves out moves, for searchmoves, the tablebase or the later lines of
+        // multi-PV, has a result that doesn't belong in the table
+        if ply > 0 || self.full_root {
+            let bound = if best >= beta {
+                Bound::Lower
+            } else if best > alpha_orig {
+                Bound::Exact
+            } else {
+                Bound::Upper
+            };
+            self.tt.store(TtEntry {
+                key: board.hash(),
+                best_move,
+                depth,
+                score: score_to_tt(best, ply),
+                bound,
+            });
+        }
+
+        best
+    }
+
+    /// Resolves captures and promotions, so that only quiet positions are evaluated.
+    fn quiescence(&mut self, board: &mut Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        self.check_limits();
+        if self.stopped {
+            return 0;
+        }
+        if ply >= MAX_PLY - 1 {
+            return self.eval(board);
+        }
+        if let Some(outcome) = board.variant().outcome(board) {
+            return outcome_score(&outcome, board, ply);
+        }
+
+        let in_check = board.in_check();
+        let mut moves = MoveList::new();
+        let mut best = -INFINITY;
+
+        if in_check {
+            board.generate_legal(GenType::Evasions, &mut moves);
+            if moves.is_empty() {
+                return terminal_score(board, ply);
+            }
+        } else {
+            best = self.eval(board);
+            if best >= beta {
+                return best;
+            }
+            alpha = alpha.max(best);
+            board.generate_legal(GenType::Tactical, &mut moves);
+        }
+
+        let mut order = self.order_moves(board, &moves, None, ply);
+        for i in 0..moves.len() {
+            let m = pick_next(&mut moves, &mut order, i);
+
+            board.make_move(m);
+            let score = -self.quiescence(board, ply + 1, -beta, -alpha);
+            board.unmake_move(m);
+
+            if self.stopped {
+                return 0;
+            }
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    if score >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        best
+    }
+
+    fn eval(&self, board: &Board) -> i32 {
+        let score = evaluate_using(board, &self.eval_params);
+
+        match self.limits.skill_level {
+            Some(skill) if skill < 20 => {
+                let amplitude = (20 - skill as i64) * 15;
+                let noise = (splitmix64(board.hash() ^ self.noise_seed) % (2 * amplitude as u64 + 1))
+                    as i64
+                    - amplitude;
+                score + noise as i32
+            }
+            _ => score,
+        }
+    }
+
+    /// Sort keys for the moves: the transposition table move, captures by most valuable victim
+    /// and least valuable attacker, promotions, killers and then the quiet moves.
+    fn order_moves(
+        &self,
+        board: &Board,
+        moves: &MoveList,
+        tt_move: Option<Move>,
+        ply: usize,
+    ) -> [i32; MoveList::CAPACITY] {
+        let mut order = [0; MoveList::CAPACITY];
+
+        for (i, m) in moves.iter().enumerate() {
+            order[i] = self.move_key(board, m, tt_move, ply);
+        }
+
+        order
+    }
+
+    /// The sort key of a single move, see `order_moves`.
+    fn move_key(&self, board: &Board, m: &Move, tt_move: Option<Move>, ply: usize) -> i32 {
+        if Some(*m) == tt_move {
+            1_000_000
+        } else if m.is_capture() {
+            let victim = match m.kind {
+                MoveKind::EnPassant => PieceType::Pawn,
+                _ => board.get_piece(&m.to).map_or(PieceType::Pawn, |p| p.piece_type),
+            };
+            let attacker = board.get_piece(&m.from).map_or(PieceType::Pawn, |p| p.piece_type);
+            100_000 + piece_value(victim) * 10 - piece_value(attacker) / 10
+        } else if let Some(t) = m.promotion {
+            90_000 + piece_value(t)
+        } else if self.killers[ply].contains(&Some(*m)) {
+            80_000
+        } else {
+            0
+        }
+    }
+
+    fn check_limits(&mut self) {
+        if !self.nodes.is_multiple_of(1024) {
+            return;
+        }
+        if self.helper {
+            self.helper_nodes.fetch_add(1024, Ordering::Relaxed);
+        }
+
+        self.check_clock();
+        let elapsed = self.clock_start.map(|start| start.elapsed());
+        self.stopped = self.stop.load(Ordering::Relaxed)
+            || self.limits.nodes.is_some_and(|n| self.total_nodes() >= n)
+            || elapsed.is_some_and(|e| {
+                self.limits.time.is_some_and(|t| e >= t)
+                    || self.time.as_ref().is_some_and(|time| e >= time.maximum())
+            });
+    }
+
+    /// Starts the time limits once the search doesn't ponder anymore.
+    fn check_clock(&mut self) {
+        if self.clock_start.is_none() && !self.ponder.load(Ordering::Relaxed) {
+            self.clock_start = Some(Instant::now());
+        }
+    }
+
+    /// Whether to start no further iteration, given the number of root moves.
+    fn out_of_time(&mut self, root_moves: usize) -> bool {
+        self.check_clock();
+        let Some(elapsed) = self.clock_start.map(|start| start.elapsed()) else {
+            return false;
+        };
+
+        // the next iteration takes longer than all previous ones together
+        self.limits.time.is_some_and(|t| elapsed * 2 > t)
+            || self.time.as_ref().is_some_and(|time| {
+                // a forced move needs no thought
+                root_moves == 1 || time.should_stop(elapsed)
+            })
+    }
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A search running on its own thread, which reports its progress through a channel until it
+/// reaches its limits or is stopped. Without limits that is infinite analysis.
+pub struct Analysis {
+    stop: Arc<AtomicBool>,
+    info: Receiver<SearchInfo>,
+    handle: JoinHandle<(Search, SearchResult)>,
+}
+
+impl Analysis {
+    pub fn start(mut search: Search, mut board: Board, limits: SearchLimits) -> Analysis {
+        let stop = search.stop_handle();
+        let (tx, info) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let result = search.run(&mut board, limits, |info| {
+                let _ = tx.send(info.clone());
+            });
+            (search, result)
+        });
+
+        Analysis { stop, info, handle }
+    }
+
+    /// The reports of the search, every line of every completed iteration. The channel is
+    /// closed once the search ends.
+    pub fn info(&self) -> &Receiver<SearchInfo> {
+        &self.info
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops the search and waits for it, returning it for reuse together with its result.
+    pub fn finish(self) -> (Search, SearchResult) {
+        self.stop();
+        self.handle.join().expect("the search thread panicked")
+    }
+}
+
+/// Moves the move with the highest sort key of the moves from `i` on to `i` and returns it.
+fn pick_next(moves: &mut MoveList, order: &mut [i32; MoveList::CAPACITY], i: usize) -> Move {
+    let best = (i..moves.len()).max_by_key(|&j| order[j]).unwrap_or(i);
+
+    moves.swap(i, best);
+    order.swap(i, best);
+    moves[i]
+}
+
+fn has_non_pawn_material(board: &Board) -> bool {
+    let us = board.current_player();
+
+    Position::iter().any(|pos| {
+        board.get_piece(&pos).is_some_and(|p| {
+            p.color == us && !matches!(p.piece_type, PieceType::Pawn | PieceType::King)
+        })
+    })
+}
+
+/// The score of a position without legal moves, from the view of the side to move. The variant
+/// decides, e.g. a king in the center has won and running out of moves wins in Antichess.
+fn terminal_score(board: &Board, ply: usize) -> i32 {
+    let variant = board.variant();
+    let outcome = variant
+        .outcome(board)
+        .unwrap_or_else(|| variant.outcome_without_moves(board));
+
+    outcome_score(&outcome, board, ply)
+}
+
+fn outcome_score(outcome: &Outcome, board: &Board, ply: usize) -> i32 {
+    match outcome {
+        Outcome::Win { winner, .. } if *winner == board.current_player() => MATE - ply as i32,
+        Outcome::Win { .. } => -MATE + ply as i32,
+        Outcome::Draw(_) => 0,
+    }
+}
+
+/// Mate scores are stored relative to the node, not the root.
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score > MATE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score < -MATE + MAX_PLY as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score > MATE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score < -MATE + MAX_PLY as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::evaluation::EvalParams;
+    use crate::piece::PieceType;
+    use crate::search::time::TimeLeft;
+    use crate::search::{Analysis, Score, Search, SearchLimits};
+    use crate::variant::from_name;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    fn search(fen: &str, depth: u32) -> (String, Score) {
+        let mut b: Board = fen.parse().unwrap();
+        let hash = b.hash();
+        let limits = SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        };
+        let result = Search::new().run(&mut b, limits, |_| {});
+
+        assert_eq!(hash, b.hash());
+        (result.best_move.unwrap().to_string(), result.score)
+    }
+
+    #[test]
+    fn mate_in_one() {
+        let (m, score) = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 2);
+        assert_eq!("a1a8", m);
+        assert_eq!(Score::Mate(1), score);
+    }
+
+    #[test]
+    fn variant_outcomes() {
+        let search = |fen: &str, variant: &str, depth: u32| {
+            let mut b = Board::from_fen_variant(fen, from_name(variant).unwrap()).unwrap();
+            let limits = SearchLimits {
+                depth: Some(depth),
+                ..SearchLimits::default()
+            };
+            let result = Search::new().run(&mut b, limits, |_| {});
+            (result.best_move.map(|m| m.to_string()), result.score)
+        };
+
+        // the king wins by stepping into the center, not by mating
+        let (m, score) = search("k7/8/8/8/8/4K3/8/8 w - - 0 1", "kingofthehill", 2);
+        assert!(matches!(m.as_deref(), Some("e3d4" | "e3e4")));
+        assert_eq!(Score::Mate(1), score);
+
+        // running out of pieces wins Antichess
+        let (m, score) = search("7r/8/8/8/8/8/8/R7 w - - 0 1", "antichess", 3);
+        assert!(matches!(m.as_deref(), Some("a1a8" | "a1h1")));
+        assert_eq!(Score::Mate(1), score);
+
+        // and so does having no moves, right away at the root
+        let (m, score) = search("8/8/8/p7/P7/8/8/8 w - - 0 1", "antichess", 3);
+        assert_eq!(None, m);
+        assert_eq!(Score::Mate(0), score);
+    }
+
+    #[test]
+    fn eval_params() {
+        let mut b: Board = "4k3/8/8/8/8/8/8/4K2R w - - 0 1".parse().unwrap();
+        let limits = SearchLimits {
+            depth: Some(1),
+            ..SearchLimits::default()
+        };
+        let mut search = Search::new();
+        let Score::Cp(before) = search.run(&mut b, limits.clone(), |_| {}).score else {
+            panic!("no mate in one");
+        };
+        assert!(before > 400);
+
+        // a rook that is a burden
+        let mut params = EvalParams::DEFAULT;
+        params.material[PieceType::Rook as usize] = -1000;
+        search.set_eval_params(params.clone());
+        search.clear();
+        assert_eq!(&params, search.eval_params());
+        let Score::Cp(after) = search.run(&mut b, limits, |_| {}).score else {
+            panic!("no mate in one");
+        };
+        assert!(after < -900);
+    }
+
+    #[test]
+    fn mate_in_two() {
+        // e.g. 1. Kb6 Kb8 2. Rh8#
+        let (m, score) = search("k7/8/2K5/8/8/8/8/7R w - - 0 1", 4);
+        assert_eq!(Score::Mate(2), score, "{}", m);
+    }
+
+    #[test]
+    fn getting_mated() {
+        let (_, score) = search("7k/8/5K2/8/8/8/8/6Q1 b - - 0 1", 4);
+        assert!(matches!(score, Score::Mate(n) if n < 0), "{:?}", score);
+    }
+
+    #[test]
+    fn wins_material() {
+        // the knight on d5 hangs
+        let (m, _) = search("4k3/8/8/3n4/8/8/3Q4/4K3 w - - 0 1", 3);
+        assert_eq!("d2d5", m);
+    }
+
+    #[test]
+    fn no_moves() {
+        let mut b: Board = "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1".parse().unwrap();
+        let result = Search::new().run(&mut b, SearchLimits::default(), |_| {});
+
+        assert_eq!(None, result.best_move);
+        assert_eq!(Score::Cp(0), result.score);
+    }
+
+    #[test]
+    fn limits_and_info() {
+        let mut b = Board::new();
+        let limits = SearchLimits {
+            nodes: Some(5000),
+            ..SearchLimits::default()
+        };
+
+        let mut depths = Vec::new();
+        let result = Search::new().run(&mut b, limits, |info| {
+            assert!(!info.pv.is_empty());
+            depths.push(info.depth);
+        });
+
+        assert!(result.best_move.is_some());
+        assert!(result.nodes < 6000);
+        assert_eq!((1..=result.depth).collect::<Vec<_>>(), depths);
+    }
+
+    #[test]
+    fn skill_level() {
+        let mut b = Board::new();
+        let limits = SearchLimits {
+            skill_level: Some(0),
+            ..SearchLimits::default()
+        };
+        let result = Search::new().run(&mut b, limits, |_| {});
+
+        assert_eq!(1, result.depth);
+        assert!(b.is_legal(result.best_move.unwrap()));
+    }
+
+    #[test]
+    fn multi_pv() {
+        let mut b = Board::new();
+        let limits = SearchLimits {
+            depth: Some(3),
+            multi_pv: Some(3),
+            ..SearchLimits::default()
+        };
+
+        let mut infos = Vec::new();
+        let result = Search::new().run(&mut b, limits, |info| infos.push(info.clone()));
+
+        assert_eq!(9, infos.len());
+        let last: Vec<_> = infos.iter().filter(|i| i.depth == 3).collect();
+        assert_eq!(vec![1, 2, 3], last.iter().map(|i| i.multipv).collect::<Vec<_>>());
+        assert_eq!(result.best_move, last[0].pv.first().copied());
+        assert_ne!(last[0].pv[0], last[1].pv[0]);
+        assert_ne!(last[1].pv[0], last[2].pv[0]);
+        assert_ne!(last[0].pv[0], last[2].pv[0]);
+
+        // more lines than moves
+        let mut b: Board = "7k/8/8/8/8/8/8/K7 w - - 0 1".parse().unwrap();
+        let limits = SearchLimits {
+            depth: Some(2),
+            multi_pv: Some(10),
+            ..SearchLimits::default()
+        };
+        let mut lines = 0;
+        Search::new().run(&mut b, limits, |info| lines = lines.max(info.multipv));
+        assert_eq!(3, lines);
+    }
+
+    #[test]
+    fn search_moves() {
+        let mut b: Board = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".parse().unwrap();
+        let quiet = b.parse_san("Kf1").unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            search_moves: vec![quiet, b.parse_san("Kg2").unwrap()],
+            ..SearchLimits::default()
+        };
+        let mut search = Search::new();
+        let result = search.run(&mut b, limits, |_| {});
+        assert!(result.best_move == Some(quiet) || result.best_move == b.parse_san("Kg2"));
+        // the restricted root isn't stored
+        assert_eq!(None, search.tt.probe(b.hash()));
+
+        // moves that aren't legal don't restrict the search
+        let limits = SearchLimits {
+            depth: Some(2),
+            search_moves: Board::new().parse_san("e4").into_iter().collect(),
+            ..SearchLimits::default()
+        };
+        let result = Search::new().run(&mut b, limits, |_| {});
+        assert_eq!(b.parse_san("Ra8"), result.best_move);
+    }
+
+    #[test]
+    fn threads() {
+        let limits = SearchLimits {
+            depth: Some(5),
+            ..SearchLimits::default()
+        };
+
+        // one thread searches the same way every time
+        let single: Vec<_> = (0..2)
+            .map(|_| Search::new().run(&mut Board::new(), limits.clone(), |_| {}))
+            .collect();
+        assert_eq!(single[0], single[1]);
+
+        let mut search = Search::new();
+        search.set_threads(4);
+        assert_eq!(4, search.threads());
+        let mut b: Board = "k7/8/2K5/8/8/8/8/7R w - - 0 1".parse().unwrap();
+        let hash = b.hash();
+        let result = search.run(&mut b, limits, |_| {});
+
+        assert_eq!(hash, b.hash());
+        assert_eq!(Score::Mate(2), result.score);
+        assert_eq!(5, result.depth);
+        assert!(result.nodes > 0);
+
+        search.set_threads(0);
+        assert_eq!(1, search.threads());
+    }
+
+    #[test]
+    fn clock() {
+        // the only legal move is played without thinking
+        let mut b: Board = "k7/8/8/8/8/8/8/1R5K b - - 0 1".parse().unwrap();
+        let limits = SearchLimits {
+            clock: Some(TimeLeft {
+                remaining: Duration::from_secs(600),
+                ..TimeLeft::default()
+            }),
+            ..SearchLimits::default()
+        };
+        let result = Search::new().run(&mut b, limits.clone(), |_| {});
+        assert_eq!((b.parse_san("Ka7"), 1), (result.best_move, result.depth));
+
+        // pondering ignores the clock
+        let mut search = Search::new();
+        search.ponder_handle().store(true, Ordering::Relaxed);
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..limits
+        };
+        assert_eq!(3, search.run(&mut b, limits, |_| {}).depth);
+    }
+
+    #[test]
+    fn background_analysis() {
+        let analysis = Analysis::start(Search::new(), Board::new(), SearchLimits::default());
+
+        let info = analysis.info().recv().unwrap();
+        assert_eq!((1, 1), (info.multipv, info.depth));
+
+        let (_, result) = analysis.finish();
+        assert!(result.best_move.is_some());
+        assert!(result.depth >= 1);
+    }
+
+    #[test]
+    fn score_display() {
+        assert_eq!("+0.35", Score::Cp(35).to_string());
+        assert_eq!("-1.20", Score::Cp(-120).to_string());
+        assert_eq!("#3", Score::Mate(3).to_string());
+        assert_eq!("#-2", Score::Mate(-2).to_string());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::chess_move::Move;
+use std::time::Duration;
+
+/// Time kept back from every move for the GUI and the transmission.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+/// The moves the remaining time is spread over when the time control doesn't say.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// The clock of the side to move, to search with instead of a fixed time.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct TimeLeft {
+    pub remaining: Duration,
+    pub increment: Duration,
+    /// The moves until the next time control, if there is one.
+    pub moves_to_go: Option<u32>,
+}
+
+/// Decides how long to think about a move when playing on a clock.
+///
+/// Each move gets an optimum share of the remaining time, which is stretched when the best move
+/// keeps changing or the score drops and shrunk when the best move stays the same, but never
+/// beyond a maximum that leaves enough time for the following moves.
+#[derive(Clone, Debug)]
+pub struct TimeManager {
+    optimum: Duration,
+    maximum: Duration,
+    best_move: Option<Move>,
+    score: Option<i32>,
+    /// The iterations in a row that kept the best move.
+    stable: u32,
+    /// Grows with every change of the best move and halves every iteration.
+    instability: f64,
+    /// How far the score of the last iteration fell, in centipawns.
+    fall: i32,
+}
+
+impl TimeManager {
+    pub fn new(time: TimeLeft) -> TimeManager {
+        let available = time.remaining.saturating_sub(MOVE_OVERHEAD);
+        let moves = time.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).clamp(1, 50);
+
+        let optimum = (available / moves + time.increment * 3 / 4).min(available);
+        let maximum = if moves == 1 {
+            available
+        } else {
+            (optimum * 5).min(available * 4 / 5).max(optimum)
+        };
+
+        TimeManager {
+            optimum,
+            maximum,
+            best_move: None,
+            score: None,
+            stable: 0,
+            instability: 0.0,
+            fall: 0,
+        }
+    }
+
+    pub fn optimum(&self) -> Duration {
+        self.optimum
+    }
+
+    /// The time after which the search stops, even in the middle of an iteration.
+    pub fn maximum(&self) -> Duration {
+        self.maximum
+    }
+
+    /// Records the result of a completed iteration.
+    pub fn iteration(&mut self, best_move: Move, score: i32) {
+        self.instability /= 2.0;
+        if self.best_move == Some(best_move) {
+            self.stable += 1;
+        } else {
+            if self.best_move.is_some() {
+                self.instability += 1.0;
+            }
+            self.stable = 0;
+        }
+
+        self.fall = self.score.map_or(0, |s| s - score).max(0);
+        self.best_move = Some(best_move);
+        self.score = Some(score);
+    }
+
+    /// How long the search should take after the iterations so far.
+    pub fn target(&self) -> Duration {
+        let mut factor = 1.0 + self.instability.min(1.5);
+        if self.fall > 25 {
+            factor *= 1.0 + (self.fall as f64 / 100.0).min(1.0);
+        }
+        // an obvious move doesn't need much thought
+        if self.stable >= 6 {
+            factor *= 0.5;
+        }
+
+        self.optimum.mul_f64(factor).min(self.maximum)
+    }
+
+    /// Whether to start no further iteration after `elapsed`, as it wouldn't finish in time.
+    pub fn should_stop(&self, elapsed: Duration) -> bool {
+        elapsed * 2 > self.target()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::search::time::{TimeLeft, TimeManager};
+    use std::time::Duration;
+
+    fn secs(s: f64) -> Duration {
+        Duration::from_secs_f64(s)
+    }
+
+    #[test]
+    fn allocation() {
+        let tm = TimeManager::new(TimeLeft {
+            remaining: secs(60.05),
+            increment: secs(1.0),
+            moves_to_go: None,
+        });
+        assert_eq!(secs(2.75), tm.optimum());
+        assert_eq!(secs(13.75), tm.maximum());
+
+        let tm = TimeManager::new(TimeLeft {
+            remaining: secs(10.05),
+            increment: Duration::ZERO,
+            moves_to_go: Some(2),
+        });
+        assert_eq!((secs(5.0), secs(8.0)), (tm.optimum(), tm.maximum()));
+
+        let last_move = TimeManager::new(TimeLeft {
+            remaining: secs(3.05),
+            increment: Duration::ZERO,
+            moves_to_go: Some(1),
+        });
+        assert_eq!(
+            (secs(3.0), secs(3.0)),
+            (last_move.optimum(), last_move.maximum())
+        );
+
+        let flagging = TimeManager::new(TimeLeft {
+            remaining: secs(0.01),
+            increment: secs(2.0),
+            moves_to_go: None,
+        });
+        assert_eq!(
+            (Duration::ZERO, Duration::ZERO),
+            (flagging.optimum(), flagging.maximum())
+        );
+    }
+
+    #[test]
+    fn stability() {
+        let mut b = Board::new();
+        let (e4, d4) = (b.parse_san("e4").unwrap(), b.parse_san("d4").unwrap());
+        let mut tm = TimeManager::new(TimeLeft {
+            remaining: secs(30.05),
+            increment: Duration::ZERO,
+            moves_to_go: None,
+        });
+        assert_eq!(secs(1.0), tm.target());
+
+        // the best move changing makes it think longer
+        tm.iteration(e4, 20);
+        tm.iteration(d4, 20);
+        assert_eq!(secs(2.0), tm.target());
+        tm.iteration(d4, 20);
+        assert_eq!(secs(1.5), tm.target());
+        assert!(tm.should_stop(secs(0.8)) && !tm.should_stop(secs(0.7)));
+
+        // so does a falling score
+        tm.iteration(d4, -80);
+        assert_eq!(secs(1.25 * 2.0), tm.target());
+
+        // and an obvious move makes it stop early
+        for _ in 0..8 {
+            tm.iteration(d4, -80);
+        }
+        assert!(tm.target() < secs(0.51));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::chess_move::{Move, MoveKind};
+use crate::piece::PieceType;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Bound {
+    Exact,
+    /// The score is at least this high (fail high).
+    Lower,
+    /// The score is at most this high (fail low).
+    Upper,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TtEntry {
+    pub key: u64,
+    pub best_move: Option<Move>,
+    pub depth: u32,
+    pub score: i32,
+    pub bound: Bound,
+}
+
+const KINDS: [MoveKind; 6] = [
+    MoveKind::Quiet,
+    MoveKind::DoublePawnPush,
+    MoveKind::Capture,
+    MoveKind::EnPassant,
+    MoveKind::CastleShort,
+    MoveKind::CastleLong,
+];
+const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::King,
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+/// The kind of drops, whose piece is stored in place of the promotion.
+const DROP: u64 = 6;
+const BOUNDS: [Bound; 3] = [Bound::Exact, Bound::Lower, Bound::Upper];
+/// Set in every stored entry, so that an empty slot never matches.
+const VALID: u64 = 1 << 63;
+
+/// A fixed-size hash table of search results, indexed by Zobrist key, that threads share
+/// without locking. Newer entries always replace older ones.
+///
+/// An entry is two words, the data and the key xor the data. Writes of several threads to the
+/// same slot can interleave, but a torn entry then fails the key check and is ignored.
+#[derive(Debug)]
+pub struct TranspositionTable {
+    entries: Vec<[AtomicU64; 2]>,
+}
+
+impl TranspositionTable {
+    /// A table of about `mb` megabytes, rounded down to a power of two number of entries.
+    pub fn new(mb: usize) -> Self {
+        let wanted = (mb.max(1) << 20) / std::mem::size_of::<[AtomicU64; 2]>();
+        let len = 1 << (usize::BITS - 1 - wanted.leading_zeros());
+
+        TranspositionTable {
+            entries: (0..len)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect(),
+        }
+    }
+
+    pub fn clear(&self) {
+        for [check, data] in &self.entries {
+            check.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TtEntry> {
+        let [check, data] = &self.entries[self.idx(key)];
+        let data = data.load(Ordering::Relaxed);
+
+        if data & VALID == 0 || check.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+        Some(unpack(key, data))
+    }
+
+    pub fn store(&self, entry: TtEntry) {
+        let [check, data] = &self.entries[self.idx(entry.key)];
+        let packed = pack(&entry);
+
+        check.store(entry.key ^ packed, Ordering::Relaxed);
+        data.store(packed, Ordering::Relaxed);
+    }
+
+    fn idx(&self, key: u64) -> usize {
+        key as usize & (self.entries.len() - 1)
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(16)
+    }
+}
+
+/// Packs everything but the key into a word: the move in the low 19 bits, then the depth, the
+/// score and the bound.
+fn pack(entry: &TtEntry) -> u64 {
+    let mut bits = VALID;
+
+    if let Some(m) = entry.best_move {
+        let (kind, piece) = match m.kind {
+            MoveKind::Drop(t) => (DROP, Some(t)),
+            kind => (
+                KINDS.iter().position(|k| *k == kind).unwrap_or(0) as u64,
+                m.promotion,
+            ),
+        };
+        let promotion = piece
+            .and_then(|t| PIECE_TYPES.iter().position(|p| *p == t))
+            .map_or(0, |i| i as u64 + 1);
+        bits |=
+            m.from.idx() as u64 | (m.to.idx() as u64) << 6 | kind << 12 | promotion << 15 | 1 << 18;
+    }
+    let bound = BOUNDS.iter().position(|b| *b == entry.bound).unwrap_or(0) as u64;
+
+    bits | (entry.depth.min(255) as u64) << 19
+        | (entry.score as i16 as u16 as u64) << 27
+        | bound << 43
+}
+
+fn unpack(key: u64, bits: u64) -> TtEntry {
+    let best_move = (bits & 1 << 18 != 0).then(|| {
+        let promotion = (bits >> 15 & 7) as usize;
+        let piece = (promotion > 0).then(|| PIECE_TYPES[(promotion - 1) % PIECE_TYPES.len()]);
+        let to = ((bits >> 6 & 63) as usize).into();
+
+        match (bits >> 12 & 7, piece) {
+            (DROP, Some(t)) => Move::drop(t, to),
+            (kind, _) => Move {
+                from: ((bits & 63) as usize).into(),
+                to,
+                kind: KINDS[kind as usize % KINDS.len()],
+                promotion: piece,
+            },
+        }
+    });
+
+    TtEntry {
+        key,
+        best_move,
+        depth: (bits >> 19 & 255) as u32,
+        score: (bits >> 27 & 0xffff) as u16 as i16 as i32,
+        bound: BOUNDS[(bits >> 43 & 3) as usize % BOUNDS.len()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::chess_move::Move;
+    use crate::piece::PieceType;
+    use crate::search::tt::{Bound, TranspositionTable, TtEntry};
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        let mut b: Board = "4k3/1P6/8/8/8/8/8/R3K3 w Q - 0 1".parse().unwrap();
+
+        for (m, score, bound) in [
+            (b.parse_san("b8=N"), -29_950, Bound::Upper),
+            (b.parse_san("O-O-O"), 35, Bound::Exact),
+            (Some(Move::drop(PieceType::Knight, "f3".parse().unwrap())), 8, Bound::Exact),
+            (None, 0, Bound::Lower),
+        ] {
+            let entry = TtEntry {
+                key: 0x1234_5678_9abc_def0,
+                best_move: m,
+                depth: 7,
+                score,
+                bound,
+            };
+            tt.store(entry);
+            assert_eq!(Some(entry), tt.probe(entry.key));
+        }
+
+        assert_eq!(None, tt.probe(0x1234_5678_9abc_def1));
+        assert_eq!(None, tt.probe(0));
+
+        tt.clear();
+        assert_eq!(None, tt.probe(0x1234_5678_9abc_def0));
+    }
+
+    #[test]
+    fn torn_entries_are_ignored() {
+        let tt = TranspositionTable::new(1);
+        let entry = |key, depth| TtEntry {
+            key,
+            best_move: None,
+            depth,
+            score: 10,
+            bound: Bound::Exact,
+        };
+        tt.store(entry(64, 3));
+
+        // another thread wrote the data of a different entry in between
+        let idx = tt.idx(64);
+        tt.store(entry(64 + tt.entries.len() as u64, 9));
+        let data = tt.entries[idx][1].load(Ordering::Relaxed);
+        tt.store(entry(64, 3));
+        tt.entries[idx][1].store(data, Ordering::Relaxed);
+
+        assert_eq!(None, tt.probe(64));
+        assert_eq!(None, tt.probe(64 + tt.entries.len() as u64));
+    }
+}
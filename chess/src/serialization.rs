@@ -0,0 +1,390 @@
+//! Serde support, enabled by the `serde` feature.
+//!
+//! The formats are part of the public interface and only change with a new major version:
+//!
+//! | Type | Format | Example |
+//! |---|---|---|
+//! | `Color` | lowercase name | `"white"` |
+//! | `PieceType` | lowercase name | `"knight"` |
+//! | `Piece` | FEN letter, uppercase for white | `"N"`, `"q"` |
+//! | `Position` | square name | `"e4"` |
+//! | `MoveKind` | snake case name, drops with the piece | `"double_pawn_push"`, `{"drop": "knight"}` |
+//! | `Move` | struct | `{"from": "e2", "to": "e4", "kind": "double_pawn_push", "promotion": null}` |
+//! | `Board` | FEN string | `"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"` |
+//! | `Outcome` | struct | `{"win": {"winner": "white", "reason": "checkmate"}}`, `{"draw": "stalemate"}` |
+//! | `PgnGame` | struct | `{"headers": [["White", "A"]], "moves": ["e4"], "main_line": [], "comment": null, "result": "*"}` |
+//!
+//! A FEN string doesn't name the variant, so a `Board` read from one is a standard chess board.
+//! The structured form of [`structured_board`] keeps the variant and breaks the position up into
+//! its parts:
+//!
+//! ```json
+//! {
+//!   "variant": "Crazyhouse",
+//!   "pieces": {"e1": "K", "e8": "k", "d7": "Q"},
+//!   "turn": "white",
+//!   "castling": "-",
+//!   "en_passant": null,
+//!   "halfmove_clock": 0,
+//!   "fullmove_number": 1,
+//!   "promoted": ["d7"],
+//!   "pockets": "Nn",
+//!   "checks_given": [0, 0]
+//! }
+//! ```
+//!
+//! `promoted` and `pockets` are only written for variants with pockets and `checks_given` only
+//! for variants counting checks, they may be left out when reading.
+
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::piece::Piece;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+impl Serialize for Piece {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut buf = [0; 4];
+        serializer.serialize_str(self.fen_char().encode_utf8(&mut buf))
+    }
+}
+
+impl<'de> Deserialize<'de> for Piece {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Piece::from_fen_char(&c),
+            _ => None,
+        }
+        .ok_or_else(|| D::Error::custom(format!("invalid piece '{}'", s)))
+    }
+}
+
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_fen())
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// The structured form of a board, for use with `#[serde(with = "chess::serialization::structured_board")]`.
+pub mod structured_board {
+    use crate::board::position::Position;
+    use crate::board::Board;
+    use crate::piece::{Color, Piece, PieceType};
+    use crate::variant;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize)]
+    struct StructuredBoard {
+        variant: String,
+        #[serde(serialize_with = "serialize_pieces")]
+        pieces: HashMap<Position, Piece>,
+        turn: Color,
+        castling: String,
+        en_passant: Option<Position>,
+        halfmove_clock: u8,
+        fullmove_number: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        promoted: Option<Vec<Position>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pockets: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        checks_given: Option<[u8; 2]>,
+    }
+
+    /// Writes the pieces from a1 to h8 so that the output doesn't depend on the hash order.
+    fn serialize_pieces<S: Serializer>(
+        pieces: &HashMap<Position, Piece>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            Position::iter().filter_map(|pos| pieces.get(&pos).map(|piece| (pos, piece))),
+        )
+    }
+
+    pub fn serialize<S: Serializer>(board: &Board, serializer: S) -> Result<S::Ok, S::Error> {
+        let variant = board.variant();
+        let pieces = Position::iter()
+            .filter_map(|pos| board.get_piece(&pos).map(|piece| (pos, piece)))
+            .collect();
+        let ep_rank = match board.current_player() {
+            Color::White => 5,
+            Color::Black => 2,
+        };
+
+        StructuredBoard {
+            variant: variant.name().to_string(),
+            pieces,
+            turn: board.current_player(),
+            castling: board.castling().to_string(),
+            en_passant: board
+                .en_passant_file()
+                .map(|file| Position::from(ep_rank, file)),
+            halfmove_clock: board.fifty_move_counter(),
+            fullmove_number: board.ply() / 2 + 1,
+            promoted: variant.has_pockets().then(|| {
+                Position::iter()
+                    .filter(|pos| board.is_promoted(pos))
+                    .collect()
+            }),
+            pockets: variant.has_pockets().then(|| {
+                let mut pockets = String::new();
+                for c in Color::iter() {
+                    for t in PieceType::iter() {
+                        for _ in 0..board.pocket(c, t) {
+                            pockets.push(Piece::new(c, t).fen_char());
+                        }
+                    }
+                }
+                pockets
+            }),
+            checks_given: variant.counts_checks().then(|| {
+                [
+                    board.checks_given(Color::White),
+                    board.checks_given(Color::Black),
+                ]
+            }),
+        }
+        .serialize(serializer)
+    }
+
+    /// Reads the board by putting its FEN together, so it's validated the same way.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Board, D::Error> {
+        let s = StructuredBoard::deserialize(deserializer)?;
+        let variant = variant::from_name(&s.variant)
+            .ok_or_else(|| D::Error::custom(format!("unknown variant '{}'", s.variant)))?;
+        let promoted = s.promoted.unwrap_or_default();
+        if let Some(pos) = promoted.iter().find(|pos| !s.pieces.contains_key(pos)) {
+            return Err(D::Error::custom(format!(
+                "promoted piece on empty square {}",
+                pos
+            )));
+        }
+
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                let pos = Position::from(rank, file);
+                match s.pieces.get(&pos) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece.fen_char());
+                        if promoted.contains(&pos) {
+                            fen.push('~');
+                        }
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+        if let Some(pockets) = &s.pockets {
+            fen.push_str(&format!("[{}]", pockets));
+        }
+        let turn = match s.turn {
+            Color::White => 'w',
+            Color::Black => 'b',
+        };
+        let en_passant = s
+            .en_passant
+            .map_or(String::from("-"), |pos| pos.to_string());
+        fen.push_str(&format!(
+            " {} {} {} {} {}",
+            turn, s.castling, en_passant, s.halfmove_clock, s.fullmove_number
+        ));
+        if let Some([white, black]) = s.checks_given {
+            fen.push_str(&format!(" +{}+{}", white, black));
+        }
+
+        Board::from_fen_variant(&fen, variant).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::outcome::{DrawReason, Outcome, WinReason};
+    use crate::board::position::Position;
+    use crate::board::Board;
+    use crate::chess_move::{Move, MoveKind};
+    use crate::pgn::{parse_pgn, PgnGame};
+    use crate::piece::{Color, Piece, PieceType};
+    use crate::variant::ThreeCheck;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Record {
+        #[serde(with = "super::structured_board")]
+        board: Board,
+    }
+
+    fn round_trip<T>(value: &T, json: &str)
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+    {
+        assert_eq!(json, serde_json::to_string(value).unwrap());
+        assert_eq!(*value, serde_json::from_str::<T>(json).unwrap());
+    }
+
+    #[test]
+    fn pieces_and_moves() {
+        round_trip(&Color::Black, r#""black""#);
+        round_trip(&PieceType::Knight, r#""knight""#);
+        round_trip(&Piece::new(Color::White, PieceType::Queen), r#""Q""#);
+        round_trip(&Position::from(3, 4), r#""e4""#);
+        round_trip(
+            &Move::new(
+                Position::from(1, 4),
+                Position::from(3, 4),
+                MoveKind::DoublePawnPush,
+            ),
+            r#"{"from":"e2","to":"e4","kind":"double_pawn_push","promotion":null}"#,
+        );
+        round_trip(
+            &Move::promotion(
+                Position::from(6, 0),
+                Position::from(7, 1),
+                MoveKind::Capture,
+                PieceType::Knight,
+            ),
+            r#"{"from":"a7","to":"b8","kind":"capture","promotion":"knight"}"#,
+        );
+        round_trip(
+            &Move::drop(PieceType::Knight, Position::from(2, 5)),
+            r#"{"from":"f3","to":"f3","kind":{"drop":"knight"},"promotion":null}"#,
+        );
+
+        assert!(serde_json::from_str::<Piece>(r#""x""#).is_err());
+        assert!(serde_json::from_str::<Piece>(r#""Qq""#).is_err());
+        assert!(serde_json::from_str::<Position>(r#""i9""#).is_err());
+    }
+
+    #[test]
+    fn outcomes() {
+        round_trip(
+            &Outcome::Win {
+                winner: Color::White,
+                reason: WinReason::Checkmate,
+            },
+            r#"{"win":{"winner":"white","reason":"checkmate"}}"#,
+        );
+        round_trip(
+            &Outcome::Draw(DrawReason::Stalemate),
+            r#"{"draw":"stalemate"}"#,
+        );
+    }
+
+    #[test]
+    fn board_as_fen() {
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2";
+        let b: Board = fen.parse().unwrap();
+        let json = serde_json::to_string(&b).unwrap();
+        assert_eq!(format!("\"{}\"", fen), json);
+        assert_eq!(fen, serde_json::from_str::<Board>(&json).unwrap().to_fen());
+
+        assert!(serde_json::from_str::<Board>(r#""8/8/8 w - - 0 1""#).is_err());
+    }
+
+    #[test]
+    fn structured_board() {
+        let b = Board::new();
+        let json = serde_json::to_string(&Record { board: b.clone() }).unwrap();
+        assert!(json.starts_with(r#"{"board":{"variant":"Standard","pieces":{"a1":"R","b1":"N""#));
+        assert!(json.ends_with(
+            r#""turn":"white","castling":"KQkq","en_passant":null,"halfmove_clock":0,"fullmove_number":1}}"#
+        ));
+        let read: Record = serde_json::from_str(&json).unwrap();
+        assert_eq!(b.to_fen(), read.board.to_fen());
+        assert_eq!("Standard", read.board.variant().name());
+
+        let json = r#"{"board": {
+            "variant": "Crazyhouse",
+            "pieces": {"e1": "K", "e8": "k", "d7": "Q"},
+            "turn": "black",
+            "castling": "-",
+            "en_passant": null,
+            "halfmove_clock": 0,
+            "fullmove_number": 30,
+            "promoted": ["d7"],
+            "pockets": "Nn"
+        }}"#;
+        let read: Record = serde_json::from_str(json).unwrap();
+        assert_eq!("4k3/3Q~4/8/8/8/8/8/4K3[Nn] b - - 0 30", read.board.to_fen());
+        let again: Record = serde_json::from_str(&serde_json::to_string(&read).unwrap()).unwrap();
+        assert_eq!(read.board.to_fen(), again.board.to_fen());
+
+        let b = Board::from_fen_variant(
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2 +1+0",
+            &ThreeCheck,
+        )
+        .unwrap();
+        let json = serde_json::to_string(&Record { board: b.clone() }).unwrap();
+        assert!(json.contains(r#""checks_given":[1,0]"#));
+        let read: Record = serde_json::from_str(&json).unwrap();
+        assert_eq!(b.to_fen(), read.board.to_fen());
+        assert_eq!(1, read.board.checks_given(Color::White));
+
+        let invalid = [
+            r#"{"board": {"variant": "Shogi", "pieces": {}, "turn": "white", "castling": "-",
+                "en_passant": null, "halfmove_clock": 0, "fullmove_number": 1}}"#,
+            r#"{"board": {"variant": "Standard", "pieces": {"e1": "K", "e8": "k"}, "turn": "white",
+                "castling": "KQ", "en_passant": "e9", "halfmove_clock": 0, "fullmove_number": 1}}"#,
+            r#"{"board": {"variant": "Crazyhouse", "pieces": {"e1": "K", "e8": "k"},
+                "turn": "white", "castling": "-", "en_passant": null, "halfmove_clock": 0,
+                "fullmove_number": 1, "promoted": ["d7"]}}"#,
+            r#"{"board": {"variant": "Standard", "pieces": {"e1": "K", "e8": "k"}, "turn": "white",
+                "castling": "-", "en_passant": null, "halfmove_clock": 0, "fullmove_number": 1,
+                "pockets": "N"}}"#,
+        ];
+        for json in invalid {
+            assert!(serde_json::from_str::<Record>(json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn games() {
+        let game = parse_pgn("[White \"A\"]\n\n1. e4 {good} e5 *")
+            .unwrap()
+            .remove(0);
+        let json = serde_json::to_string(&game).unwrap();
+        assert_eq!(game, serde_json::from_str::<PgnGame>(&json).unwrap());
+
+        let read: PgnGame =
+            serde_json::from_str(r#"{"headers": [["Event", "Test"]], "moves": ["d4"]}"#).unwrap();
+        assert_eq!(Some("Test"), read.header("Event"));
+        assert_eq!(1, read.replay().unwrap().1.len());
+    }
+}
@@ -0,0 +1,608 @@
+//! Probing of Syzygy endgame tablebases.
+//!
+//! WDL tables (`.rtbw`) tell whether a position is won, drawn or lost with best play, DTZ
+//! tables (`.rtbz`) how many plies it then takes to the next capture or pawn move. Only
+//! standard chess positions without castling rights can be probed. Tables are memory-mapped the
+//! first time they are needed, so only the parts that probes touch are read.
+
+mod table;
+
+use crate::board::outcome::{DrawReason, Outcome, WinReason};
+use crate::board::Board;
+use crate::chess_move::Move;
+use crate::piece::{Color, Piece, PieceType};
+use crate::variant::{Standard, Variant};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use table::{Kind, Material, Probe, Table};
+
+/// The rank of a move that wins before the 50-move rule could intervene.
+const MAX_DTZ: i32 = 1 << 18;
+
+/// The result of a position with best play, from the view of the side to move. Cursed wins and
+/// blessed losses turn into draws by the 50-move rule.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            ..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+}
+
+/// The tables found in one or more directories.
+#[derive(Default)]
+pub struct Tablebase {
+    tables: HashMap<String, Entry>,
+    max_pieces: usize,
+}
+
+/// The files of a material, e.g. `KRvK`, and their tables once read.
+struct Entry {
+    material: Material,
+    wdl: PathBuf,
+    dtz: Option<PathBuf>,
+    wdl_table: OnceLock<Option<Table>>,
+    dtz_table: OnceLock<Option<Table>>,
+}
+
+impl Tablebase {
+    pub fn open(dir: impl AsRef<Path>) -> std::io::Result<Tablebase> {
+        let mut tablebase = Tablebase::default();
+        tablebase.add_directory(dir)?;
+
+        Ok(tablebase)
+    }
+
+    /// Adds the tables in `dir`, keeping the ones already known. Only materials with a WDL table
+    /// can be probed.
+    pub fn add_directory(&mut self, dir: impl AsRef<Path>) -> std::io::Result<()> {
+        for file in std::fs::read_dir(&dir)? {
+            let path = file?.path();
+            if path.extension().is_none_or(|ext| ext != "rtbw") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let Some(material) = Material::from_name(name) else {
+                continue;
+            };
+
+            let dtz = path.with_extension("rtbz");
+            self.max_pieces = self.max_pieces.max(material.piece_count);
+            self.tables.entry(name.to_string()).or_insert(Entry {
+                material,
+                dtz: dtz.exists().then_some(dtz),
+                wdl: path,
+                wdl_table: OnceLock::new(),
+                dtz_table: OnceLock::new(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// How many materials have tables.
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// The most pieces, kings included, of a table.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// The result of the position with best play, if it's in the tables.
+    pub fn probe_wdl(&self, board: &mut Board) -> Option<Wdl> {
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        self.search(board, false).map(|(wdl, _)| Wdl::from_value(wdl))
+    }
+
+    /// The plies to the next capture or pawn move with best play, positive if the side to move
+    /// wins, negative if it loses and 0 for a draw. Cursed wins and blessed losses are 100 plies
+    /// further from 0. The distance may be a ply too long, as some tables store full moves.
+    pub fn probe_dtz(&self, board: &mut Board) -> Option<i32> {
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        self.dtz(board)
+    }
+
+    /// Ranks `moves`, higher is better: wins that the 50-move rule can't spoil rank highest,
+    /// then the other wins by how far they are from it, draws, and losses last.
+    pub fn rank_moves(&self, board: &mut Board, moves: &[Move]) -> Option<Vec<i32>> {
+        if !self.can_probe(board) {
+            return None;
+        }
+        let fifty = board.fifty_move_counter() as i32;
+        let repeated = board.repetition_count() >= 2;
+
+        moves
+            .iter()
+            .map(|&m| {
+                board.make_move(m);
+                let mate = board.in_check() && board.legal_moves().is_empty();
+                let dtz = if board.fifty_move_counter() == 0 {
+                    self.search(board, false)
+                        .map(|(wdl, _)| dtz_before_zeroing(-wdl))
+                } else if board.repetition_count() >= 2
+                    || (board.fifty_move_counter() >= 100 && !mate)
+                {
+                    Some(0)
+                } else {
+                    self.dtz(board).map(|dtz| -dtz - dtz.signum())
+                };
+                board.unmake_move(m);
+
+                let dtz = if mate { 1 } else { dtz? };
+                Some(if dtz > 0 {
+                    if dtz + fifty <= 99 && !repeated {
+                        MAX_DTZ
+                    } else {
+                        MAX_DTZ - (dtz + fifty)
+                    }
+                } else if dtz < 0 {
+                    if -dtz * 2 + fifty < 100 {
+                        -MAX_DTZ
+                    } else {
+                        -MAX_DTZ + (-dtz + fifty)
+                    }
+                } else {
+                    0
+                })
+            })
+            .collect()
+    }
+
+    fn can_probe(&self, board: &Board) -> bool {
+        let castling = board.castling();
+        let pieces = Color::iter()
+            .flat_map(|c| PieceType::iter().map(move |t| (c, t)))
+            .map(|(c, t)| board.pieces(&Piece::new(c, t)).count())
+            .sum::<usize>();
+
+        board.variant().name() == Standard.name()
+            && !(castling.white_short
+                || castling.white_long
+                || castling.black_short
+                || castling.black_long)
+            && pieces <= self.max_pieces
+    }
+
+    /// The WDL result, searching the captures first, as the tables don't tell them apart from
+    /// what's left after them. Also tells whether the best move is a capture or, if
+    /// `check_zeroing`, a pawn move.
+    fn search(&self, board: &mut Board, check_zeroing: bool) -> Option<(i32, bool)> {
+        let moves = board.legal_moves();
+        let mut best = -2;
+        let mut searched = 0;
+
+        for &m in moves.iter() {
+            let pawn = board.get_piece(&m.from).map(|p| p.piece_type) == Some(PieceType::Pawn);
+            if !(m.is_capture() || check_zeroing && pawn) {
+                continue;
+            }
+            searched += 1;
+
+            board.make_move(m);
+            let value = self.search(board, false);
+            board.unmake_move(m);
+
+            let value = -value?.0;
+            if value > best {
+                best = value;
+                if value >= 2 {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        let only_zeroing = searched > 0 && searched == moves.len();
+        let value = if only_zeroing {
+            best
+        } else {
+            match self.probe_table(board, Kind::Wdl, 0)? {
+                Probe::Value(value) => value,
+                Probe::ChangeStm => return None,
+            }
+        };
+
+        if best >= value {
+            Some((best, best > 0 || only_zeroing))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    fn dtz(&self, board: &mut Board) -> Option<i32> {
+        let (wdl, zeroing) = self.search(board, true)?;
+        if wdl == 0 {
+            return Some(0);
+        }
+        if zeroing {
+            return Some(dtz_before_zeroing(wdl));
+        }
+        if let Probe::Value(dtz) = self.probe_table(board, Kind::Dtz, wdl)? {
+            let cursed = if wdl.abs() == 1 { 100 } else { 0 };
+            return Some((dtz + cursed) * wdl.signum());
+        }
+
+        // the table has the other side to move: find the best move to it
+        let mut best = None;
+        for &m in board.legal_moves().iter() {
+            let zeroing = m.is_capture()
+                || board.get_piece(&m.from).map(|p| p.piece_type) == Some(PieceType::Pawn);
+
+            board.make_move(m);
+            let dtz = if zeroing {
+                self.search(board, false)
+                    .map(|(wdl, _)| -dtz_before_zeroing(wdl))
+            } else {
+                self.dtz(board).map(|dtz| -dtz)
+            };
+            let mate = dtz == Some(1) && board.in_check() && board.legal_moves().is_empty();
+            board.unmake_move(m);
+
+            let dtz = dtz?;
+            let dtz = if zeroing { dtz } else { dtz + dtz.signum() };
+            if mate {
+                best = Some(1);
+            } else if dtz.signum() == wdl.signum() && best.is_none_or(|best| dtz < best) {
+                best = Some(dtz);
+            }
+        }
+
+        Some(best.unwrap_or(-1))
+    }
+
+    /// Looks up the position in the table of its material.
+    fn probe_table(&self, board: &Board, kind: Kind, wdl: i32) -> Option<Probe> {
+        let white = Material::side_name(board, Color::White);
+        let black = Material::side_name(board, Color::Black);
+        if white == "K" && black == "K" {
+            return Some(Probe::Value(0));
+        }
+
+        // the tables have the stronger side as white
+        let black_to_move = board.current_player() == Color::Black;
+        let (entry, flip) = match self.tables.get(&format!("{}v{}", white, black)) {
+            Some(entry) => (entry, entry.material.is_symmetric() && black_to_move),
+            None => (self.tables.get(&format!("{}v{}", black, white))?, true),
+        };
+
+        let m = &entry.material;
+        let table = match kind {
+            Kind::Wdl => entry.wdl_table.get_or_init(|| Table::open(&entry.wdl, kind, m)),
+            Kind::Dtz => entry
+                .dtz_table
+                .get_or_init(|| Table::open(entry.dtz.as_ref()?, kind, m)),
+        };
+
+        table.as_ref()?.probe(board, &entry.material, flip, wdl)
+    }
+}
+
+/// The DTZ of a position whose best move is a capture or pawn move with the result `wdl`.
+fn dtz_before_zeroing(wdl: i32) -> i32 {
+    match wdl {
+        2 => 1,
+        1 => 101,
+        -1 => -101,
+        -2 => -1,
+        _ => 0,
+    }
+}
+
+impl Debug for Tablebase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tablebase")
+            .field("tables", &self.tables.len())
+            .field("max_pieces", &self.max_pieces)
+            .finish()
+    }
+}
+
+impl Board {
+    /// The result the attached tablebase proves: a draw, or a win that the 50-move rule can't
+    /// spoil anymore.
+    pub(crate) fn tablebase_outcome(&mut self) -> Option<Outcome> {
+        let tablebase = self.tablebase()?.clone();
+
+        match tablebase.probe_wdl(self)? {
+            Wdl::Win | Wdl::Loss => {
+                let dtz = tablebase.probe_dtz(self)?;
+                if dtz.abs() + self.fifty_move_counter() as i32 > 99 {
+                    return None;
+                }
+                let winner = if dtz > 0 {
+                    self.current_player()
+                } else {
+                    self.current_player().opponent()
+                };
+
+                Some(Outcome::Win {
+                    winner,
+                    reason: WinReason::Tablebase,
+                })
+            }
+            _ => Some(Outcome::Draw(DrawReason::Tablebase)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::outcome::{DrawReason, Outcome, WinReason};
+    use crate::board::Board;
+    use crate::chess_move::Move;
+    use crate::piece::Color;
+    use crate::search::{Search, SearchLimits};
+    use crate::syzygy::table::{encode, Material, PairsData, DTZ_MAGIC, WDL_MAGIC};
+    use crate::syzygy::{Tablebase, Wdl, MAX_DTZ};
+    use std::path::PathBuf;
+    use std::sync::{Arc, OnceLock};
+
+    /// The FEN of a position with `pieces` on the squares numbered from a1.
+    fn fen(pieces: &[(char, usize)], rest: &str) -> String {
+        let mut ranks = Vec::new();
+        for rank in (0..8).rev() {
+            let mut row = String::new();
+            let mut empty = 0;
+            for file in 0..8 {
+                match pieces.iter().find(|(_, sq)| *sq == rank * 8 + file) {
+                    Some((c, _)) => {
+                        if empty > 0 {
+                            row.push_str(&empty.to_string());
+                        }
+                        empty = 0;
+                        row.push(*c);
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                row.push_str(&empty.to_string());
+            }
+            ranks.push(row);
+        }
+
+        format!("{} {}", ranks.join("/"), rest)
+    }
+
+    /// A KRvK WDL table as the generator would write it: a single value for white to move, who
+    /// always wins, and Huffman coded values for black to move, who loses unless stalemated.
+    fn krk_wdl() -> &'static [u8] {
+        static FILE: OnceLock<Vec<u8>> = OnceLock::new();
+        FILE.get_or_init(write_krk_wdl)
+    }
+
+    fn write_krk_wdl() -> Vec<u8> {
+        let m = Material::from_name("KRvK").unwrap();
+        let pieces = vec![6, 4, 14];
+        let d = PairsData::new(pieces.clone(), [0, 0xF], 0, &m).unwrap();
+
+        // the value is the result plus 2, all positions are reached with the white king in the
+        // a1-d1-d4 triangle
+        let mut values = vec![0; 31332];
+        for k in (0..28).filter(|&sq| sq % 8 <= 3 && sq / 8 <= sq % 8) {
+            for r in (0..64).filter(|&r| r != k) {
+                for bk in (0..64).filter(|&bk| bk != k && bk != r) {
+                    let fen = fen(&[('K', k), ('R', r), ('k', bk)], "b - - 0 1");
+                    let Ok(mut board) = fen.parse::<Board>() else {
+                        continue;
+                    };
+                    let stalemate = !board.in_check() && board.legal_moves().is_empty();
+                    let idx = encode(&d, &m, &mut [k, r, bk], &mut pieces.clone(), 0).unwrap();
+                    values[idx as usize] = if stalemate { 2 } else { 0 };
+                }
+            }
+        }
+
+        // codes: four losses `1`, two losses `01`, a loss `001`, a draw `0000`, a win `0001`,
+        // in blocks of 256 bits
+        let mut blocks: Vec<Vec<bool>> = Vec::new();
+        let mut starts = Vec::new();
+        let mut i = 0;
+        while i < values.len() {
+            let losses = values[i..].iter().take(4).take_while(|&&v| v == 0).count();
+            let (code, n) = match (losses, values[i]) {
+                (4, _) => ("1", 4),
+                (2 | 3, _) => ("01", 2),
+                (1, _) => ("001", 1),
+                (_, 2) => ("0000", 1),
+                _ => ("0001", 1),
+            };
+            if blocks.last().is_none_or(|bits| bits.len() + code.len() > 256) {
+                blocks.push(Vec::new());
+                starts.push(i);
+            }
+            blocks.last_mut().unwrap().extend(code.chars().map(|c| c == '1'));
+            i += n;
+        }
+        starts.push(values.len());
+
+        let mut file = WDL_MAGIC.to_vec();
+        // split by side to move, the order of the groups, the pieces, alignment
+        file.extend([0x01, 0x00, 0x66, 0x44, 0xEE, 0x00]);
+        // white to move: a single value
+        file.extend([0x80, 4]);
+        // black to move: 32 byte blocks, a span of 512 values and the Huffman code
+        file.extend([0x00, 5, 9, 0]);
+        file.extend((blocks.len() as u32).to_le_bytes());
+        file.extend([4, 1]);
+        for sym in [4u16, 3, 2, 0] {
+            file.extend(sym.to_le_bytes());
+        }
+        file.extend(5u16.to_le_bytes());
+        for (left, right) in [(2u16, 0xFFFu16), (4, 0xFFF), (0, 0xFFF), (2, 2), (3, 3)] {
+            file.extend([left as u8, (left >> 8 | (right & 0xF) << 4) as u8, (right >> 4) as u8]);
+        }
+        file.push(0);
+
+        for k in 0..values.len().div_ceil(512) {
+            let idx = k * 512 + 256;
+            let block = starts.iter().rposition(|&s| s <= idx.min(values.len() - 1)).unwrap();
+            file.extend((block as u32).to_le_bytes());
+            file.extend(((idx - starts[block]) as u16).to_le_bytes());
+        }
+        for block in 0..blocks.len() {
+            file.extend(((starts[block + 1] - starts[block] - 1) as u16).to_le_bytes());
+        }
+        file.resize(file.len().div_ceil(64) * 64, 0);
+        for bits in blocks {
+            let mut bytes = [0u8; 32];
+            for (i, _) in bits.iter().enumerate().filter(|(_, &bit)| bit) {
+                bytes[i / 8] |= 0x80 >> (i % 8);
+            }
+            file.extend(bytes);
+        }
+
+        file
+    }
+
+    /// A KRvK DTZ table with white to move 5 moves from the next capture or pawn move
+    /// everywhere.
+    fn krk_dtz() -> Vec<u8> {
+        let mut file = DTZ_MAGIC.to_vec();
+        file.extend([0x01, 0x00, 0x06, 0x04, 0x0E, 0x00, 0x80, 5]);
+        file.resize(64, 0);
+        file
+    }
+
+    fn tables(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("syzygy-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("KRvK.rtbw"), krk_wdl()).unwrap();
+        std::fs::write(dir.join("KRvK.rtbz"), krk_dtz()).unwrap();
+        dir
+    }
+
+    #[test]
+    fn probe() {
+        let dir = tables("probe");
+        let tb = Tablebase::open(&dir).unwrap();
+        assert_eq!((1, 3), (tb.len(), tb.max_pieces()));
+        assert!(Tablebase::open(dir.join("missing")).is_err());
+
+        let wdl = |fen: &str| tb.probe_wdl(&mut fen.parse().unwrap());
+        let dtz = |fen: &str| tb.probe_dtz(&mut fen.parse().unwrap());
+
+        assert_eq!(Some(Wdl::Win), wdl("8/8/8/8/8/2k5/8/R3K3 w - - 0 1"));
+        assert_eq!(Some(Wdl::Loss), wdl("8/8/8/8/8/2k5/8/R3K3 b - - 0 1"));
+        // stalemate
+        assert_eq!(Some(Wdl::Draw), wdl("k7/1R6/2K5/8/8/8/8/8 b - - 0 1"));
+        // the rook hangs
+        assert_eq!(Some(Wdl::Draw), wdl("8/8/8/8/8/8/1kR5/4K3 b - - 0 1"));
+        // black has the rook
+        assert_eq!(Some(Wdl::Win), wdl("r3k3/8/2K5/8/8/8/8/8 b - - 0 1"));
+        assert_eq!(Some(Wdl::Loss), wdl("r3k3/8/2K5/8/8/8/8/8 w - - 0 1"));
+        // kings alone
+        assert_eq!(Some(Wdl::Draw), wdl("8/8/8/8/8/2k5/8/4K3 w - - 0 1"));
+        // castling rights, no table and too many pieces
+        assert_eq!(None, wdl("8/8/8/8/8/2k5/8/R3K3 w Q - 0 1"));
+        assert_eq!(None, wdl("8/8/8/8/8/2k5/8/Q3K3 w - - 0 1"));
+        assert_eq!(None, wdl("8/8/8/8/8/2k5/8/RR2K3 w - - 0 1"));
+
+        assert_eq!(Some(11), dtz("8/8/8/8/8/2k5/8/R3K3 w - - 0 1"));
+        // the table has white to move only, so black looks a move ahead
+        assert_eq!(Some(-12), dtz("8/8/8/8/8/2k5/8/R3K3 b - - 0 1"));
+        assert_eq!(Some(11), dtz("r3k3/8/2K5/8/8/8/8/8 b - - 0 1"));
+        assert_eq!(Some(0), dtz("k7/1R6/2K5/8/8/8/8/8 b - - 0 1"));
+        // capturing the rook draws
+        assert_eq!(Some(0), dtz("8/8/8/8/8/8/1kR5/4K3 b - - 0 1"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn root_moves() {
+        let dir = tables("root");
+        let tb = Arc::new(Tablebase::open(&dir).unwrap());
+
+        // moving the rook next to the black king gives it away
+        let mut b: Board = "8/8/8/8/8/2k5/8/1R2K3 w - - 0 1".parse().unwrap();
+        let moves: Vec<Move> = b.legal_moves().iter().copied().collect();
+        let ranks = tb.rank_moves(&mut b, &moves).unwrap();
+        for (m, rank) in moves.iter().zip(&ranks) {
+            let hangs = ["b1b2", "b1b3", "b1b4"].contains(&m.to_string().as_str());
+            assert_eq!(if hangs { 0 } else { MAX_DTZ }, *rank, "{}", m);
+        }
+
+        // going back to a position seen before draws
+        let mut repeated = b.clone();
+        for m in ["e1e2", "c3c4", "e2e1", "c4c3"] {
+            let m = repeated.parse_uci_move(m).unwrap();
+            repeated.make_move(m);
+        }
+        let moves = ["e1e2", "e1f1"].map(|m| repeated.parse_uci_move(m).unwrap());
+        let ranks = tb.rank_moves(&mut repeated, &moves).unwrap();
+        assert_eq!(0, ranks[0]);
+        assert!(ranks[1] > 0);
+
+        // so the search doesn't consider them
+        b.set_tablebase(Some(tb.clone()));
+        let limits = SearchLimits {
+            depth: Some(1),
+            search_moves: ["b1b3", "b1b8"].map(|m| b.parse_uci_move(m).unwrap()).to_vec(),
+            ..SearchLimits::default()
+        };
+        let result = Search::new().run(&mut b, limits, |_| {});
+        assert_eq!(Some("b1b8"), result.best_move.map(|m| m.to_string()).as_deref());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn outcome() {
+        let dir = tables("outcome");
+        let tb = Arc::new(Tablebase::open(&dir).unwrap());
+        let outcome = |fen: &str| {
+            let mut b: Board = fen.parse().unwrap();
+            b.set_tablebase(Some(tb.clone()));
+            b.outcome()
+        };
+        let white_wins = Some(Outcome::Win {
+            winner: Color::White,
+            reason: WinReason::Tablebase,
+        });
+
+        assert_eq!(white_wins, outcome("8/8/8/8/8/2k5/8/R3K3 w - - 0 1"));
+        assert_eq!(white_wins, outcome("8/8/8/8/8/2k5/8/R3K3 b - - 0 1"));
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::Tablebase)),
+            outcome("8/8/8/8/8/8/1kR5/4K3 b - - 0 1")
+        );
+        // the rules come first
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::Stalemate)),
+            outcome("k7/1R6/2K5/8/8/8/8/8 b - - 0 1")
+        );
+        // the 50-move rule may still save black
+        assert_eq!(None, outcome("8/8/8/8/8/2k5/8/R3K3 w - - 95 80"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,902 @@
+//! The Syzygy file format: how positions are indexed and how the values are compressed.
+//!
+//! A table stores one value per position of its material, the positions numbered by placing
+//! the pieces group by group, with the symmetries of the board removed. The values are
+//! Huffman coded symbols, each standing for a run of values by recursive pairing, in blocks
+//! found through a sparse index.
+
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::piece::{Color, Piece, PieceType};
+use memmap2::Mmap;
+use std::fs::File;
+use std::path::Path;
+use std::sync::OnceLock;
+
+pub const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+pub const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+/// The most pieces a table can have.
+pub const MAX_PIECES: usize = 7;
+
+// the flags of a table, per side to move and file
+const STM: u8 = 1;
+const MAPPED: u8 = 2;
+const WIN_PLIES: u8 = 4;
+const LOSS_PLIES: u8 = 8;
+const WIDE: u8 = 16;
+const SINGLE_VALUE: u8 = 128;
+
+/// The leading group of three unique pieces has this many placements.
+const UNIQUE_PIECES_SIZE: u64 = 31332;
+/// The two kings have this many placements.
+const KINGS_SIZE: u64 = 462;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Kind {
+    Wdl,
+    Dtz,
+}
+
+/// The pieces of a table, e.g. `KRvK`. The side written first is called white, even when the
+/// position has them as black.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Material {
+    /// The piece count by side and type, in the order of `PieceType::iter`.
+    counts: [[u8; 6]; 2],
+    pub piece_count: usize,
+    pub has_pawns: bool,
+    /// Whether a side has a piece other than the king just once.
+    pub has_unique_pieces: bool,
+    /// The pawns of the side with the leading pawns, then those of the other side.
+    pub pawn_count: [u8; 2],
+}
+
+impl Material {
+    /// Reads a table name like `KRvK`, the stronger side first.
+    pub fn from_name(name: &str) -> Option<Material> {
+        let (white, black) = name.split_once('v')?;
+        let mut counts = [[0; 6]; 2];
+
+        for (side, pieces) in [white, black].iter().enumerate() {
+            for c in pieces.chars() {
+                let t = PieceType::from_fen_char(&c).filter(|_| c.is_ascii_uppercase())?;
+                counts[side][PieceType::iter().position(|i| i == t)?] += 1;
+            }
+            if counts[side][0] != 1 {
+                return None;
+            }
+        }
+
+        let material = Material::new(counts);
+        (2 < material.piece_count && material.piece_count <= MAX_PIECES).then_some(material)
+    }
+
+    fn new(counts: [[u8; 6]; 2]) -> Material {
+        let pawns = [counts[0][5], counts[1][5]];
+        // the side with fewer pawns leads, as that compresses better
+        let white_leads = pawns[1] == 0 || (pawns[0] > 0 && pawns[1] >= pawns[0]);
+
+        Material {
+            counts,
+            piece_count: counts.iter().flatten().map(|&n| n as usize).sum(),
+            has_pawns: pawns[0] + pawns[1] > 0,
+            has_unique_pieces: counts.iter().any(|side| side[1..].contains(&1)),
+            pawn_count: if white_leads { pawns } else { [pawns[1], pawns[0]] },
+        }
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        self.counts[0] == self.counts[1]
+    }
+
+    /// The name of the pieces of one side, e.g. `KR`.
+    pub fn side_name(board: &Board, color: Color) -> String {
+        PieceType::iter()
+            .flat_map(|t| {
+                let count = board.pieces(&Piece::new(color, t)).count();
+                std::iter::repeat_n(t.get_fen_char().to_ascii_uppercase(), count)
+            })
+            .collect()
+    }
+}
+
+/// The lookup tables of the position indexing.
+struct Maps {
+    /// Numbers the squares below the a1-h8 diagonal 0 to 27.
+    b1h1h7: [u64; 64],
+    /// Numbers the a1-d1-d4 triangle 0 to 9, the diagonal last.
+    a1d1d4: [u64; 64],
+    /// The 462 placements of two kings, by the `a1d1d4` code of the first and the second square.
+    kk: [[u64; 64]; 10],
+    /// The ways to choose `k` of `n` squares.
+    binomial: [[u64; 64]; 6],
+    /// Numbers the pawn squares, the leading pawn being the one with the highest number.
+    pawns: [u64; 64],
+    /// The index of the leading pawns by count and square of the first one.
+    lead_pawn_idx: [[u64; 64]; 6],
+    /// The placements of the leading pawns by count and file.
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+fn maps() -> &'static Maps {
+    static MAPS: OnceLock<Maps> = OnceLock::new();
+    MAPS.get_or_init(Maps::new)
+}
+
+/// How far the square is above the a1-h8 diagonal, negative below it.
+fn off_diagonal(sq: usize) -> i32 {
+    (sq / 8) as i32 - (sq % 8) as i32
+}
+
+impl Maps {
+    fn new() -> Maps {
+        let mut maps = Maps {
+            b1h1h7: [0; 64],
+            a1d1d4: [0; 64],
+            kk: [[0; 64]; 10],
+            binomial: [[0; 64]; 6],
+            pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+        };
+
+        for (code, sq) in (0..64).filter(|&sq| off_diagonal(sq) < 0).enumerate() {
+            maps.b1h1h7[sq] = code as u64;
+        }
+
+        let triangle = (0..28).filter(|&sq| sq % 8 <= 3);
+        let below = triangle.clone().filter(|&sq| off_diagonal(sq) < 0);
+        let diagonal = triangle.filter(|&sq| off_diagonal(sq) == 0);
+        for (code, sq) in below.chain(diagonal).enumerate() {
+            maps.a1d1d4[sq] = code as u64;
+        }
+
+        // with the first king on the diagonal, the second isn't above it; the placements with
+        // both on the diagonal come last
+        let mut code = 0;
+        let mut both_on_diagonal = Vec::new();
+        for idx in 0..10 {
+            // b1 has code 0 as well as the squares outside the triangle
+            let first = (0..28).find(|&sq| {
+                sq % 8 <= 3
+                    && off_diagonal(sq) <= 0
+                    && maps.a1d1d4[sq] == idx as u64
+                    && (idx > 0 || sq == 1)
+            });
+            let Some(s1) = first else { continue };
+
+            for s2 in 0..64 {
+                let touching = (s1 / 8).abs_diff(s2 / 8) <= 1 && (s1 % 8).abs_diff(s2 % 8) <= 1;
+                if touching || (off_diagonal(s1) == 0 && off_diagonal(s2) > 0) {
+                    continue;
+                }
+                if off_diagonal(s1) == 0 && off_diagonal(s2) == 0 {
+                    both_on_diagonal.push((idx, s2));
+                } else {
+                    maps.kk[idx][s2] = code;
+                    code += 1;
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            maps.kk[idx][s2] = code;
+            code += 1;
+        }
+
+        maps.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..6.min(n + 1) {
+                maps.binomial[k][n] = if k > 0 { maps.binomial[k - 1][n - 1] } else { 0 }
+                    + if k < n { maps.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        let mut available = 47;
+        for lead_count in 1..6 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let sq = rank * 8 + file;
+                    if lead_count == 1 {
+                        maps.pawns[sq] = available;
+                        maps.pawns[sq ^ 7] = available - 1;
+                        available = available.saturating_sub(2);
+                    }
+                    maps.lead_pawn_idx[lead_count][sq] = idx;
+                    idx += maps.binomial[lead_count - 1][maps.pawns[sq] as usize];
+                }
+                maps.lead_pawns_size[lead_count][file] = idx;
+            }
+        }
+
+        maps
+    }
+}
+
+/// The decoding data of one side to move and file of the leading pawn.
+#[derive(Clone, Debug, Default)]
+pub struct PairsData {
+    flags: u8,
+    /// The pieces in the order they are indexed, as nibbles with the type from 1 (pawn) to 6
+    /// (king), plus 8 for black.
+    pieces: Vec<u8>,
+    /// The sizes of the groups of pieces placed together, ended by a zero.
+    group_len: [usize; MAX_PIECES + 1],
+    /// The factor of each group in the index, the table size after the last group.
+    group_idx: [u64; MAX_PIECES + 1],
+    /// The shortest code, or the value of a single valued table.
+    min_sym_len: u8,
+    block_size: usize,
+    span: u64,
+    num_blocks: usize,
+    block_length_size: usize,
+    sparse_index_size: usize,
+    // offsets into the file
+    lowest_sym: usize,
+    btree: usize,
+    sparse_index: usize,
+    block_length: usize,
+    data: usize,
+    /// The lowest code of each code length, left aligned.
+    base64: Vec<u64>,
+    /// How many values each symbol stands for, minus one.
+    symlen: Vec<u8>,
+    /// Where the value maps of a DTZ table start, by result.
+    map_idx: [usize; 4],
+}
+
+impl PairsData {
+    /// Splits `pieces` into groups and orders them as given by `order`: the leading group is
+    /// placed as `order[0]`, the remaining pawns as `order[1]`.
+    pub fn new(pieces: Vec<u8>, order: [u8; 2], file: usize, m: &Material) -> Option<PairsData> {
+        let maps = maps();
+        let mut d = PairsData {
+            pieces,
+            ..PairsData::default()
+        };
+
+        let mut first_len = if m.has_pawns { 0 } else if m.has_unique_pieces { 3 } else { 2 };
+        let mut n = 0;
+        d.group_len[0] = 1;
+        for i in 1..d.pieces.len() {
+            first_len -= 1;
+            if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+                d.group_len[n] += 1;
+            } else {
+                n += 1;
+                *d.group_len.get_mut(n)? = 1;
+            }
+        }
+        n += 1;
+
+        let both_pawns = m.has_pawns && m.pawn_count[1] > 0;
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free = 64 - d.group_len[0] - if both_pawns { d.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            let size = if k == order[0] {
+                d.group_idx[0] = idx;
+                if m.has_pawns {
+                    *maps.lead_pawns_size.get(d.group_len[0])?.get(file)?
+                } else if m.has_unique_pieces {
+                    UNIQUE_PIECES_SIZE
+                } else {
+                    KINGS_SIZE
+                }
+            } else if k == order[1] {
+                d.group_idx[1] = idx;
+                *maps.binomial.get(d.group_len[1])?.get(48 - d.group_len[0])?
+            } else {
+                d.group_idx[next] = idx;
+                let size = *maps.binomial.get(d.group_len[next])?.get(free)?;
+                free -= d.group_len[next];
+                next += 1;
+                size
+            };
+            idx = idx.checked_mul(size)?;
+            k += 1;
+        }
+        d.group_idx[n] = idx;
+
+        Some(d)
+    }
+
+    fn table_size(&self) -> u64 {
+        let groups = self.group_len.iter().position(|&len| len == 0).unwrap_or(MAX_PIECES);
+        self.group_idx[groups]
+    }
+
+    /// Reads the sizes of the compressed data starting at `at`, returning where they end.
+    fn set_sizes(&mut self, b: &[u8], mut at: usize) -> Option<usize> {
+        self.flags = byte(b, at)?;
+        if self.flags & SINGLE_VALUE != 0 {
+            self.min_sym_len = byte(b, at + 1)?;
+            return Some(at + 2);
+        }
+
+        self.block_size = 1usize.checked_shl(byte(b, at + 1)? as u32)?;
+        self.span = 1u64.checked_shl(byte(b, at + 2)? as u32)?;
+        self.sparse_index_size = self.table_size().div_ceil(self.span) as usize;
+        let padding = byte(b, at + 3)? as usize;
+        self.num_blocks = u32_le(b, at + 4)? as usize;
+        self.block_length_size = self.num_blocks + padding;
+        let max_sym_len = byte(b, at + 8)?;
+        self.min_sym_len = byte(b, at + 9)?;
+        at += 10;
+
+        if max_sym_len > 64 || max_sym_len < self.min_sym_len {
+            return None;
+        }
+        // the canonical code gives longer codes lower values: the lowest code of each length
+        // follows from the lowest symbols
+        let lengths = (max_sym_len - self.min_sym_len + 1) as usize;
+        self.lowest_sym = at;
+        self.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            self.base64[i] = self.base64[i + 1]
+                .wrapping_add(u16_le(b, at + 2 * i)? as u64)
+                .wrapping_sub(u16_le(b, at + 2 * i + 2)? as u64)
+                / 2;
+        }
+        for (i, base) in self.base64.iter_mut().enumerate() {
+            *base = base
+                .checked_shl((64 - i - self.min_sym_len as usize) as u32)
+                .unwrap_or(0);
+        }
+        at += 2 * lengths;
+
+        let symbols = u16_le(b, at)? as usize;
+        self.btree = at + 2;
+        self.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for sym in 0..symbols {
+            if !visited[sym] {
+                self.symlen[sym] = self.set_symlen(b, sym, &mut visited)?;
+            }
+        }
+
+        Some(self.btree + 3 * symbols + (symbols & 1))
+    }
+
+    fn set_symlen(&mut self, b: &[u8], sym: usize, visited: &mut [bool]) -> Option<u8> {
+        visited[sym] = true;
+        let (left, right) = pair(b, self.btree, sym)?;
+        if right == 0xFFF {
+            return Some(0);
+        }
+
+        for s in [left, right] {
+            if !*visited.get(s)? {
+                self.symlen[s] = self.set_symlen(b, s, visited)?;
+            }
+        }
+        Some(self.symlen[left].wrapping_add(self.symlen[right]).wrapping_add(1))
+    }
+}
+
+pub enum Probe {
+    Value(i32),
+    /// The DTZ table only has the positions with the other side to move.
+    ChangeStm,
+}
+
+/// A memory-mapped table file.
+pub struct Table {
+    kind: Kind,
+    bytes: Mmap,
+    /// By side to move (both only in WDL tables of unequal material) and file of the leading
+    /// pawn (all four only with pawns).
+    data: Vec<Vec<PairsData>>,
+    /// Where the value maps of a DTZ table start.
+    map: usize,
+}
+
+impl Table {
+    pub fn open(path: &Path, kind: Kind, m: &Material) -> Option<Table> {
+        let file = File::open(path).ok()?;
+        // SAFETY: the table is only read, and table files don't change while they are in use.
+        let bytes = unsafe { Mmap::map(&file) }.ok()?;
+
+        Self::parse(bytes, kind, m)
+    }
+
+    fn parse(bytes: Mmap, kind: Kind, m: &Material) -> Option<Table> {
+        let magic = match kind {
+            Kind::Wdl => WDL_MAGIC,
+            Kind::Dtz => DTZ_MAGIC,
+        };
+        if bytes.get(..4)? != magic || (byte(&bytes, 4)? & 2 != 0) != m.has_pawns {
+            return None;
+        }
+
+        let sides = if kind == Kind::Wdl && !m.is_symmetric() { 2 } else { 1 };
+        let files = if m.has_pawns { 4 } else { 1 };
+        let both_pawns = m.has_pawns && m.pawn_count[1] > 0;
+        let b = &bytes;
+
+        let mut data = vec![Vec::new(); sides];
+        let mut at = 5;
+        for file in 0..files {
+            let order = [byte(b, at)?, if both_pawns { byte(b, at + 1)? } else { 0xFF }];
+            at += 1 + both_pawns as usize;
+            let pieces = b.get(at..at + m.piece_count)?;
+            at += m.piece_count;
+
+            for (side, side_data) in data.iter_mut().enumerate() {
+                let nibble = |byte: u8| if side == 0 { byte & 0xF } else { byte >> 4 };
+                let pieces = pieces.iter().map(|&p| nibble(p)).collect();
+                side_data.push(PairsData::new(pieces, order.map(nibble), file, m)?);
+            }
+        }
+        at += at & 1;
+
+        for file in 0..files {
+            for side in data.iter_mut() {
+                at = side[file].set_sizes(b, at)?;
+            }
+        }
+
+        let map = at;
+        if kind == Kind::Dtz {
+            for d in data[0].iter_mut().filter(|d| d.flags & MAPPED != 0) {
+                if d.flags & WIDE != 0 {
+                    at += at & 1;
+                    for i in 0..4 {
+                        d.map_idx[i] = (at - map) / 2 + 1;
+                        at += 2 * u16_le(b, at)? as usize + 2;
+                    }
+                } else {
+                    for i in 0..4 {
+                        d.map_idx[i] = at - map + 1;
+                        at += byte(b, at)? as usize + 1;
+                    }
+                }
+            }
+            at += at & 1;
+        }
+
+        for file in 0..files {
+            for side in data.iter_mut() {
+                side[file].sparse_index = at;
+                at += 6 * side[file].sparse_index_size;
+            }
+        }
+        for file in 0..files {
+            for side in data.iter_mut() {
+                side[file].block_length = at;
+                at += 2 * side[file].block_length_size;
+            }
+        }
+        for file in 0..files {
+            for side in data.iter_mut() {
+                at = at.div_ceil(64) * 64;
+                side[file].data = at;
+                at += side[file].num_blocks * side[file].block_size;
+            }
+        }
+
+        (at <= bytes.len()).then_some(Table {
+            kind,
+            bytes,
+            data,
+            map,
+        })
+    }
+
+    fn get(&self, stm: usize, file: usize) -> &PairsData {
+        &self.data[stm.min(self.data.len() - 1)][file]
+    }
+
+    /// Looks up `board`, which has the material of the table with the colors swapped if
+    /// `flip`. `wdl` is the result of the position, needed to read DTZ tables.
+    pub fn probe(&self, board: &Board, m: &Material, flip: bool, wdl: i32) -> Option<Probe> {
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = flip as usize ^ (board.current_player() == Color::Black) as usize;
+
+        let mut squares = Vec::with_capacity(m.piece_count);
+        let mut pieces = Vec::with_capacity(m.piece_count);
+        let mut lead = 0;
+        let mut file = 0;
+
+        // the leading pawns come first, their color being the one of the first piece
+        let lead_pawn = m.has_pawns.then(|| {
+            let black = (self.get(0, 0).pieces[0] ^ flip_color) & 8 != 0;
+            let color = if black { Color::Black } else { Color::White };
+            Piece::new(color, PieceType::Pawn)
+        });
+        if let Some(pawn) = lead_pawn {
+            let maps = maps();
+            for pos in Position::iter().filter(|pos| board.get_piece(pos) == Some(pawn)) {
+                squares.push(pos.idx() ^ flip_squares);
+                pieces.push(tb_piece(&pawn) ^ flip_color);
+            }
+            lead = squares.len();
+            let first = (0..lead).rev().max_by_key(|&i| maps.pawns[squares[i]])?;
+            squares.swap(0, first);
+            file = (squares[0] % 8).min(7 - squares[0] % 8);
+        }
+
+        if self.kind == Kind::Dtz {
+            let flags = self.get(0, file).flags;
+            // symmetric tables without pawns look the same from both sides
+            let one_sided = m.has_pawns || !m.is_symmetric();
+            if one_sided && (flags & STM) as usize != stm {
+                return Some(Probe::ChangeStm);
+            }
+        }
+
+        for pos in Position::iter() {
+            match board.get_piece(&pos) {
+                Some(p) if Some(p) != lead_pawn => {
+                    squares.push(pos.idx() ^ flip_squares);
+                    pieces.push(tb_piece(&p) ^ flip_color);
+                }
+                _ => {}
+            }
+        }
+        if squares.len() != m.piece_count {
+            return None;
+        }
+
+        let d = self.get(stm, file);
+        let idx = encode(d, m, &mut squares, &mut pieces, lead)?;
+        let value = self.decompress(d, idx)? as i32;
+
+        Some(Probe::Value(match self.kind {
+            Kind::Wdl => value - 2,
+            Kind::Dtz => self.map_score(d, value, wdl)?,
+        }))
+    }
+
+    /// The value stored at `idx`.
+    fn decompress(&self, d: &PairsData, idx: u64) -> Option<u16> {
+        if d.flags & SINGLE_VALUE != 0 {
+            return Some(d.min_sym_len as u16);
+        }
+        let b = &self.bytes;
+
+        // the sparse index points to the value in the middle of each span
+        let entry = d.sparse_index + 6 * (idx / d.span) as usize;
+        let mut block = u32_le(b, entry)? as usize;
+        let mut offset = u16_le(b, entry + 4)? as i64 + (idx % d.span) as i64
+            - (d.span / 2) as i64;
+
+        let block_length = |block: usize| {
+            (block < d.block_length_size)
+                .then(|| u16_le(b, d.block_length + 2 * block))
+                .flatten()
+                .map(|len| len as i64)
+        };
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+        let mut offset = offset as usize;
+
+        let mut ptr = d.data + block * d.block_size;
+        let mut buf = u64_be(b, ptr);
+        let mut buf_size = 64;
+        ptr += 8;
+
+        let mut sym = loop {
+            let mut len = 0;
+            while buf < *d.base64.get(len)? {
+                len += 1;
+            }
+            let shift = (64 - len - d.min_sym_len as usize) as u32;
+            let sym = (buf - d.base64[len]).checked_shr(shift).unwrap_or(0) as u16;
+            let sym = sym.wrapping_add(u16_le(b, d.lowest_sym + 2 * len)?) as usize;
+
+            let values = *d.symlen.get(sym)? as usize + 1;
+            if offset < values {
+                break sym;
+            }
+            offset -= values;
+
+            len += d.min_sym_len as usize;
+            buf = buf.checked_shl(len as u32).unwrap_or(0);
+            buf_size -= len as i32;
+            if buf_size <= 32 {
+                buf_size += 32;
+                buf |= (u64_be(b, ptr) >> 32).checked_shl(64 - buf_size as u32).unwrap_or(0);
+                ptr += 4;
+            }
+        };
+
+        // walk down the pairs to the value
+        while d.symlen[sym] != 0 {
+            let (left, right) = pair(b, d.btree, sym)?;
+            let left_values = *d.symlen.get(left)? as usize + 1;
+            let next = if offset < left_values {
+                left
+            } else {
+                offset -= left_values;
+                right
+            };
+            if *d.symlen.get(next)? >= d.symlen[sym] {
+                return None;
+            }
+            sym = next;
+        }
+
+        pair(b, d.btree, sym).map(|(value, _)| value as u16)
+    }
+
+    /// Converts the stored DTZ value to plies, counting the move into the zeroing move.
+    fn map_score(&self, d: &PairsData, value: i32, wdl: i32) -> Option<i32> {
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+
+        let mut value = value;
+        if d.flags & MAPPED != 0 {
+            let idx = d.map_idx[WDL_MAP[(wdl + 2) as usize]] + value as usize;
+            value = if d.flags & WIDE != 0 {
+                u16_le(&self.bytes, self.map + 2 * idx)? as i32
+            } else {
+                byte(&self.bytes, self.map + idx)? as i32
+            };
+        }
+
+        let in_moves = match wdl {
+            2 => d.flags & WIN_PLIES == 0,
+            -2 => d.flags & LOSS_PLIES == 0,
+            _ => true,
+        };
+        Some(if in_moves { 2 * value } else { value } + 1)
+    }
+}
+
+/// The index of a position in the table of `d`, given the squares and pieces with the stronger
+/// side as white and the `lead` leading pawns first.
+pub fn encode(
+    d: &PairsData,
+    m: &Material,
+    squares: &mut [usize],
+    pieces: &mut [u8],
+    lead: usize,
+) -> Option<u64> {
+    let maps = maps();
+    let size = squares.len();
+
+    // order the pieces as the table does
+    for i in lead..size.saturating_sub(1) {
+        if let Some(j) = (i + 1..size).find(|&j| d.pieces.get(i) == Some(&pieces[j])) {
+            pieces.swap(i, j);
+            squares.swap(i, j);
+        }
+    }
+
+    // mirror the leading piece to the queen side, and without pawns below the fifth rank and
+    // the a1-h8 diagonal
+    if squares[0] % 8 > 3 {
+        squares.iter_mut().for_each(|sq| *sq ^= 7);
+    }
+
+    let mut idx = if m.has_pawns {
+        let mut idx = maps.lead_pawn_idx[lead][squares[0]];
+        squares[1..lead].sort_by_key(|&sq| maps.pawns[sq]);
+        for (i, &sq) in squares.iter().enumerate().take(lead).skip(1) {
+            idx += maps.binomial[i][maps.pawns[sq] as usize];
+        }
+        idx
+    } else {
+        if squares[0] / 8 > 3 {
+            squares.iter_mut().for_each(|sq| *sq ^= 56);
+        }
+        for i in 0..d.group_len[0] {
+            if off_diagonal(squares[i]) == 0 {
+                continue;
+            }
+            if off_diagonal(squares[i]) > 0 {
+                for sq in squares[i..].iter_mut() {
+                    *sq = ((*sq >> 3) | (*sq << 3)) & 63;
+                }
+            }
+            break;
+        }
+
+        if m.has_unique_pieces {
+            encode_unique(squares)
+        } else {
+            maps.kk[maps.a1d1d4[squares[0]] as usize][squares[1]]
+        }
+    };
+    idx *= d.group_idx[0];
+
+    // the other groups, each with its squares in ascending order, leaving out the squares of
+    // the groups before
+    let mut start = d.group_len[0];
+    let mut remaining_pawns = m.has_pawns && m.pawn_count[1] > 0;
+    for next in 1..=MAX_PIECES {
+        let len = d.group_len[next];
+        if len == 0 {
+            break;
+        }
+        let group = squares.get_mut(start..start + len)?;
+        group.sort_unstable();
+
+        let mut n = 0;
+        for i in 0..len {
+            let sq = squares[start + i];
+            let before = squares[..start].iter().filter(|&&s| sq > s).count();
+            let free = sq.checked_sub(before + 8 * remaining_pawns as usize)?;
+            n += maps.binomial.get(i + 1)?[free];
+        }
+        remaining_pawns = false;
+        idx += n * d.group_idx[next];
+        start += len;
+    }
+
+    Some(idx)
+}
+
+/// The index of the leading group of three unique pieces, the first one in the a1-d1-d4
+/// triangle.
+fn encode_unique(squares: &[usize]) -> u64 {
+    let maps = maps();
+    let [s0, s1, s2] = [squares[0], squares[1], squares[2]];
+    let adjust1 = (s1 > s0) as u64;
+    let adjust2 = (s2 > s0) as u64 + (s2 > s1) as u64;
+    let (s0, s1, s2) = (s0 as u64, s1 as u64, s2 as u64);
+
+    if off_diagonal(squares[0]) != 0 {
+        (maps.a1d1d4[squares[0]] * 63 + s1 - adjust1) * 62 + s2 - adjust2
+    } else if off_diagonal(squares[1]) != 0 {
+        (6 * 63 + (s0 / 8) * 28 + maps.b1h1h7[squares[1]]) * 62 + s2 - adjust2
+    } else if off_diagonal(squares[2]) != 0 {
+        6 * 63 * 62 + 4 * 28 * 62 + (s0 / 8) * 7 * 28 + (s1 / 8 - adjust1) * 28
+            + maps.b1h1h7[squares[2]]
+    } else {
+        6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + (s0 / 8) * 7 * 6 + (s1 / 8 - adjust1) * 6
+            + (s2 / 8 - adjust2)
+    }
+}
+
+/// The piece as written in the tables: the type from 1 (pawn) to 6 (king), plus 8 for black.
+pub fn tb_piece(p: &Piece) -> u8 {
+    let t = match p.piece_type {
+        PieceType::Pawn => 1,
+        PieceType::Knight => 2,
+        PieceType::Bishop => 3,
+        PieceType::Rook => 4,
+        PieceType::Queen => 5,
+        PieceType::King => 6,
+    };
+
+    if p.color == Color::Black {
+        t + 8
+    } else {
+        t
+    }
+}
+
+fn byte(b: &[u8], at: usize) -> Option<u8> {
+    b.get(at).copied()
+}
+
+fn u16_le(b: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(b.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_le(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// The compressed data, read big endian. The decoder reads ahead of the last code, past the
+/// end of the file reads zeros.
+fn u64_be(b: &[u8], at: usize) -> u64 {
+    (0..8).fold(0, |n, i| n << 8 | b.get(at + i).copied().unwrap_or(0) as u64)
+}
+
+/// The two symbols a symbol of the tree pairs, or its value and 0xFFF for a leaf.
+fn pair(b: &[u8], btree: usize, sym: usize) -> Option<(usize, usize)> {
+    let lr = b.get(btree + 3 * sym..btree + 3 * sym + 3)?;
+    let left = ((lr[1] as usize & 0xF) << 8) | lr[0] as usize;
+    let right = ((lr[2] as usize) << 4) | (lr[1] as usize >> 4);
+
+    Some((left, right))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syzygy::table::{encode, maps, Material, PairsData};
+    use std::collections::HashSet;
+
+    #[test]
+    fn lookup_tables() {
+        let maps = maps();
+
+        let kings: HashSet<u64> = (0..10)
+            .flat_map(|idx| (0..64).map(move |sq| maps.kk[idx][sq]))
+            .collect();
+        assert_eq!(462, kings.len());
+        assert_eq!(Some(&461), kings.iter().max());
+
+        let pawns: HashSet<u64> = (8..56).map(|sq| maps.pawns[sq]).collect();
+        assert_eq!((0..48).collect::<HashSet<u64>>(), pawns);
+        // the leading pawn is the one nearest to the edge, then the one on the lowest rank
+        assert!(maps.pawns[8] > maps.pawns[15] && maps.pawns[15] > maps.pawns[16]);
+
+        assert_eq!([6; 4], maps.lead_pawns_size[1]);
+        assert_eq!(10, maps.binomial[2][5]);
+        assert_eq!(1, maps.binomial[0][63]);
+    }
+
+    /// The square seen in the mirror `symmetry`: flipping the files, the ranks or the diagonal.
+    fn mirror(sq: usize, symmetry: usize) -> usize {
+        let sq = if symmetry & 1 != 0 { sq ^ 7 } else { sq };
+        let sq = if symmetry & 2 != 0 { sq ^ 56 } else { sq };
+        if symmetry & 4 != 0 {
+            ((sq >> 3) | (sq << 3)) & 63
+        } else {
+            sq
+        }
+    }
+
+    #[test]
+    fn pawnless_index() {
+        let m = Material::from_name("KRvK").unwrap();
+        let pieces = vec![6, 4, 14];
+        let d = PairsData::new(pieces.clone(), [0, 0xF], 0, &m).unwrap();
+        assert_eq!(31332, d.table_size());
+
+        // mirrored positions share their index, all others differ
+        let mut classes = HashSet::new();
+        let mut indexes = HashSet::new();
+        for k in 0..64 {
+            for r in (0..64).filter(|&r| r != k) {
+                for bk in (0..64).filter(|&bk| bk != k && bk != r) {
+                    let idx = |sym| {
+                        let mut squares = [k, r, bk].map(|sq| mirror(sq, sym));
+                        encode(&d, &m, &mut squares, &mut pieces.clone(), 0).unwrap()
+                    };
+                    let i = idx(0);
+                    assert!(i < d.table_size());
+                    assert!((1..8).all(|sym| idx(sym) == i));
+
+                    indexes.insert(i);
+                    classes.insert((0..8).map(|sym| [k, r, bk].map(|sq| mirror(sq, sym))).min());
+                }
+            }
+        }
+        assert_eq!(classes.len(), indexes.len());
+    }
+
+    #[test]
+    fn pawn_index() {
+        let m = Material::from_name("KPvK").unwrap();
+        let pieces = vec![1, 6, 14];
+        let tables: Vec<PairsData> = (0..4)
+            .map(|file| PairsData::new(pieces.clone(), [0, 0xF], file, &m).unwrap())
+            .collect();
+        assert_eq!(6 * 63 * 62, tables[0].table_size());
+
+        let mut indexes = HashSet::new();
+        let mut classes = HashSet::new();
+        for p in 8..56 {
+            for k in (0..64).filter(|&k| k != p) {
+                for bk in (0..64).filter(|&bk| bk != k && bk != p) {
+                    let file = (p % 8).min(7 - p % 8);
+                    let idx = |sym| {
+                        let mut squares = [p, k, bk].map(|sq| mirror(sq, sym));
+                        encode(&tables[file], &m, &mut squares, &mut pieces.clone(), 1).unwrap()
+                    };
+                    let i = idx(0);
+                    assert!(i < tables[file].table_size());
+                    assert_eq!(i, idx(1));
+
+                    indexes.insert((file, i));
+                    classes.insert([p, k, bk].min([p, k, bk].map(|sq| mirror(sq, 1))));
+                }
+            }
+        }
+        assert_eq!(classes.len(), indexes.len());
+    }
+}
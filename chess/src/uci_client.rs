@@ -0,0 +1,812 @@
+use crate::board::Board;
+use crate::chess_move::Move;
+use crate::piece::Color;
+use crate::search::tt::Bound;
+use crate::search::Score;
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long an engine may take to answer by default.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a running search checks whether it was asked to stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum UciErrorKind {
+    /// Starting the engine or writing to it failed.
+    Io,
+    /// The engine didn't answer in time.
+    Timeout,
+    /// The engine exited or closed its output.
+    Exited,
+    /// A move that isn't legal in the position, sent by the engine or given to it.
+    IllegalMove,
+    /// A command the engine doesn't support, like an unknown option.
+    Unsupported,
+}
+
+#[derive(Clone, Debug)]
+pub struct UciError {
+    kind: UciErrorKind,
+    description: String,
+}
+
+impl UciError {
+    pub fn kind(&self) -> UciErrorKind {
+        self.kind
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+impl Display for UciError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for UciError {}
+
+impl From<std::io::Error> for UciError {
+    fn from(e: std::io::Error) -> Self {
+        UciError {
+            kind: UciErrorKind::Io,
+            description: e.to_string(),
+        }
+    }
+}
+
+macro_rules! uci_error {
+    ($kind:ident, $($fmt:expr),*) => {
+        Err(UciError{kind: UciErrorKind::$kind, description:format!($($fmt),*)})
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum UciOptionType {
+    Check,
+    Spin,
+    Combo,
+    Button,
+    String,
+}
+
+/// An option the engine announced during the handshake.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UciOption {
+    pub name: String,
+    pub option_type: UciOptionType,
+    pub default: Option<String>,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    /// The choices of a combo option.
+    pub vars: Vec<String>,
+}
+
+impl UciOption {
+    /// Reads a line like `option name Hash type spin default 16 min 1 max 1024`.
+    pub fn parse(line: &str) -> Option<UciOption> {
+        let mut words = line.split_whitespace();
+        if words.next()? != "option" {
+            return None;
+        }
+
+        // names and values may contain spaces, so a value is everything up to the next keyword
+        let mut fields: Vec<(&str, Vec<&str>)> = Vec::new();
+        for word in words {
+            match word {
+                "name" | "type" | "default" | "min" | "max" | "var" => {
+                    fields.push((word, Vec::new()))
+                }
+                _ => fields.last_mut()?.1.push(word),
+            }
+        }
+
+        let mut name = None;
+        let mut option_type = None;
+        let mut option = UciOption {
+            name: String::new(),
+            option_type: UciOptionType::Button,
+            default: None,
+            min: None,
+            max: None,
+            vars: Vec::new(),
+        };
+
+        for (key, value) in fields {
+            let value = value.join(" ");
+            match key {
+                "name" => name = Some(value),
+                "type" => {
+                    option_type = Some(match value.as_str() {
+                        "check" => UciOptionType::Check,
+                        "spin" => UciOptionType::Spin,
+                        "combo" => UciOptionType::Combo,
+                        "button" => UciOptionType::Button,
+                        "string" => UciOptionType::String,
+                        _ => return None,
+                    })
+                }
+                "default" => option.default = Some(value),
+                "min" => option.min = value.parse().ok(),
+                "max" => option.max = value.parse().ok(),
+                _ => option.vars.push(value),
+            }
+        }
+
+        option.name = name.filter(|n| !n.is_empty())?;
+        option.option_type = option_type?;
+        Some(option)
+    }
+}
+
+/// What an engine reports about its search in an `info` line. Fields it didn't send are empty.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UciInfo {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<Score>,
+    /// `Lower` or `Upper` if the score is only a bound, `None` if it is exact.
+    pub bound: Option<Bound>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time: Option<Duration>,
+    /// The principal variation, up to the first move that isn't legal.
+    pub pv: Vec<Move>,
+    pub string: Option<String>,
+}
+
+impl UciInfo {
+    /// Reads an `info` line sent while searching `board`.
+    pub fn parse(line: &str, board: &Board) -> Option<UciInfo> {
+        let mut words = line.split_whitespace().peekable();
+        if words.next()? != "info" {
+            return None;
+        }
+
+        let mut info = UciInfo::default();
+        while let Some(word) = words.next() {
+            match word {
+                "depth" => info.depth = next_number(&mut words).map(|n| n as u32),
+                "seldepth" => info.seldepth = next_number(&mut words).map(|n| n as u32),
+                "multipv" => info.multipv = next_number(&mut words).map(|n| n as u32),
+                "nodes" => info.nodes = next_number(&mut words),
+                "nps" => info.nps = next_number(&mut words),
+                "time" => info.time = next_number(&mut words).map(Duration::from_millis),
+                "score" => loop {
+                    match words.peek().copied() {
+                        Some(unit @ ("cp" | "mate")) => {
+                            words.next();
+                            let n = words.next().and_then(|n| n.parse().ok());
+                            info.score = n.map(|n| {
+                                if unit == "cp" {
+                                    Score::Cp(n)
+                                } else {
+                                    Score::Mate(n)
+                                }
+                            });
+                        }
+                        Some("lowerbound") => {
+                            words.next();
+                            info.bound = Some(Bound::Lower);
+                        }
+                        Some("upperbound") => {
+                            words.next();
+                            info.bound = Some(Bound::Upper);
+                        }
+                        _ => break,
+                    }
+                },
+                "pv" => {
+                    let mut b = board.clone();
+                    while let Some(m) = words.peek().and_then(|w| b.parse_uci_move(w)) {
+                        b.make_move(m);
+                        info.pv.push(m);
+                        words.next();
+                    }
+                }
+                "string" => {
+                    info.string = Some(words.by_ref().collect::<Vec<_>>().join(" "));
+                }
+                // anything else, like `currmove e2e4` or `hashfull 300`, is skipped word by word
+                _ => {}
+            }
+        }
+
+        Some(info)
+    }
+}
+
+fn next_number<'a>(words: &mut impl Iterator<Item = &'a str>) -> Option<u64> {
+    words.next().and_then(|w| w.parse().ok())
+}
+
+/// The move an engine decided on, and the reply it expects.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct BestMove {
+    /// `None` if the engine had no legal move.
+    pub best_move: Option<Move>,
+    pub ponder: Option<Move>,
+}
+
+impl BestMove {
+    /// Reads a line like `bestmove e2e4 ponder e7e5` sent after searching `board`.
+    pub fn parse(line: &str, board: &Board) -> Result<BestMove, UciError> {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("bestmove") {
+            return uci_error!(Unsupported, "expected 'bestmove', got '{}'", line);
+        }
+
+        let mut b = board.clone();
+        let best_move = match words.next() {
+            None | Some("(none)") | Some("0000") => return Ok(BestMove::default()),
+            Some(s) => match b.parse_uci_move(s) {
+                Some(m) => m,
+                None => {
+                    return uci_error!(IllegalMove, "the engine played the illegal move '{}'", s)
+                }
+            },
+        };
+
+        b.make_move(best_move);
+        let ponder = match (words.next(), words.next()) {
+            (Some("ponder"), Some(s)) => b.parse_uci_move(s),
+            _ => None,
+        };
+
+        Ok(BestMove {
+            best_move: Some(best_move),
+            ponder,
+        })
+    }
+}
+
+/// The limits of a `go` command.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GoParams {
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    /// Search until stopped, ignoring the other limits.
+    pub infinite: bool,
+    /// Think on the opponent's time, the clock only starts with `ponderhit`.
+    pub ponder: bool,
+    /// Only search these moves, all if empty.
+    pub searchmoves: Vec<Move>,
+}
+
+impl GoParams {
+    /// Reads a `go` command sent for `board`, skipping what it doesn't know.
+    pub fn parse(line: &str, board: &Board) -> Option<GoParams> {
+        let mut words = line.split_whitespace().peekable();
+        if words.next()? != "go" {
+            return None;
+        }
+
+        let mut params = GoParams::default();
+        while let Some(word) = words.next() {
+            let millis = |n: Option<u64>| n.map(Duration::from_millis);
+            match word {
+                "wtime" => params.wtime = millis(next_number(&mut words)),
+                "btime" => params.btime = millis(next_number(&mut words)),
+                "winc" => params.winc = millis(next_number(&mut words)),
+                "binc" => params.binc = millis(next_number(&mut words)),
+                "movetime" => params.movetime = millis(next_number(&mut words)),
+                "movestogo" => params.movestogo = next_number(&mut words).map(|n| n as u32),
+                "depth" => params.depth = next_number(&mut words).map(|n| n as u32),
+                "nodes" => params.nodes = next_number(&mut words),
+                "infinite" => params.infinite = true,
+                "ponder" => params.ponder = true,
+                "searchmoves" => {
+                    let mut b = board.clone();
+                    while let Some(m) = words.peek().and_then(|w| b.parse_uci_move(w)) {
+                        params.searchmoves.push(m);
+                        words.next();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Some(params)
+    }
+
+    /// The `go` command with these limits.
+    pub fn command(&self) -> String {
+        let mut cmd = String::from("go");
+
+        let times = [
+            ("wtime", self.wtime),
+            ("btime", self.btime),
+            ("winc", self.winc),
+            ("binc", self.binc),
+            ("movetime", self.movetime),
+        ];
+        for (name, time) in times {
+            if let Some(t) = time {
+                cmd.push_str(&format!(" {} {}", name, t.as_millis()));
+            }
+        }
+        if let Some(n) = self.movestogo {
+            cmd.push_str(&format!(" movestogo {}", n));
+        }
+        if let Some(d) = self.depth {
+            cmd.push_str(&format!(" depth {}", d));
+        }
+        if let Some(n) = self.nodes {
+            cmd.push_str(&format!(" nodes {}", n));
+        }
+        if self.infinite {
+            cmd.push_str(" infinite");
+        }
+        if self.ponder {
+            cmd.push_str(" ponder");
+        }
+        if !self.searchmoves.is_empty() {
+            cmd.push_str(" searchmoves");
+            for m in &self.searchmoves {
+                cmd.push_str(&format!(" {}", m));
+            }
+        }
+
+        cmd
+    }
+
+    /// The most time the search may take with `color` to move, if time limits it at all.
+    fn time_limit(&self, color: Color) -> Option<Duration> {
+        if self.infinite || self.ponder {
+            return None;
+        }
+
+        let (time, inc) = match color {
+            Color::White => (self.wtime, self.winc),
+            Color::Black => (self.btime, self.binc),
+        };
+        let clock = time.map(|t| t + inc.unwrap_or_default());
+
+        match (self.movetime, clock) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// An engine process spoken to with the Universal Chess Interface.
+///
+/// The engine is told to quit, and killed if it doesn't, when this is dropped.
+pub struct UciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    name: Option<String>,
+    author: Option<String>,
+    options: Vec<UciOption>,
+    board: Board,
+    stop: Arc<AtomicBool>,
+    /// How long to wait for answers like `uciok` and `readyok`, and for the best move beyond the
+    /// time the search was given.
+    pub timeout: Duration,
+}
+
+impl UciEngine {
+    /// Starts the engine and performs the handshake.
+    pub fn start(program: impl AsRef<OsStr>, args: &[&str]) -> Result<UciEngine, UciError> {
+        Self::start_with_timeout(program, args, DEFAULT_TIMEOUT)
+    }
+
+    pub fn start_with_timeout(
+        program: impl AsRef<OsStr>,
+        args: &[&str],
+        timeout: Duration,
+    ) -> Result<UciEngine, UciError> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        // a thread does the blocking reads, so that waiting for a line can time out
+        let (tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = UciEngine {
+            child,
+            stdin,
+            lines,
+            name: None,
+            author: None,
+            options: Vec::new(),
+            board: Board::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+            timeout,
+        };
+
+        engine.send("uci")?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            let line = engine.read_line(Some(deadline), "uciok")?;
+            let line = line.trim();
+
+            if line == "uciok" {
+                break;
+            } else if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = Some(name.trim().to_string());
+            } else if let Some(author) = line.strip_prefix("id author ") {
+                engine.author = Some(author.trim().to_string());
+            } else if let Some(option) = UciOption::parse(line) {
+                engine.options.push(option);
+            }
+        }
+
+        Ok(engine)
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn options(&self) -> &[UciOption] {
+        &self.options
+    }
+
+    /// The option with the given name, which UCI compares ignoring case.
+    pub fn option(&self, name: &str) -> Option<&UciOption> {
+        self.options
+            .iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Sets an option the engine announced. Buttons ignore the value.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), UciError> {
+        let option = match self.option(name) {
+            Some(option) => option,
+            None => return uci_error!(Unsupported, "the engine has no option '{}'", name),
+        };
+
+        let cmd = if option.option_type == UciOptionType::Button {
+            format!("setoption name {}", option.name)
+        } else {
+            format!("setoption name {} value {}", option.name, value)
+        };
+        self.send(&cmd)
+    }
+
+    /// Waits until the engine has processed all commands sent so far.
+    pub fn is_ready(&mut self) -> Result<(), UciError> {
+        self.send("isready")?;
+
+        let deadline = Instant::now() + self.timeout;
+        while self.read_line(Some(deadline), "readyok")?.trim() != "readyok" {}
+
+        Ok(())
+    }
+
+    /// Tells the engine that the next position is from a different game.
+    pub fn new_game(&mut self) -> Result<(), UciError> {
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    /// Sets up the position after playing `moves` from `start`, so that the engine knows the
+    /// earlier positions too.
+    pub fn position(&mut self, start: &Board, moves: &[Move]) -> Result<(), UciError> {
+        let mut board = start.clone();
+        let fen = board.to_fen();
+        let mut cmd = if fen == Board::new().to_fen() {
+            String::from("position startpos")
+        } else {
+            format!("position fen {}", fen)
+        };
+
+        if !moves.is_empty() {
+            cmd.push_str(" moves");
+        }
+        for (idx, &m) in moves.iter().enumerate() {
+            if !board.legal_moves().contains(&m) {
+                return uci_error!(IllegalMove, "illegal move '{}' at index {}", m, idx);
+            }
+            board.make_move(m);
+            cmd.push_str(&format!(" {}", m));
+        }
+
+        self.send(&cmd)?;
+        self.board = board;
+        Ok(())
+    }
+
+    /// The position last set up with [`UciEngine::position`].
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// A flag that makes a running [`UciEngine::go`] send `stop` when set.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Searches the current position, reporting the `info` lines, until the engine sends its
+    /// best move.
+    ///
+    /// The engine gets the time of the search plus the timeout to answer. Searches limited only
+    /// by depth or nodes, and infinite ones, wait until they finish or are stopped.
+    pub fn go(
+        &mut self,
+        params: &GoParams,
+        mut on_info: impl FnMut(&UciInfo),
+    ) -> Result<BestMove, UciError> {
+        self.stop.store(false, Ordering::Relaxed);
+        self.send(&params.command())?;
+
+        let mut deadline = params
+            .time_limit(self.board.current_player())
+            .map(|t| Instant::now() + t + self.timeout);
+        let mut stopped = false;
+
+        loop {
+            if !stopped && self.stop.load(Ordering::Relaxed) {
+                self.send("stop")?;
+                stopped = true;
+                deadline = Some(Instant::now() + self.timeout);
+            }
+
+            let wait = match deadline {
+                Some(d) => d
+                    .saturating_duration_since(Instant::now())
+                    .min(POLL_INTERVAL),
+                None => POLL_INTERVAL,
+            };
+            let line = match self.lines.recv_timeout(wait) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    if deadline.is_some_and(|d| Instant::now() >= d) {
+                        return uci_error!(Timeout, "the engine didn't send 'bestmove' in time");
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return uci_error!(Exited, "the engine exited while searching")
+                }
+            };
+
+            if line.starts_with("bestmove") {
+                return BestMove::parse(&line, &self.board);
+            }
+            if let Some(info) = UciInfo::parse(&line, &self.board) {
+                on_info(&info);
+            }
+        }
+    }
+
+    fn send(&mut self, cmd: &str) -> Result<(), UciError> {
+        writeln!(self.stdin, "{}", cmd)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    /// The next line from the engine, which was waiting for `expected`.
+    fn read_line(&mut self, deadline: Option<Instant>, expected: &str) -> Result<String, UciError> {
+        let result = match deadline {
+            Some(d) => self
+                .lines
+                .recv_timeout(d.saturating_duration_since(Instant::now())),
+            None => self
+                .lines
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match result {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => {
+                uci_error!(Timeout, "the engine didn't send '{}' in time", expected)
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                uci_error!(Exited, "the engine exited before sending '{}'", expected)
+            }
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+
+        let deadline = Instant::now() + Duration::from_millis(200);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::piece::Color;
+    use crate::search::tt::Bound;
+    use crate::search::Score;
+    use crate::uci_client::{BestMove, GoParams, UciErrorKind, UciInfo, UciOption, UciOptionType};
+    use std::time::Duration;
+
+    #[test]
+    fn options() {
+        let o = UciOption::parse("option name Hash type spin default 16 min 1 max 1024").unwrap();
+        assert_eq!("Hash", o.name);
+        assert_eq!(UciOptionType::Spin, o.option_type);
+        assert_eq!(Some("16".to_string()), o.default);
+        assert_eq!((Some(1), Some(1024)), (o.min, o.max));
+
+        let o = UciOption::parse(
+            "option name Play Style type combo default Normal var Solid var Normal var Risky",
+        )
+        .unwrap();
+        assert_eq!("Play Style", o.name);
+        assert_eq!(vec!["Solid", "Normal", "Risky"], o.vars);
+
+        let o = UciOption::parse("option name Clear Hash type button").unwrap();
+        assert_eq!(UciOptionType::Button, o.option_type);
+        assert_eq!(None, o.default);
+
+        assert_eq!(None, UciOption::parse("option name Hash"));
+        assert_eq!(None, UciOption::parse("option type check default true"));
+        assert_eq!(None, UciOption::parse("id name Engine"));
+    }
+
+    #[test]
+    fn info() {
+        let b = Board::new();
+        let info = UciInfo::parse(
+            "info depth 12 seldepth 18 multipv 2 score cp -35 upperbound nodes 123456 nps 987654 \
+             hashfull 40 time 125 pv e2e4 e7e5 g1f3",
+            &b,
+        )
+        .unwrap();
+
+        assert_eq!(Some(12), info.depth);
+        assert_eq!(Some(18), info.seldepth);
+        assert_eq!(Some(2), info.multipv);
+        assert_eq!(Some(Score::Cp(-35)), info.score);
+        assert_eq!(Some(Bound::Upper), info.bound);
+        assert_eq!(Some(123456), info.nodes);
+        assert_eq!(Some(987654), info.nps);
+        assert_eq!(Some(Duration::from_millis(125)), info.time);
+        assert_eq!(
+            vec!["e2e4", "e7e5", "g1f3"],
+            info.pv.iter().map(|m| m.to_string()).collect::<Vec<_>>()
+        );
+
+        let info = UciInfo::parse(
+            "info score mate -3 lowerbound currmove e2e4 string hi there",
+            &b,
+        )
+        .unwrap();
+        assert_eq!(Some(Score::Mate(-3)), info.score);
+        assert_eq!(Some(Bound::Lower), info.bound);
+        assert_eq!(Some("hi there".to_string()), info.string);
+
+        // the variation ends at the first move that doesn't fit
+        let info = UciInfo::parse("info depth 3 pv e2e4 e2e4 g1f3 nodes 5", &b).unwrap();
+        assert_eq!(1, info.pv.len());
+
+        assert_eq!(None, UciInfo::parse("bestmove e2e4", &b));
+        assert_eq!(Some(UciInfo::default()), UciInfo::parse("info", &b));
+    }
+
+    #[test]
+    fn best_move() {
+        let b = Board::new();
+
+        let best = BestMove::parse("bestmove g1f3 ponder d7d5", &b).unwrap();
+        assert_eq!("g1f3", best.best_move.unwrap().to_string());
+        assert_eq!("d7d5", best.ponder.unwrap().to_string());
+
+        let best = BestMove::parse("bestmove e2e4", &b).unwrap();
+        assert_eq!(None, best.ponder);
+
+        assert_eq!(
+            BestMove::default(),
+            BestMove::parse("bestmove (none)", &b).unwrap()
+        );
+        assert_eq!(
+            UciErrorKind::IllegalMove,
+            BestMove::parse("bestmove e2e5", &b).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn go_command() {
+        assert_eq!("go", GoParams::default().command());
+
+        let params = GoParams {
+            wtime: Some(Duration::from_secs(60)),
+            btime: Some(Duration::from_millis(59500)),
+            winc: Some(Duration::from_secs(1)),
+            binc: Some(Duration::from_secs(1)),
+            movestogo: Some(20),
+            ..GoParams::default()
+        };
+        assert_eq!(
+            "go wtime 60000 btime 59500 winc 1000 binc 1000 movestogo 20",
+            params.command()
+        );
+        assert_eq!(
+            Some(params.clone()),
+            GoParams::parse(&params.command(), &Board::new())
+        );
+        assert_eq!(
+            Some(Duration::from_millis(60500)),
+            params.time_limit(Color::Black)
+        );
+
+        let params = GoParams {
+            depth: Some(8),
+            nodes: Some(10000),
+            movetime: Some(Duration::from_millis(250)),
+            ..GoParams::default()
+        };
+        assert_eq!("go movetime 250 depth 8 nodes 10000", params.command());
+        assert_eq!(
+            Some(params.clone()),
+            GoParams::parse("go depth 8 mate 3 movetime 250 nodes 10000", &Board::new())
+        );
+        assert_eq!(None, GoParams::parse("stop", &Board::new()));
+        assert_eq!(
+            Some(Duration::from_millis(250)),
+            params.time_limit(Color::White)
+        );
+
+        let mut b = Board::new();
+        let params = GoParams {
+            infinite: true,
+            searchmoves: vec![b.parse_san("e4").unwrap(), b.parse_san("Nf3").unwrap()],
+            ..GoParams::default()
+        };
+        assert_eq!("go infinite searchmoves e2e4 g1f3", params.command());
+        assert_eq!(Some(params.clone()), GoParams::parse("go infinite searchmoves e2e4 g1f3", &b));
+        assert_eq!(None, params.time_limit(Color::White));
+
+        let params = GoParams {
+            wtime: Some(Duration::from_secs(5)),
+            ponder: true,
+            ..GoParams::default()
+        };
+        assert_eq!("go wtime 5000 ponder", params.command());
+        assert_eq!(Some(params.clone()), GoParams::parse("go ponder wtime 5000", &b));
+        assert_eq!(None, params.time_limit(Color::White));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::board::outcome::{Outcome, WinReason};
+use crate::board::Board;
+use crate::chess_move::MoveList;
+use crate::piece::{Color, Piece, PieceType};
+use crate::variant::Variant;
+
+/// Losing chess: captures are compulsory, the king is an ordinary piece and whoever loses all
+/// pieces or has no moves left wins.
+#[derive(Copy, Clone, Debug)]
+pub struct Antichess;
+
+impl Variant for Antichess {
+    fn name(&self) -> &'static str {
+        "Antichess"
+    }
+
+    fn starting_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1"
+    }
+
+    fn promotion_types(&self) -> &'static [PieceType] {
+        &[
+            PieceType::Queen,
+            PieceType::Rook,
+            PieceType::Bishop,
+            PieceType::Knight,
+            PieceType::King,
+        ]
+    }
+
+    fn in_check(&self, _board: &Board, _color: Color) -> bool {
+        false
+    }
+
+    fn filter_legal_moves(&self, moves: &mut MoveList) {
+        if moves.iter().any(|m| m.is_capture()) {
+            moves.retain(|m| m.is_capture());
+        }
+    }
+
+    fn restricts_moves(&self) -> bool {
+        true
+    }
+
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        let winner = Color::iter().find(|c| {
+            PieceType::iter().all(|t| board.pieces(&Piece::new(*c, t)).next().is_none())
+        })?;
+
+        Some(Outcome::Win {
+            winner,
+            reason: WinReason::AllPiecesLost,
+        })
+    }
+
+    fn outcome_without_moves(&self, board: &Board) -> Outcome {
+        Outcome::Win {
+            winner: board.current_player(),
+            reason: WinReason::Stalemated,
+        }
+    }
+
+    /// Isn't detected, the sides may still be forced to take each other's pieces.
+    fn is_insufficient_material(&self, _board: &Board) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::outcome::{Outcome, WinReason};
+    use crate::board::Board;
+    use crate::piece::Color;
+    use crate::variant::Antichess;
+
+    fn perft(fen: &str, depth: u32) -> u64 {
+        Board::from_fen_variant(fen, &Antichess)
+            .unwrap()
+            .perft(depth)
+    }
+
+    #[test]
+    fn perft_start_position() {
+        let mut b = Board::new_variant(&Antichess);
+
+        assert_eq!(20, b.perft(1));
+        assert_eq!(400, b.perft(2));
+        assert_eq!(8_067, b.perft(3));
+        assert_eq!(153_299, b.perft(4));
+    }
+
+    #[test]
+    fn perft_pawn_endings() {
+        let fen = "8/1p6/8/8/8/8/P7/8 w - - 0 1";
+        let nodes: Vec<u64> = (1..=6).map(|d| perft(fen, d)).collect();
+        assert_eq!(vec![2, 4, 4, 3, 1, 0], nodes);
+
+        let fen = "8/2p5/8/8/8/8/P7/8 w - - 0 1";
+        assert_eq!(4, perft(fen, 8));
+        assert_eq!(12, perft(fen, 9));
+        assert_eq!(36, perft(fen, 10));
+        assert_eq!(312, perft(fen, 11));
+    }
+
+    #[test]
+    fn forced_captures() {
+        let mut b: Board =
+            Board::from_fen_variant("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", &Antichess).unwrap();
+        let moves: Vec<String> = b.legal_moves().iter().map(|m| m.to_string()).collect();
+        assert_eq!(vec!["e4d5"], moves);
+
+        // kings may be captured and promoted to
+        let mut b = Board::from_fen_variant("8/4P3/8/8/8/8/8/k7 w - - 0 1", &Antichess).unwrap();
+        assert_eq!(5, b.legal_moves().len());
+        assert!(b.parse_uci_move("e7e8k").is_some());
+    }
+
+    #[test]
+    fn outcome() {
+        let win = |winner, reason| Some(Outcome::Win { winner, reason });
+
+        let mut b = Board::from_fen_variant("8/8/8/8/8/8/8/k7 w - - 0 1", &Antichess).unwrap();
+        assert_eq!(win(Color::White, WinReason::AllPiecesLost), b.outcome());
+
+        // the white pawn is blocked
+        let mut b = Board::from_fen_variant("8/8/8/8/8/p7/P7/8 w - - 0 1", &Antichess).unwrap();
+        assert_eq!(win(Color::White, WinReason::Stalemated), b.outcome());
+    }
+}
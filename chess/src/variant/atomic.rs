@@ -0,0 +1,139 @@
+use crate::board::outcome::{Outcome, WinReason};
+use crate::board::Board;
+use crate::piece::Color;
+use crate::variant::{only_kings, Variant};
+
+/// Captures explode, blowing up the capturer and every piece but pawns around the capture
+/// square. Blowing up the enemy king wins, kings can't capture and touching kings can't check
+/// each other.
+#[derive(Copy, Clone, Debug)]
+pub struct Atomic;
+
+impl Variant for Atomic {
+    fn name(&self) -> &'static str {
+        "Atomic"
+    }
+
+    fn in_check(&self, board: &Board, color: Color) -> bool {
+        match (
+            board.king_position(color),
+            board.king_position(color.opponent()),
+        ) {
+            (Some(king), Some(other)) => {
+                let touching =
+                    king.rank.abs_diff(other.rank) <= 1 && king.file.abs_diff(other.file) <= 1;
+                !touching && board.is_square_attacked(&king, color.opponent())
+            }
+            _ => false,
+        }
+    }
+
+    /// The own king has to survive the move, and may be left in check if the enemy king didn't.
+    fn is_legal_after(&self, board: &Board, us: Color) -> bool {
+        board.king_position(us).is_some()
+            && (board.king_position(us.opponent()).is_none() || !self.in_check(board, us))
+    }
+
+    /// Exploding a piece next to the checking king answers a check as well.
+    fn has_standard_evasions(&self) -> bool {
+        false
+    }
+
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        let loser = Color::iter().find(|c| board.king_position(*c).is_none())?;
+
+        Some(Outcome::Win {
+            winner: loser.opponent(),
+            reason: WinReason::KingExploded,
+        })
+    }
+
+    /// Kings can't capture, so bare kings can never win.
+    fn is_insufficient_material(&self, board: &Board) -> bool {
+        only_kings(board)
+    }
+
+    fn has_explosions(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::outcome::{Outcome, WinReason};
+    use crate::board::Board;
+    use crate::piece::Color;
+    use crate::variant::Atomic;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen_variant(fen, &Atomic).unwrap()
+    }
+
+    #[test]
+    fn perft() {
+        let mut b = Board::new_variant(&Atomic);
+        assert_eq!(20, b.perft(1));
+        assert_eq!(400, b.perft(2));
+        assert_eq!(8_902, b.perft(3));
+        assert_eq!(197_326, b.perft(4));
+
+        let mut b = board("rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq - 0 1");
+        assert_eq!(40, b.perft(1));
+        assert_eq!(1_238, b.perft(2));
+        assert_eq!(45_237, b.perft(3));
+
+        let mut b = board("rn1qkb1r/p5pp/2p5/3p4/N3P3/5P2/PPP4P/R1BQK3 w Qkq - 0 1");
+        assert_eq!(28, b.perft(1));
+        assert_eq!(833, b.perft(2));
+        assert_eq!(23_353, b.perft(3));
+    }
+
+    #[test]
+    fn explosions() {
+        let mut b = board("rnbqkbnr/ppp1pppp/8/3p4/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1");
+        let before = b.to_fen();
+        let hash = b.hash();
+
+        // the pawns around d5 survive, the capturer doesn't
+        let m = b.parse_uci_move("e4d5").unwrap();
+        b.make_move(m);
+        assert_eq!(
+            "rnbqkbnr/ppp1pppp/8/8/8/5N2/PPPP1PPP/RNBQKB1R b KQkq - 0 1",
+            b.to_fen()
+        );
+        b.unmake_move(m);
+        assert_eq!(before, b.to_fen());
+        assert_eq!(hash, b.hash());
+
+        // the queen takes on f7 and blows up the king, the bishops and the knight next to it
+        let mut b = board("rnbqkbnr/pppp1ppp/8/4p2Q/8/4P3/PPPP1PPP/RNB1KBNR w KQkq - 0 1");
+        let m = b.parse_uci_move("h5f7").unwrap();
+        b.make_move(m);
+        assert_eq!(
+            "rnbq3r/pppp2pp/8/4p3/8/4P3/PPPP1PPP/RNB1KBNR b KQ - 0 1",
+            b.to_fen()
+        );
+        assert_eq!(
+            Some(Outcome::Win {
+                winner: Color::White,
+                reason: WinReason::KingExploded
+            }),
+            b.outcome()
+        );
+        b.unmake_move(m);
+        assert_eq!(b.hash(), board(&b.to_fen()).hash());
+    }
+
+    #[test]
+    fn legality() {
+        // kings can't capture, and touching kings don't give check
+        let mut b = board("8/8/8/3k4/3pK3/8/8/8 w - - 0 1");
+        assert!(!b.in_check());
+        assert!(b.parse_uci_move("e4d4").is_none());
+
+        // capturing next to the own king blows it up, even if the enemy king goes as well
+        let mut b = board("8/8/8/8/8/k7/1n6/1RK5 w - - 0 1");
+        assert!(b.parse_uci_move("b1b2").is_none());
+        assert!(b.parse_uci_move("b1a1").is_some());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::board::Board;
+use crate::piece::{Color, Piece, PieceType};
+use crate::variant::Variant;
+
+/// Captured pieces change sides and can be dropped back on the board instead of a move.
+#[derive(Copy, Clone, Debug)]
+pub struct Crazyhouse;
+
+impl Variant for Crazyhouse {
+    fn name(&self) -> &'static str {
+        "Crazyhouse"
+    }
+
+    /// Only bare kings without pieces in hand can't win anymore, everything else may come back.
+    fn is_insufficient_material(&self, board: &Board) -> bool {
+        Color::iter().all(|c| {
+            PieceType::iter()
+                .filter(|t| *t != PieceType::King)
+                .all(|t| {
+                    board.pieces(&Piece::new(c, t)).next().is_none() && board.pocket(c, t) == 0
+                })
+        })
+    }
+
+    fn has_pockets(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::piece::{Color, PieceType};
+    use crate::variant::Crazyhouse;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen_variant(fen, &Crazyhouse).unwrap()
+    }
+
+    #[test]
+    fn perft() {
+        let mut b = board("2k5/8/8/8/8/8/8/4K3[QRBNPqrbnp] w - - 0 1");
+        assert_eq!(301, b.perft(1));
+        assert_eq!(75_353, b.perft(2));
+
+        let mut b = board("2k5/8/8/8/8/8/8/4K3[Qn] w - - 0 1");
+        assert_eq!(67, b.perft(1));
+        assert_eq!(3_083, b.perft(2));
+        assert_eq!(88_634, b.perft(3));
+
+        let mut b =
+            board("r1bqk2r/pppp1ppp/2n1p3/4P3/1b1Pn3/2NB1N2/PPP2PPP/R1BQK2R[] b KQkq - 0 1");
+        assert_eq!(42, b.perft(1));
+        assert_eq!(1_347, b.perft(2));
+        assert_eq!(58_057, b.perft(3));
+
+        // the promoted queen goes into the pocket as a pawn
+        let mut b = board("4k3/1Q~6/8/8/4b3/8/Kpp5/8/ b - - 0 1");
+        assert_eq!(20, b.perft(1));
+        assert_eq!(360, b.perft(2));
+        assert_eq!(5_445, b.perft(3));
+        assert_eq!(132_758, b.perft(4));
+    }
+
+    #[test]
+    fn fen() {
+        let b = Board::new_variant(&Crazyhouse);
+        assert_eq!(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1",
+            b.to_fen()
+        );
+
+        let b = board("rnbqk1nQ~/ppppp3/8/5p2/8/5N2/PPPPPPP1/RNBQKB1R/PPBR b KQq - 0 6");
+        assert_eq!(
+            "rnbqk1nQ~/ppppp3/8/5p2/8/5N2/PPPPPPP1/RNBQKB1R[RBPP] b KQq - 0 6",
+            b.to_fen()
+        );
+        assert!(b.is_promoted(&"h8".parse().unwrap()));
+        assert_eq!(2, b.pocket(Color::White, PieceType::Pawn));
+        assert_eq!(b.hash(), board(&b.to_fen()).hash());
+        assert_ne!(
+            b.hash(),
+            board("rnbqk1nQ~/ppppp3/8/5p2/8/5N2/PPPPPPP1/RNBQKB1R[RBP] b KQq - 0 6").hash()
+        );
+
+        assert!(Board::from_fen_variant("8/8/8/8/8/8/8/8[K] w - - 0 1", &Crazyhouse).is_err());
+        assert!(Board::from_fen_variant("8/8/8/8/8/8/8/8[Q w - - 0 1", &Crazyhouse).is_err());
+        assert!("8/8/8/8/8/8/8/8[Q] w - - 0 1".parse::<Board>().is_err());
+    }
+
+    #[test]
+    fn captures_and_drops() {
+        let mut b = board("4k3/1Q~6/8/8/4b3/8/Kpp5/8[] b - - 0 1");
+        let start = b.to_fen();
+        let hash = b.hash();
+        let mut played = Vec::new();
+        let play = |b: &mut Board, uci: &str| {
+            let m = b.parse_uci_move(uci).unwrap();
+            b.make_move(m);
+            m
+        };
+
+        // the bishop takes the promoted queen, which turns back into a pawn
+        played.push(play(&mut b, "e4b7"));
+        assert_eq!(1, b.pocket(Color::Black, PieceType::Pawn));
+        assert_eq!(0, b.pocket(Color::Black, PieceType::Queen));
+        assert!(!b.is_promoted(&"b7".parse().unwrap()));
+
+        played.push(play(&mut b, "a2a3"));
+        played.push(play(&mut b, "P@e2"));
+        assert!(played[2].is_drop());
+        assert_eq!("4k3/1b6/8/8/8/K7/1pp1p3/8[] w - - 0 3", b.to_fen());
+        assert_eq!(b.hash(), board(&b.to_fen()).hash());
+
+        played.push(play(&mut b, "a3a4"));
+        played.push(play(&mut b, "c2c1q"));
+        assert!(b.is_promoted(&"c1".parse().unwrap()));
+        assert_eq!(b.hash(), board(&b.to_fen()).hash());
+
+        for m in played.into_iter().rev() {
+            b.unmake_move(m);
+        }
+        assert_eq!(hash, b.hash());
+        assert_eq!(start, b.to_fen());
+    }
+
+    #[test]
+    fn drops_block_checks() {
+        // pawns can't be dropped on the last rank, only the knight can block there
+        let mut b = board("R3k3/8/8/8/8/8/8/4K3[np] b - - 0 1");
+        let mut moves: Vec<String> = b.legal_moves().iter().map(|m| m.to_string()).collect();
+        moves.sort();
+
+        assert_eq!(vec!["N@b8", "N@c8", "N@d8", "e8d7", "e8e7", "e8f7"], moves);
+    }
+
+    #[test]
+    fn san() {
+        let mut b = board("2k5/8/8/8/8/8/8/4K3[QPn] w - - 0 1");
+
+        let m = b.parse_san("Q@c7").unwrap();
+        assert_eq!("Q@c7+", b.san(m));
+        let m = b.parse_san("P@e4").unwrap();
+        assert_eq!("@e4", b.san(m));
+        assert_eq!(Some(m), b.parse_san("@e4"));
+
+        assert_eq!(None, b.parse_san("N@f3"));
+        assert_eq!(None, b.parse_san("@e8"));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::board::outcome::{Outcome, WinReason};
+use crate::board::Board;
+use crate::piece::{Color, Piece, PieceType};
+use crate::variant::Variant;
+
+/// White has a horde of pawns and no king, black wins by capturing all of them.
+#[derive(Copy, Clone, Debug)]
+pub struct Horde;
+
+impl Variant for Horde {
+    fn name(&self) -> &'static str {
+        "Horde"
+    }
+
+    fn starting_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1"
+    }
+
+    /// The pawns on the first rank may advance two squares as well.
+    fn is_double_push_rank(&self, color: Color, rank: u8) -> bool {
+        rank == color.back_rank() || rank as i8 == color.back_rank() as i8 + color.pawn_direction()
+    }
+
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        let white_left =
+            PieceType::iter().any(|t| board.pieces(&Piece::new(Color::White, t)).next().is_some());
+
+        (!white_left).then_some(Outcome::Win {
+            winner: Color::Black,
+            reason: WinReason::AllPiecesCaptured,
+        })
+    }
+
+    /// Isn't detected, the horde usually has enough pawns left to promote.
+    fn is_insufficient_material(&self, _board: &Board) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::outcome::{Outcome, WinReason};
+    use crate::board::Board;
+    use crate::piece::Color;
+    use crate::variant::Horde;
+
+    fn perft(fen: &str, depth: u32) -> u64 {
+        Board::from_fen_variant(fen, &Horde).unwrap().perft(depth)
+    }
+
+    #[test]
+    fn perft_start_position() {
+        let mut b = Board::new_variant(&Horde);
+
+        assert_eq!(8, b.perft(1));
+        assert_eq!(128, b.perft(2));
+        assert_eq!(1_274, b.perft(3));
+        assert_eq!(23_310, b.perft(4));
+    }
+
+    #[test]
+    fn perft_open_flank() {
+        let fen = "4k3/pp4q1/3P2p1/8/P3PP2/PPP2r2/PPP5/PPPP4 b - - 0 1";
+
+        assert_eq!(30, perft(fen, 1));
+        assert_eq!(241, perft(fen, 2));
+        assert_eq!(6_633, perft(fen, 3));
+        assert_eq!(56_539, perft(fen, 4));
+    }
+
+    #[test]
+    fn perft_en_passant() {
+        // double pushes from the first rank can't be taken en passant
+        let fen = "k7/5p2/4p2P/3p2P1/2p2P2/1p2P2P/p2P2P1/2P2P2 w - - 0 1";
+
+        assert_eq!(13, perft(fen, 1));
+        assert_eq!(172, perft(fen, 2));
+        assert_eq!(2_205, perft(fen, 3));
+        assert_eq!(33_781, perft(fen, 4));
+    }
+
+    #[test]
+    fn outcome() {
+        let mut b = Board::from_fen_variant("4k3/8/8/8/8/8/8/8 w - - 0 1", &Horde).unwrap();
+        assert_eq!(
+            Some(Outcome::Win {
+                winner: Color::Black,
+                reason: WinReason::AllPiecesCaptured
+            }),
+            b.outcome()
+        );
+
+        let mut b = Board::from_fen_variant("4k3/8/8/8/8/8/8/7P w - - 0 1", &Horde).unwrap();
+        assert_eq!(None, b.outcome());
+        assert_eq!(2, b.legal_moves().len());
+    }
+}
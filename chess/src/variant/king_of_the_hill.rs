@@ -0,0 +1,77 @@
+use crate::board::outcome::{Outcome, WinReason};
+use crate::board::Board;
+use crate::piece::Color;
+use crate::variant::Variant;
+
+/// Standard chess, but bringing the king to one of the four center squares also wins.
+#[derive(Copy, Clone, Debug)]
+pub struct KingOfTheHill;
+
+impl Variant for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "King of the Hill"
+    }
+
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        let winner = Color::iter().find(|c| {
+            board
+                .king_position(*c)
+                .is_some_and(|k| (3..=4).contains(&k.rank) && (3..=4).contains(&k.file))
+        })?;
+
+        Some(Outcome::Win {
+            winner,
+            reason: WinReason::KingInCenter,
+        })
+    }
+
+    /// A king can always walk to the center.
+    fn is_insufficient_material(&self, _board: &Board) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::outcome::{Outcome, WinReason};
+    use crate::board::Board;
+    use crate::piece::Color;
+    use crate::variant::KingOfTheHill;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen_variant(fen, &KingOfTheHill).unwrap()
+    }
+
+    #[test]
+    fn perft() {
+        let mut b = Board::new_variant(&KingOfTheHill);
+        assert_eq!(20, b.perft(1));
+        assert_eq!(400, b.perft(2));
+        assert_eq!(8_902, b.perft(3));
+
+        // Kd4 ends the game, the other six king moves don't
+        let mut b = board("8/8/8/8/8/2K5/8/k7 w - - 0 1");
+        assert_eq!(7, b.perft(1));
+        let mut standard: Board = "8/8/8/8/8/2K5/8/k7 w - - 0 1".parse().unwrap();
+        let mut after_kd4 = board("8/8/8/8/3K4/8/8/k7 b - - 1 1");
+        assert_eq!(0, after_kd4.perft(1));
+        assert_eq!(standard.perft(2) - 3, b.perft(2));
+    }
+
+    #[test]
+    fn outcome() {
+        let mut b = board("4k3/8/8/3K4/8/8/8/8 b - - 0 1");
+        assert_eq!(
+            Some(Outcome::Win {
+                winner: Color::White,
+                reason: WinReason::KingInCenter
+            }),
+            b.outcome()
+        );
+        assert!(b.legal_moves().is_empty());
+
+        let mut b = board("8/8/4k3/8/8/3K4/8/8 w - - 0 1");
+        assert_eq!(None, b.outcome());
+        assert!(!b.is_insufficient_material());
+    }
+}
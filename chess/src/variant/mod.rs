@@ -0,0 +1,185 @@
+//! The rules of chess variants, plugged into `Board`.
+//!
+//! A board plays by the rules of its variant: the variant decides the starting position, adds
+//! its own ways to win or draw and can change how pieces move. Everything a variant doesn't
+//! override follows standard chess.
+
+mod antichess;
+mod atomic;
+mod crazyhouse;
+mod horde;
+mod king_of_the_hill;
+mod three_check;
+
+use crate::board::outcome::{DrawReason, Outcome, WinReason};
+use crate::board::Board;
+use crate::chess_move::MoveList;
+use crate::piece::{Color, Piece, PieceType};
+use std::fmt::Debug;
+
+pub use antichess::Antichess;
+pub use atomic::Atomic;
+pub use crazyhouse::Crazyhouse;
+pub use horde::Horde;
+pub use king_of_the_hill::KingOfTheHill;
+pub use three_check::ThreeCheck;
+
+/// The variants known by name.
+pub const VARIANTS: [&dyn Variant; 7] = [
+    &Standard,
+    &KingOfTheHill,
+    &ThreeCheck,
+    &Horde,
+    &Crazyhouse,
+    &Atomic,
+    &Antichess,
+];
+
+pub trait Variant: Debug + Send + Sync {
+    /// The name as in the `Variant` tag of PGN, e.g. `King of the Hill`.
+    fn name(&self) -> &'static str;
+
+    fn starting_fen(&self) -> &'static str {
+        Board::STARTING_FEN
+    }
+
+    /// Whether a pawn of `color` standing on `rank` may advance two squares.
+    fn is_double_push_rank(&self, color: Color, rank: u8) -> bool {
+        rank as i8 == color.back_rank() as i8 + color.pawn_direction()
+    }
+
+    /// The pieces a pawn may promote to.
+    fn promotion_types(&self) -> &'static [PieceType] {
+        &[
+            PieceType::Queen,
+            PieceType::Rook,
+            PieceType::Bishop,
+            PieceType::Knight,
+        ]
+    }
+
+    /// Whether the king of `color` is in check.
+    fn in_check(&self, board: &Board, color: Color) -> bool {
+        board
+            .king_position(color)
+            .is_some_and(|king| board.is_square_attacked(&king, color.opponent()))
+    }
+
+    /// Whether the position after a move of `us` is legal, by default if it doesn't leave their
+    /// king in check.
+    fn is_legal_after(&self, board: &Board, us: Color) -> bool {
+        !self.in_check(board, us)
+    }
+
+    /// Whether a check can only be answered by moving the king, capturing the checker or
+    /// blocking it, so that the move generator may generate just those.
+    fn has_standard_evasions(&self) -> bool {
+        true
+    }
+
+    /// Removes the legal moves the variant doesn't allow in the position, like non-captures when
+    /// a capture is possible.
+    fn filter_legal_moves(&self, _moves: &mut MoveList) {}
+
+    /// Whether `filter_legal_moves` removes any moves, so that the legal moves can't be handed
+    /// out in stages.
+    fn restricts_moves(&self) -> bool {
+        false
+    }
+
+    /// The outcome by the rules of the variant alone, looked at before checkmate, stalemate and
+    /// the draw rules. There are no legal moves once this decided the game.
+    fn outcome(&self, _board: &Board) -> Option<Outcome> {
+        None
+    }
+
+    /// The outcome when the side to move has no legal moves: checkmate or stalemate.
+    fn outcome_without_moves(&self, board: &Board) -> Outcome {
+        let us = board.current_player();
+
+        if self.in_check(board, us) {
+            Outcome::Win {
+                winner: us.opponent(),
+                reason: WinReason::Checkmate,
+            }
+        } else {
+            Outcome::Draw(DrawReason::Stalemate)
+        }
+    }
+
+    /// Whether neither side can win anymore.
+    fn is_insufficient_material(&self, board: &Board) -> bool {
+        board.lacks_mating_material()
+    }
+
+    /// Whether the checks each side gave are part of the position.
+    fn counts_checks(&self) -> bool {
+        false
+    }
+
+    /// Whether captured pieces go into the capturer's pocket, to be dropped back on the board.
+    fn has_pockets(&self) -> bool {
+        false
+    }
+
+    /// Whether a capture explodes, removing the capturer and the pieces other than pawns around
+    /// the capture square.
+    fn has_explosions(&self) -> bool {
+        false
+    }
+}
+
+/// Standard chess.
+#[derive(Copy, Clone, Debug)]
+pub struct Standard;
+
+impl Variant for Standard {
+    fn name(&self) -> &'static str {
+        "Standard"
+    }
+}
+
+/// Whether there are no pieces left but the kings.
+fn only_kings(board: &Board) -> bool {
+    Color::iter().all(|c| {
+        PieceType::iter()
+            .filter(|t| *t != PieceType::King)
+            .all(|t| board.pieces(&Piece::new(c, t)).next().is_none())
+    })
+}
+
+/// Finds a variant by its name, ignoring case, spaces and dashes, so that `kingofthehill` and
+/// `King of the Hill` both work.
+pub fn from_name(name: &str) -> Option<&'static dyn Variant> {
+    let simplify = |s: &str| -> String {
+        s.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    };
+
+    VARIANTS
+        .iter()
+        .copied()
+        .find(|v| simplify(v.name()) == simplify(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::variant::from_name;
+
+    #[test]
+    fn names() {
+        assert_eq!("Standard", from_name("standard").unwrap().name());
+        assert_eq!(
+            "King of the Hill",
+            from_name("kingofthehill").unwrap().name()
+        );
+        assert_eq!("Three-check", from_name("threeCheck").unwrap().name());
+        assert_eq!("Horde", from_name("Horde").unwrap().name());
+        assert_eq!("Crazyhouse", from_name("crazyhouse").unwrap().name());
+        assert_eq!("Atomic", from_name("atomic").unwrap().name());
+        assert_eq!("Antichess", from_name("Antichess").unwrap().name());
+        assert!(from_name("racingkings").is_none());
+    }
+}
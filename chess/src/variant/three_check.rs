@@ -0,0 +1,122 @@
+use crate::board::outcome::{Outcome, WinReason};
+use crate::board::Board;
+use crate::piece::Color;
+use crate::variant::{only_kings, Variant};
+
+/// Standard chess, but giving the third check also wins.
+#[derive(Copy, Clone, Debug)]
+pub struct ThreeCheck;
+
+impl Variant for ThreeCheck {
+    fn name(&self) -> &'static str {
+        "Three-check"
+    }
+
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        let winner = Color::iter().find(|c| board.checks_given(*c) >= 3)?;
+
+        Some(Outcome::Win {
+            winner,
+            reason: WinReason::ThirdCheck,
+        })
+    }
+
+    /// Any piece next to the king can still give checks.
+    fn is_insufficient_material(&self, board: &Board) -> bool {
+        only_kings(board)
+    }
+
+    fn counts_checks(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::outcome::{Outcome, WinReason};
+    use crate::board::Board;
+    use crate::piece::Color;
+    use crate::variant::ThreeCheck;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen_variant(fen, &ThreeCheck).unwrap()
+    }
+
+    #[test]
+    fn perft() {
+        // one check left for both sides, so any check ends the game
+        let mut b =
+            board("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 1+1 0 1");
+        assert_eq!(48, b.perft(1));
+        assert_eq!(2_039, b.perft(2));
+        assert_eq!(97_848, b.perft(3));
+
+        let mut b = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 1+1 0 1");
+        assert_eq!(26, b.perft(1));
+        assert_eq!(562, b.perft(2));
+        assert_eq!(13_410, b.perft(3));
+    }
+
+    #[test]
+    fn fen() {
+        let b = Board::new_variant(&ThreeCheck);
+        assert_eq!(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+3 0 1",
+            b.to_fen()
+        );
+
+        let b = board("4k3/8/8/8/8/8/8/4K2R w K - 2+3 0 1");
+        assert_eq!(
+            (1, 0),
+            (b.checks_given(Color::White), b.checks_given(Color::Black))
+        );
+        let given = board("4k3/8/8/8/8/8/8/4K2R w K - 0 1 +1+0");
+        assert_eq!(b.to_fen(), given.to_fen());
+        assert_eq!(b.hash(), given.hash());
+        assert_ne!(board("4k3/8/8/8/8/8/8/4K2R w K - 0 1").hash(), b.hash());
+
+        assert!(
+            Board::from_fen_variant("4k3/8/8/8/8/8/8/4K2R w K - 4+3 0 1", &ThreeCheck).is_err()
+        );
+        assert!("4k3/8/8/8/8/8/8/4K2R w K - 3+3 0 1"
+            .parse::<Board>()
+            .is_err());
+    }
+
+    #[test]
+    fn counting_checks() {
+        let mut b = board("4k3/8/8/8/8/8/8/4K2R w K - 3+1 0 1");
+        let hash = b.hash();
+        let m = b.parse_uci_move("h1h8").unwrap();
+
+        b.make_move(m);
+        assert_eq!(1, b.checks_given(Color::White));
+        assert_eq!("4k2R/8/8/8/8/8/8/4K3 b - - 2+1 1 1", b.to_fen());
+        assert!(b.to_fen().parse::<Board>().is_err());
+        assert_eq!(board(&b.to_fen()).hash(), b.hash());
+
+        b.unmake_move(m);
+        assert_eq!(0, b.checks_given(Color::White));
+        assert_eq!(hash, b.hash());
+    }
+
+    #[test]
+    fn outcome() {
+        // black already gave two checks and gives the third
+        let mut b = board("4k3/8/8/8/8/8/5q2/K7 b - - 3+1 0 1");
+        let m = b.parse_uci_move("f2f1").unwrap();
+        b.make_move(m);
+
+        assert_eq!(
+            Some(Outcome::Win {
+                winner: Color::Black,
+                reason: WinReason::ThirdCheck
+            }),
+            b.outcome()
+        );
+        assert!(b.legal_moves().is_empty());
+
+        assert!(board("4k3/8/8/8/8/8/8/K7 w - - 0 1").is_insufficient_material());
+        assert!(!board("4k3/8/8/8/8/8/8/KN6 w - - 0 1").is_insufficient_material());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::engine::Engine;
+use crate::play::pv_san;
+use crate::render::{side_by_side, Renderer};
+use chess::board::Board;
+use chess::search::{SearchInfo, SearchLimits};
+use std::io::{BufRead, Write};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::thread;
+
+/// Lets the engine analyze the position until Enter is pressed, showing the `lines` best moves.
+pub fn run(
+    board: Board,
+    mut engine: Engine,
+    lines: usize,
+    renderer: &mut Renderer,
+) -> std::io::Result<()> {
+    let stop = engine.stop_handle();
+    let title = format!("Analyzing with {}, press Enter to stop", engine.name());
+    let (tx, rx) = mpsc::channel();
+
+    let search_board = board.clone();
+    let limits = SearchLimits {
+        multi_pv: Some(lines),
+        ..SearchLimits::default()
+    };
+    let handle = thread::spawn(move || {
+        engine.search(&search_board, &[], limits, |info| {
+            let _ = tx.send(info.clone());
+        })
+    });
+
+    // the reader stays blocked if the search ends first, which is fine as the program exits then
+    thread::spawn(move || {
+        let mut line = String::new();
+        let _ = std::io::stdin().lock().read_line(&mut line);
+        stop.store(true, Ordering::Relaxed);
+    });
+
+    let mut stdout = std::io::stdout();
+    let mut panel = vec![title.clone()];
+    write!(
+        stdout,
+        "{}",
+        renderer.redraw(&side_by_side(&renderer.board(&board, None), &panel))
+    )?;
+    stdout.flush()?;
+
+    let mut latest: Vec<SearchInfo> = Vec::new();
+    for info in rx {
+        // a new iteration starts over with the best line
+        if info.multipv == 1 {
+            latest.clear();
+        }
+        latest.push(info);
+        panel = analysis_panel(&title, &board, &latest);
+
+        write!(
+            stdout,
+            "{}",
+            renderer.redraw(&side_by_side(&renderer.board(&board, None), &panel))
+        )?;
+        stdout.flush()?;
+    }
+
+    if let Ok(result) = handle.join() {
+        if let Some(m) = result.map_err(std::io::Error::other)? {
+            let mut b = board.clone();
+            writeln!(stdout, "Best move: {}", b.san(m))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The title, the progress of the search and the lines found, each with its score.
+fn analysis_panel(title: &str, board: &Board, lines: &[SearchInfo]) -> Vec<String> {
+    let mut panel = vec![title.to_string(), String::new()];
+    let Some(last) = lines.last() else {
+        return panel;
+    };
+    panel.push(format!("Depth {}", lines[0].depth));
+    panel.push(format!("Nodes {}  NPS {}", last.nodes, last.nps()));
+
+    // with several lines only the start of each fits
+    let chunks = if lines.len() == 1 { usize::MAX } else { 2 };
+    for info in lines {
+        panel.push(String::new());
+        let pv = pv_san(&mut board.clone(), &info.pv);
+        for (idx, chunk) in pv.chunks(6).take(chunks).enumerate() {
+            let score = if idx == 0 {
+                info.score.to_string()
+            } else {
+                String::new()
+            };
+            panel.push(format!("{:>6}  {}", score, chunk.join(" ")));
+        }
+    }
+
+    panel
+}
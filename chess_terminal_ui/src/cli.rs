@@ -0,0 +1,171 @@
+use std::str::FromStr;
+
+pub const USAGE: &str = "\
+Usage: chess_terminal_ui [OPTIONS]
+
+Options:
+  --fen <FEN>                    Start from this position
+  --pgn <FILE>                   Open the games of a PGN file
+  --game <N>                     Use the N-th game of the PGN file, starting at 1
+  --mode <play|view|analyze>     Play against the engine, view games or analyze the position
+  --engine <PATH>                Use an external UCI engine instead of the built-in one
+  --book <FILE>                  Let the engine play from a Polyglot opening book
+  --lines <N>                    Show the N best moves when analyzing [default: 1]
+  --flip                         Show the board from black's side
+  --ascii                        Draw pieces as letters instead of figurines
+  -h, --help                     Print this help";
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Mode {
+    Play,
+    View,
+    Analyze,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "play" => Ok(Mode::Play),
+            "view" => Ok(Mode::View),
+            "analyze" => Ok(Mode::Analyze),
+            _ => Err(format!(
+                "invalid mode '{}', expected play, view or analyze",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Args {
+    pub fen: Option<String>,
+    pub pgn: Option<String>,
+    pub game: Option<usize>,
+    pub mode: Option<Mode>,
+    pub engine: Option<String>,
+    pub book: Option<String>,
+    pub lines: Option<usize>,
+    pub flip: bool,
+    pub ascii: bool,
+    pub help: bool,
+}
+
+impl Args {
+    /// The mode asked for, or viewing for PGN files and playing otherwise.
+    pub fn mode(&self) -> Mode {
+        match (self.mode, &self.pgn) {
+            (Some(mode), _) => mode,
+            (None, Some(_)) => Mode::View,
+            (None, None) => Mode::Play,
+        }
+    }
+}
+
+/// Reads the command line arguments, without the program name.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for '{}'", arg))
+        };
+
+        match arg.as_str() {
+            "--fen" => parsed.fen = Some(value()?),
+            "--pgn" => parsed.pgn = Some(value()?),
+            "--game" => {
+                let v = value()?;
+                match v.parse() {
+                    Ok(n) if n > 0 => parsed.game = Some(n),
+                    _ => return Err(format!("invalid game number '{}'", v)),
+                }
+            }
+            "--mode" => parsed.mode = Some(value()?.parse()?),
+            "--engine" => parsed.engine = Some(value()?),
+            "--book" => parsed.book = Some(value()?),
+            "--lines" => {
+                let v = value()?;
+                match v.parse() {
+                    Ok(n) if n > 0 => parsed.lines = Some(n),
+                    _ => return Err(format!("invalid number of lines '{}'", v)),
+                }
+            }
+            "--flip" => parsed.flip = true,
+            "--ascii" => parsed.ascii = true,
+            "-h" | "--help" => parsed.help = true,
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+
+    if parsed.fen.is_some() && parsed.pgn.is_some() {
+        return Err(String::from("--fen and --pgn can't be used together"));
+    }
+    if parsed.game.is_some() && parsed.pgn.is_none() {
+        return Err(String::from("--game needs a --pgn file"));
+    }
+    if parsed.mode() == Mode::View && parsed.pgn.is_none() {
+        return Err(String::from("view mode needs a --pgn file"));
+    }
+    if parsed.book.is_some() && parsed.mode() != Mode::Play {
+        return Err(String::from("--book is only used in play mode"));
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::{parse_args, Args, Mode};
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn defaults() {
+        let args = parse(&[]).unwrap();
+        assert_eq!(Args::default(), args);
+        assert_eq!(Mode::Play, args.mode());
+
+        assert_eq!(Mode::View, parse(&["--pgn", "a.pgn"]).unwrap().mode());
+    }
+
+    #[test]
+    fn all_options() {
+        let args = parse(&[
+            "--pgn", "games.pgn", "--game", "3", "--mode", "analyze", "--engine", "/bin/sf",
+            "--lines", "3", "--flip", "--ascii",
+        ])
+        .unwrap();
+
+        assert_eq!(Some("games.pgn".to_string()), args.pgn);
+        assert_eq!(Some(3), args.game);
+        assert_eq!(Mode::Analyze, args.mode());
+        assert_eq!(Some("/bin/sf".to_string()), args.engine);
+        assert_eq!(Some(3), args.lines);
+        assert!(args.flip && args.ascii);
+
+        let args = parse(&["--book", "book.bin"]).unwrap();
+        assert_eq!(Some("book.bin".to_string()), args.book);
+
+        let args = parse(&["--fen", "8/8/8/8/8/8/8/8 w - - 0 1"]).unwrap();
+        assert_eq!(Some("8/8/8/8/8/8/8/8 w - - 0 1".to_string()), args.fen);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse(&["--fen"]).is_err());
+        assert!(parse(&["--mode", "blitz"]).is_err());
+        assert!(parse(&["--game", "0", "--pgn", "a.pgn"]).is_err());
+        assert!(parse(&["--game", "2"]).is_err());
+        assert!(parse(&["--fen", "x", "--pgn", "a.pgn"]).is_err());
+        assert!(parse(&["--mode", "view"]).is_err());
+        assert!(parse(&["--lines", "0"]).is_err());
+        assert!(parse(&["--book", "book.bin", "--mode", "analyze"]).is_err());
+        assert!(parse(&["--fast"]).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use chess::board::Board;
+use chess::chess_move::Move;
+use chess::search::{Search, SearchInfo, SearchLimits};
+use chess::uci_client::{GoParams, UciEngine, UciError, UciInfo};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The engine to play against or analyze with: the built-in search or an external UCI engine.
+pub enum Engine {
+    Builtin(Box<Search>),
+    External(Box<UciEngine>),
+}
+
+impl Engine {
+    /// Starts the UCI engine at `path` and waits until it is ready.
+    pub fn external(path: &str) -> Result<Engine, UciError> {
+        let mut engine = UciEngine::start(path, &[])?;
+        engine.is_ready()?;
+
+        Ok(Engine::External(Box::new(engine)))
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Engine::Builtin(_) => String::from("the built-in engine"),
+            Engine::External(e) => e.name().unwrap_or("the external engine").to_string(),
+        }
+    }
+
+    /// A flag that stops the search when set.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        match self {
+            Engine::Builtin(s) => s.stop_handle(),
+            Engine::External(e) => e.stop_handle(),
+        }
+    }
+
+    /// Searches the position after `moves` from `start` and returns the best move, if there is
+    /// a legal one.
+    pub fn search(
+        &mut self,
+        start: &Board,
+        moves: &[Move],
+        limits: SearchLimits,
+        mut on_info: impl FnMut(&SearchInfo),
+    ) -> Result<Option<Move>, UciError> {
+        match self {
+            Engine::Builtin(search) => {
+                let mut board = start.clone();
+                for &m in moves {
+                    board.make_move(m);
+                }
+
+                Ok(search.run(&mut board, limits, on_info).best_move)
+            }
+            Engine::External(engine) => {
+                if let Some(level) = limits.skill_level {
+                    if engine.option("Skill Level").is_some() {
+                        engine.set_option("Skill Level", &level.to_string())?;
+                    }
+                }
+                if engine.option("MultiPV").is_some() {
+                    engine.set_option("MultiPV", &limits.multi_pv.unwrap_or(1).to_string())?;
+                }
+                engine.position(start, moves)?;
+
+                let best = engine.go(&go_params(&limits), |info| {
+                    if let Some(info) = search_info(info) {
+                        on_info(&info);
+                    }
+                })?;
+                Ok(best.best_move)
+            }
+        }
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::Builtin(Box::default())
+    }
+}
+
+/// The `go` command for the limits of the built-in search, which searches until stopped without
+/// any.
+fn go_params(limits: &SearchLimits) -> GoParams {
+    GoParams {
+        depth: limits.depth,
+        nodes: limits.nodes,
+        movetime: limits.time,
+        infinite: limits.depth.is_none() && limits.nodes.is_none() && limits.time.is_none(),
+        searchmoves: limits.search_moves.clone(),
+        ..GoParams::default()
+    }
+}
+
+/// The progress of a line, as the built-in search reports it.
+fn search_info(info: &UciInfo) -> Option<SearchInfo> {
+    Some(SearchInfo {
+        multipv: info.multipv.unwrap_or(1) as usize,
+        depth: info.depth?,
+        score: info.score?,
+        nodes: info.nodes.unwrap_or(0),
+        time: info.time.unwrap_or(Duration::ZERO),
+        pv: info.pv.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::{go_params, search_info};
+    use chess::board::Board;
+    use chess::search::{Score, SearchLimits};
+    use chess::uci_client::UciInfo;
+    use std::time::Duration;
+
+    #[test]
+    fn limits_to_go() {
+        assert_eq!("go infinite", go_params(&SearchLimits::default()).command());
+
+        let limits = SearchLimits {
+            depth: Some(5),
+            time: Some(Duration::from_secs(2)),
+            ..SearchLimits::default()
+        };
+        assert_eq!("go movetime 2000 depth 5", go_params(&limits).command());
+    }
+
+    #[test]
+    fn uci_info_to_search_info() {
+        let b = Board::new();
+        let parse = |line| search_info(&UciInfo::parse(line, &b).unwrap());
+
+        let info = parse("info depth 7 score cp 31 nodes 5000 time 20 pv e2e4").unwrap();
+        assert_eq!(
+            (7, Score::Cp(31), 5000),
+            (info.depth, info.score, info.nodes)
+        );
+        assert_eq!(250_000, info.nps());
+        assert_eq!(1, info.pv.len());
+
+        assert_eq!(1, info.multipv);
+        assert_eq!(2, parse("info depth 7 multipv 2 score cp 10").unwrap().multipv);
+        assert!(parse("info depth 7 currmove e2e4").is_none());
+    }
+}
@@ -1,25 +1,117 @@
 use chess::board::Board;
+use chess::pgn::{parse_pgn, PgnGame};
+use chess::polyglot::Book;
+use cli::{Args, Mode};
+use engine::Engine;
+use play::Game;
+use render::{ColorMode, RenderOptions, Renderer};
+use std::error::Error;
 
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
+mod analyze;
+mod cli;
+mod engine;
+mod play;
+mod render;
+mod terminal;
+mod viewer;
+
+fn main() {
+    let args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if args.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
     }
 }
 
-pub mod terminal_ui {
-    pub fn move_printhead_upwards() {
-        print!("\x1b[17A");
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let mut renderer = Renderer::new(RenderOptions {
+        color_mode: ColorMode::detect(),
+        flipped: args.flip,
+        ascii: args.ascii,
+    });
+
+    let games = match &args.pgn {
+        Some(path) => {
+            let pgn = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            let games = parse_pgn(&pgn).map_err(|e| format!("{}: {}", path, e))?;
+
+            match args.game {
+                Some(n) if n > games.len() => {
+                    return Err(format!("{} has only {} games", path, games.len()).into())
+                }
+                Some(n) => vec![games[n - 1].clone()],
+                None if games.is_empty() => return Err(format!("{} has no games", path).into()),
+                None => games,
+            }
+        }
+        None => Vec::new(),
+    };
+
+    if args.mode() == Mode::View {
+        viewer::run(&games, &mut renderer)?;
+        return Ok(());
+    }
+
+    let board = match (&args.fen, games.first()) {
+        (Some(fen), _) => fen
+            .parse::<Board>()
+            .map_err(|e| format!("invalid FEN ({}): {}", e.kind(), e))?,
+        (None, Some(game)) => final_position(game)?,
+        (None, None) => Board::new(),
+    };
+
+    let engine = match &args.engine {
+        Some(path) => Engine::external(path).map_err(|e| format!("{}: {}", path, e))?,
+        None => Engine::default(),
+    };
+
+    match args.mode() {
+        Mode::Play => {
+            let book = match &args.book {
+                Some(path) => Some(Book::open(path).map_err(|e| format!("{}: {}", path, e))?),
+                None => None,
+            };
+            let (human, limits) = play::setup()?;
+            let mut game = Game::new(board, human, limits, engine);
+            if let Some(book) = book {
+                game = game.with_book(book);
+            }
+            game.run(&mut renderer)?;
+        }
+        Mode::Analyze => analyze::run(board, engine, args.lines.unwrap_or(1), &mut renderer)?,
+        Mode::View => unreachable!(),
     }
+
+    Ok(())
 }
 
-fn main() {
-    let b: Board = "r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq - 0 1".parse().unwrap_or_else(|_| Board::new());
+/// The position at the end of the main line of a game.
+fn final_position(game: &PgnGame) -> Result<Board, Box<dyn Error>> {
+    let (mut board, moves) = game.replay()?;
+    for m in moves {
+        board.make_move(m);
+    }
 
-    print!("{}", b);
-    terminal_ui::move_printhead_upwards();
-    println!("{}", b);
+    Ok(board)
+}
 
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn it_works() {
+        let result = 2 + 2;
+        assert_eq!(result, 4);
+    }
+}
@@ -0,0 +1,688 @@
+use crate::engine::Engine;
+use crate::render::{side_by_side, Highlights, Renderer};
+use crate::terminal::{read_keys, FullScreen, Key, RawMode};
+use chess::board::position::Position;
+use chess::board::Board;
+use chess::chess_move::Move;
+use chess::piece::{Color, PieceType};
+use chess::polyglot::Book;
+use chess::search::{SearchInfo, SearchLimits};
+use chess::uci_client::UciError;
+use std::io::{BufRead, Write};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+enum EngineMessage {
+    Info(SearchInfo),
+    Done(Engine, Result<Option<Move>, UciError>),
+}
+
+/// A game of a human against an engine.
+pub struct Game {
+    /// The position the game started from.
+    start: Board,
+    board: Board,
+    moves: Vec<Move>,
+    human: Color,
+    limits: SearchLimits,
+    engine: Option<Engine>,
+    engine_name: String,
+    /// The engine plays from it while the position is in it.
+    book: Option<Book>,
+    info: Option<SearchInfo>,
+    message: String,
+    /// A move or command being typed.
+    input: String,
+    cursor: Position,
+    selected: Option<Position>,
+    /// The moves to choose from while asking for the promotion piece.
+    promotion: Vec<Move>,
+}
+
+impl Game {
+    pub fn new(board: Board, human: Color, limits: SearchLimits, engine: Engine) -> Self {
+        Game {
+            start: board.clone(),
+            board,
+            moves: Vec::new(),
+            human,
+            limits,
+            engine_name: engine.name(),
+            engine: Some(engine),
+            book: None,
+            info: None,
+            message: String::new(),
+            input: String::new(),
+            cursor: Position::from(if human == Color::White { 1 } else { 6 }, 4),
+            selected: None,
+            promotion: Vec::new(),
+        }
+    }
+
+    /// Lets the engine play the moves of an opening book before it starts searching.
+    pub fn with_book(mut self, book: Book) -> Self {
+        self.book = Some(book);
+        self
+    }
+
+    /// Plays until the game ends or the human quits.
+    pub fn run(&mut self, renderer: &mut Renderer) -> std::io::Result<()> {
+        let _raw = RawMode::enable()?;
+        let _screen = FullScreen::enable()?;
+
+        loop {
+            if let Some(outcome) = self.board.outcome() {
+                self.message = format!("Game over: {}, press any key to exit", outcome);
+                self.draw(renderer, false)?;
+                read_keys()?;
+                return Ok(());
+            }
+
+            if self.board.current_player() != self.human {
+                self.engine_move(renderer)?;
+                continue;
+            }
+
+            self.draw(renderer, true)?;
+            let keys = read_keys()?;
+            if keys.is_empty() {
+                return Ok(());
+            }
+
+            for key in keys {
+                if !self.key(key, renderer) {
+                    return Ok(());
+                }
+                if self.board.current_player() != self.human {
+                    // whatever else was typed ahead was meant for the old position
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Handles a key press, returns false to quit.
+    fn key(&mut self, key: Key, renderer: &mut Renderer) -> bool {
+        if !self.promotion.is_empty() {
+            self.pick_promotion(key);
+            return true;
+        }
+
+        match key {
+            Key::Up | Key::Down | Key::Left | Key::Right => {
+                let (dr, df) = match key {
+                    Key::Up => (1, 0),
+                    Key::Down => (-1, 0),
+                    Key::Left => (0, -1),
+                    _ => (0, 1),
+                };
+                let sign = if renderer.options.flipped { -1 } else { 1 };
+                if let Some(pos) = self.cursor.offset(dr * sign, df * sign) {
+                    self.cursor = pos;
+                }
+            }
+            Key::Click { col, row } => {
+                if let Some(pos) = renderer.square_at(col, row) {
+                    self.cursor = pos;
+                    self.select(pos);
+                }
+            }
+            Key::Char(' ') if self.input.is_empty() => self.select(self.cursor),
+            Key::Enter if self.input.is_empty() => self.select(self.cursor),
+            Key::Enter => {
+                let input = std::mem::take(&mut self.input);
+                return self.command(input.trim(), renderer);
+            }
+            Key::Backspace => {
+                self.input.pop();
+            }
+            Key::Esc => {
+                self.input.clear();
+                self.selected = None;
+                self.message.clear();
+            }
+            Key::Char(c) if !c.is_control() => self.input.push(c),
+            _ => {}
+        }
+
+        true
+    }
+
+    /// Picks up the piece on `pos`, or moves the picked up piece there.
+    fn select(&mut self, pos: Position) {
+        self.message.clear();
+        let own_piece = self
+            .board
+            .get_piece(&pos)
+            .is_some_and(|p| p.color == self.human);
+
+        let from = match self.selected {
+            Some(from) if from == pos => {
+                self.selected = None;
+                return;
+            }
+            Some(from) if !own_piece => from,
+            _ => {
+                if !own_piece {
+                    return;
+                }
+                if self.board.legal_moves().iter().any(|m| m.from == pos) {
+                    self.selected = Some(pos);
+                } else {
+                    self.message = format!("The piece on {} can't move", pos);
+                }
+                return;
+            }
+        };
+
+        let moves: Vec<Move> = self
+            .board
+            .legal_moves()
+            .iter()
+            .filter(|m| m.from == from && m.to == pos)
+            .copied()
+            .collect();
+
+        match moves.len() {
+            0 => self.message = format!("Illegal move {}{}", from, pos),
+            1 => {
+                self.selected = None;
+                self.play(moves[0]);
+            }
+            _ => {
+                self.message = String::from("Promote to (q)ueen, (r)ook, (b)ishop or k(n)ight?");
+                self.promotion = moves;
+            }
+        }
+    }
+
+    /// Completes a pending promotion with the piece chosen by `key`, or cancels it.
+    fn pick_promotion(&mut self, key: Key) {
+        let piece_type = match key {
+            Key::Char('q') => PieceType::Queen,
+            Key::Char('r') => PieceType::Rook,
+            Key::Char('b') => PieceType::Bishop,
+            Key::Char('n') => PieceType::Knight,
+            Key::Esc => {
+                self.promotion.clear();
+                self.selected = None;
+                self.message.clear();
+                return;
+            }
+            _ => return,
+        };
+
+        if let Some(&m) = self
+            .promotion
+            .iter()
+            .find(|m| m.promotion == Some(piece_type))
+        {
+            self.promotion.clear();
+            self.selected = None;
+            self.message.clear();
+            self.play(m);
+        }
+    }
+
+    /// Handles a line of input, returns false to quit.
+    fn command(&mut self, input: &str, renderer: &mut Renderer) -> bool {
+        self.message.clear();
+
+        match input {
+            "quit" | "exit" => return false,
+            "flip" => renderer.options.flipped = !renderer.options.flipped,
+            "undo" | "takeback" => {
+                if !self.take_back() {
+                    self.message = String::from("No move to take back");
+                }
+            }
+            "help" => {
+                self.message = String::from(
+                    "Click or use the arrows and Enter to move, or type e4, Nf3, e2e4, undo, flip, quit",
+                )
+            }
+            "" => {}
+            s => match self
+                .board
+                .parse_san(s)
+                .or_else(|| self.board.parse_uci_move(s))
+            {
+                Some(m) => {
+                    self.selected = None;
+                    self.play(m)
+                }
+                None => self.message = format!("Illegal or ambiguous move '{}'", s),
+            },
+        }
+
+        true
+    }
+
+    fn play(&mut self, m: Move) {
+        self.board.make_move(m);
+        self.moves.push(m);
+    }
+
+    /// Takes back moves until the human is to move again, returns false if there was none.
+    pub fn take_back(&mut self) -> bool {
+        let m = match self.moves.pop() {
+            Some(m) => m,
+            None => return false,
+        };
+        self.board.unmake_move(m);
+
+        while self.board.current_player() != self.human {
+            match self.moves.pop() {
+                Some(m) => self.board.unmake_move(m),
+                None => break,
+            }
+        }
+
+        self.info = None;
+        self.selected = None;
+        true
+    }
+
+    /// Lets the engine search on a background thread, showing its progress, then plays its move.
+    fn engine_move(&mut self, renderer: &mut Renderer) -> std::io::Result<()> {
+        if let Some(m) = self.book_move() {
+            self.message = format!("Engine plays {} from the book", self.board.san(m));
+            self.info = None;
+            self.play(m);
+            return Ok(());
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let start = self.start.clone();
+        let moves = self.moves.clone();
+        let mut engine = self.engine.take().unwrap_or_default();
+        let limits = self.limits.clone();
+
+        thread::spawn(move || {
+            let info_tx = tx.clone();
+            let result = engine.search(&start, &moves, limits, |info| {
+                let _ = info_tx.send(EngineMessage::Info(info.clone()));
+            });
+            let _ = tx.send(EngineMessage::Done(engine, result));
+        });
+
+        self.message = String::from("Thinking...");
+        self.info = None;
+        self.draw(renderer, false)?;
+
+        for msg in rx {
+            match msg {
+                EngineMessage::Info(info) => {
+                    if info.multipv == 1 {
+                        self.info = Some(info);
+                        self.draw(renderer, false)?;
+                    }
+                }
+                EngineMessage::Done(engine, result) => {
+                    self.engine = Some(engine);
+                    self.message.clear();
+                    let best_move = result.map_err(std::io::Error::other)?;
+                    if let Some(m) = best_move {
+                        self.message = format!("Engine plays {}", self.board.san(m));
+                        self.play(m);
+
+                        // keep showing the expected continuation from the new position
+                        if let Some(info) = &mut self.info {
+                            if info.pv.first() == Some(&m) {
+                                info.pv.remove(0);
+                            } else {
+                                info.pv.clear();
+                            }
+                        }
+                    }
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A move of the book in the current position, picked by weight.
+    fn book_move(&mut self) -> Option<Move> {
+        let random = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+
+        self.book.as_ref()?.choose_move(&mut self.board, random)
+    }
+
+    fn draw(&mut self, renderer: &mut Renderer, prompt: bool) -> std::io::Result<()> {
+        let highlights = self.highlights(prompt);
+        let board = renderer.board_highlighted(&self.board, &highlights);
+        let panel = self.panel();
+
+        let mut frame = side_by_side(&board, &panel);
+        frame.push_str(&self.message);
+        frame.push('\n');
+        if prompt {
+            frame.push_str("Your move: ");
+            frame.push_str(&self.input);
+        }
+
+        let mut stdout = std::io::stdout();
+        write!(stdout, "{}", FullScreen::frame(&frame))?;
+        stdout.flush()
+    }
+
+    /// The last move, and while it is the human's turn the cursor, the selected piece and where
+    /// it can go.
+    fn highlights(&mut self, human_to_move: bool) -> Highlights {
+        let mut highlights = Highlights {
+            last_move: self.moves.last().copied(),
+            ..Highlights::default()
+        };
+
+        if human_to_move {
+            highlights.cursor = Some(self.cursor);
+            highlights.selected = self.selected;
+            if let Some(from) = self.selected {
+                highlights.targets = self
+                    .board
+                    .legal_moves()
+                    .iter()
+                    .filter(|m| m.from == from)
+                    .map(|m| m.to)
+                    .collect();
+            }
+        }
+
+        highlights
+    }
+
+    /// The side panel with the engine's thinking.
+    fn panel(&mut self) -> Vec<String> {
+        let mut lines = vec![
+            format!("You play {:?} against {}", self.human, self.engine_name),
+            format!("Move {}", self.board.ply() / 2 + 1),
+        ];
+
+        if let Some(info) = &self.info {
+            lines.push(String::new());
+            lines.push(format!("Depth {}  Score {}", info.depth, info.score));
+            lines.push(format!("Nodes {}  NPS {}", info.nodes, info.nps()));
+
+            let mut board = self.board.clone();
+            let pv = pv_san(&mut board, &info.pv);
+            for chunk in pv.chunks(6) {
+                lines.push(chunk.join(" "));
+            }
+        }
+
+        lines
+    }
+}
+
+/// The moves of a principal variation in SAN.
+pub fn pv_san(board: &mut Board, pv: &[Move]) -> Vec<String> {
+    let mut san = Vec::with_capacity(pv.len());
+
+    for &m in pv {
+        if !board.legal_moves().contains(&m) {
+            break;
+        }
+        san.push(board.san(m));
+        board.make_move(m);
+    }
+
+    san
+}
+
+/// Reads an engine strength like `depth 6`, `time 2.5` (seconds) or `skill 10`.
+pub fn parse_strength(s: &str) -> Option<SearchLimits> {
+    let mut limits = SearchLimits::default();
+    let mut words = s.split_whitespace();
+
+    match (words.next()?, words.next()?, words.next()) {
+        ("depth", d, None) => limits.depth = Some(d.parse().ok()?),
+        ("time", t, None) => limits.time = Some(Duration::try_from_secs_f64(t.parse().ok()?).ok()?),
+        ("skill", l, None) => {
+            limits.skill_level = Some(l.parse().ok().filter(|l| *l <= 20)?);
+            limits.time = Some(Duration::from_secs(1));
+        }
+        _ => return None,
+    }
+
+    Some(limits)
+}
+
+/// Asks for the human's color and the engine strength.
+pub fn setup() -> std::io::Result<(Color, SearchLimits)> {
+    let ask = |question: &str| -> std::io::Result<String> {
+        print!("{}", question);
+        std::io::stdout().flush()?;
+
+        let mut answer = String::new();
+        std::io::stdin().lock().read_line(&mut answer)?;
+        Ok(answer.trim().to_string())
+    };
+
+    let human = match ask("Play as (w)hite or (b)lack? [w] ")?.as_str() {
+        "b" | "black" => Color::Black,
+        _ => Color::White,
+    };
+
+    let limits = loop {
+        let answer = ask("Engine strength: depth N, time SECONDS or skill 0-20 [time 2] ")?;
+        if answer.is_empty() {
+            break SearchLimits {
+                time: Some(Duration::from_secs(2)),
+                ..SearchLimits::default()
+            };
+        }
+        if let Some(limits) = parse_strength(&answer) {
+            break limits;
+        }
+    };
+
+    Ok((human, limits))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::Engine;
+    use crate::play::{parse_strength, pv_san, Game};
+    use crate::render::{RenderOptions, Renderer};
+    use crate::terminal::Key;
+    use chess::board::position::Position;
+    use chess::board::Board;
+    use chess::pgn::parse_pgn;
+    use chess::piece::{Color, PieceType};
+    use chess::polyglot::{Book, BookOptions};
+    use chess::search::SearchLimits;
+    use std::time::Duration;
+
+    #[test]
+    fn strength() {
+        assert_eq!(Some(6), parse_strength("depth 6").unwrap().depth);
+        assert_eq!(
+            Some(Duration::from_millis(2500)),
+            parse_strength("time 2.5").unwrap().time
+        );
+        assert_eq!(Some(10), parse_strength("skill 10").unwrap().skill_level);
+        assert_eq!(None, parse_strength("skill 21"));
+        assert_eq!(None, parse_strength("depth"));
+        assert_eq!(None, parse_strength("depth 6 7"));
+        assert_eq!(None, parse_strength("fast"));
+    }
+
+    #[test]
+    fn take_back() {
+        let mut g = game(Board::new(), Color::White);
+        assert!(!g.take_back());
+
+        for m in ["e4", "e5", "Nf3", "Nc6"] {
+            let m = g.board.parse_san(m).unwrap();
+            g.play(m);
+        }
+        let hash = g.board.hash();
+        let m = g.board.parse_san("Bb5").unwrap();
+        g.play(m);
+        let m = g.board.parse_san("a6").unwrap();
+        g.play(m);
+
+        assert!(g.take_back());
+        assert_eq!(hash, g.board.hash());
+        assert_eq!(4, g.moves.len());
+    }
+
+    #[test]
+    fn take_back_engine_first() {
+        let mut g = game(Board::new(), Color::Black);
+        let m = g.board.parse_san("e4").unwrap();
+        g.play(m);
+
+        assert!(g.take_back());
+        assert!(g.moves.is_empty());
+        assert_eq!(Color::White, g.board.current_player());
+    }
+
+    #[test]
+    fn principal_variation() {
+        let mut b = Board::new();
+        let pv: Vec<_> = ["e2e4", "e7e5", "g1f3"]
+            .iter()
+            .map(|m| {
+                let m = b.parse_uci_move(m).unwrap();
+                b.make_move(m);
+                m
+            })
+            .collect();
+
+        let mut b = Board::new();
+        assert_eq!(vec!["e4", "e5", "Nf3"], pv_san(&mut b, &pv));
+
+        // a line that does not fit the position is cut off
+        let mut b = Board::new();
+        assert!(pv_san(&mut b, &pv[1..]).is_empty());
+    }
+
+    #[test]
+    fn book_moves() {
+        let games = parse_pgn("1. e4 e5 2. Nf3 1-0\n\n1. e4 c5 0-1\n").unwrap();
+        let book = Book::from_games(&games, &BookOptions::default());
+        let mut g = game(Board::new(), Color::Black).with_book(book);
+
+        assert_eq!("e2e4", g.book_move().unwrap().to_string());
+        let m = g.board.parse_san("e4").unwrap();
+        g.play(m);
+        let m = g.board.parse_san("e5").unwrap();
+        g.play(m);
+        assert_eq!("g1f3", g.book_move().unwrap().to_string());
+
+        // out of the book the engine searches
+        let m = g.board.parse_san("Nf3").unwrap();
+        g.play(m);
+        let m = g.board.parse_san("Nc6").unwrap();
+        g.play(m);
+        assert_eq!(None, g.book_move());
+        assert_eq!(None, game(Board::new(), Color::Black).book_move());
+    }
+
+    fn game(board: Board, human: Color) -> Game {
+        Game::new(board, human, SearchLimits::default(), Engine::default())
+    }
+
+    fn sq(s: &str) -> Position {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn select_and_move() {
+        let mut g = game(Board::new(), Color::White);
+        let mut r = Renderer::default();
+
+        // empty squares and the opponent's pieces can't be picked up, nor can a blocked rook
+        g.select(sq("e4"));
+        g.select(sq("e7"));
+        assert_eq!(None, g.selected);
+        g.select(sq("a1"));
+        assert_eq!(None, g.selected);
+
+        // the cursor starts on e2, pick up the pawn and see where it can go
+        assert!(g.key(Key::Enter, &mut r));
+        assert_eq!(Some(sq("e2")), g.selected);
+        let mut targets = g.highlights(true).targets;
+        targets.sort_by_key(|p| p.idx());
+        assert_eq!(vec![sq("e3"), sq("e4")], targets);
+
+        // another own piece replaces the selection, a second click on it drops it
+        g.select(sq("g1"));
+        assert_eq!(Some(sq("g1")), g.selected);
+        g.select(sq("g1"));
+        assert_eq!(None, g.selected);
+
+        g.key(Key::Char(' '), &mut r);
+        g.select(sq("e5"));
+        assert!(g.moves.is_empty());
+        assert!(g.message.starts_with("Illegal"));
+
+        // e4 with a click on the fourth row, the fifth column of squares
+        g.key(Key::Click { col: 3 + 4 * 3, row: 5 }, &mut r);
+        assert_eq!(1, g.moves.len());
+        assert_eq!(sq("e4"), g.moves[0].to);
+        assert_eq!(None, g.selected);
+    }
+
+    #[test]
+    fn typed_moves_and_cursor() {
+        let mut g = game(Board::new(), Color::Black);
+        let mut r = Renderer::new(RenderOptions {
+            flipped: true,
+            ..RenderOptions::default()
+        });
+        let m = g.board.parse_san("d4").unwrap();
+        g.play(m);
+
+        // on a flipped board up goes towards the first rank
+        assert_eq!(sq("e7"), g.cursor);
+        g.key(Key::Up, &mut r);
+        g.key(Key::Left, &mut r);
+        assert_eq!(sq("f6"), g.cursor);
+
+        for c in "Nf6x".chars() {
+            g.key(Key::Char(c), &mut r);
+        }
+        g.key(Key::Backspace, &mut r);
+        assert_eq!("Nf6", g.input);
+        g.key(Key::Enter, &mut r);
+        assert_eq!(2, g.moves.len());
+        assert!(g.input.is_empty());
+
+        for c in "quit".chars() {
+            g.key(Key::Char(c), &mut r);
+        }
+        assert!(!g.key(Key::Enter, &mut r));
+    }
+
+    #[test]
+    fn promotion_picker() {
+        let board: Board = "8/4P3/8/8/8/k7/8/K7 w - - 0 1".parse().unwrap();
+        let mut g = game(board, Color::White);
+        let mut r = Renderer::default();
+
+        g.select(sq("e7"));
+        g.select(sq("e8"));
+        assert_eq!(4, g.promotion.len());
+        assert!(g.moves.is_empty());
+
+        // other keys are ignored, escape cancels
+        g.key(Key::Char('x'), &mut r);
+        assert_eq!(4, g.promotion.len());
+        g.key(Key::Esc, &mut r);
+        assert!(g.promotion.is_empty());
+        assert_eq!(None, g.selected);
+
+        g.select(sq("e7"));
+        g.select(sq("e8"));
+        g.key(Key::Char('n'), &mut r);
+        assert!(g.promotion.is_empty());
+        assert_eq!(Some(PieceType::Knight), g.moves[0].promotion);
+    }
+}
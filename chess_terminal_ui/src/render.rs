@@ -0,0 +1,370 @@
+use chess::board::position::Position;
+use chess::board::Board;
+use chess::chess_move::Move;
+use chess::piece::{Color, Piece};
+use std::fmt::Write;
+
+/// How many colors the terminal can show.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ColorMode {
+    None,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorMode {
+    /// Guesses the color support from the usual environment variables.
+    pub fn detect() -> ColorMode {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+
+        if std::env::var_os("NO_COLOR").is_some() {
+            ColorMode::None
+        } else if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit") {
+            ColorMode::TrueColor
+        } else if var("TERM").contains("256color") {
+            ColorMode::Ansi256
+        } else {
+            ColorMode::None
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Shade {
+    Light,
+    Dark,
+    LastMove,
+    Check,
+    Cursor,
+    Selected,
+    Target,
+}
+
+impl Shade {
+    /// RGB for true color and the closest entry of the 256 color palette.
+    fn rgb_and_index(&self) -> ((u8, u8, u8), u8) {
+        match self {
+            Shade::Light => ((240, 217, 181), 223),
+            Shade::Dark => ((181, 136, 99), 137),
+            Shade::LastMove => ((205, 210, 106), 186),
+            Shade::Check => ((224, 80, 80), 167),
+            Shade::Cursor => ((100, 150, 230), 75),
+            Shade::Selected => ((246, 246, 105), 227),
+            Shade::Target => ((130, 190, 100), 107),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RenderOptions {
+    pub color_mode: ColorMode,
+    /// Black at the bottom.
+    pub flipped: bool,
+    /// Letters instead of figurines, for terminals without the chess glyphs.
+    pub ascii: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            color_mode: ColorMode::detect(),
+            flipped: false,
+            ascii: false,
+        }
+    }
+}
+
+/// Squares to mark on the board besides a king in check.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Highlights {
+    pub last_move: Option<Move>,
+    /// The square the cursor keys or the mouse are on.
+    pub cursor: Option<Position>,
+    /// The square of the piece about to move.
+    pub selected: Option<Position>,
+    /// Where the selected piece can go.
+    pub targets: Vec<Position>,
+}
+
+/// Draws boards in place: every frame replaces the previous one, however many lines that had.
+#[derive(Debug, Default)]
+pub struct Renderer {
+    pub options: RenderOptions,
+    height: usize,
+}
+
+impl Renderer {
+    pub fn new(options: RenderOptions) -> Self {
+        Renderer { options, height: 0 }
+    }
+
+    /// The board with coordinates, highlighting the last move and a king in check.
+    pub fn board(&self, board: &Board, last_move: Option<Move>) -> String {
+        self.board_highlighted(
+            board,
+            &Highlights {
+                last_move,
+                ..Highlights::default()
+            },
+        )
+    }
+
+    /// The board with coordinates and the given squares marked.
+    pub fn board_highlighted(&self, board: &Board, highlights: &Highlights) -> String {
+        let mut s = String::new();
+        let checked_king = if board.in_check() {
+            board.king_position(board.current_player())
+        } else {
+            None
+        };
+
+        for row in 0..8u8 {
+            let rank = if self.options.flipped { row } else { 7 - row };
+            write!(s, "{} ", rank + 1).unwrap();
+
+            for col in 0..8u8 {
+                let file = if self.options.flipped { 7 - col } else { col };
+                let pos = Position::from(rank, file);
+
+                let shade = if Some(pos) == highlights.cursor {
+                    Shade::Cursor
+                } else if Some(pos) == highlights.selected {
+                    Shade::Selected
+                } else if Some(pos) == checked_king {
+                    Shade::Check
+                } else if highlights.targets.contains(&pos) {
+                    Shade::Target
+                } else if highlights
+                    .last_move
+                    .is_some_and(|m| m.from == pos || m.to == pos)
+                {
+                    Shade::LastMove
+                } else if (rank + file) % 2 == 0 {
+                    Shade::Dark
+                } else {
+                    Shade::Light
+                };
+
+                self.square(&mut s, board.get_piece(&pos), shade);
+            }
+
+            s.push('\n');
+        }
+
+        s.push_str("  ");
+        for col in 0..8u8 {
+            let file = if self.options.flipped { 7 - col } else { col };
+            write!(s, " {} ", (b'a' + file) as char).unwrap();
+        }
+        s.push('\n');
+
+        s
+    }
+
+    /// Returns `frame` prefixed with the escape codes that move the cursor back over the previous
+    /// frame and clear it.
+    pub fn redraw(&mut self, frame: &str) -> String {
+        let mut s = String::new();
+        if self.height > 0 {
+            write!(s, "\x1b[{}A\r\x1b[J", self.height).unwrap();
+        }
+        s.push_str(frame);
+
+        self.height = frame.matches('\n').count();
+        s
+    }
+
+    /// The square drawn at the given column and row, counted from 1 at the top left corner of
+    /// the board, as in terminal mouse reports.
+    pub fn square_at(&self, col: u16, row: u16) -> Option<Position> {
+        let x = col.checked_sub(3)? / 3;
+        let y = row.checked_sub(1)?;
+        if x >= 8 || y >= 8 {
+            return None;
+        }
+
+        let (rank, file) = (7 - y as u8, x as u8);
+        Some(if self.options.flipped {
+            Position::from(7 - rank, 7 - file)
+        } else {
+            Position::from(rank, file)
+        })
+    }
+
+    fn square(&self, s: &mut String, piece: Option<Piece>, shade: Shade) {
+        let glyph = match piece {
+            None => ' ',
+            Some(p) if self.options.ascii => p.fen_char(),
+            // the filled glyphs read best on colored squares, the foreground gives the color
+            Some(p) if self.options.color_mode != ColorMode::None => {
+                Piece::new(Color::White, p.piece_type).figurine()
+            }
+            Some(p) => p.figurine(),
+        };
+
+        let ((r, g, b), idx) = shade.rgb_and_index();
+        let fg = match piece.map(|p| p.color) {
+            Some(Color::Black) => 16,
+            _ => 231,
+        };
+
+        match self.options.color_mode {
+            ColorMode::None => {
+                let c = match (piece, shade) {
+                    (Some(_), _) => glyph,
+                    (None, Shade::Dark) => '.',
+                    (None, Shade::LastMove) => '*',
+                    (None, Shade::Target) => '+',
+                    (None, _) => ' ',
+                };
+                let (left, right) = match shade {
+                    Shade::Cursor => ('[', ']'),
+                    Shade::Selected => ('(', ')'),
+                    Shade::Target if piece.is_some() => ('+', ' '),
+                    _ => (' ', ' '),
+                };
+                write!(s, "{}{}{}", left, c, right).unwrap();
+            }
+            ColorMode::Ansi256 => {
+                write!(s, "\x1b[48;5;{};38;5;{}m {} \x1b[0m", idx, fg, glyph).unwrap();
+            }
+            ColorMode::TrueColor => {
+                let (fr, fg, fb) = if fg == 16 { (0, 0, 0) } else { (255, 255, 255) };
+                write!(
+                    s,
+                    "\x1b[48;2;{};{};{};38;2;{};{};{}m {} \x1b[0m",
+                    r, g, b, fr, fg, fb, glyph
+                )
+                .unwrap();
+            }
+        }
+    }
+}
+
+/// Joins the lines of the board and a panel next to it.
+pub fn side_by_side(board: &str, panel: &[String]) -> String {
+    let board: Vec<&str> = board.lines().collect();
+    // the coordinate line is the only one without escape codes
+    let width = board.last().map_or(0, |l| l.chars().count());
+
+    let mut frame = String::new();
+    for idx in 0..board.len().max(panel.len()) {
+        match board.get(idx) {
+            Some(line) => frame.push_str(line),
+            None => frame.push_str(&" ".repeat(width)),
+        }
+        if let Some(p) = panel.get(idx) {
+            frame.push_str("   ");
+            frame.push_str(p);
+        }
+        frame.push('\n');
+    }
+
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::{ColorMode, Highlights, RenderOptions, Renderer};
+    use chess::board::position::Position;
+    use chess::board::Board;
+
+    fn renderer(flipped: bool) -> Renderer {
+        Renderer::new(RenderOptions {
+            color_mode: ColorMode::None,
+            flipped,
+            ascii: true,
+        })
+    }
+
+    #[test]
+    fn coordinates_and_flipping() {
+        let b = Board::new();
+
+        let s = renderer(false).board(&b, None);
+        let lines: Vec<&str> = s.lines().collect();
+        assert_eq!(9, lines.len());
+        assert_eq!("8  r  n  b  q  k  b  n  r ", lines[0]);
+        assert_eq!("1  R  N  B  Q  K  B  N  R ", lines[7]);
+        assert_eq!("   a  b  c  d  e  f  g  h ", lines[8]);
+
+        let s = renderer(true).board(&b, None);
+        let lines: Vec<&str> = s.lines().collect();
+        assert_eq!("1  R  N  B  K  Q  B  N  R ", lines[0]);
+        assert_eq!("   h  g  f  e  d  c  b  a ", lines[8]);
+    }
+
+    #[test]
+    fn last_move_highlight() {
+        let mut b = Board::new();
+        let m = b.parse_uci_move("e2e4").unwrap();
+        b.make_move(m);
+
+        let s = renderer(false).board(&b, Some(m));
+        let lines: Vec<&str> = s.lines().collect();
+        assert_eq!("2  P  P  P  P  *  P  P  P ", lines[6]);
+    }
+
+    #[test]
+    fn colors() {
+        let b: Board = "4k3/8/8/8/8/8/8/4K2R b - - 0 1".parse().unwrap();
+        let mut r = renderer(false);
+
+        r.options.color_mode = ColorMode::Ansi256;
+        assert!(r.board(&b, None).contains("\x1b[48;5;223;38;5;231m R \x1b[0m"));
+
+        r.options.color_mode = ColorMode::TrueColor;
+        r.options.ascii = false;
+        assert!(r.board(&b, None).contains("m ♜ \x1b[0m"));
+    }
+
+    #[test]
+    fn check_highlight() {
+        let b: Board = "4k3/8/8/8/8/8/8/4R1K1 b - - 0 1".parse().unwrap();
+        let mut r = renderer(false);
+        r.options.color_mode = ColorMode::Ansi256;
+
+        assert!(r.board(&b, None).contains("\x1b[48;5;167;38;5;16m k "));
+    }
+
+    #[test]
+    fn redraw_adapts_to_height() {
+        let mut r = renderer(false);
+
+        assert_eq!("a\nb\n", r.redraw("a\nb\n"));
+        assert_eq!("\x1b[2A\r\x1b[Jc\nd\ne\n", r.redraw("c\nd\ne\n"));
+        assert_eq!("\x1b[3A\r\x1b[Jf\n", r.redraw("f\n"));
+    }
+
+    #[test]
+    fn selection_highlights() {
+        let b = Board::new();
+        let highlights = Highlights {
+            cursor: Some("e2".parse().unwrap()),
+            selected: Some("g1".parse().unwrap()),
+            targets: vec!["f3".parse().unwrap(), "h3".parse().unwrap()],
+            ..Highlights::default()
+        };
+
+        let s = renderer(false).board_highlighted(&b, &highlights);
+        let lines: Vec<&str> = s.lines().collect();
+        assert_eq!("3  .     .     .  +  .  + ", lines[5]);
+        assert_eq!("2  P  P  P  P [P] P  P  P ", lines[6]);
+        assert_eq!("1  R  N  B  Q  K  B (N) R ", lines[7]);
+    }
+
+    #[test]
+    fn squares_on_screen() {
+        let r = renderer(false);
+
+        assert_eq!(Some(Position::from(7, 0)), r.square_at(3, 1));
+        assert_eq!(Some(Position::from(7, 0)), r.square_at(5, 1));
+        assert_eq!(Some(Position::from(0, 7)), r.square_at(26, 8));
+        assert_eq!(None, r.square_at(2, 1));
+        assert_eq!(None, r.square_at(27, 1));
+        assert_eq!(None, r.square_at(3, 9));
+
+        let r = renderer(true);
+        assert_eq!(Some(Position::from(0, 7)), r.square_at(3, 1));
+    }
+}
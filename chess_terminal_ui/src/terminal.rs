@@ -0,0 +1,194 @@
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+/// A key press read from the terminal.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    Enter,
+    Backspace,
+    Esc,
+    Char(char),
+    /// A press of the left mouse button, columns and rows counted from 1.
+    Click { col: u16, row: u16 },
+}
+
+/// Switches the terminal to unbuffered input without echo until dropped, using `stty`.
+pub struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    pub fn enable() -> std::io::Result<RawMode> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "min", "1"])?;
+
+        Ok(RawMode {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// Switches to the alternate screen and turns on mouse reporting until dropped.
+pub struct FullScreen;
+
+impl FullScreen {
+    pub fn enable() -> std::io::Result<FullScreen> {
+        let mut stdout = std::io::stdout();
+        // alternate screen, report button presses, in the SGR format
+        write!(stdout, "\x1b[?1049h\x1b[?1000h\x1b[?1006h")?;
+        stdout.flush()?;
+
+        Ok(FullScreen)
+    }
+
+    /// The escape codes to draw `frame` over the whole screen.
+    pub fn frame(frame: &str) -> String {
+        format!("\x1b[H\x1b[J{}", frame)
+    }
+}
+
+impl Drop for FullScreen {
+    fn drop(&mut self) {
+        let mut stdout = std::io::stdout();
+        let _ = write!(stdout, "\x1b[?1006l\x1b[?1000l\x1b[?1049l");
+        let _ = stdout.flush();
+    }
+}
+
+fn stty(args: &[&str]) -> std::io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Reads the next batch of keys, blocking until at least one byte arrives. Empty at the end of
+/// input.
+pub fn read_keys() -> std::io::Result<Vec<Key>> {
+    let mut buf = [0; 64];
+    let n = std::io::stdin().lock().read(&mut buf)?;
+
+    Ok(parse_keys(&buf[..n]))
+}
+
+/// Decodes the bytes a terminal sends for key presses, including the escape sequences of the
+/// cursor keys.
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut rest = bytes;
+
+    while !rest.is_empty() {
+        let (key, len) = match rest {
+            [0x1b, b'[', b'<', ..] => {
+                let len = escape_len(rest);
+                (parse_mouse(&rest[3..len]), len)
+            }
+            [0x1b, b'[' | b'O', c, ..] => match c {
+                b'A' => (Some(Key::Up), 3),
+                b'B' => (Some(Key::Down), 3),
+                b'C' => (Some(Key::Right), 3),
+                b'D' => (Some(Key::Left), 3),
+                b'H' => (Some(Key::Home), 3),
+                b'F' => (Some(Key::End), 3),
+                _ => (None, escape_len(rest)),
+            },
+            [0x1b, ..] => (Some(Key::Esc), 1),
+            [b'\r' | b'\n', ..] => (Some(Key::Enter), 1),
+            [0x7f | 0x08, ..] => (Some(Key::Backspace), 1),
+            _ => {
+                let len = utf8_len(rest[0]).min(rest.len());
+                let c = std::str::from_utf8(&rest[..len])
+                    .ok()
+                    .and_then(|s| s.chars().next());
+                (c.map(Key::Char), len)
+            }
+        };
+
+        keys.extend(key);
+        rest = &rest[len..];
+    }
+
+    keys
+}
+
+/// Reads the parameters `button;col;row` and the final `M` (press) or `m` (release) of an SGR
+/// mouse report.
+fn parse_mouse(report: &[u8]) -> Option<Key> {
+    let (last, params) = report.split_last()?;
+    let params = std::str::from_utf8(params).ok()?;
+    let mut numbers = params.split(';').map(|n| n.parse::<u16>().ok());
+
+    match (numbers.next()??, numbers.next()??, numbers.next()??, last) {
+        (0, col, row, b'M') => Some(Key::Click { col, row }),
+        _ => None,
+    }
+}
+
+/// The length of an escape sequence like `ESC [ 1 ; 5 C`, which ends in a byte from `@` to `~`.
+fn escape_len(bytes: &[u8]) -> usize {
+    bytes[2..]
+        .iter()
+        .position(|b| (0x40..=0x7e).contains(b))
+        .map_or(bytes.len(), |p| p + 3)
+}
+
+fn utf8_len(first: u8) -> usize {
+    match first {
+        0xf0.. => 4,
+        0xe0.. => 3,
+        0xc0.. => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terminal::{parse_keys, Key};
+
+    #[test]
+    fn keys() {
+        assert_eq!(
+            vec![Key::Up, Key::Right, Key::Char('q'), Key::Enter],
+            parse_keys(b"\x1b[A\x1b[Cq\n")
+        );
+        assert_eq!(vec![Key::Esc], parse_keys(b"\x1b"));
+        assert_eq!(vec![Key::Home, Key::End], parse_keys(b"\x1bOH\x1b[F"));
+        assert_eq!(vec![Key::Char('é'), Key::Backspace], parse_keys("é\x7f".as_bytes()));
+    }
+
+    #[test]
+    fn mouse() {
+        assert_eq!(
+            vec![Key::Click { col: 12, row: 3 }, Key::Char('a')],
+            parse_keys(b"\x1b[<0;12;3M\x1b[<0;12;3ma")
+        );
+        // right button and wheel
+        assert!(parse_keys(b"\x1b[<2;1;1M\x1b[<64;1;1M").is_empty());
+    }
+
+    #[test]
+    fn unknown_sequences_are_skipped() {
+        // page down and ctrl+right
+        assert_eq!(vec![Key::Char('x')], parse_keys(b"\x1b[6~\x1b[1;5Cx"));
+    }
+}
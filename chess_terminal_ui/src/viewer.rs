@@ -0,0 +1,536 @@
+use crate::render::{side_by_side, Renderer};
+use crate::terminal::{read_keys, Key, RawMode};
+use chess::board::Board;
+use chess::chess_move::Move;
+use chess::pgn::{nag_symbol, PgnGame, PgnNode};
+use std::io::Write;
+
+struct TreeNode {
+    mv: Move,
+    san: String,
+    nags: Vec<u8>,
+    comment: Option<String>,
+    parent: Option<usize>,
+    /// The main continuation first, then the variations.
+    children: Vec<usize>,
+    /// The ply of the position the move was played in.
+    ply: u32,
+}
+
+/// The moves of a game with all variations, checked against the rules.
+pub struct GameTree {
+    start: Board,
+    nodes: Vec<TreeNode>,
+    roots: Vec<usize>,
+}
+
+impl GameTree {
+    pub fn from_game(game: &PgnGame) -> Result<GameTree, String> {
+        let start = game.start_position().map_err(|e| e.to_string())?;
+        let mut tree = GameTree {
+            start: start.clone(),
+            nodes: Vec::new(),
+            roots: Vec::new(),
+        };
+
+        let mut board = start;
+        tree.add_line(&mut board, &game.main_line, None)?;
+        Ok(tree)
+    }
+
+    /// Adds the moves of `line` after `parent`, the board is left as it was passed in.
+    fn add_line(
+        &mut self,
+        board: &mut Board,
+        line: &[PgnNode],
+        mut parent: Option<usize>,
+    ) -> Result<(), String> {
+        let mut played = Vec::with_capacity(line.len());
+
+        for node in line {
+            let m = match board.parse_san(&node.san) {
+                Some(m) => m,
+                None => {
+                    for &m in played.iter().rev() {
+                        board.unmake_move(m);
+                    }
+                    return Err(format!("illegal or ambiguous move '{}'", node.san));
+                }
+            };
+
+            let idx = self.nodes.len();
+            self.nodes.push(TreeNode {
+                mv: m,
+                san: board.san(m),
+                nags: node.nags.clone(),
+                comment: node.comment.clone(),
+                parent,
+                children: Vec::new(),
+                ply: board.ply(),
+            });
+            match parent {
+                Some(p) => self.nodes[p].children.push(idx),
+                None => self.roots.push(idx),
+            }
+
+            // the variations replace this move, so they start from the same position
+            for variation in &node.variations {
+                self.add_line(board, variation, parent)?;
+            }
+
+            board.make_move(m);
+            played.push(m);
+            parent = Some(idx);
+        }
+
+        for &m in played.iter().rev() {
+            board.unmake_move(m);
+        }
+        Ok(())
+    }
+
+    fn children(&self, node: Option<usize>) -> &[usize] {
+        match node {
+            Some(idx) => &self.nodes[idx].children,
+            None => &self.roots,
+        }
+    }
+
+    fn parent(&self, node: Option<usize>) -> Option<usize> {
+        node.and_then(|idx| self.nodes[idx].parent)
+    }
+
+    /// The moves from the start to `node`.
+    fn path(&self, node: Option<usize>) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut current = node;
+
+        while let Some(idx) = current {
+            path.push(idx);
+            current = self.nodes[idx].parent;
+        }
+
+        path.reverse();
+        path
+    }
+
+    /// The line through `node`: the moves leading to it followed by the main continuation.
+    fn line(&self, node: Option<usize>) -> Vec<usize> {
+        let mut line = self.path(node);
+        let mut current = node;
+
+        while let Some(&next) = self.children(current).first() {
+            line.push(next);
+            current = Some(next);
+        }
+
+        line
+    }
+}
+
+/// Stepping through a game. The current node is the last move played, `None` at the start.
+pub struct Viewer {
+    tree: GameTree,
+    headers: Vec<(String, String)>,
+    current: Option<usize>,
+    /// Digits typed so far for jumping to a move number.
+    jump_to: String,
+}
+
+impl Viewer {
+    pub fn new(game: &PgnGame) -> Result<Viewer, String> {
+        Ok(Viewer {
+            tree: GameTree::from_game(game)?,
+            headers: game.headers.clone(),
+            current: None,
+            jump_to: String::new(),
+        })
+    }
+
+    pub fn next(&mut self) {
+        if let Some(&next) = self.tree.children(self.current).first() {
+            self.current = Some(next);
+        }
+    }
+
+    pub fn prev(&mut self) {
+        self.current = self.tree.parent(self.current);
+    }
+
+    pub fn first(&mut self) {
+        self.current = None;
+    }
+
+    pub fn last(&mut self) {
+        self.current = self.tree.line(self.current).last().copied();
+    }
+
+    /// Switches to the next alternative to the current move, i.e. from the main line into the
+    /// first variation and from there on to the following ones.
+    pub fn enter_variation(&mut self) {
+        let siblings = self.tree.children(self.tree.parent(self.current));
+
+        if let Some(pos) = siblings.iter().position(|&s| Some(s) == self.current) {
+            if let Some(&next) = siblings.get(pos + 1) {
+                self.current = Some(next);
+            }
+        }
+    }
+
+    /// Goes back to where the current variation branched off.
+    pub fn exit_variation(&mut self) {
+        while let Some(idx) = self.current {
+            let parent = self.tree.nodes[idx].parent;
+            self.current = parent;
+
+            if self.tree.children(parent).first() != Some(&idx) {
+                return;
+            }
+        }
+    }
+
+    /// Goes to the position after white's move `number` in the current line, or the closest one.
+    pub fn jump(&mut self, number: u32) {
+        let target = (number.max(1) - 1) * 2;
+        let line = self.tree.line(self.current);
+
+        self.current = line
+            .iter()
+            .find(|&&idx| self.tree.nodes[idx].ply >= target)
+            .or(line.last())
+            .copied();
+    }
+
+    /// The position at the current node.
+    pub fn board(&self) -> Board {
+        let mut board = self.tree.start.clone();
+
+        for idx in self.tree.path(self.current) {
+            board.make_move(self.tree.nodes[idx].mv);
+        }
+
+        board
+    }
+
+    fn header(&self, name: &str) -> &str {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map_or("?", |(_, v)| v.as_str())
+    }
+
+    /// A move with its number and annotations, e.g. `12... Nf6!?`.
+    fn move_text(&self, idx: usize, with_number: bool) -> String {
+        let node = &self.tree.nodes[idx];
+        let mut s = String::new();
+
+        if node.ply.is_multiple_of(2) {
+            s.push_str(&format!("{}. ", node.ply / 2 + 1));
+        } else if with_number {
+            s.push_str(&format!("{}... ", node.ply / 2 + 1));
+        }
+        s.push_str(&node.san);
+        for &nag in &node.nags {
+            s.push_str(&nag_symbol(nag));
+        }
+
+        s
+    }
+
+    /// The side panel: headers, the current line with the current move in brackets, its comment
+    /// and the variations to the next move.
+    pub fn panel(&self, width: usize) -> Vec<String> {
+        let mut lines = vec![
+            format!(
+                "{} - {}  {}",
+                self.header("White"),
+                self.header("Black"),
+                self.header("Result")
+            ),
+            format!("{}, {}", self.header("Event"), self.header("Date")),
+            String::new(),
+        ];
+
+        let mut moves = Vec::new();
+        for (i, &idx) in self.tree.line(self.current).iter().enumerate() {
+            let text = self.move_text(idx, i == 0);
+            moves.push(if Some(idx) == self.current {
+                format!("[{}]", text)
+            } else {
+                text
+            });
+        }
+        lines.extend(wrap(&moves.join(" "), width));
+
+        if let Some(comment) = self.current.and_then(|idx| self.tree.nodes[idx].comment.as_ref()) {
+            lines.push(String::new());
+            lines.extend(wrap(comment, width));
+        }
+
+        let next = self.tree.children(self.current);
+        if next.len() > 1 {
+            lines.push(String::new());
+            let variations: Vec<String> = next[1..]
+                .iter()
+                .map(|&idx| self.move_text(idx, true))
+                .collect();
+            lines.push(format!("Variations: {}", variations.join(", ")));
+        }
+
+        lines
+    }
+
+    /// Handles a key, returns false when the viewer should close.
+    fn key(&mut self, key: Key, renderer: &mut Renderer) -> bool {
+        match key {
+            Key::Right => self.next(),
+            Key::Left => self.prev(),
+            Key::Down => self.enter_variation(),
+            Key::Up => self.exit_variation(),
+            Key::Home => self.first(),
+            Key::End => self.last(),
+            Key::Char(c) if c.is_ascii_digit() => self.jump_to.push(c),
+            Key::Backspace => {
+                self.jump_to.pop();
+            }
+            Key::Enter => {
+                if let Ok(n) = self.jump_to.parse() {
+                    self.jump(n);
+                }
+                self.jump_to.clear();
+            }
+            Key::Char('f') => renderer.options.flipped = !renderer.options.flipped,
+            Key::Char('q') | Key::Esc => return false,
+            _ => {}
+        }
+
+        true
+    }
+
+    fn frame(&self, renderer: &Renderer) -> String {
+        let board = self.board();
+        let last_move = self.current.map(|idx| self.tree.nodes[idx].mv);
+
+        let mut frame = side_by_side(&renderer.board(&board, last_move), &self.panel(48));
+        if self.jump_to.is_empty() {
+            frame.push_str("←/→ moves  ↓ next variation  ↑ leave variation  Home/End  f flip  q back\n");
+        } else {
+            frame.push_str(&format!("Go to move: {}\n", self.jump_to));
+        }
+
+        frame
+    }
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// The list of games to pick from.
+fn game_list(games: &[PgnGame], selected: usize) -> String {
+    let mut s = String::new();
+
+    for (idx, game) in games.iter().enumerate() {
+        let header = |name| game.header(name).unwrap_or("?");
+        s.push_str(&format!(
+            "{} {:>3}. {} - {}  {}  {}, {}\n",
+            if idx == selected { '>' } else { ' ' },
+            idx + 1,
+            header("White"),
+            header("Black"),
+            header("Result"),
+            header("Event"),
+            header("Date"),
+        ));
+    }
+    s.push_str("↑/↓ select  Enter open  q quit\n");
+
+    s
+}
+
+/// Browses the games with the cursor keys. A single game is opened right away.
+pub fn run(games: &[PgnGame], renderer: &mut Renderer) -> std::io::Result<()> {
+    let _raw = RawMode::enable()?;
+    let mut stdout = std::io::stdout();
+    let mut selected = 0;
+    let mut viewer: Option<Viewer> = None;
+    let mut message = String::new();
+
+    if games.len() == 1 {
+        match Viewer::new(&games[0]) {
+            Ok(v) => viewer = Some(v),
+            Err(e) => message = e,
+        }
+    }
+
+    loop {
+        let frame = match &viewer {
+            Some(v) => v.frame(renderer),
+            None => format!("{}{}\n", game_list(games, selected), message),
+        };
+        write!(stdout, "{}", renderer.redraw(&frame))?;
+        stdout.flush()?;
+
+        let keys = read_keys()?;
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        for key in keys {
+            match &mut viewer {
+                Some(v) => {
+                    if !v.key(key, renderer) {
+                        if games.len() == 1 {
+                            return Ok(());
+                        }
+                        viewer = None;
+                    }
+                }
+                None => match key {
+                    Key::Up => selected = selected.saturating_sub(1),
+                    Key::Down => selected = (selected + 1).min(games.len().saturating_sub(1)),
+                    Key::Enter if !games.is_empty() => match Viewer::new(&games[selected]) {
+                        Ok(v) => {
+                            viewer = Some(v);
+                            message.clear();
+                        }
+                        Err(e) => message = e,
+                    },
+                    Key::Char('q') | Key::Esc => return Ok(()),
+                    _ => {}
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::viewer::{wrap, Viewer};
+    use chess::board::Board;
+    use chess::pgn::parse_pgn;
+
+    const GAME: &str = r#"[White "A"] [Black "B"] [Result "*"]
+1. e4 e5 {Open game} (1... c5 2. Nf3 (2. Nc3 Nc6) d6) (1... e6) 2. Nf3 Nc6 3. Bb5 a6!? *"#;
+
+    fn viewer() -> Viewer {
+        Viewer::new(&parse_pgn(GAME).unwrap()[0]).unwrap()
+    }
+
+    fn current(v: &Viewer) -> String {
+        v.current.map_or(String::new(), |idx| v.tree.nodes[idx].san.clone())
+    }
+
+    #[test]
+    fn stepping() {
+        let mut v = viewer();
+
+        v.prev();
+        assert_eq!("", current(&v));
+        v.next();
+        v.next();
+        assert_eq!("e5", current(&v));
+        v.last();
+        assert_eq!("a6", current(&v));
+        v.prev();
+        assert_eq!("Bb5", current(&v));
+        v.first();
+        assert_eq!(Board::new().hash(), v.board().hash());
+    }
+
+    #[test]
+    fn variations() {
+        let mut v = viewer();
+        v.next();
+        v.next();
+
+        v.enter_variation();
+        assert_eq!("c5", current(&v));
+        v.enter_variation();
+        assert_eq!("e6", current(&v));
+        v.enter_variation();
+        assert_eq!("e6", current(&v));
+
+        v.exit_variation();
+        assert_eq!("e4", current(&v));
+
+        v.next();
+        v.enter_variation();
+        v.next();
+        v.enter_variation();
+        assert_eq!("Nc3", current(&v));
+        v.next();
+        assert_eq!("Nc6", current(&v));
+
+        // leaves the inner and then the outer variation
+        v.exit_variation();
+        assert_eq!("c5", current(&v));
+        v.exit_variation();
+        assert_eq!("e4", current(&v));
+        v.exit_variation();
+        assert_eq!("", current(&v));
+    }
+
+    #[test]
+    fn jump() {
+        let mut v = viewer();
+
+        v.jump(3);
+        assert_eq!("Bb5", current(&v));
+        v.jump(99);
+        assert_eq!("a6", current(&v));
+
+        // jumps stay in the current variation
+        v.first();
+        v.next();
+        v.next();
+        v.enter_variation();
+        v.jump(2);
+        assert_eq!("Nf3", current(&v));
+        assert_eq!(3, v.board().ply());
+    }
+
+    #[test]
+    fn panel() {
+        let mut v = viewer();
+        v.next();
+        v.next();
+
+        let panel = v.panel(80);
+        assert_eq!("A - B  *", panel[0]);
+        assert_eq!("1. e4 [e5] 2. Nf3 Nc6 3. Bb5 a6!?", panel[3]);
+        assert_eq!("Open game", panel[5]);
+
+        v.prev();
+        let panel = v.panel(80);
+        assert_eq!("Variations: 1... c5, 1... e6", panel[5]);
+    }
+
+    #[test]
+    fn illegal_moves() {
+        let games = parse_pgn("1. e4 (1. e5) *").unwrap();
+        assert!(Viewer::new(&games[0]).is_err());
+    }
+
+    #[test]
+    fn wrapping() {
+        assert_eq!(vec!["ab cd", "ef"], wrap("ab cd ef", 5));
+        assert_eq!(vec!["abcdefg", "h"], wrap("abcdefg h", 5));
+    }
+}
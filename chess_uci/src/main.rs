@@ -0,0 +1,33 @@
+use std::io::{BufRead, Write};
+use std::sync::mpsc;
+use std::thread;
+use uci::Uci;
+
+mod uci;
+
+fn main() {
+    let (tx, rx) = mpsc::channel::<String>();
+    let printer = thread::spawn(move || {
+        let mut stdout = std::io::stdout();
+        for line in rx {
+            if writeln!(stdout, "{}", line)
+                .and_then(|_| stdout.flush())
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    let mut uci = Uci::new(tx);
+    for line in std::io::stdin().lock().lines() {
+        match line {
+            Ok(line) if uci.handle(&line) => {}
+            _ => break,
+        }
+    }
+
+    // the printer ends once the engine and with it the last sender is gone
+    drop(uci);
+    let _ = printer.join();
+}
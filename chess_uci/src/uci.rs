@@ -0,0 +1,682 @@
+use chess::board::Board;
+use chess::chess_move::Move;
+use chess::evaluation::EvalParams;
+#[cfg(feature = "nnue")]
+use chess::nnue::Network;
+use chess::piece::Color;
+use chess::polyglot::Book;
+use chess::search::time::TimeLeft;
+use chess::search::{Score, Search, SearchInfo, SearchLimits, SearchResult};
+#[cfg(feature = "syzygy")]
+use chess::syzygy::Tablebase;
+use chess::uci_client::GoParams;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NAME: &str = "chess";
+const AUTHOR: &str = "the chess authors";
+
+const OPTIONS: [&str; 8] = [
+    "Hash type spin default 16 min 1 max 4096",
+    "Ponder type check default false",
+    "Threads type spin default 1 min 1 max 256",
+    "MultiPV type spin default 1 min 1 max 256",
+    "Skill Level type spin default 20 min 0 max 20",
+    "OwnBook type check default false",
+    "Book File type string default <empty>",
+    "EvalWeights type string default <empty>",
+];
+
+/// The engine side of the Universal Chess Interface. Searches run on their own thread, so that
+/// commands like `stop` and `isready` are answered while searching.
+pub struct Uci {
+    out: Sender<String>,
+    board: Board,
+    /// `None` while the search thread has it.
+    search: Option<Search>,
+    thread: Option<JoinHandle<Search>>,
+    /// The stop flag of the search.
+    stop: Arc<AtomicBool>,
+    /// Set by `stop`, an infinite search doesn't answer before it.
+    stop_requested: Arc<AtomicBool>,
+    /// The ponder flag of the search, set by `go ponder` until `ponderhit`.
+    pondering: Arc<AtomicBool>,
+    hash_size: usize,
+    multi_pv: usize,
+    skill_level: u8,
+    /// Whether to play from `book` before searching.
+    own_book: bool,
+    book: Option<Book>,
+    #[cfg(feature = "nnue")]
+    network: Option<Arc<Network>>,
+    #[cfg(feature = "syzygy")]
+    tablebase: Option<Arc<Tablebase>>,
+}
+
+impl Uci {
+    /// An engine that writes its answers, a line each, to `out`.
+    pub fn new(out: Sender<String>) -> Uci {
+        let search = Search::new();
+
+        Uci {
+            out,
+            board: Board::new(),
+            stop: search.stop_handle(),
+            pondering: search.ponder_handle(),
+            search: Some(search),
+            thread: None,
+            stop_requested: Arc::new(AtomicBool::new(false)),
+            hash_size: 16,
+            multi_pv: 1,
+            skill_level: 20,
+            own_book: false,
+            book: None,
+            #[cfg(feature = "nnue")]
+            network: None,
+            #[cfg(feature = "syzygy")]
+            tablebase: None,
+        }
+    }
+
+    /// Handles a command of the GUI, returning `false` once it asked to quit.
+    pub fn handle(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+
+        match command {
+            "uci" => {
+                self.send(format!("id name {}", NAME));
+                self.send(format!("id author {}", AUTHOR));
+                for option in OPTIONS {
+                    self.send(format!("option name {}", option));
+                }
+                #[cfg(feature = "nnue")]
+                self.send(String::from("option name EvalFile type string default <empty>"));
+                #[cfg(feature = "syzygy")]
+                self.send(String::from("option name SyzygyPath type string default <empty>"));
+                self.send(String::from("uciok"));
+            }
+            "isready" => self.send(String::from("readyok")),
+            "setoption" => self.set_option(args),
+            "ucinewgame" => {
+                self.wait();
+                if let Some(search) = &mut self.search {
+                    search.clear();
+                }
+            }
+            "position" => self.position(args),
+            "go" => self.go(line),
+            "ponderhit" => self.pondering.store(false, Ordering::Relaxed),
+            "stop" => self.wait(),
+            "quit" => {
+                self.wait();
+                return false;
+            }
+            "" => {}
+            _ => self.send(format!("info string unknown command '{}'", command)),
+        }
+
+        true
+    }
+
+    fn send(&self, line: String) {
+        let _ = self.out.send(line);
+    }
+
+    /// Reads `name <NAME> value <VALUE>`, the name may have spaces.
+    fn set_option(&mut self, args: &str) {
+        let Some(rest) = args.trim().strip_prefix("name ") else {
+            return self.send(format!("info string invalid option '{}'", args));
+        };
+        let (name, value) = match rest.split_once(" value ") {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (rest.trim(), ""),
+        };
+
+        // the settings can't change under a running search
+        self.wait();
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        let number = value.parse::<usize>().ok();
+        match (name.to_ascii_lowercase().as_str(), number) {
+            // the GUI decides whether to ponder, the engine needn't know
+            ("ponder", None) if matches!(value, "true" | "false") => {}
+            ("hash", Some(mb)) => {
+                self.hash_size = mb.clamp(1, 4096);
+                search.set_hash_size(self.hash_size);
+            }
+            ("threads", Some(n)) => search.set_threads(n.clamp(1, 256)),
+            ("multipv", Some(n)) => self.multi_pv = n.clamp(1, 256),
+            ("skill level", Some(n)) => self.skill_level = n.min(20) as u8,
+            ("ownbook", None) if matches!(value, "true" | "false") => {
+                self.own_book = value == "true"
+            }
+            ("book file", _) => self.load_book(value),
+            // a weights file written by the tuner
+            ("evalweights", _) if value.is_empty() || value == "<empty>" => {
+                search.set_eval_params(EvalParams::DEFAULT)
+            }
+            ("evalweights", _) => match EvalParams::load(value) {
+                Ok(params) => {
+                    search.set_eval_params(params);
+                    self.send(format!("info string loaded {}", value));
+                }
+                Err(e) => self.send(format!("info string invalid weights: {}", e)),
+            },
+            #[cfg(feature = "nnue")]
+            ("evalfile", _) => self.load_network(value),
+            #[cfg(feature = "syzygy")]
+            ("syzygypath", _) => self.load_tablebase(value),
+            _ => self.send(format!(
+                "info string invalid option '{}' = '{}'",
+                name, value
+            )),
+        }
+    }
+
+    /// Reads `startpos` or `fen <FEN>`, optionally followed by `moves` and the moves played.
+    fn position(&mut self, args: &str) {
+        let (setup, moves) = match args.split_once("moves") {
+            Some((setup, moves)) => (setup.trim(), moves.split_whitespace().collect()),
+            None => (args.trim(), Vec::new()),
+        };
+        let fen = match setup.strip_prefix("fen") {
+            Some(fen) => fen.trim().to_string(),
+            None if setup == "startpos" => Board::new().to_fen(),
+            None => return self.send(format!("info string invalid position '{}'", args)),
+        };
+
+        match Board::from_fen_and_moves(&fen, moves) {
+            Ok(board) => {
+                self.board = board;
+                #[cfg(feature = "nnue")]
+                self.board.set_network(self.network.clone());
+                #[cfg(feature = "syzygy")]
+                self.board.set_tablebase(self.tablebase.clone());
+            }
+            Err(e) => self.send(format!("info string invalid position: {}", e)),
+        }
+    }
+
+    /// Plays from the Polyglot book in the file at `path` from now on, or from no book if the
+    /// path is empty. A book that doesn't load keeps the current one.
+    fn load_book(&mut self, path: &str) {
+        if path.is_empty() || path == "<empty>" {
+            self.book = None;
+            return;
+        }
+
+        match Book::open(path) {
+            Ok(book) => {
+                self.send(format!(
+                    "info string loaded {} with {} entries",
+                    path,
+                    book.len()
+                ));
+                self.book = Some(book);
+            }
+            Err(e) => self.send(format!("info string invalid book: {}: {}", path, e)),
+        }
+    }
+
+    /// A move of the book for a search with `params`. Analysis, pondering and restricted
+    /// searches don't use the book.
+    fn book_move(&mut self, params: &GoParams) -> Option<Move> {
+        if !self.own_book || params.infinite || params.ponder || !params.searchmoves.is_empty() {
+            return None;
+        }
+        let random = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+
+        self.book.as_ref()?.choose_move(&mut self.board, random)
+    }
+
+    /// Evaluates with the network in the file at `path` from now on, or with the handcrafted
+    /// evaluation if the path is empty. A network that doesn't load keeps the current one.
+    #[cfg(feature = "nnue")]
+    fn load_network(&mut self, path: &str) {
+        if path.is_empty() || path == "<empty>" {
+            self.network = None;
+        } else {
+            match Network::load(path) {
+                Ok(network) => {
+                    self.send(format!(
+                        "info string loaded {} with {} hidden neurons",
+                        path,
+                        network.hidden_size()
+                    ));
+                    self.network = Some(Arc::new(network));
+                }
+                Err(e) => return self.send(format!("info string invalid network: {}", e)),
+            }
+        }
+        self.board.set_network(self.network.clone());
+    }
+
+    /// Probes the Syzygy tables in the directories of `paths`, separated like in `PATH`, from
+    /// now on, or no tables if it's empty. Tables that don't load keep the current ones.
+    #[cfg(feature = "syzygy")]
+    fn load_tablebase(&mut self, paths: &str) {
+        if paths.is_empty() || paths == "<empty>" {
+            self.tablebase = None;
+        } else {
+            let mut tablebase = Tablebase::default();
+            for dir in std::env::split_paths(paths) {
+                if let Err(e) = tablebase.add_directory(&dir) {
+                    let dir = dir.display();
+                    return self.send(format!("info string invalid tablebase: {}: {}", dir, e));
+                }
+            }
+            self.send(format!(
+                "info string loaded {} tables of up to {} pieces",
+                tablebase.len(),
+                tablebase.max_pieces()
+            ));
+            self.tablebase = Some(Arc::new(tablebase));
+        }
+        self.board.set_tablebase(self.tablebase.clone());
+    }
+
+    fn go(&mut self, line: &str) {
+        self.wait();
+        let Some(params) = GoParams::parse(line, &self.board) else {
+            return;
+        };
+        if let Some(m) = self.book_move(&params) {
+            return self.send(format!("bestmove {}", m));
+        }
+        let Some(mut search) = self.search.take() else {
+            return;
+        };
+
+        let limits = SearchLimits {
+            depth: params.depth,
+            time: params.movetime.filter(|_| !params.infinite),
+            clock: time_left(&params, self.board.current_player()),
+            nodes: params.nodes,
+            skill_level: (self.skill_level < 20).then_some(self.skill_level),
+            multi_pv: Some(self.multi_pv),
+            search_moves: params.searchmoves.clone(),
+        };
+        let infinite = params.infinite;
+        let pondering = self.pondering.clone();
+        pondering.store(params.ponder, Ordering::Relaxed);
+        let mut board = self.board.clone();
+        let out = self.out.clone();
+        let stop_requested = self.stop_requested.clone();
+        stop_requested.store(false, Ordering::Relaxed);
+
+        self.thread = Some(thread::spawn(move || {
+            let result = search.run(&mut board, limits, |info| {
+                let _ = out.send(info_line(info));
+            });
+
+            // the GUI expects no best move before it stops an infinite search or a ponder miss
+            while (infinite || pondering.load(Ordering::Relaxed))
+                && !stop_requested.load(Ordering::Relaxed)
+            {
+                thread::sleep(Duration::from_millis(1));
+            }
+            let _ = out.send(best_move_line(&result));
+            search
+        }));
+    }
+
+    /// Stops a running search and waits for its best move.
+    fn wait(&mut self) {
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+
+        self.stop_requested.store(true, Ordering::Relaxed);
+        // the search clears its stop flag when it starts, so it is set until the thread ends
+        while !thread.is_finished() {
+            self.stop.store(true, Ordering::Relaxed);
+            thread::sleep(Duration::from_millis(1));
+        }
+        self.search = thread.join().ok();
+    }
+}
+
+/// The clock of the side to move, `None` if it doesn't play on one.
+fn time_left(params: &GoParams, color: Color) -> Option<TimeLeft> {
+    let (time, inc) = match color {
+        Color::White => (params.wtime, params.winc),
+        Color::Black => (params.btime, params.binc),
+    };
+
+    time.filter(|_| !params.infinite).map(|remaining| TimeLeft {
+        remaining,
+        increment: inc.unwrap_or_default(),
+        moves_to_go: params.movestogo,
+    })
+}
+
+fn info_line(info: &SearchInfo) -> String {
+    let score = match info.score {
+        Score::Cp(cp) => format!("cp {}", cp),
+        Score::Mate(n) => format!("mate {}", n),
+    };
+    let pv: Vec<String> = info.pv.iter().map(|m| m.to_string()).collect();
+
+    format!(
+        "info depth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+        info.depth,
+        info.multipv,
+        score,
+        info.nodes,
+        info.nps(),
+        info.time.as_millis(),
+        pv.join(" ")
+    )
+}
+
+fn best_move_line(result: &SearchResult) -> String {
+    match (result.best_move, result.ponder) {
+        (Some(m), Some(p)) => format!("bestmove {} ponder {}", m, p),
+        (Some(m), None) => format!("bestmove {}", m),
+        (None, _) => String::from("bestmove 0000"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::uci::{time_left, Uci};
+    use chess::board::Board;
+    use chess::evaluation::EvalParams;
+    use chess::pgn::parse_pgn;
+    use chess::piece::{Color, PieceType};
+    use chess::polyglot::{Book, BookOptions};
+    use chess::search::time::TimeLeft;
+    use chess::uci_client::{BestMove, GoParams};
+    use std::sync::mpsc::{self, Receiver};
+    use std::time::Duration;
+
+    fn engine() -> (Uci, Receiver<String>) {
+        let (tx, rx) = mpsc::channel();
+        (Uci::new(tx), rx)
+    }
+
+    /// The lines sent up to and including the first one starting with `prefix`.
+    fn until(rx: &Receiver<String>, prefix: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        loop {
+            let line = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+            let done = line.starts_with(prefix);
+            lines.push(line);
+            if done {
+                return lines;
+            }
+        }
+    }
+
+    #[test]
+    fn handshake_and_options() {
+        let (mut uci, rx) = engine();
+
+        assert!(uci.handle("uci"));
+        let lines = until(&rx, "uciok");
+        assert_eq!("id name chess", lines[0]);
+        assert!(
+            lines.contains(&"option name Threads type spin default 1 min 1 max 256".to_string())
+        );
+
+        uci.handle("setoption name Threads value 3");
+        uci.handle("setoption name Hash value 1");
+        uci.handle("setoption name Skill Level value 5");
+        uci.handle("setoption name MultiPV value 2");
+        assert_eq!(Some(3), uci.search.as_ref().map(|s| s.threads()));
+        assert_eq!((1, 5, 2), (uci.hash_size, uci.skill_level, uci.multi_pv));
+
+        uci.handle("setoption name Ponder value true");
+        uci.handle("setoption name Contempt value 10");
+        assert!(rx.recv().unwrap().starts_with("info string invalid option"));
+
+        uci.handle("isready");
+        assert_eq!("readyok", rx.recv().unwrap());
+        assert!(!uci.handle("quit"));
+    }
+
+    #[test]
+    fn position_and_go() {
+        let (mut uci, rx) = engine();
+
+        uci.handle("position startpos moves e2e4 e7e5 g1f3");
+        assert_eq!(
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+            uci.board.to_fen()
+        );
+        uci.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        uci.handle("go depth 3");
+
+        let lines = until(&rx, "bestmove");
+        assert!(lines[0].starts_with("info depth 1 multipv 1 score "));
+        let best = BestMove::parse(lines.last().unwrap(), &uci.board).unwrap();
+        assert_eq!("a1a8", best.best_move.unwrap().to_string());
+        assert!(lines[lines.len() - 2].contains(" score mate 1 "));
+
+        uci.handle("position startpos moves e2e5");
+        assert!(rx
+            .recv()
+            .unwrap()
+            .starts_with("info string invalid position"));
+        assert_eq!("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", uci.board.to_fen());
+    }
+
+    #[test]
+    fn infinite_search_waits_for_stop() {
+        let (mut uci, rx) = engine();
+        uci.handle("setoption name Threads value 2");
+        uci.handle("position startpos");
+        uci.handle("go infinite searchmoves a2a3");
+
+        assert!(rx
+            .recv_timeout(Duration::from_secs(10))
+            .unwrap()
+            .starts_with("info depth 1"));
+        uci.handle("isready");
+        assert!(until(&rx, "readyok")
+            .iter()
+            .all(|l| !l.starts_with("bestmove")));
+
+        uci.handle("stop");
+        assert_eq!(
+            "bestmove a2a3",
+            until(&rx, "bestmove")
+                .last()
+                .unwrap()
+                .split(" ponder")
+                .next()
+                .unwrap()
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn pondering() {
+        let (mut uci, rx) = engine();
+        uci.handle("position startpos moves e2e4");
+        uci.handle("go ponder wtime 100 btime 100");
+
+        // the clock doesn't run while pondering
+        std::thread::sleep(Duration::from_millis(300));
+        uci.handle("isready");
+        assert!(until(&rx, "readyok")
+            .iter()
+            .all(|l| !l.starts_with("bestmove")));
+
+        uci.handle("ponderhit");
+        let best = until(&rx, "bestmove");
+        assert!(BestMove::parse(best.last().unwrap(), &uci.board).is_ok());
+
+        // a ponder miss stops the search, which still answers
+        uci.handle("go ponder btime 60000 wtime 60000");
+        assert!(rx
+            .recv_timeout(Duration::from_secs(10))
+            .unwrap()
+            .starts_with("info depth 1"));
+        uci.handle("stop");
+        until(&rx, "bestmove");
+
+        // and the next search doesn't ponder
+        uci.handle("go depth 2");
+        until(&rx, "bestmove");
+    }
+
+    #[test]
+    fn own_book() {
+        let (mut uci, rx) = engine();
+
+        let games = parse_pgn("1. e4 e5 1-0\n\n1. e4 c5 1-0\n").unwrap();
+        let path = std::env::temp_dir().join(format!("uci-book-{}.bin", std::process::id()));
+        Book::from_games(&games, &BookOptions::default()).save(&path).unwrap();
+
+        uci.handle(&format!("setoption name Book File value {}", path.display()));
+        assert_eq!(
+            format!("info string loaded {} with 3 entries", path.display()),
+            rx.recv().unwrap()
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        // the book is only used once asked to
+        uci.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        uci.handle("setoption name OwnBook value true");
+        uci.handle("go depth 3");
+        assert_eq!("bestmove a1a8", until(&rx, "bestmove").last().unwrap());
+
+        uci.handle("position startpos");
+        uci.handle("go wtime 1000 btime 1000");
+        assert_eq!("bestmove e2e4", rx.recv().unwrap());
+        uci.handle("go infinite");
+        assert!(rx.recv().unwrap().starts_with("info depth 1"));
+        uci.handle("stop");
+        until(&rx, "bestmove");
+
+        uci.handle("setoption name OwnBook value false");
+        uci.handle("go depth 1");
+        assert!(rx.recv().unwrap().starts_with("info depth 1"));
+        until(&rx, "bestmove");
+
+        uci.handle("setoption name Book File value /nonexistent/book.bin");
+        assert!(rx
+            .recv()
+            .unwrap()
+            .starts_with("info string invalid book: /nonexistent/book.bin"));
+        assert!(uci.book.is_some());
+    }
+
+    #[test]
+    fn eval_weights() {
+        let (mut uci, rx) = engine();
+
+        let mut params = EvalParams::DEFAULT;
+        params.material[PieceType::Knight as usize] = 400;
+        let path = std::env::temp_dir().join(format!("uci-weights-{}.txt", std::process::id()));
+        std::fs::write(&path, params.to_string()).unwrap();
+
+        uci.handle(&format!("setoption name EvalWeights value {}", path.display()));
+        assert_eq!(format!("info string loaded {}", path.display()), rx.recv().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(Some(&params), uci.search.as_ref().map(|s| s.eval_params()));
+
+        uci.handle("setoption name EvalWeights value /nonexistent/weights.txt");
+        assert!(rx
+            .recv()
+            .unwrap()
+            .starts_with("info string invalid weights: /nonexistent/weights.txt"));
+        assert_eq!(Some(&params), uci.search.as_ref().map(|s| s.eval_params()));
+
+        uci.handle("setoption name EvalWeights value <empty>");
+        assert_eq!(Some(&EvalParams::DEFAULT), uci.search.as_ref().map(|s| s.eval_params()));
+    }
+
+    #[cfg(feature = "nnue")]
+    #[test]
+    fn eval_file() {
+        let (mut uci, rx) = engine();
+
+        // a network that sees every position a pawn up for the side to move
+        let mut bytes = b"NNUE".to_vec();
+        bytes.extend(16u32.to_le_bytes());
+        bytes.resize(8 + 2 * (768 * 16 + 3 * 16), 0);
+        bytes.extend(4080i32.to_le_bytes());
+        let path = std::env::temp_dir().join(format!("uci-network-{}.bin", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+
+        uci.handle(&format!("setoption name EvalFile value {}", path.display()));
+        assert_eq!(
+            format!("info string loaded {} with 16 hidden neurons", path.display()),
+            rx.recv().unwrap()
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        uci.handle("position startpos");
+        uci.handle("go depth 1");
+        assert!(until(&rx, "bestmove")[0].contains(" score cp -100 "));
+
+        uci.handle("setoption name EvalFile value /nonexistent/network.bin");
+        assert!(rx
+            .recv()
+            .unwrap()
+            .starts_with("info string invalid network: /nonexistent/network.bin"));
+        assert!(uci.network.is_some());
+    }
+
+    #[cfg(feature = "syzygy")]
+    #[test]
+    fn syzygy_path() {
+        let (mut uci, rx) = engine();
+
+        // the tables are only read when probed
+        let dir = std::env::temp_dir().join(format!("uci-syzygy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("KRvK.rtbw"), b"").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"").unwrap();
+
+        uci.handle(&format!("setoption name SyzygyPath value {}", dir.display()));
+        assert_eq!("info string loaded 1 tables of up to 3 pieces", rx.recv().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        uci.handle("position fen 8/8/8/8/8/2k5/8/R3K3 w - - 0 1");
+        assert!(uci.board.tablebase().is_some());
+
+        uci.handle("setoption name SyzygyPath value /nonexistent/syzygy");
+        assert!(rx
+            .recv()
+            .unwrap()
+            .starts_with("info string invalid tablebase: /nonexistent/syzygy"));
+        assert!(uci.tablebase.is_some());
+
+        uci.handle("setoption name SyzygyPath value <empty>");
+        assert!(uci.board.tablebase().is_none());
+    }
+
+    #[test]
+    fn clock() {
+        let b = Board::new();
+        let go = |line| GoParams::parse(line, &b).unwrap();
+
+        assert_eq!(None, time_left(&go("go depth 5 movetime 500"), Color::White));
+        assert_eq!(None, time_left(&go("go infinite wtime 1000"), Color::White));
+        assert_eq!(
+            Some(TimeLeft {
+                remaining: Duration::from_secs(60),
+                increment: Duration::from_secs(1),
+                moves_to_go: None,
+            }),
+            time_left(&go("go wtime 1000 btime 60000 binc 1000"), Color::Black)
+        );
+        assert_eq!(
+            Some(TimeLeft {
+                remaining: Duration::from_secs(1),
+                increment: Duration::from_millis(100),
+                moves_to_go: Some(20),
+            }),
+            time_left(&go("go wtime 1000 winc 100 movestogo 20"), Color::White)
+        );
+    }
+}
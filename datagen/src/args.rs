@@ -0,0 +1,155 @@
+use crate::format::Format;
+
+pub const USAGE: &str = "\
+Usage: datagen [OPTIONS] --out <FILE>
+
+Plays the engine against itself from random openings and writes the quiet positions of the
+games with their score and the result, for tuning and network training.
+
+Options:
+  --out <FILE>                   Where to write the positions, appended to if it exists
+  --format <binary|text>         Records of 35 bytes, or EPD lines with the score in 'ce' and
+                                 the result in 'c9' [default: binary]
+  --games <N>                    Number of games [default: 100]
+  --nodes <N>                    Nodes searched per move [default: 5000]
+  --random-plies <N>             Random moves at the start of each game [default: 8]
+  --threads <N>                  Games played at the same time [default: 1]
+  --seed <N>                     Seed of the random openings [default: the time]
+  -h, --help                     Print this help";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Args {
+    pub out: Option<String>,
+    pub format: Format,
+    pub games: usize,
+    pub nodes: u64,
+    pub random_plies: usize,
+    pub threads: usize,
+    pub seed: Option<u64>,
+    pub help: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            out: None,
+            format: Format::Binary,
+            games: 100,
+            nodes: 5000,
+            random_plies: 8,
+            threads: 1,
+            seed: None,
+            help: false,
+        }
+    }
+}
+
+/// Reads the command line arguments, without the program name.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for '{}'", arg))
+        };
+        let positive = |v: String, what: &str| match v.parse::<u64>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("invalid {} '{}'", what, v)),
+        };
+
+        match arg.as_str() {
+            "--out" => parsed.out = Some(value()?),
+            "--format" => {
+                parsed.format = match value()?.as_str() {
+                    "binary" => Format::Binary,
+                    "text" => Format::Text,
+                    v => return Err(format!("invalid format '{}'", v)),
+                }
+            }
+            "--games" => parsed.games = positive(value()?, "number of games")? as usize,
+            "--nodes" => parsed.nodes = positive(value()?, "number of nodes")?,
+            "--random-plies" => {
+                let v = value()?;
+                match v.parse() {
+                    Ok(n) => parsed.random_plies = n,
+                    _ => return Err(format!("invalid number of random plies '{}'", v)),
+                }
+            }
+            "--threads" => parsed.threads = positive(value()?, "number of threads")? as usize,
+            "--seed" => {
+                let v = value()?;
+                match v.parse() {
+                    Ok(n) => parsed.seed = Some(n),
+                    _ => return Err(format!("invalid seed '{}'", v)),
+                }
+            }
+            "-h" | "--help" => parsed.help = true,
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+
+    if !parsed.help && parsed.out.is_none() {
+        return Err(String::from("no output file given"));
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::{parse_args, Args};
+    use crate::format::Format;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn arguments() {
+        let args = parse(&["--out", "data.bin"]).unwrap();
+        assert_eq!(Some("data.bin".to_string()), args.out);
+        assert_eq!(Format::Binary, args.format);
+        assert_eq!(
+            (100, 5000, 8, 1),
+            (args.games, args.nodes, args.random_plies, args.threads)
+        );
+        assert_eq!(None, args.seed);
+
+        let args = parse(&[
+            "--format",
+            "text",
+            "--games",
+            "10",
+            "--nodes",
+            "800",
+            "--random-plies",
+            "0",
+            "--threads",
+            "4",
+            "--seed",
+            "42",
+            "--out",
+            "data.txt",
+        ])
+        .unwrap();
+        assert_eq!(Format::Text, args.format);
+        assert_eq!(
+            (10, 800, 0, 4),
+            (args.games, args.nodes, args.random_plies, args.threads)
+        );
+        assert_eq!(Some(42), args.seed);
+
+        assert!(parse(&["-h"]).unwrap().help);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--out", "a", "--format", "csv"]).is_err());
+        assert!(parse(&["--out", "a", "--games", "0"]).is_err());
+        assert!(parse(&["--out", "a", "--threads", "x"]).is_err());
+        assert!(parse(&["--out", "a", "a.bin"]).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use chess::board::packed::{PackError, PACKED_SIZE};
+use chess::board::Board;
+use chess::epd::{parse_epd, Epd};
+use chess::piece::Color;
+use std::io::{self, Read, Write};
+
+/// The size of a binary record.
+pub const RECORD_SIZE: usize = PACKED_SIZE + 3;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Format {
+    Binary,
+    Text,
+}
+
+/// A position of a self-play game.
+#[derive(Clone, Debug)]
+pub struct Record {
+    pub board: Board,
+    /// The search score in centipawns for white.
+    pub score: i16,
+    /// The result of the game for white: 0 for a loss, 1 for a draw and 2 for a win.
+    pub result: u8,
+}
+
+impl Record {
+    /// Writes the record in `format`.
+    ///
+    /// A binary record is the position packed by `Board::to_packed`, the score as a
+    /// little-endian `i16` and the result.
+    ///
+    /// A text record is a line of EPD with the score from the view of the side to move in the
+    /// `ce` operation and the result in `c9`, e.g. `... w KQkq - ce 25; c9 "1/2-1/2";`, which the
+    /// tuner reads.
+    pub fn write(&self, format: Format, out: &mut impl Write) -> io::Result<()> {
+        match format {
+            Format::Binary => {
+                let bytes = self
+                    .to_bytes()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                out.write_all(&bytes)
+            }
+            Format::Text => writeln!(out, "{}", self.to_epd()),
+        }
+    }
+
+    pub fn to_epd(&self) -> Epd {
+        let score = match self.board.current_player() {
+            Color::White => self.score,
+            Color::Black => -self.score,
+        };
+        let result = ["0-1", "1/2-1/2", "1-0"][self.result.min(2) as usize];
+
+        let mut epd = Epd::new(self.board.clone());
+        epd.set_operation("ce", vec![score.to_string()]);
+        epd.set_operation("c9", vec![result.to_string()]);
+        epd
+    }
+
+    /// Reads a text record, `None` without a score or a result.
+    pub fn from_epd(epd: Epd) -> Option<Record> {
+        let score = i16::try_from(epd.centipawns()?).ok()?;
+        let result = match epd.operation("c9")?.first()?.as_str() {
+            "0-1" => 0,
+            "1/2-1/2" => 1,
+            "1-0" => 2,
+            _ => return None,
+        };
+
+        Some(Record {
+            score: match epd.board.current_player() {
+                Color::White => score,
+                Color::Black => -score,
+            },
+            board: epd.board,
+            result,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; RECORD_SIZE], PackError> {
+        let mut bytes = [0; RECORD_SIZE];
+
+        bytes[..PACKED_SIZE].copy_from_slice(&self.board.to_packed()?);
+        bytes[PACKED_SIZE..PACKED_SIZE + 2].copy_from_slice(&self.score.to_le_bytes());
+        bytes[PACKED_SIZE + 2] = self.result;
+
+        Ok(bytes)
+    }
+
+    /// Reads a binary record, an error if it isn't a valid position.
+    pub fn from_bytes(bytes: &[u8; RECORD_SIZE]) -> Result<Record, PackError> {
+        let (board, rest) = bytes.split_at(PACKED_SIZE);
+
+        Ok(Record {
+            board: Board::from_packed(board.try_into().unwrap())?,
+            score: i16::from_le_bytes([rest[0], rest[1]]),
+            result: rest[2].min(2),
+        })
+    }
+}
+
+/// Reads binary records until the end of `input`.
+pub fn read_records(input: &mut impl Read) -> io::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    let mut bytes = [0; RECORD_SIZE];
+
+    loop {
+        match input.read_exact(&mut bytes) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(records),
+            Err(e) => return Err(e),
+        }
+        match Record::from_bytes(&bytes) {
+            Ok(record) => records.push(record),
+            Err(e) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("record {} isn't a valid position: {}", records.len() + 1, e),
+                ))
+            }
+        }
+    }
+}
+
+/// Reads text records until the end of `input`.
+pub fn read_text_records(input: &mut impl Read) -> io::Result<Vec<Record>> {
+    let mut contents = String::new();
+    input.read_to_string(&mut contents)?;
+    let positions =
+        parse_epd(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    positions
+        .into_iter()
+        .enumerate()
+        .map(|(idx, epd)| {
+            Record::from_epd(epd).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("record {} has no score or result", idx + 1),
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{read_records, read_text_records, Format, Record, RECORD_SIZE};
+    use chess::board::Board;
+    use chess::variant::from_name;
+
+    fn record(fen: &str, score: i16, result: u8) -> Record {
+        Record {
+            board: fen.parse().unwrap(),
+            score,
+            result,
+        }
+    }
+
+    #[test]
+    fn binary() {
+        let records = [
+            record(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                25,
+                1,
+            ),
+            record("r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 0 12", -310, 0),
+            record("8/8/4k3/8/8/8/1K6/8 b - - 0 1", 0, 2),
+        ];
+        let mut bytes = Vec::new();
+        for r in &records {
+            r.write(Format::Binary, &mut bytes).unwrap();
+        }
+        assert_eq!(3 * RECORD_SIZE, bytes.len());
+        assert_eq!(records[1].board.to_packed().unwrap(), bytes[RECORD_SIZE..][..32]);
+        assert_eq!([0xca, 0xfe, 0], bytes[2 * RECORD_SIZE - 3..2 * RECORD_SIZE]);
+
+        let read = read_records(&mut bytes.as_slice()).unwrap();
+        for (r, read) in records.iter().zip(&read) {
+            assert_eq!(r.board.to_fen(), read.board.to_fen());
+            assert_eq!((r.score, r.result), (read.score, read.result));
+        }
+
+        // the reserved bytes of a packed position are zero
+        bytes[RECORD_SIZE + 31] = 1;
+        assert!(read_records(&mut bytes.as_slice()).is_err());
+
+        // only standard chess positions can be written
+        let horde = Board::from_fen_variant(
+            "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1",
+            from_name("horde").unwrap(),
+        )
+        .unwrap();
+        let r = Record {
+            board: horde,
+            score: 0,
+            result: 1,
+        };
+        assert!(r.write(Format::Binary, &mut Vec::new()).is_err());
+        // a cut off record is ignored
+        assert_eq!(
+            1,
+            read_records(&mut &bytes[..RECORD_SIZE + 5]).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn text() {
+        let mut out = Vec::new();
+        record(&Board::new().to_fen(), -12, 2)
+            .write(Format::Text, &mut out)
+            .unwrap();
+        record("8/8/4k3/8/8/8/1K6/8 b - - 0 1", 40, 1)
+            .write(Format::Text, &mut out)
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - ce -12; c9 \"1-0\";\n\
+             8/8/4k3/8/8/8/1K6/8 b - - ce -40; c9 \"1/2-1/2\";\n",
+            text
+        );
+
+        let read = read_text_records(&mut text.as_bytes()).unwrap();
+        assert_eq!(2, read.len());
+        assert_eq!((-12, 2), (read[0].score, read[0].result));
+        assert_eq!((40, 1), (read[1].score, read[1].result));
+        assert_eq!("8/8/4k3/8/8/8/1K6/8 b - - 0 1", read[1].board.to_fen());
+
+        assert!(read_text_records(&mut "8/8/4k3/8/8/8/1K6/8 b - - ce 3;".as_bytes()).is_err());
+        assert!(read_text_records(&mut "8/8/4k3 b - -".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use crate::format::Record;
+use chess::board::Board;
+use chess::piece::Color;
+use chess::search::{Score, Search, SearchLimits};
+
+/// Games that last this long are adjudicated as draws.
+const MAX_PLIES: u32 = 400;
+/// A game is adjudicated as won once the score stays beyond this for `WIN_PLIES` plies.
+const WIN_SCORE: i32 = 1000;
+const WIN_PLIES: u32 = 4;
+
+/// A small random number generator for the openings (xorshift64*).
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32) as usize % n
+    }
+}
+
+/// Plays a game of the engine against itself from `board`, returning the positions worth
+/// learning from: those not in check whose best move is quiet and not a mate.
+pub fn play_game(search: &mut Search, mut board: Board, nodes: u64) -> Vec<Record> {
+    let limits = SearchLimits {
+        nodes: Some(nodes),
+        ..SearchLimits::default()
+    };
+    let mut positions = Vec::new();
+    // the plies in a row the score was beyond `WIN_SCORE`, positive for white
+    let mut winning: i32 = 0;
+    search.clear();
+
+    let winner = loop {
+        if let Some(outcome) = board.outcome() {
+            break outcome.winner();
+        }
+        if board.repetition_count() >= 3
+            || board.fifty_move_counter() >= 100
+            || board.ply() >= MAX_PLIES
+        {
+            break None;
+        }
+
+        let result = search.run(&mut board, limits.clone(), |_| {});
+        let Some(best) = result.best_move else {
+            break None;
+        };
+        let white = |score: i32| match board.current_player() {
+            Color::White => score,
+            Color::Black => -score,
+        };
+        match result.score {
+            // the game is decided, no need to play it out
+            Score::Mate(n) => {
+                break Some(if white(n) > 0 {
+                    Color::White
+                } else {
+                    Color::Black
+                })
+            }
+            Score::Cp(cp) if !board.in_check() && !best.is_tactical() => positions.push(Record {
+                board: board.clone(),
+                score: white(cp).clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+                result: 1,
+            }),
+            Score::Cp(_) => {}
+        }
+
+        let cp = match result.score {
+            Score::Cp(cp) => white(cp),
+            Score::Mate(_) => 0,
+        };
+        winning = match cp {
+            cp if cp > WIN_SCORE => winning.max(0) + 1,
+            cp if cp < -WIN_SCORE => winning.min(0) - 1,
+            _ => 0,
+        };
+        if winning.unsigned_abs() >= WIN_PLIES {
+            break Some(if winning > 0 {
+                Color::White
+            } else {
+                Color::Black
+            });
+        }
+
+        board.make_move(best);
+    };
+
+    let result = match winner {
+        Some(Color::White) => 2,
+        Some(Color::Black) => 0,
+        None => 1,
+    };
+    for record in &mut positions {
+        record.result = result;
+    }
+
+    positions
+}
+
+/// The position after `plies` random moves from the start, without one that already ended.
+pub fn random_opening(plies: usize, rng: &mut Rng) -> Board {
+    loop {
+        let mut board = Board::new();
+        for _ in 0..plies {
+            let moves = board.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            let m = moves[rng.below(moves.len())];
+            board.make_move(m);
+        }
+
+        if board.outcome().is_none() {
+            return board;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{play_game, random_opening, Rng};
+    use chess::board::Board;
+    use chess::search::Search;
+
+    #[test]
+    fn openings() {
+        let a = random_opening(6, &mut Rng::new(7));
+        assert_eq!(6, a.ply());
+        assert_eq!(a.to_fen(), random_opening(6, &mut Rng::new(7)).to_fen());
+        assert_ne!(a.to_fen(), random_opening(6, &mut Rng::new(8)).to_fen());
+    }
+
+    #[test]
+    fn self_play() {
+        let mut search = Search::new();
+        let b: Board = "8/8/3k4/8/8/2P5/1PK5/8 w - - 0 60".parse().unwrap();
+        let positions = play_game(&mut search, b.clone(), 2000);
+
+        assert_eq!(b.to_fen(), positions[0].board.to_fen());
+        for record in &positions {
+            assert!(!record.board.in_check());
+            assert_eq!(2, record.result);
+            assert!(record.score > 0);
+        }
+
+        // a drawn ending
+        let b: Board = "8/8/3k4/8/8/8/2K5/7N w - - 0 60".parse().unwrap();
+        assert_eq!(
+            None,
+            play_game(&mut search, b, 500).first().map(|r| r.result)
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use args::Args;
+use chess::search::Search;
+use format::{read_records, read_text_records, Format, Record};
+use game::{play_game, random_opening, Rng};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod args;
+mod format;
+mod game;
+
+/// The games between progress reports.
+const REPORT_INTERVAL: usize = 10;
+
+fn main() {
+    let args = match args::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, args::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if args.help {
+        println!("{}", args::USAGE);
+        return;
+    }
+
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = args.out.as_deref().unwrap_or_default();
+    // positions already in the file aren't written again
+    let mut seen = HashSet::new();
+    if let Ok(file) = File::open(path) {
+        let mut input = BufReader::new(file);
+        let records = match args.format {
+            Format::Binary => read_records(&mut input),
+            Format::Text => read_text_records(&mut input),
+        };
+        let records = records.map_err(|e| format!("{}: {}", path, e))?;
+        seen.extend(records.iter().map(|r| r.board.hash()));
+    }
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let mut out = BufWriter::new(file);
+    let seed = args.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(1, |d| d.as_nanos() as u64)
+    });
+
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel::<Vec<Record>>();
+    let (mut games, mut written, mut duplicates) = (0, 0, 0);
+
+    thread::scope(|s| -> Result<(), Box<dyn Error>> {
+        for _ in 0..args.threads.min(args.games) {
+            let (tx, next) = (tx.clone(), &next);
+            s.spawn(move || {
+                let mut search = Search::new();
+                loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    if idx >= args.games {
+                        return;
+                    }
+                    // every game has its own opening, however the games are spread over threads
+                    let mut rng = Rng::new(seed ^ (idx as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+                    let board = random_opening(args.random_plies, &mut rng);
+                    let positions = play_game(&mut search, board, args.nodes);
+                    if tx.send(positions).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        for positions in rx {
+            games += 1;
+            for record in positions {
+                if !seen.insert(record.board.hash()) {
+                    duplicates += 1;
+                    continue;
+                }
+                record
+                    .write(args.format, &mut out)
+                    .map_err(|e| format!("{}: {}", path, e))?;
+                written += 1;
+            }
+
+            if games % REPORT_INTERVAL == 0 || games == args.games {
+                println!(
+                    "{} of {} games, {} positions, {} duplicates",
+                    games, args.games, written, duplicates
+                );
+            }
+        }
+
+        Ok(())
+    })?;
+
+    out.flush().map_err(|e| format!("{}: {}", path, e))?;
+    println!("Wrote {} positions to {}", written, path);
+
+    Ok(())
+}
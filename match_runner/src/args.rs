@@ -0,0 +1,297 @@
+use crate::game::{Adjudication, DrawRule, ResignRule};
+use crate::stats::Sprt;
+
+pub const USAGE: &str = "\
+Usage: match_runner --engine1 <CMD> --engine2 <CMD> [OPTIONS]
+
+Options:
+  --engine1 <CMD>                The first engine, a program followed by its arguments
+  --engine2 <CMD>                The second engine
+  --option1 <NAME=VALUE>         Set a UCI option of the first engine, may be repeated
+  --option2 <NAME=VALUE>         Set a UCI option of the second engine, may be repeated
+  --games <N>                    Games to play, colors alternate between them [default: 2]
+  --openings <FILE>              Start from the positions of an EPD or PGN file, each twice
+  --tc <TC>                      Time control in PGN notation, e.g. 40/60 or 10+0.1 [default: 10+0.1]
+  --concurrency <N>              Games to play at the same time [default: 1]
+  --pgn-out <FILE>               Append the finished games to a PGN file
+  --resign <SCORE,MOVES>         Adjudicate a win once the engines agree on at least SCORE
+                                 centipawns for MOVES moves each
+  --draw <MOVE,SCORE,MOVES>      Adjudicate a draw from move MOVE on, once both engines scored
+                                 within SCORE centipawns of zero for MOVES moves each
+  --sprt <ELO0,ELO1[,ALPHA,BETA]>
+                                 Stop as soon as a sequential probability ratio test decides
+  -h, --help                     Print this help";
+
+/// How to start an engine and set it up.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EngineSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub options: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Args {
+    pub engines: [EngineSpec; 2],
+    pub games: usize,
+    pub openings: Option<String>,
+    pub tc: String,
+    pub concurrency: usize,
+    pub pgn_out: Option<String>,
+    pub adjudication: Adjudication,
+    pub sprt: Option<Sprt>,
+    pub help: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            engines: Default::default(),
+            games: 2,
+            openings: None,
+            tc: String::from("10+0.1"),
+            concurrency: 1,
+            pgn_out: None,
+            adjudication: Adjudication::default(),
+            sprt: None,
+            help: false,
+        }
+    }
+}
+
+/// Reads the command line arguments, without the program name.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for '{}'", arg))
+        };
+
+        match arg.as_str() {
+            "--engine1" | "--engine2" => {
+                let v = value()?;
+                let mut words = v.split_whitespace().map(String::from);
+                let spec = &mut parsed.engines[engine_index(&arg)];
+                spec.program = words.next().ok_or("empty engine command")?;
+                spec.args = words.collect();
+            }
+            "--option1" | "--option2" => {
+                let v = value()?;
+                match v.split_once('=') {
+                    Some((name, value)) if !name.trim().is_empty() => parsed.engines
+                        [engine_index(&arg)]
+                    .options
+                    .push((name.trim().to_string(), value.trim().to_string())),
+                    _ => return Err(format!("invalid option '{}', expected NAME=VALUE", v)),
+                }
+            }
+            "--games" => parsed.games = positive(&value()?, "number of games")?,
+            "--openings" => parsed.openings = Some(value()?),
+            "--tc" => parsed.tc = value()?,
+            "--concurrency" => parsed.concurrency = positive(&value()?, "concurrency")?,
+            "--pgn-out" => parsed.pgn_out = Some(value()?),
+            "--resign" => {
+                let v = value()?;
+                match numbers(&v)[..] {
+                    [Some(score), Some(moves)] if score > 0.0 && moves >= 1.0 => {
+                        parsed.adjudication.resign = Some(ResignRule {
+                            score: score as i32,
+                            moves: moves as usize,
+                        })
+                    }
+                    _ => return Err(format!("invalid resign rule '{}', expected SCORE,MOVES", v)),
+                }
+            }
+            "--draw" => {
+                let v = value()?;
+                match numbers(&v)[..] {
+                    [Some(after), Some(score), Some(moves)] if score >= 0.0 && moves >= 1.0 => {
+                        parsed.adjudication.draw = Some(DrawRule {
+                            after: after as u32,
+                            score: score as i32,
+                            moves: moves as usize,
+                        })
+                    }
+                    _ => {
+                        return Err(format!(
+                            "invalid draw rule '{}', expected MOVE,SCORE,MOVES",
+                            v
+                        ))
+                    }
+                }
+            }
+            "--sprt" => {
+                let v = value()?;
+                let probability = |p: f64| p > 0.0 && p < 0.5;
+                parsed.sprt = match numbers(&v)[..] {
+                    [Some(elo0), Some(elo1)] if elo0 < elo1 => Some(Sprt::new(elo0, elo1)),
+                    [Some(elo0), Some(elo1), Some(alpha), Some(beta)]
+                        if elo0 < elo1 && probability(alpha) && probability(beta) =>
+                    {
+                        Some(Sprt {
+                            elo0,
+                            elo1,
+                            alpha,
+                            beta,
+                        })
+                    }
+                    _ => {
+                        return Err(format!(
+                            "invalid SPRT '{}', expected ELO0,ELO1 or ELO0,ELO1,ALPHA,BETA",
+                            v
+                        ))
+                    }
+                };
+            }
+            "-h" | "--help" => parsed.help = true,
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+
+    if !parsed.help && parsed.engines.iter().any(|e| e.program.is_empty()) {
+        return Err(String::from(
+            "two engines are needed, --engine1 and --engine2",
+        ));
+    }
+
+    Ok(parsed)
+}
+
+fn engine_index(arg: &str) -> usize {
+    if arg.ends_with('1') {
+        0
+    } else {
+        1
+    }
+}
+
+fn positive(s: &str, what: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("invalid {} '{}'", what, s)),
+    }
+}
+
+/// The comma separated numbers of `s`, `None` for those that aren't any.
+fn numbers(s: &str) -> Vec<Option<f64>> {
+    s.split(',').map(|n| n.trim().parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::{parse_args, Args, EngineSpec};
+    use crate::game::{DrawRule, ResignRule};
+    use crate::stats::Sprt;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn defaults() {
+        let args = parse(&["--engine1", "./new", "--engine2", "stockfish"]).unwrap();
+
+        assert_eq!(2, args.games);
+        assert_eq!("10+0.1", args.tc);
+        assert_eq!(1, args.concurrency);
+        assert_eq!(None, args.adjudication.resign);
+        assert_eq!(None, args.sprt);
+        assert_eq!("stockfish", args.engines[1].program);
+
+        assert!(parse(&["--help"]).unwrap().help);
+    }
+
+    #[test]
+    fn all_options() {
+        let args = parse(&[
+            "--engine1",
+            "./engine --uci -v",
+            "--engine2",
+            "./base",
+            "--option1",
+            "Hash=64",
+            "--option1",
+            "Skill Level = 5",
+            "--option2",
+            "Threads=2",
+            "--games",
+            "100",
+            "--openings",
+            "book.epd",
+            "--tc",
+            "40/60",
+            "--concurrency",
+            "4",
+            "--pgn-out",
+            "out.pgn",
+            "--resign",
+            "600,3",
+            "--draw",
+            "40,10,8",
+            "--sprt",
+            "0,5,0.05,0.1",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            EngineSpec {
+                program: "./engine".to_string(),
+                args: vec!["--uci".to_string(), "-v".to_string()],
+                options: vec![
+                    ("Hash".to_string(), "64".to_string()),
+                    ("Skill Level".to_string(), "5".to_string())
+                ],
+            },
+            args.engines[0]
+        );
+        assert_eq!(1, args.engines[1].options.len());
+        assert_eq!((100, 4), (args.games, args.concurrency));
+        assert_eq!(Some("book.epd".to_string()), args.openings);
+        assert_eq!("40/60", args.tc);
+        assert_eq!(Some("out.pgn".to_string()), args.pgn_out);
+        assert_eq!(
+            Some(ResignRule {
+                score: 600,
+                moves: 3
+            }),
+            args.adjudication.resign
+        );
+        assert_eq!(
+            Some(DrawRule {
+                after: 40,
+                score: 10,
+                moves: 8
+            }),
+            args.adjudication.draw
+        );
+        assert_eq!(
+            Some(Sprt {
+                elo0: 0.0,
+                elo1: 5.0,
+                alpha: 0.05,
+                beta: 0.1
+            }),
+            args.sprt
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let engines = ["--engine1", "a", "--engine2", "b"];
+        let with = |extra: &[&str]| parse(&[&engines[..], extra].concat());
+
+        assert!(parse(&["--engine1", "a"]).is_err());
+        assert!(with(&["--games", "0"]).is_err());
+        assert!(with(&["--concurrency", "x"]).is_err());
+        assert!(with(&["--option1", "=5"]).is_err());
+        assert!(with(&["--resign", "600"]).is_err());
+        assert!(with(&["--draw", "40,10"]).is_err());
+        assert!(with(&["--sprt", "5,0"]).is_err());
+        assert!(with(&["--sprt", "0,5,0.5,0.05"]).is_err());
+        assert!(with(&["--tc"]).is_err());
+        assert!(with(&["--fast"]).is_err());
+    }
+}
@@ -0,0 +1,573 @@
+use crate::openings::Opening;
+use chess::board::history::DrawClaim;
+use chess::board::outcome::{DrawReason, Outcome, WinReason};
+use chess::board::Board;
+use chess::chess_move::Move;
+use chess::clock::{Clock, TimeSource};
+use chess::pgn::{PgnGame, PgnNode};
+use chess::piece::Color;
+use chess::search::Score;
+use chess::uci_client::{BestMove, GoParams, UciEngine, UciError, UciInfo};
+
+/// Where a mate score counts for adjudication.
+const MATE_SCORE: i32 = 100_000;
+
+/// An engine taking part in a match.
+pub trait Player {
+    /// Gets ready for a game that has nothing to do with the previous one.
+    fn new_game(&mut self) -> Result<(), UciError>;
+
+    /// Decides on a move in the position after `moves` from `start`, returning it with the last
+    /// search info that had a score.
+    fn think(
+        &mut self,
+        start: &Board,
+        moves: &[Move],
+        params: &GoParams,
+    ) -> Result<(BestMove, Option<UciInfo>), UciError>;
+}
+
+impl Player for UciEngine {
+    fn new_game(&mut self) -> Result<(), UciError> {
+        UciEngine::new_game(self)
+    }
+
+    fn think(
+        &mut self,
+        start: &Board,
+        moves: &[Move],
+        params: &GoParams,
+    ) -> Result<(BestMove, Option<UciInfo>), UciError> {
+        self.position(start, moves)?;
+
+        let mut last = None;
+        let best = self.go(params, |info| {
+            if info.score.is_some() && info.multipv.is_none_or(|n| n == 1) {
+                last = Some(info.clone());
+            }
+        })?;
+        Ok((best, last))
+    }
+}
+
+/// Ends a game once one engine scored at least `score` centipawns and the other at most
+/// `-score` for their last `moves` moves.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ResignRule {
+    pub score: i32,
+    pub moves: usize,
+}
+
+/// Ends a game as a draw from move `after` on, once both engines scored within `score`
+/// centipawns of zero for their last `moves` moves.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DrawRule {
+    pub after: u32,
+    pub score: i32,
+    pub moves: usize,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Adjudication {
+    pub resign: Option<ResignRule>,
+    pub draw: Option<DrawRule>,
+}
+
+impl Adjudication {
+    /// The result of the game from the scores so far, each with the side that reported it.
+    fn judge(&self, scores: &[(Color, Option<i32>)], fullmove: u32) -> Option<Option<Color>> {
+        let recent = |color: Color, n: usize| -> Option<Vec<i32>> {
+            let own: Vec<_> = scores
+                .iter()
+                .rev()
+                .filter(|(c, _)| *c == color)
+                .take(n)
+                .collect();
+            if own.len() < n {
+                return None;
+            }
+            own.iter().map(|(_, s)| *s).collect()
+        };
+
+        if let Some(rule) = self.resign {
+            for winner in [Color::White, Color::Black] {
+                let won =
+                    recent(winner, rule.moves).is_some_and(|s| s.iter().all(|&s| s >= rule.score));
+                let lost = recent(winner.opponent(), rule.moves)
+                    .is_some_and(|s| s.iter().all(|&s| s <= -rule.score));
+                if won && lost {
+                    return Some(Some(winner));
+                }
+            }
+        }
+
+        if let Some(rule) = self.draw {
+            let quiet = |color| {
+                recent(color, rule.moves).is_some_and(|s| s.iter().all(|s| s.abs() <= rule.score))
+            };
+            if fullmove >= rule.after && quiet(Color::White) && quiet(Color::Black) {
+                return Some(None);
+            }
+        }
+
+        None
+    }
+}
+
+/// A finished game.
+#[derive(Clone, Debug)]
+pub struct PlayedGame {
+    pub start: Board,
+    pub main_line: Vec<PgnNode>,
+    pub winner: Option<Color>,
+    /// The value of the PGN `Termination` tag.
+    pub termination: &'static str,
+    /// How the game ended in words, e.g. `White mates`.
+    pub reason: String,
+    /// The side whose engine failed, to be restarted before the next game.
+    pub failed: Option<Color>,
+}
+
+impl PlayedGame {
+    pub fn result(&self) -> &'static str {
+        match self.winner {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+
+    /// The game in PGN, with the given tags before `Result` and the position tags.
+    pub fn pgn(&self, mut headers: Vec<(String, String)>) -> PgnGame {
+        headers.push(("Result".to_string(), self.result().to_string()));
+
+        let fen = self.start.to_fen();
+        if fen != Board::new().to_fen() {
+            headers.push(("FEN".to_string(), fen));
+            headers.push(("SetUp".to_string(), "1".to_string()));
+        }
+        headers.push(("Termination".to_string(), self.termination.to_string()));
+
+        let mut main_line = self.main_line.clone();
+        let mut comment = None;
+        match main_line.last_mut() {
+            Some(node) => {
+                let c = node.comment.get_or_insert_with(String::new);
+                if !c.is_empty() {
+                    c.push_str(", ");
+                }
+                c.push_str(&self.reason);
+            }
+            None => comment = Some(self.reason.clone()),
+        }
+
+        PgnGame {
+            headers,
+            moves: main_line.iter().map(|n| n.san.clone()).collect(),
+            main_line,
+            comment,
+            result: Some(self.result().to_string()),
+        }
+    }
+}
+
+/// Plays a game from the opening, the first player having white.
+pub fn play_game<T: TimeSource>(
+    players: [&mut dyn Player; 2],
+    opening: &Opening,
+    adjudication: &Adjudication,
+    mut clock: Clock<T>,
+) -> PlayedGame {
+    let mut board = opening.start.clone();
+    let mut moves = Vec::new();
+    let mut main_line = Vec::new();
+    for &m in &opening.moves {
+        main_line.push(PgnNode {
+            san: board.san(m),
+            ..PgnNode::default()
+        });
+        board.make_move(m);
+        moves.push(m);
+    }
+
+    let mut scores = Vec::new();
+    let ended = |winner, termination, reason: String, failed| PlayedGame {
+        start: opening.start.clone(),
+        main_line: Vec::new(),
+        winner,
+        termination,
+        reason,
+        failed,
+    };
+
+    for color in [Color::White, Color::Black] {
+        if let Err(e) = players[color as usize].new_game() {
+            let reason = format!("{:?}'s engine failed: {}", color, e);
+            return ended(
+                Some(color.opponent()),
+                "rules infraction",
+                reason,
+                Some(color),
+            );
+        }
+    }
+
+    clock.start(board.current_player());
+    let mut game = loop {
+        if let Some(outcome) = board.outcome() {
+            break ended(outcome.winner(), "normal", describe(outcome), None);
+        }
+        match board.can_claim_draw() {
+            Some(DrawClaim::ThreefoldRepetition) => {
+                break ended(
+                    None,
+                    "normal",
+                    String::from("Draw by threefold repetition"),
+                    None,
+                )
+            }
+            Some(DrawClaim::FiftyMoveRule) => {
+                break ended(
+                    None,
+                    "normal",
+                    String::from("Draw by the fifty-move rule"),
+                    None,
+                )
+            }
+            None => {}
+        }
+
+        let color = board.current_player();
+        let params = GoParams {
+            wtime: Some(clock.remaining(Color::White)),
+            btime: Some(clock.remaining(Color::Black)),
+            winc: Some(clock.increment(Color::White)),
+            binc: Some(clock.increment(Color::Black)),
+            movestogo: clock.moves_to_go(color),
+            ..GoParams::default()
+        };
+
+        let (best, info) = match players[color as usize].think(&opening.start, &moves, &params) {
+            Ok(answer) => answer,
+            Err(e) => {
+                let reason = format!("{:?}'s engine failed: {}", color, e);
+                break ended(
+                    Some(color.opponent()),
+                    "rules infraction",
+                    reason,
+                    Some(color),
+                );
+            }
+        };
+
+        if let Some(flagged) = clock.press() {
+            let outcome = board.outcome_on_time(flagged);
+            break ended(outcome.winner(), "time forfeit", describe(outcome), None);
+        }
+
+        let m = match best.best_move.filter(|m| board.legal_moves().contains(m)) {
+            Some(m) => m,
+            None => {
+                let reason = format!("{:?}'s engine made no legal move", color);
+                break ended(
+                    Some(color.opponent()),
+                    "rules infraction",
+                    reason,
+                    Some(color),
+                );
+            }
+        };
+
+        let score = info.as_ref().and_then(|i| i.score);
+        main_line.push(PgnNode {
+            san: board.san(m),
+            comment: info
+                .as_ref()
+                .and_then(|i| Some(format!("{}/{}", i.score?, i.depth?))),
+            ..PgnNode::default()
+        });
+        board.make_move(m);
+        moves.push(m);
+        scores.push((color, score.map(centipawns)));
+
+        let fullmove = board.ply() / 2 + 1;
+        if let Some(winner) = adjudication.judge(&scores, fullmove) {
+            let reason = match winner {
+                Some(w) => format!("{:?} resigns", w.opponent()),
+                None => String::from("Draw by adjudication"),
+            };
+            break ended(winner, "adjudication", reason, None);
+        }
+    };
+
+    clock.pause();
+    game.main_line = main_line;
+    game
+}
+
+/// A score as centipawns, mates counting as very high scores.
+fn centipawns(score: Score) -> i32 {
+    match score {
+        Score::Cp(cp) => cp,
+        Score::Mate(n) if n > 0 => MATE_SCORE - n,
+        Score::Mate(n) => -MATE_SCORE - n,
+    }
+}
+
+fn describe(outcome: Outcome) -> String {
+    match outcome {
+        Outcome::Win { winner, reason } => match reason {
+            WinReason::Checkmate => format!("{:?} mates", winner),
+            WinReason::TimeForfeit => format!("{:?} loses on time", winner.opponent()),
+            WinReason::KingInCenter => format!("{:?} reaches the center", winner),
+            WinReason::ThirdCheck => format!("{:?} gives the third check", winner),
+            WinReason::AllPiecesCaptured => {
+                format!("{:?} loses all pieces", winner.opponent())
+            }
+            WinReason::KingExploded => format!("{:?} blows up the king", winner),
+            WinReason::AllPiecesLost => format!("{:?} loses all pieces and wins", winner),
+            WinReason::Stalemated => format!("{:?} has no moves and wins", winner),
+            WinReason::Tablebase => format!("{:?} wins by the tablebase", winner),
+        },
+        Outcome::Draw(reason) => String::from(match reason {
+            DrawReason::Stalemate => "Draw by stalemate",
+            DrawReason::InsufficientMaterial => "Draw by insufficient material",
+            DrawReason::FivefoldRepetition => "Draw by fivefold repetition",
+            DrawReason::SeventyFiveMoveRule => "Draw by the 75-move rule",
+            DrawReason::TimeoutVsInsufficientMaterial => "Draw by timeout vs insufficient material",
+            DrawReason::Tablebase => "Draw by the tablebase",
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{play_game, Adjudication, DrawRule, Player, ResignRule};
+    use crate::openings::Opening;
+    use chess::board::Board;
+    use chess::chess_move::Move;
+    use chess::clock::{Clock, ManualTime, TimeControl};
+    use chess::piece::Color;
+    use chess::search::Score;
+    use chess::uci_client::{BestMove, GoParams, UciError, UciInfo};
+    use std::time::Duration;
+
+    /// Plays the given moves over and over, taking the same time and reporting the same score
+    /// for each. A move that doesn't fit the position is reported as no move at all.
+    struct Scripted {
+        moves: Vec<&'static str>,
+        played: usize,
+        score: Score,
+        think_time: Duration,
+        time: ManualTime,
+        last_params: Option<GoParams>,
+    }
+
+    impl Scripted {
+        fn new(moves: &[&'static str], score: Score, time: &ManualTime) -> Scripted {
+            Scripted {
+                moves: moves.to_vec(),
+                played: 0,
+                score,
+                think_time: Duration::from_millis(100),
+                time: time.clone(),
+                last_params: None,
+            }
+        }
+    }
+
+    impl Player for Scripted {
+        fn new_game(&mut self) -> Result<(), UciError> {
+            Ok(())
+        }
+
+        fn think(
+            &mut self,
+            start: &Board,
+            moves: &[Move],
+            params: &GoParams,
+        ) -> Result<(BestMove, Option<UciInfo>), UciError> {
+            let mut board = start.clone();
+            for &m in moves {
+                board.make_move(m);
+            }
+
+            self.time.advance(self.think_time);
+            self.last_params = Some(params.clone());
+            let s = self.moves[self.played % self.moves.len()];
+            self.played += 1;
+
+            let info = UciInfo {
+                depth: Some(5),
+                score: Some(self.score),
+                ..UciInfo::default()
+            };
+            let best = BestMove {
+                best_move: board.parse_uci_move(s),
+                ponder: None,
+            };
+            Ok((best, Some(info)))
+        }
+    }
+
+    fn clock(time: &ManualTime) -> Clock<ManualTime> {
+        Clock::new(
+            TimeControl::fischer(Duration::from_secs(10), Duration::from_secs(1)),
+            time.clone(),
+        )
+    }
+
+    #[test]
+    fn checkmate() {
+        let time = ManualTime::new();
+        let mut white = Scripted::new(&["f2f3", "g2g4"], Score::Cp(-50), &time);
+        let mut black = Scripted::new(&["e7e5", "d8h4"], Score::Cp(50), &time);
+
+        let game = play_game(
+            [&mut white, &mut black],
+            &Opening::default(),
+            &Adjudication::default(),
+            clock(&time),
+        );
+        assert_eq!(Some(Color::Black), game.winner);
+        assert_eq!("0-1", game.result());
+        assert_eq!("Black mates", game.reason);
+        assert_eq!(None, game.failed);
+
+        let pgn = game.pgn(vec![("Event".to_string(), "Test".to_string())]);
+        assert_eq!(Some("normal"), pgn.header("Termination"));
+        assert_eq!(None, pgn.header("FEN"));
+        assert_eq!(vec!["f3", "e5", "g4", "Qh4#"], pgn.moves);
+        assert!(pgn.to_string().contains("{+0.50/5, Black mates} 0-1"));
+
+        // both engines saw the clocks, with the increment of every move
+        let params = black.last_params.unwrap();
+        assert_eq!(Some(Duration::from_millis(11800)), params.wtime);
+        assert_eq!(Some(Duration::from_millis(10900)), params.btime);
+        assert_eq!(Some(Duration::from_secs(1)), params.binc);
+    }
+
+    #[test]
+    fn opening_moves() {
+        let time = ManualTime::new();
+        let start: Board = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".parse().unwrap();
+        let mut b = start.clone();
+        let e4 = b.parse_uci_move("e2e4").unwrap();
+        let opening = Opening {
+            start,
+            moves: vec![e4],
+        };
+
+        // the black king shuffles until the position repeats for the third time
+        let mut white = Scripted::new(&["e1d1", "d1e1"], Score::Cp(0), &time);
+        let mut black = Scripted::new(&["e8d8", "d8e8"], Score::Cp(0), &time);
+        let game = play_game(
+            [&mut white, &mut black],
+            &opening,
+            &Adjudication::default(),
+            clock(&time),
+        );
+
+        assert_eq!(None, game.winner);
+        assert_eq!("Draw by threefold repetition", game.reason);
+        assert_eq!(9, game.main_line.len());
+        assert_eq!("e4", game.main_line[0].san);
+        assert_eq!(None, game.main_line[0].comment);
+
+        let pgn = game.pgn(Vec::new());
+        assert_eq!(Some("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"), pgn.header("FEN"));
+        assert_eq!(Some("1"), pgn.header("SetUp"));
+    }
+
+    #[test]
+    fn failures() {
+        let time = ManualTime::new();
+        let mut white = Scripted::new(&["e2e4"], Score::Cp(0), &time);
+        let mut black = Scripted::new(&["e7e4"], Score::Cp(0), &time);
+
+        let game = play_game(
+            [&mut white, &mut black],
+            &Opening::default(),
+            &Adjudication::default(),
+            clock(&time),
+        );
+        assert_eq!(Some(Color::White), game.winner);
+        assert_eq!(Some(Color::Black), game.failed);
+        assert_eq!("Black's engine made no legal move", game.reason);
+        assert_eq!(
+            Some("rules infraction"),
+            game.pgn(Vec::new()).header("Termination")
+        );
+
+        // white needs too long for its second move
+        let mut white = Scripted::new(&["e2e4", "d2d4"], Score::Cp(0), &time);
+        let mut black = Scripted::new(&["e7e5"], Score::Cp(0), &time);
+        white.think_time = Duration::from_secs(6);
+        let game = play_game(
+            [&mut white, &mut black],
+            &Opening::default(),
+            &Adjudication::default(),
+            clock(&time),
+        );
+        assert_eq!(Some(Color::Black), game.winner);
+        assert_eq!("White loses on time", game.reason);
+        assert_eq!(None, game.failed);
+        assert_eq!(2, game.main_line.len());
+    }
+
+    #[test]
+    fn adjudication() {
+        let time = ManualTime::new();
+        let resign = Adjudication {
+            resign: Some(ResignRule {
+                score: 600,
+                moves: 2,
+            }),
+            draw: None,
+        };
+        let mut white = Scripted::new(&["g1f3", "f3g1"], Score::Cp(-700), &time);
+        let mut black = Scripted::new(&["g8f6", "f6g8"], Score::Mate(4), &time);
+
+        let game = play_game(
+            [&mut white, &mut black],
+            &Opening::default(),
+            &resign,
+            clock(&time),
+        );
+        assert_eq!(Some(Color::Black), game.winner);
+        assert_eq!("White resigns", game.reason);
+        assert_eq!("adjudication", game.termination);
+        assert_eq!(4, game.main_line.len());
+
+        let draw = Adjudication {
+            resign: None,
+            draw: Some(DrawRule {
+                after: 2,
+                score: 10,
+                moves: 2,
+            }),
+        };
+        let mut white = Scripted::new(&["g1f3", "f3g1"], Score::Cp(5), &time);
+        let mut black = Scripted::new(&["g8f6", "f6g8"], Score::Cp(-10), &time);
+        let game = play_game(
+            [&mut white, &mut black],
+            &Opening::default(),
+            &draw,
+            clock(&time),
+        );
+        assert_eq!(None, game.winner);
+        assert_eq!("Draw by adjudication", game.reason);
+        assert_eq!(4, game.main_line.len());
+
+        // a single score out of bounds holds it off
+        let mut white = Scripted::new(&["g1f3", "f3g1"], Score::Cp(5), &time);
+        let mut black = Scripted::new(&["g8f6", "f6g8"], Score::Cp(-30), &time);
+        let game = play_game(
+            [&mut white, &mut black],
+            &Opening::default(),
+            &draw,
+            clock(&time),
+        );
+        assert_eq!("Draw by threefold repetition", game.reason);
+    }
+}
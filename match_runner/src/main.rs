@@ -0,0 +1,311 @@
+use args::{Args, EngineSpec};
+use chess::clock::{Clock, MonotonicTime, TimeControl};
+use chess::piece::Color;
+use chess::uci_client::{UciEngine, UciError};
+use game::{play_game, PlayedGame};
+use openings::{read_openings, Opening};
+use stats::{Hypothesis, Stats};
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod args;
+mod game;
+mod openings;
+mod stats;
+
+/// A game finished by one of the workers.
+struct Finished {
+    idx: usize,
+    /// Whether the first engine had white.
+    first_white: bool,
+    game: PlayedGame,
+}
+
+fn main() {
+    let args = match args::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, args::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if args.help {
+        println!("{}", args::USAGE);
+        return;
+    }
+
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let time_control: TimeControl = args.tc.parse()?;
+    let openings = match &args.openings {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            read_openings(path, &contents)?
+        }
+        None => vec![Opening::default()],
+    };
+    let mut pgn_out = match &args.pgn_out {
+        Some(path) => Some(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("{}: {}", path, e))?,
+        ),
+        None => None,
+    };
+
+    // the engines started for their names play the games of the first worker
+    let mut engines: [Option<UciEngine>; 2] = [None, None];
+    let mut names = Vec::new();
+    for (engine, spec) in engines.iter_mut().zip(&args.engines) {
+        let started = start_engine(spec).map_err(|e| format!("{}: {}", spec.program, e))?;
+        names.push(started.name().unwrap_or(&spec.program).to_string());
+        *engine = Some(started);
+    }
+    if names[0] == names[1] {
+        names[1].push_str(" (2)");
+    }
+    println!(
+        "Match of {} vs {}, {} games at {}",
+        names[0], names[1], args.games, args.tc
+    );
+
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+    let date = today();
+    let mut stats = Stats::default();
+    let mut decision = None;
+
+    thread::scope(|s| -> Result<(), Box<dyn Error>> {
+        for _ in 0..args.concurrency.min(args.games) {
+            let tx = tx.clone();
+            let (next, stop, openings, time_control) = (&next, &stop, &openings, &time_control);
+            let engines = std::mem::take(&mut engines);
+            s.spawn(move || worker(args, engines, openings, time_control, next, stop, tx));
+        }
+        drop(tx);
+
+        for finished in rx {
+            let finished = match finished {
+                Ok(f) => f,
+                Err(e) => {
+                    stop.store(true, Ordering::Relaxed);
+                    return Err(e.into());
+                }
+            };
+
+            let game = &finished.game;
+            let (white, black) = if finished.first_white {
+                (&names[0], &names[1])
+            } else {
+                (&names[1], &names[0])
+            };
+            let first_color = if finished.first_white {
+                Color::White
+            } else {
+                Color::Black
+            };
+            match game.winner {
+                Some(c) if c == first_color => stats.wins += 1,
+                Some(_) => stats.losses += 1,
+                None => stats.draws += 1,
+            }
+
+            println!(
+                "Finished game {} ({} vs {}): {} {{{}}}",
+                finished.idx + 1,
+                white,
+                black,
+                game.result(),
+                game.reason
+            );
+            println!("Score of {} vs {}: {}", names[0], names[1], stats);
+            print_elo(&stats);
+
+            if let Some(sprt) = args.sprt {
+                let (lower, upper) = sprt.bounds();
+                println!(
+                    "SPRT: llr {:.2} ({:.2}, {:.2})",
+                    sprt.llr(&stats),
+                    lower,
+                    upper
+                );
+                if decision.is_none() {
+                    decision = sprt.decision(&stats);
+                    if decision.is_some() {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                }
+            }
+
+            if let Some(file) = &mut pgn_out {
+                let headers = [
+                    ("Event", "Engine match"),
+                    ("Site", "?"),
+                    ("Date", &date),
+                    ("Round", &(finished.idx + 1).to_string()),
+                    ("White", white),
+                    ("Black", black),
+                    ("TimeControl", &args.tc),
+                ];
+                let pgn = game.pgn(
+                    headers
+                        .iter()
+                        .map(|(n, v)| (n.to_string(), v.to_string()))
+                        .collect(),
+                );
+                writeln!(file, "{}", pgn)?;
+            }
+        }
+
+        Ok(())
+    })?;
+
+    match (args.sprt, decision) {
+        (Some(sprt), Some(h)) => println!(
+            "SPRT: {} accepted, elo0 {} elo1 {}",
+            match h {
+                Hypothesis::H0 => "H0",
+                Hypothesis::H1 => "H1",
+            },
+            sprt.elo0,
+            sprt.elo1
+        ),
+        (Some(_), None) => println!("SPRT: no decision after {} games", stats.games()),
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn print_elo(stats: &Stats) {
+    match stats.elo() {
+        Some((elo, margin)) => println!(
+            "Elo difference: {:.1} +/- {:.1}, LOS: {:.1} %",
+            elo,
+            margin,
+            stats.los() * 100.0
+        ),
+        None => println!("Elo difference: -, LOS: {:.1} %", stats.los() * 100.0),
+    }
+}
+
+/// Plays games until all are taken or the match is stopped, with its own pair of engines. The
+/// ones not passed in `engines` are started first.
+fn worker(
+    args: &Args,
+    mut engines: [Option<UciEngine>; 2],
+    openings: &[Opening],
+    time_control: &TimeControl,
+    next: &AtomicUsize,
+    stop: &AtomicBool,
+    tx: Sender<Result<Finished, String>>,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        let idx = next.fetch_add(1, Ordering::Relaxed);
+        if idx >= args.games {
+            break;
+        }
+
+        for (engine, spec) in engines.iter_mut().zip(&args.engines) {
+            if engine.is_none() {
+                match start_engine(spec) {
+                    Ok(e) => *engine = Some(e),
+                    Err(e) => {
+                        let _ = tx.send(Err(format!("{}: {}", spec.program, e)));
+                        return;
+                    }
+                }
+            }
+        }
+
+        let [Some(first), Some(second)] = &mut engines else {
+            unreachable!("both engines were started");
+        };
+        let first_white = idx.is_multiple_of(2);
+        let opening = &openings[idx / 2 % openings.len()];
+        let clock = Clock::new(time_control.clone(), MonotonicTime::new());
+        let game = if first_white {
+            play_game([first, second], opening, &args.adjudication, clock)
+        } else {
+            play_game([second, first], opening, &args.adjudication, clock)
+        };
+
+        // an engine that misbehaved gets a fresh start
+        if let Some(color) = game.failed {
+            let first_failed = (color == Color::White) == first_white;
+            engines[if first_failed { 0 } else { 1 }] = None;
+        }
+
+        let finished = Finished {
+            idx,
+            first_white,
+            game,
+        };
+        if tx.send(Ok(finished)).is_err() {
+            return;
+        }
+    }
+}
+
+fn start_engine(spec: &EngineSpec) -> Result<UciEngine, UciError> {
+    let args: Vec<&str> = spec.args.iter().map(String::as_str).collect();
+    let mut engine = UciEngine::start(&spec.program, &args)?;
+    for (name, value) in &spec.options {
+        engine.set_option(name, value)?;
+    }
+    engine.is_ready()?;
+
+    Ok(engine)
+}
+
+/// The current date as written in PGN, e.g. `2024.03.09`.
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / 86400) as i64;
+    let (y, m, d) = civil_from_days(days);
+
+    format!("{:04}.{:02}.{:02}", y, m, d)
+}
+
+/// The calendar date of a day counted from 1970-01-01, after Howard Hinnant's algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + (m <= 2) as i64;
+
+    (y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::civil_from_days;
+
+    #[test]
+    fn dates() {
+        assert_eq!((1970, 1, 1), civil_from_days(0));
+        assert_eq!((2000, 2, 29), civil_from_days(11016));
+        assert_eq!((2024, 3, 9), civil_from_days(19791));
+        assert_eq!((1969, 12, 31), civil_from_days(-1));
+    }
+}
@@ -0,0 +1,83 @@
+use chess::board::Board;
+use chess::chess_move::Move;
+use chess::epd::parse_epd;
+use chess::pgn::parse_pgn;
+
+/// A position to start games from, with the moves that led to it.
+#[derive(Clone, Debug, Default)]
+pub struct Opening {
+    pub start: Board,
+    pub moves: Vec<Move>,
+}
+
+/// Reads the openings of a PGN file, the main line of each game, or of an EPD file, a position
+/// per line.
+pub fn read_openings(path: &str, contents: &str) -> Result<Vec<Opening>, String> {
+    let openings = if path.to_ascii_lowercase().ends_with(".pgn") {
+        let games = parse_pgn(contents).map_err(|e| format!("{}: {}", path, e))?;
+        games
+            .iter()
+            .enumerate()
+            .map(|(idx, game)| {
+                let (start, moves) = game
+                    .replay()
+                    .map_err(|e| format!("{}: game {}: {}", path, idx + 1, e))?;
+                Ok(Opening { start, moves })
+            })
+            .collect::<Result<Vec<_>, String>>()?
+    } else {
+        parse_epd(contents)
+            .map_err(|e| format!("{}: {}", path, e))?
+            .into_iter()
+            .map(|epd| Opening {
+                start: epd.board,
+                moves: Vec::new(),
+            })
+            .collect()
+    };
+
+    if openings.is_empty() {
+        return Err(format!("{} has no openings", path));
+    }
+
+    Ok(openings)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::openings::read_openings;
+    use chess::board::Board;
+
+    #[test]
+    fn epd() {
+        let epd = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 id \"open game\";\n\n\
+                   4k3/8/8/8/8/8/4P3/4K3 b - - bm Kd7;\n";
+        let openings = read_openings("book.epd", epd).unwrap();
+
+        assert_eq!(2, openings.len());
+        assert!(openings[0].moves.is_empty());
+        assert_eq!(
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 1",
+            openings[0].start.to_fen()
+        );
+
+        let err = read_openings("book.epd", "4k3/8/8 w - -\n").unwrap_err();
+        assert!(err.starts_with("book.epd: line 1"));
+    }
+
+    #[test]
+    fn pgn() {
+        let pgn = "1. e4 e5 2. Nf3 *\n\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n\n1. e4 *";
+        let openings = read_openings("book.PGN", pgn).unwrap();
+
+        assert_eq!(2, openings.len());
+        assert_eq!(Board::new().to_fen(), openings[0].start.to_fen());
+        assert_eq!(3, openings[0].moves.len());
+        let mut board = openings[1].start.clone();
+        board.make_move(openings[1].moves[0]);
+        assert_eq!("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1", board.to_fen());
+
+        assert!(read_openings("book.pgn", "1. e5 *").is_err());
+        assert!(read_openings("book.pgn", "").is_err());
+    }
+}
@@ -0,0 +1,244 @@
+use std::fmt::{Display, Formatter};
+
+/// The results of a match from the first engine's view.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl Stats {
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    /// The points per game, from 0 to 1.
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// The variance of the points of a single game.
+    fn variance(&self) -> f64 {
+        let n = self.games() as f64;
+        let s = self.score();
+
+        (self.wins as f64 * (1.0 - s).powi(2)
+            + self.losses as f64 * s.powi(2)
+            + self.draws as f64 * (0.5 - s).powi(2))
+            / n
+    }
+
+    /// The Elo difference and its 95% error margin, `None` while the score is 0 or 100%.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let s = self.score();
+        if s <= 0.0 || s >= 1.0 {
+            return None;
+        }
+
+        let sigma = (self.variance() / self.games() as f64).sqrt();
+        let low = (s - 1.96 * sigma).max(1e-6);
+        let high = (s + 1.96 * sigma).min(1.0 - 1e-6);
+
+        Some((elo(s), (elo(high) - elo(low)) / 2.0))
+    }
+
+    /// The likelihood of superiority, the probability that the first engine is the stronger one.
+    pub fn los(&self) -> f64 {
+        let decisive = (self.wins + self.losses) as f64;
+        if decisive == 0.0 {
+            return 0.5;
+        }
+
+        0.5 * (1.0 + erf((self.wins as f64 - self.losses as f64) / (2.0 * decisive).sqrt()))
+    }
+}
+
+/// Prints the results like `12 - 8 - 20 [0.550] 40`, wins, losses, draws, score and games.
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} - {} - {} [{:.3}] {}",
+            self.wins,
+            self.losses,
+            self.draws,
+            self.score(),
+            self.games()
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Hypothesis {
+    /// The engines differ by `elo0`.
+    H0,
+    /// The engines differ by `elo1`.
+    H1,
+}
+
+/// A sequential probability ratio test between two Elo differences.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// The chance to accept H1 when H0 is true.
+    pub alpha: f64,
+    /// The chance to accept H0 when H1 is true.
+    pub beta: f64,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Sprt {
+        Sprt {
+            elo0,
+            elo1,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+
+    /// The log-likelihood ratio of the results, in the normal approximation.
+    pub fn llr(&self, stats: &Stats) -> f64 {
+        let variance = stats.variance();
+        if stats.games() == 0 || variance == 0.0 {
+            return 0.0;
+        }
+
+        let s0 = expected_score(self.elo0);
+        let s1 = expected_score(self.elo1);
+        stats.games() as f64 * (s1 - s0) * (2.0 * stats.score() - s0 - s1) / (2.0 * variance)
+    }
+
+    /// The ratios below which H0 and above which H1 is accepted.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// The accepted hypothesis, `None` while more games are needed.
+    pub fn decision(&self, stats: &Stats) -> Option<Hypothesis> {
+        let llr = self.llr(stats);
+        let (lower, upper) = self.bounds();
+
+        if llr >= upper {
+            Some(Hypothesis::H1)
+        } else if llr <= lower {
+            Some(Hypothesis::H0)
+        } else {
+            None
+        }
+    }
+}
+
+/// The Elo difference that gives the stronger side `score` points per game.
+fn elo(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// The error function, approximated as in Abramowitz and Stegun 7.1.26.
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let y = 1.0 - poly * (-x * x).exp();
+
+    if x < 0.0 {
+        -y
+    } else {
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::{erf, Hypothesis, Sprt, Stats};
+
+    fn close(a: f64, b: f64, eps: f64) -> bool {
+        (a - b).abs() < eps
+    }
+
+    #[test]
+    fn score_and_elo() {
+        let s = Stats {
+            wins: 60,
+            losses: 40,
+            draws: 0,
+        };
+        assert_eq!("60 - 40 - 0 [0.600] 100", s.to_string());
+
+        let (elo, margin) = s.elo().unwrap();
+        assert!(close(70.44, elo, 0.01));
+        assert!(margin > 60.0 && margin < 80.0);
+
+        let all_won = Stats {
+            wins: 3,
+            ..Stats::default()
+        };
+        assert_eq!(None, all_won.elo());
+        assert!(close(
+            0.0,
+            Stats {
+                draws: 5,
+                ..Stats::default()
+            }
+            .elo()
+            .unwrap()
+            .0,
+            1e-9
+        ));
+    }
+
+    #[test]
+    fn likelihood_of_superiority() {
+        let s = Stats {
+            wins: 60,
+            losses: 40,
+            draws: 30,
+        };
+        assert!(close(0.9772, s.los(), 1e-3));
+        assert_eq!(0.5, Stats::default().los());
+        assert!(close(0.8427, erf(1.0), 1e-6));
+        assert!(close(-0.8427, erf(-1.0), 1e-6));
+    }
+
+    #[test]
+    fn sprt() {
+        let sprt = Sprt::new(0.0, 5.0);
+        let (lower, upper) = sprt.bounds();
+        assert!(close(-2.944, lower, 1e-3) && close(2.944, upper, 1e-3));
+
+        assert_eq!(None, sprt.decision(&Stats::default()));
+        let few = Stats {
+            wins: 6,
+            losses: 4,
+            draws: 10,
+        };
+        assert_eq!(None, sprt.decision(&few));
+
+        let better = Stats {
+            wins: 3000,
+            losses: 2000,
+            draws: 5000,
+        };
+        assert_eq!(Some(Hypothesis::H1), sprt.decision(&better));
+
+        let equal = Stats {
+            wins: 6000,
+            losses: 6000,
+            draws: 10000,
+        };
+        assert!(sprt.llr(&equal) < 0.0);
+        assert_eq!(Some(Hypothesis::H0), sprt.decision(&equal));
+    }
+}
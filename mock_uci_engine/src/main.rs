@@ -0,0 +1,128 @@
+//! A scripted UCI engine for testing the UCI client, see `tests/uci_client.rs`.
+//!
+//! It plays the first legal move in UCI notation order and expects the first reply in the same
+//! order. Flags make it misbehave:
+//!
+//! - `--no-uciok` never finishes the handshake
+//! - `--ignore-go` never answers `go`, not even after `stop`
+//! - `--exit-on-go` exits instead of searching
+
+use chess::board::Board;
+use chess::chess_move::Move;
+use std::io::{BufRead, Write};
+
+fn first_move(board: &mut Board) -> Option<Move> {
+    board
+        .legal_moves()
+        .iter()
+        .copied()
+        .min_by_key(|m| m.to_string())
+}
+
+fn read_position(args: &str) -> Option<Board> {
+    let (fen, moves) = match args.split_once(" moves ") {
+        Some((fen, moves)) => (fen, moves),
+        None => (args.strip_suffix(" moves").unwrap_or(args), ""),
+    };
+    let fen = match fen.trim() {
+        "startpos" => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        fen => fen.strip_prefix("fen ")?,
+    };
+
+    Board::from_fen_and_moves(fen, moves.split_whitespace()).ok()
+}
+
+fn main() {
+    let flags: Vec<String> = std::env::args().skip(1).collect();
+    let flag = |name: &str| flags.iter().any(|f| f == name);
+
+    let stdin = std::io::stdin();
+    let mut out = std::io::stdout();
+    let mut board = Board::new();
+    let mut options: Vec<String> = Vec::new();
+    let mut searching = false;
+
+    for line in stdin.lock().lines().map_while(Result::ok) {
+        let line = line.trim();
+        let (cmd, args) = line.split_once(' ').unwrap_or((line, ""));
+
+        let reply = match cmd {
+            "uci" if flag("--no-uciok") => String::from("id name Mock Engine\n"),
+            "uci" => String::from(
+                "id name Mock Engine\n\
+                 id author The Chess Authors\n\
+                 option name Hash type spin default 16 min 1 max 1024\n\
+                 option name Ponder type check default false\n\
+                 option name Play Style type combo default Normal var Solid var Normal var Risky\n\
+                 option name Clear Hash type button\n\
+                 uciok\n",
+            ),
+            "isready" => String::from("readyok\n"),
+            "setoption" => {
+                options.push(args.replacen("name ", "", 1).replacen(" value ", "=", 1));
+                String::new()
+            }
+            "ucinewgame" => {
+                options.clear();
+                String::new()
+            }
+            "position" => {
+                board = read_position(args).unwrap_or_default();
+                String::new()
+            }
+            "go" if flag("--exit-on-go") => return,
+            "go" if flag("--ignore-go") => String::new(),
+            "go" => {
+                let mut reply = format!("info string fen {}\n", board.to_fen());
+                if !options.is_empty() {
+                    reply.push_str(&format!("info string options {}\n", options.join(", ")));
+                }
+
+                let mut pv = Vec::new();
+                let mut b = board.clone();
+                while let Some(m) = first_move(&mut b).filter(|_| pv.len() < 2) {
+                    b.make_move(m);
+                    pv.push(m.to_string());
+                }
+                reply.push_str(&format!(
+                    "info depth 1 seldepth 1 multipv 1 score cp 12 nodes 20 nps 2000 time 10 pv {}\n\
+                     info depth 2 currmove {} currmovenumber 1\n\
+                     info depth 2 seldepth 4 multipv 1 score mate 3 lowerbound nodes 400 nps 4000 time 100 pv {}\n",
+                    pv.join(" "),
+                    pv.first().map_or("0000", String::as_str),
+                    pv.join(" ")
+                ));
+
+                searching = args.split_whitespace().any(|a| a == "infinite");
+                if !searching {
+                    reply.push_str(&best_move(&pv));
+                }
+                reply
+            }
+            "stop" if searching && !flag("--ignore-go") => {
+                searching = false;
+                let mut b = board.clone();
+                let pv: Vec<String> = first_move(&mut b).iter().map(|m| m.to_string()).collect();
+                best_move(&pv)
+            }
+            "quit" => return,
+            _ => String::new(),
+        };
+
+        if out
+            .write_all(reply.as_bytes())
+            .and_then(|_| out.flush())
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+fn best_move(pv: &[String]) -> String {
+    match pv {
+        [] => String::from("bestmove (none)\n"),
+        [m] => format!("bestmove {}\n", m),
+        [m, ponder, ..] => format!("bestmove {} ponder {}\n", m, ponder),
+    }
+}
@@ -0,0 +1,147 @@
+use chess::board::Board;
+use chess::search::tt::Bound;
+use chess::search::Score;
+use chess::uci_client::{GoParams, UciEngine, UciErrorKind, UciOptionType};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MOCK: &str = env!("CARGO_BIN_EXE_mock_uci_engine");
+
+fn start(flags: &[&str]) -> UciEngine {
+    UciEngine::start_with_timeout(MOCK, flags, Duration::from_secs(5)).unwrap()
+}
+
+fn movetime() -> GoParams {
+    GoParams {
+        movetime: Some(Duration::from_millis(50)),
+        ..GoParams::default()
+    }
+}
+
+#[test]
+fn handshake_and_options() {
+    let mut engine = start(&[]);
+
+    assert_eq!(Some("Mock Engine"), engine.name());
+    assert_eq!(Some("The Chess Authors"), engine.author());
+    assert_eq!(4, engine.options().len());
+    assert_eq!(
+        UciOptionType::Combo,
+        engine.option("play style").unwrap().option_type
+    );
+
+    engine.set_option("hash", "32").unwrap();
+    engine.set_option("Play Style", "Risky").unwrap();
+    assert_eq!(
+        UciErrorKind::Unsupported,
+        engine.set_option("Threads", "4").unwrap_err().kind()
+    );
+    engine.is_ready().unwrap();
+
+    let mut strings = Vec::new();
+    engine
+        .go(&movetime(), |info| strings.extend(info.string.clone()))
+        .unwrap();
+    assert!(strings.contains(&"options Hash=32, Play Style=Risky".to_string()));
+}
+
+#[test]
+fn position_and_search() {
+    let mut engine = start(&[]);
+    engine.new_game().unwrap();
+
+    let start: Board = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".parse().unwrap();
+    let mut b = start.clone();
+    let moves: Vec<_> = ["e2e4", "e8d7"]
+        .iter()
+        .map(|s| {
+            let m = b.parse_uci_move(s).unwrap();
+            b.make_move(m);
+            m
+        })
+        .collect();
+    engine.position(&start, &moves).unwrap();
+    assert_eq!(b.to_fen(), engine.board().to_fen());
+
+    let mut infos = Vec::new();
+    let best = engine
+        .go(&movetime(), |info| infos.push(info.clone()))
+        .unwrap();
+
+    // the mock sent the fen it set up and two iterations with a move list in between
+    assert_eq!(Some(format!("fen {}", b.to_fen())), infos[0].string);
+    assert_eq!(4, infos.len());
+    assert_eq!(Some(Score::Cp(12)), infos[1].score);
+    assert_eq!(None, infos[1].bound);
+    assert_eq!(Some(Score::Mate(3)), infos[3].score);
+    assert_eq!(Some(Bound::Lower), infos[3].bound);
+    assert_eq!((Some(2), Some(4)), (infos[3].depth, infos[3].seldepth));
+    assert_eq!(Some(Duration::from_millis(100)), infos[3].time);
+
+    let pv: Vec<_> = infos[3].pv.iter().map(|m| m.to_string()).collect();
+    assert_eq!(vec!["e1d1", "d7c6"], pv);
+    assert_eq!(Some(infos[3].pv[0]), best.best_move);
+    assert_eq!(Some(infos[3].pv[1]), best.ponder);
+
+    // the start position is sent as such
+    engine.position(&Board::new(), &[]).unwrap();
+    let best = engine.go(&movetime(), |_| {}).unwrap();
+    assert_eq!("a2a3", best.best_move.unwrap().to_string());
+
+    // moves that don't fit the position aren't sent
+    let err = engine.position(&start, &moves[1..]).unwrap_err();
+    assert_eq!(UciErrorKind::IllegalMove, err.kind());
+}
+
+#[test]
+fn no_legal_moves() {
+    let mut engine = start(&[]);
+    let mated: Board = "k7/1Q6/1K6/8/8/8/8/8 b - - 0 1".parse().unwrap();
+    engine.position(&mated, &[]).unwrap();
+
+    let best = engine.go(&movetime(), |_| {}).unwrap();
+    assert_eq!(None, best.best_move);
+}
+
+#[test]
+fn infinite_search_stops() {
+    let mut engine = start(&[]);
+    let stop = engine.stop_handle();
+
+    let stopper = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        stop.store(true, Ordering::Relaxed);
+    });
+
+    let started = Instant::now();
+    let params = GoParams {
+        infinite: true,
+        ..GoParams::default()
+    };
+    let best = engine.go(&params, |_| {}).unwrap();
+    stopper.join().unwrap();
+
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert_eq!("a2a3", best.best_move.unwrap().to_string());
+}
+
+#[test]
+fn timeouts_and_exits() {
+    let err = UciEngine::start_with_timeout(MOCK, &["--no-uciok"], Duration::from_millis(100))
+        .err()
+        .unwrap();
+    assert_eq!(UciErrorKind::Timeout, err.kind());
+
+    let mut engine = start(&["--ignore-go"]);
+    engine.timeout = Duration::from_millis(100);
+    let err = engine.go(&movetime(), |_| {}).unwrap_err();
+    assert_eq!(UciErrorKind::Timeout, err.kind());
+
+    let mut engine = start(&["--exit-on-go"]);
+    let err = engine.go(&movetime(), |_| {}).unwrap_err();
+    assert_eq!(UciErrorKind::Exited, err.kind());
+
+    let err = UciEngine::start("/nonexistent/engine", &[]).err().unwrap();
+    assert_eq!(UciErrorKind::Io, err.kind());
+}
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Usage: test_suite [OPTIONS] <FILE>...
+
+Searches every position of the EPD files and checks the moves found against their bm and am
+operations.
+
+Options:
+  --depth <N>                    Search each position to this depth
+  --time <SECONDS>               Search each position this long, e.g. 0.5 [default: 1]
+  --hash <MB>                    Size of the transposition table [default: 16]
+  --out <FILE>                   Write the positions with the search results as EPD
+  -h, --help                     Print this help";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Args {
+    pub files: Vec<String>,
+    pub depth: Option<u32>,
+    pub time: Option<Duration>,
+    pub hash: Option<usize>,
+    pub out: Option<String>,
+    pub help: bool,
+}
+
+/// Reads the command line arguments, without the program name.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for '{}'", arg))
+        };
+
+        match arg.as_str() {
+            "--depth" => {
+                let v = value()?;
+                match v.parse() {
+                    Ok(n) if n > 0 => parsed.depth = Some(n),
+                    _ => return Err(format!("invalid depth '{}'", v)),
+                }
+            }
+            "--time" => {
+                let v = value()?;
+                match v
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|s| Duration::try_from_secs_f64(s).ok())
+                {
+                    Some(time) if !time.is_zero() => parsed.time = Some(time),
+                    _ => return Err(format!("invalid time '{}'", v)),
+                }
+            }
+            "--hash" => {
+                let v = value()?;
+                match v.parse() {
+                    Ok(n) if n > 0 => parsed.hash = Some(n),
+                    _ => return Err(format!("invalid hash size '{}'", v)),
+                }
+            }
+            "--out" => parsed.out = Some(value()?),
+            "-h" | "--help" => parsed.help = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown argument '{}'", arg)),
+            _ => parsed.files.push(arg),
+        }
+    }
+
+    if !parsed.help && parsed.files.is_empty() {
+        return Err(String::from("no EPD file given"));
+    }
+    if parsed.depth.is_none() && parsed.time.is_none() {
+        parsed.time = Some(Duration::from_secs(1));
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::{parse_args, Args};
+    use std::time::Duration;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn arguments() {
+        let args = parse(&["wac.epd"]).unwrap();
+        assert_eq!(vec!["wac.epd".to_string()], args.files);
+        assert_eq!(
+            (None, Some(Duration::from_secs(1))),
+            (args.depth, args.time)
+        );
+
+        let args = parse(&[
+            "--depth", "8", "--hash", "64", "a.epd", "--out", "r.epd", "b.epd",
+        ])
+        .unwrap();
+        assert_eq!(2, args.files.len());
+        assert_eq!((Some(8), None), (args.depth, args.time));
+        assert_eq!(Some(64), args.hash);
+        assert_eq!(Some("r.epd".to_string()), args.out);
+
+        let args = parse(&["--time", "0.25", "a.epd"]).unwrap();
+        assert_eq!(Some(Duration::from_millis(250)), args.time);
+
+        assert!(parse(&["-h"]).unwrap().help);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--depth", "0", "a.epd"]).is_err());
+        assert!(parse(&["--time", "-1", "a.epd"]).is_err());
+        assert!(parse(&["--hash"]).is_err());
+        assert!(parse(&["--fast", "a.epd"]).is_err());
+    }
+}
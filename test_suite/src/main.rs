@@ -0,0 +1,184 @@
+use args::Args;
+use chess::epd::{parse_epd, Epd};
+use chess::search::{Score, Search, SearchLimits, SearchResult, MATE};
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+
+mod args;
+
+fn main() {
+    let args = match args::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, args::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if args.help {
+        println!("{}", args::USAGE);
+        return;
+    }
+
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let mut positions = Vec::new();
+    for path in &args.files {
+        let contents = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        positions.extend(parse_epd(&contents).map_err(|e| format!("{}: {}", path, e))?);
+    }
+    let mut out = match &args.out {
+        Some(path) => Some(File::create(path).map_err(|e| format!("{}: {}", path, e))?),
+        None => None,
+    };
+
+    let mut search = match args.hash {
+        Some(mb) => Search::with_hash_size(mb),
+        None => Search::new(),
+    };
+    let limits = SearchLimits {
+        depth: args.depth,
+        time: args.time,
+        ..SearchLimits::default()
+    };
+    let (mut solved, mut tested, mut errors) = (0, 0, 0);
+
+    for (idx, epd) in positions.iter_mut().enumerate() {
+        search.clear();
+        let result = search.run(&mut epd.board.clone(), limits.clone(), |_| {});
+        // a position with an unreadable `bm` or `am` doesn't stop the other tests
+        let verdict = result.best_move.map_or(Ok(None), |m| epd.test_move(m));
+        let label = epd
+            .id()
+            .map_or_else(|| format!("#{}", idx + 1), str::to_string);
+        let expected = expectation(epd);
+
+        annotate(epd, &result);
+        let found = epd
+            .operation("pm")
+            .and_then(|pm| pm.first())
+            .map_or("-", String::as_str);
+        let status = match &verdict {
+            Ok(Some(true)) => format!("solved ({})", expected),
+            Ok(Some(false)) => format!("failed ({})", expected),
+            Ok(None) => String::from("untested"),
+            Err(e) => format!("error: {}", e),
+        };
+        println!(
+            "{:>4} {}: {} {} depth {}, {}",
+            idx + 1,
+            label,
+            found,
+            result.score,
+            result.depth,
+            status
+        );
+
+        match verdict {
+            Ok(Some(passed)) => {
+                tested += 1;
+                solved += passed as u32;
+            }
+            Ok(None) => {}
+            Err(_) => errors += 1,
+        }
+        if let Some(file) = &mut out {
+            writeln!(file, "{}", epd)?;
+        }
+    }
+
+    println!(
+        "Solved {} of {} positions, {} without a test, {} with errors",
+        solved,
+        tested,
+        positions.len() - tested - errors,
+        errors
+    );
+
+    Ok(())
+}
+
+/// The tests of a position as written in the EPD, e.g. `bm Nf3 Nc3; am h4`.
+fn expectation(epd: &Epd) -> String {
+    ["bm", "am"]
+        .iter()
+        .filter_map(|&opcode| Some(format!("{} {}", opcode, epd.operation(opcode)?.join(" "))))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Records the search result in the operations of the position: the predicted move `pm`, the
+/// depth `acd`, the nodes `acn` and the evaluation as `ce` or, for mates, `dm`. Getting mated is
+/// a `ce` of `-MATE` plus the plies to the mate, as the search scores it, e.g. -29996 when mated
+/// in 2.
+fn annotate(epd: &mut Epd, result: &SearchResult) {
+    if let Some(m) = result.best_move {
+        let san = epd.board.clone().san(m);
+        epd.set_operation("pm", vec![san]);
+    }
+    epd.set_operation("acd", vec![result.depth.to_string()]);
+    epd.set_operation("acn", vec![result.nodes.to_string()]);
+    match result.score {
+        Score::Mate(n) if n > 0 => epd.set_operation("dm", vec![n.to_string()]),
+        Score::Mate(n) => epd.set_operation("ce", vec![(-MATE - 2 * n).to_string()]),
+        Score::Cp(cp) => epd.set_operation("ce", vec![cp.to_string()]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{annotate, expectation};
+    use chess::epd::Epd;
+    use chess::search::{Score, Search, SearchLimits, SearchResult};
+
+    #[test]
+    fn search_results() {
+        let mut epd: Epd = "6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - bm Ra8#; id \"back rank\";"
+            .parse()
+            .unwrap();
+        assert_eq!("bm Ra8#", expectation(&epd));
+
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let result = Search::new().run(&mut epd.board.clone(), limits, |_| {});
+        assert_eq!(
+            Some(true),
+            epd.test_move(result.best_move.unwrap()).unwrap()
+        );
+
+        annotate(&mut epd, &result);
+        assert_eq!(Some(&["Ra8#".to_string()][..]), epd.operation("pm"));
+        assert_eq!(Some(&["1".to_string()][..]), epd.operation("dm"));
+        assert_eq!(Some(3), epd.depth());
+
+        let mut quiet: Epd = "4k3/8/8/8/8/8/4P3/4K3 w - - am Kd1;".parse().unwrap();
+        let result = SearchResult {
+            best_move: None,
+            ponder: None,
+            score: Score::Cp(120),
+            depth: 7,
+            nodes: 5000,
+        };
+        annotate(&mut quiet, &result);
+        assert_eq!(
+            "4k3/8/8/8/8/8/4P3/4K3 w - - am Kd1; acd 7; acn 5000; ce 120;",
+            quiet.to_string()
+        );
+        assert_eq!("am Kd1", expectation(&quiet));
+
+        let mated = SearchResult {
+            score: Score::Mate(-2),
+            ..result
+        };
+        annotate(&mut quiet, &mated);
+        assert_eq!(Some(&["-29996".to_string()][..]), quiet.operation("ce"));
+    }
+}
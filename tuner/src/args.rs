@@ -0,0 +1,110 @@
+pub const USAGE: &str = "\
+Usage: tuner [OPTIONS] <FILE>...
+
+Tunes the weights of the handcrafted evaluation with Texel's method on labeled positions: EPD
+files with the result of the game in a c9 operation, e.g. c9 \"1-0\", or PGN files, whose
+positions get the result of their game.
+
+Options:
+  --iterations <N>               Gradient descent steps [default: 500]
+  --weights <FILE>               Start from these weights instead of the built-in ones
+  --out <FILE>                   Where to write the tuned weights [default: weights.txt]
+  -h, --help                     Print this help";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Args {
+    pub files: Vec<String>,
+    pub iterations: u32,
+    pub weights: Option<String>,
+    pub out: String,
+    pub help: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            files: Vec::new(),
+            iterations: 500,
+            weights: None,
+            out: String::from("weights.txt"),
+            help: false,
+        }
+    }
+}
+
+/// Reads the command line arguments, without the program name.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for '{}'", arg))
+        };
+
+        match arg.as_str() {
+            "--iterations" => {
+                let v = value()?;
+                match v.parse() {
+                    Ok(n) if n > 0 => parsed.iterations = n,
+                    _ => return Err(format!("invalid number of iterations '{}'", v)),
+                }
+            }
+            "--weights" => parsed.weights = Some(value()?),
+            "--out" => parsed.out = value()?,
+            "-h" | "--help" => parsed.help = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown argument '{}'", arg)),
+            _ => parsed.files.push(arg),
+        }
+    }
+
+    if !parsed.help && parsed.files.is_empty() {
+        return Err(String::from("no file of positions given"));
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::{parse_args, Args};
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn arguments() {
+        let args = parse(&["quiet.epd"]).unwrap();
+        assert_eq!(vec!["quiet.epd".to_string()], args.files);
+        assert_eq!((500, None), (args.iterations, args.weights));
+        assert_eq!("weights.txt", args.out);
+
+        let args = parse(&[
+            "a.pgn",
+            "--iterations",
+            "20",
+            "--weights",
+            "old.txt",
+            "b.epd",
+            "--out",
+            "new.txt",
+        ])
+        .unwrap();
+        assert_eq!(2, args.files.len());
+        assert_eq!(20, args.iterations);
+        assert_eq!(Some("old.txt".to_string()), args.weights);
+        assert_eq!("new.txt", args.out);
+
+        assert!(parse(&["-h"]).unwrap().help);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--iterations", "0", "a.epd"]).is_err());
+        assert!(parse(&["--out"]).is_err());
+        assert!(parse(&["--fast", "a.epd"]).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use chess::board::movegen::GenType;
+use chess::board::Board;
+use chess::chess_move::{Move, MoveList};
+use chess::epd::parse_epd;
+use chess::evaluation::evaluate;
+use chess::pgn::parse_pgn;
+
+/// The plies at the start of a PGN game that aren't used, as they come from an opening book.
+const OPENING_PLIES: usize = 8;
+/// How deep the quiescence search goes at most.
+const MAX_QUIESCENCE_PLY: usize = 16;
+
+/// A position and the result of its game, from 1 for a white win to 0 for a black one.
+#[derive(Clone, Debug)]
+pub struct LabeledPosition {
+    pub board: Board,
+    pub result: f64,
+}
+
+/// Reads the positions of a PGN file if the name ends in `.pgn`, of an EPD file otherwise.
+pub fn read_positions(name: &str, contents: &str) -> Result<Vec<LabeledPosition>, String> {
+    if name.to_ascii_lowercase().ends_with(".pgn") {
+        labeled_pgn(contents)
+    } else {
+        labeled_epd(contents)
+    }
+}
+
+/// Reads the positions of an EPD file, each labeled by a `c9` operation with the result.
+pub fn labeled_epd(contents: &str) -> Result<Vec<LabeledPosition>, String> {
+    let positions = parse_epd(contents).map_err(|e| e.to_string())?;
+
+    positions
+        .into_iter()
+        .enumerate()
+        .map(|(idx, epd)| {
+            let label = epd.operation("c9").and_then(|c| c.first());
+            match label.and_then(|l| result_value(l)) {
+                Some(result) => Ok(LabeledPosition {
+                    board: epd.board,
+                    result,
+                }),
+                None => Err(format!("position {}: no result in 'c9'", idx + 1)),
+            }
+        })
+        .collect()
+}
+
+/// Reads the positions of the games of a PGN file after the opening, labeled by the result of
+/// their game. Unfinished games and positions in check are left out.
+pub fn labeled_pgn(contents: &str) -> Result<Vec<LabeledPosition>, String> {
+    let mut positions = Vec::new();
+
+    for (idx, game) in parse_pgn(contents)
+        .map_err(|e| e.to_string())?
+        .iter()
+        .enumerate()
+    {
+        let result = game.result.as_deref().or_else(|| game.header("Result"));
+        let Some(result) = result.and_then(result_value) else {
+            continue;
+        };
+        let (mut board, moves) = game
+            .replay()
+            .map_err(|e| format!("game {}: {}", idx + 1, e))?;
+
+        for (ply, m) in moves.into_iter().enumerate() {
+            board.make_move(m);
+            if ply >= OPENING_PLIES && !board.in_check() {
+                positions.push(LabeledPosition {
+                    board: board.clone(),
+                    result,
+                });
+            }
+        }
+    }
+
+    Ok(positions)
+}
+
+/// The score of a result like `1-0` or `0.5` for white.
+fn result_value(result: &str) -> Option<f64> {
+    match result {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" => Some(0.5),
+        _ => result.parse().ok().filter(|r| (0.0..=1.0).contains(r)),
+    }
+}
+
+/// The position at the end of the quiescence search's principal variation, where no captures
+/// are pending and the static evaluation applies.
+pub fn quiet_position(board: &Board) -> Board {
+    let mut board = board.clone();
+    let mut pv = Vec::new();
+
+    quiescence(&mut board, -i32::MAX, i32::MAX, 0, &mut pv);
+    for m in pv {
+        board.make_move(m);
+    }
+
+    board
+}
+
+fn quiescence(board: &mut Board, mut alpha: i32, beta: i32, ply: usize, pv: &mut Vec<Move>) -> i32 {
+    pv.clear();
+    let stand_pat = evaluate(board);
+    if stand_pat >= beta || ply >= MAX_QUIESCENCE_PLY {
+        return stand_pat;
+    }
+    alpha = alpha.max(stand_pat);
+
+    let mut moves = MoveList::new();
+    board.generate_legal(GenType::Tactical, &mut moves);
+    let mut child_pv = Vec::new();
+
+    for m in moves.iter().copied() {
+        board.make_move(m);
+        let score = -quiescence(board, -beta, -alpha, ply + 1, &mut child_pv);
+        board.unmake_move(m);
+
+        if score > alpha {
+            alpha = score;
+            pv.clear();
+            pv.push(m);
+            pv.extend_from_slice(&child_pv);
+            if score >= beta {
+                break;
+            }
+        }
+    }
+
+    alpha
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{labeled_epd, labeled_pgn, quiet_position, read_positions};
+    use chess::board::Board;
+
+    #[test]
+    fn epd_labels() {
+        // the first as written by datagen
+        let positions = labeled_epd(
+            "4k3/8/8/8/8/8/8/3QK3 w - - ce 910; c9 \"1-0\";\n\
+             4k3/8/8/8/8/8/8/4K3 b - - c9 \"1/2-1/2\"; id \"draw\";\n\
+             4k3/8/8/8/8/8/8/3qK3 w - - c9 0;",
+        )
+        .unwrap();
+        let results: Vec<f64> = positions.iter().map(|p| p.result).collect();
+        assert_eq!(vec![1.0, 0.5, 0.0], results);
+
+        assert_eq!(
+            "position 2: no result in 'c9'",
+            labeled_epd("4k3/8/8/8/8/8/8/3QK3 w - - c9 \"1-0\";\n4k3/8/8/8/8/8/8/4K3 b - -")
+                .unwrap_err()
+        );
+        assert!(labeled_epd("4k3/8/8/8/8/8/8/4K3 b - - c9 2;").is_err());
+    }
+
+    #[test]
+    fn pgn_labels() {
+        let pgn = "[Result \"0-1\"]\n\n\
+            1. f3 e5 2. g4 Nc6 3. Nc3 d6 4. Nb5 Bd7 5. Nc3 Qh4# 0-1\n\n\
+            [Result \"*\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O *\n";
+
+        // the unfinished game, the opening and the final check are left out
+        let positions = read_positions("games.PGN", pgn).unwrap();
+        assert_eq!(1, positions.len());
+        assert_eq!(0.0, positions[0].result);
+        assert_eq!(
+            "r2qkbnr/pppb1ppp/2np4/4p3/6P1/2N2P2/PPPPP2P/R1BQKBNR b KQkq - 3 5",
+            positions[0].board.to_fen()
+        );
+
+        assert!(labeled_pgn("1. e4 e5 2. Ke3 Ke6 1-0").is_err());
+    }
+
+    #[test]
+    fn quiescence() {
+        // the hanging rook is taken and the recapture isn't possible
+        let b = "4k3/8/8/3r4/8/8/8/3QK3 w - - 0 1".parse().unwrap();
+        assert_eq!(
+            "4k3/8/8/3Q4/8/8/8/4K3 b - - 0 1",
+            quiet_position(&b).to_fen()
+        );
+
+        // a defended pawn isn't worth the queen
+        let b: Board = "4k3/2p5/3p4/8/8/8/8/3QK3 w - - 0 1".parse().unwrap();
+        assert_eq!(b.to_fen(), quiet_position(&b).to_fen());
+    }
+}
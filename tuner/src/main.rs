@@ -0,0 +1,80 @@
+use args::Args;
+use chess::evaluation::EvalParams;
+use data::{quiet_position, read_positions};
+use std::error::Error;
+use tune::{error, fit_k, Sample, Tuner};
+
+mod args;
+mod data;
+mod tune;
+
+/// The steps between reports of the error.
+const REPORT_INTERVAL: u32 = 50;
+
+fn main() {
+    let args = match args::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, args::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if args.help {
+        println!("{}", args::USAGE);
+        return;
+    }
+
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let params = match &args.weights {
+        Some(path) => EvalParams::load(path)?,
+        None => EvalParams::DEFAULT,
+    };
+
+    let mut samples = Vec::new();
+    for path in &args.files {
+        let contents = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let positions = read_positions(path, &contents).map_err(|e| format!("{}: {}", path, e))?;
+        samples.extend(
+            positions
+                .iter()
+                .map(|p| Sample::new(&quiet_position(&p.board), p.result)),
+        );
+    }
+    if samples.is_empty() {
+        return Err("no labeled positions found".into());
+    }
+
+    let start: Vec<f64> = params.weights().iter().map(|w| *w as f64).collect();
+    let k = fit_k(&samples, &start);
+    println!(
+        "{} positions, K = {:.4}, error {:.6}",
+        samples.len(),
+        k,
+        error(&samples, &start, k)
+    );
+
+    let mut tuner = Tuner::new(&params, k);
+    for step in 1..=args.iterations {
+        tuner.step(&samples);
+        if step % REPORT_INTERVAL == 0 || step == args.iterations {
+            println!(
+                "iteration {}: error {:.6}",
+                step,
+                error(&samples, tuner.weights(), k)
+            );
+        }
+    }
+
+    std::fs::write(&args.out, tuner.params().to_string())
+        .map_err(|e| format!("{}: {}", args.out, e))?;
+    println!("Wrote the weights to {}", args.out);
+
+    Ok(())
+}
@@ -0,0 +1,187 @@
+use chess::board::Board;
+use chess::evaluation::EvalParams;
+use std::f64::consts::LN_10;
+
+/// A position reduced to the coefficients of the weights in its evaluation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub features: Vec<(usize, f64)>,
+    /// The result of the game for white.
+    pub result: f64,
+}
+
+impl Sample {
+    pub fn new(board: &Board, result: f64) -> Sample {
+        Sample {
+            features: EvalParams::features(board),
+            result,
+        }
+    }
+
+    /// The evaluation with `weights` in centipawns for white.
+    fn evaluate(&self, weights: &[f64]) -> f64 {
+        self.features.iter().map(|(i, c)| c * weights[*i]).sum()
+    }
+}
+
+/// The expected score for a centipawn evaluation, with `k` scaling the evaluation.
+fn sigmoid(k: f64, eval: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+/// The mean squared difference between the results and the scores the evaluation predicts.
+pub fn error(samples: &[Sample], weights: &[f64], k: f64) -> f64 {
+    let sum: f64 = samples
+        .iter()
+        .map(|s| (s.result - sigmoid(k, s.evaluate(weights))).powi(2))
+        .sum();
+
+    sum / samples.len().max(1) as f64
+}
+
+/// The scaling of the evaluation that fits the results best, found by golden-section search.
+pub fn fit_k(samples: &[Sample], weights: &[f64]) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (0.05, 5.0);
+
+    while high - low > 1e-4 {
+        let a = high - ratio * (high - low);
+        let b = low + ratio * (high - low);
+        if error(samples, weights, a) < error(samples, weights, b) {
+            high = b;
+        } else {
+            low = a;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+/// Minimizes the error of the evaluation weights with the Adam variant of gradient descent.
+pub struct Tuner {
+    weights: Vec<f64>,
+    k: f64,
+    /// The moving averages of the gradient and of its square.
+    moments: Vec<(f64, f64)>,
+    steps: i32,
+}
+
+impl Tuner {
+    const LEARNING_RATE: f64 = 1.0;
+    const BETA1: f64 = 0.9;
+    const BETA2: f64 = 0.999;
+
+    pub fn new(params: &EvalParams, k: f64) -> Tuner {
+        Tuner {
+            weights: params.weights().iter().map(|w| *w as f64).collect(),
+            k,
+            moments: vec![(0.0, 0.0); EvalParams::LEN],
+            steps: 0,
+        }
+    }
+
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    /// The weights rounded to whole centipawns.
+    pub fn params(&self) -> EvalParams {
+        let weights: Vec<i32> = self.weights.iter().map(|w| w.round() as i32).collect();
+        EvalParams::from_weights(&weights).expect("the tuner has all weights")
+    }
+
+    /// Takes a step down the gradient of the error over all samples.
+    pub fn step(&mut self, samples: &[Sample]) {
+        let mut gradient = vec![0.0; self.weights.len()];
+        let scale = 2.0 * self.k * LN_10 / 400.0 / samples.len().max(1) as f64;
+
+        for sample in samples {
+            let s = sigmoid(self.k, sample.evaluate(&self.weights));
+            let slope = (s - sample.result) * s * (1.0 - s) * scale;
+            for (i, c) in &sample.features {
+                gradient[*i] += slope * c;
+            }
+        }
+
+        self.steps += 1;
+        let (correction1, correction2) = (
+            1.0 - Self::BETA1.powi(self.steps),
+            1.0 - Self::BETA2.powi(self.steps),
+        );
+        for ((w, (m, v)), g) in self.weights.iter_mut().zip(&mut self.moments).zip(gradient) {
+            *m = Self::BETA1 * *m + (1.0 - Self::BETA1) * g;
+            *v = Self::BETA2 * *v + (1.0 - Self::BETA2) * g * g;
+            *w -= Self::LEARNING_RATE * (*m / correction1) / ((*v / correction2).sqrt() + 1e-8);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tune::{error, fit_k, sigmoid, Sample, Tuner};
+    use chess::board::Board;
+    use chess::evaluation::EvalParams;
+    use chess::search::{Score, Search, SearchLimits};
+
+    fn weights(params: &EvalParams) -> Vec<f64> {
+        params.weights().iter().map(|w| *w as f64).collect()
+    }
+
+    #[test]
+    fn scaling() {
+        // positions a few pawns up, labeled with what a scaling of 1.3 predicts
+        let samples: Vec<Sample> = (-4..=4)
+            .map(|n| Sample {
+                features: vec![(5, n as f64)],
+                result: sigmoid(1.3, 100.0 * n as f64),
+            })
+            .collect();
+        let w = weights(&EvalParams::DEFAULT);
+
+        assert!(error(&samples, &w, 1.3) < 1e-12);
+        assert!((fit_k(&samples, &w) - 1.3).abs() < 1e-3);
+    }
+
+    #[test]
+    fn tuning() {
+        // white wins the games with an extra knight, but draws those with an extra bishop
+        let samples: Vec<Sample> = [
+            ("4k3/8/8/8/8/2N5/8/4K3 w - - 0 1", 1.0),
+            ("4k3/8/8/8/3N4/8/8/4K3 b - - 0 1", 1.0),
+            ("4k3/8/8/8/8/2B5/8/4K3 w - - 0 1", 0.5),
+            ("4k3/8/8/8/3B4/8/8/4K3 b - - 0 1", 0.5),
+        ]
+        .iter()
+        .map(|(fen, result)| Sample::new(&fen.parse::<Board>().unwrap(), *result))
+        .collect();
+
+        let mut tuner = Tuner::new(&EvalParams::DEFAULT, 1.0);
+        let before = error(&samples, tuner.weights(), 1.0);
+        for _ in 0..200 {
+            tuner.step(&samples);
+        }
+        assert!(error(&samples, tuner.weights(), 1.0) < before / 2.0);
+
+        let params = tuner.params();
+        assert!(params.material[4] > 320 && params.material[3] < 330);
+        assert_eq!(EvalParams::DEFAULT.material[1], params.material[1]);
+
+        // the weights file written by the tuner is what the engine evaluates with
+        let path = std::env::temp_dir().join(format!("tuned-{}.txt", std::process::id()));
+        std::fs::write(&path, params.to_string()).unwrap();
+        let mut search = Search::new();
+        search.set_eval_params(EvalParams::load(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&params, search.eval_params());
+
+        // which values the extra knight higher than the built-in weights do
+        let mut b: Board = "4k3/8/8/8/8/2N5/8/4K3 w - - 0 1".parse().unwrap();
+        let limits = SearchLimits {
+            depth: Some(1),
+            ..SearchLimits::default()
+        };
+        let tuned = search.run(&mut b, limits.clone(), |_| {}).score;
+        let built_in = Search::new().run(&mut b, limits, |_| {}).score;
+        assert!(matches!((tuned, built_in), (Score::Cp(t), Score::Cp(d)) if t > d));
+    }
+}